@@ -0,0 +1,161 @@
+//! A background task reloading a file-backed value whenever its file changes.
+//!
+//! The file changes are detected by polling the file modification time.
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tokio::task::JoinHandle;
+
+/// Spawn a background task calling `reload` whenever the file at `path` changes, checking the file
+/// modification time every `interval`.
+///
+/// The `reload` function returns whether to keep reloading, e.g., `false` once all the handles to
+/// the reloaded value were dropped. The task stops once the `shutdown` future completes, or
+/// `reload` returns `false`.
+///
+/// The file is not reloaded on start, callers are expected to load it before spawning the task.
+pub fn spawn_file_reloader<F>(
+    path: PathBuf,
+    interval: Duration,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    mut reload: F,
+) -> JoinHandle<()>
+where
+    F: FnMut(&Path) -> bool + Send + 'static,
+{
+    tokio::spawn(async move {
+        tokio::pin!(shutdown);
+        let mut last_modified = modified_time(&path);
+        let mut timer = tokio::time::interval(interval);
+        loop {
+            tokio::select! { biased;
+                _ = &mut shutdown => break,
+                _ = timer.tick() => {}
+            }
+
+            let modified = modified_time(&path);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            if !reload(&path) {
+                break;
+            }
+        }
+    })
+}
+
+/// Get the file modification time. If the file is missing, `None` is returned.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, SystemTime},
+    };
+
+    use super::spawn_file_reloader;
+
+    /// Test utility function to get a unique file path in the temp directory.
+    fn test_file_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("file-reloader-{}-{name}.txt", std::process::id()))
+    }
+
+    /// Test utility function to set the file modification time, so the file changes do not depend
+    /// on the filesystem timestamps resolution.
+    fn set_modified(path: &Path, secs: u64) {
+        File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)))
+            .expect("failed to set the file modification time");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn file_changes_are_reloaded_until_shutdown() {
+        //* Given
+        let path = test_file_path("shutdown");
+        std::fs::write(&path, "").unwrap();
+        set_modified(&path, 1);
+
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let reloader = spawn_file_reloader(
+            path.clone(),
+            Duration::from_secs(1),
+            async {
+                let _ = shutdown_rx.await;
+            },
+            {
+                let reloads = reloads.clone();
+                move |_| {
+                    reloads.fetch_add(1, Ordering::SeqCst);
+                    true
+                }
+            },
+        );
+
+        // Let the reloader start, and check it picks up the file change at the next check
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        set_modified(&path, 2);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(reloads.load(Ordering::SeqCst), 1);
+
+        //* When
+        shutdown_tx.send(()).unwrap();
+        reloader.await.expect("reloader task panicked");
+
+        set_modified(&path, 3);
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        //* Then
+        // The file changes after the shutdown are not reloaded
+        assert_eq!(reloads.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unchanged_file_is_not_reloaded() {
+        //* Given
+        let path = test_file_path("unchanged");
+        std::fs::write(&path, "").unwrap();
+        set_modified(&path, 1);
+
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let _reloader = spawn_file_reloader(
+            path.clone(),
+            Duration::from_secs(1),
+            std::future::pending(),
+            {
+                let reloads = reloads.clone();
+                move |_| {
+                    reloads.fetch_add(1, Ordering::SeqCst);
+                    true
+                }
+            },
+        );
+
+        //* When
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        //* Then
+        assert_eq!(reloads.load(Ordering::SeqCst), 0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 pub mod blocklist;
+pub mod file_reloader;
 pub mod ttl_hash_map;
 pub mod types;
 pub mod utils;
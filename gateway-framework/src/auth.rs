@@ -4,6 +4,7 @@ use thegraph_core::types::SubgraphId;
 pub use self::context::AuthContext;
 use self::methods::{api_keys, subscriptions};
 
+pub mod authorization;
 pub mod context;
 pub mod methods;
 
@@ -43,10 +44,15 @@ impl AuthToken {
     }
 
     /// Check if the given origin domain is authorized for this auth token.
-    pub fn is_domain_authorized(&self, domain: &str) -> bool {
+    ///
+    /// Requests with no origin, i.e., no `Origin` header, are authorized according to the
+    /// `allow_missing_origin` policy.
+    pub fn is_domain_authorized(&self, origin: Option<&str>, allow_missing_origin: bool) -> bool {
         match self {
-            AuthToken::ApiKey(auth) => auth.is_domain_authorized(domain),
-            AuthToken::SubscriptionsAuthToken(auth) => auth.is_domain_authorized(domain),
+            AuthToken::ApiKey(auth) => auth.is_domain_authorized(origin, allow_missing_origin),
+            AuthToken::SubscriptionsAuthToken(auth) => {
+                auth.is_domain_authorized(origin, allow_missing_origin)
+            }
         }
     }
 }
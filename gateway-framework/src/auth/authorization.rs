@@ -0,0 +1,436 @@
+//! Composable query authorization.
+//!
+//! The [`QueryAuthorization`] combines the authorization building blocks in
+//! [`methods::common`](super::methods::common) into a single entry point.
+
+use thegraph_core::types::{DeploymentId, SubgraphId};
+
+use super::methods::common;
+use crate::topology::network::GraphNetwork;
+
+/// The target of a query, i.e., the subgraph or deployment the query was sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryTarget {
+    /// The query targets a subgraph, by [`SubgraphId`].
+    Subgraph(SubgraphId),
+    /// The query targets a deployment, by [`DeploymentId`].
+    Deployment(DeploymentId),
+}
+
+/// The reason a query was not authorized.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AuthError {
+    /// The query origin domain is not authorized.
+    #[error("domain not authorized: {0}")]
+    DomainNotAuthorized(String),
+    /// The query target subgraph is not authorized.
+    #[error("subgraph not authorized: {0}")]
+    SubgraphNotAuthorized(SubgraphId),
+    /// One of the deployments the query would touch is not authorized.
+    #[error("deployment not authorized: {0}")]
+    DeploymentNotAuthorized(DeploymentId),
+    /// The query would touch more deployments than allowed.
+    #[error("too many deployments: {count}, the maximum is {max}")]
+    TooManyDeployments { count: usize, max: usize },
+}
+
+/// The set of authorization scopes applied to a query.
+///
+/// Empty authorized sets allow any subgraph, deployment, or domain.
+#[derive(Debug, Clone, Default)]
+pub struct QueryAuthorization {
+    /// The authorized subgraphs.
+    pub subgraphs: Vec<SubgraphId>,
+    /// The authorized deployments.
+    pub deployments: Vec<DeploymentId>,
+    /// The authorized origin domains. See [`common::is_domain_authorized`] for the supported
+    /// patterns.
+    pub domains: Vec<String>,
+    /// The maximum number of deployments a single query can touch.
+    pub max_deployments_per_query: Option<usize>,
+}
+
+impl QueryAuthorization {
+    /// Check if the query is authorized.
+    ///
+    /// The checks run in order: origin domain, query target, the deployments the query would
+    /// touch, and the number of deployments. The first failure reason is returned.
+    pub fn authorize(
+        &self,
+        target: &QueryTarget,
+        deployments: &[DeploymentId],
+        origin: &str,
+    ) -> Result<(), AuthError> {
+        self.authorize_origin(origin)?;
+        self.authorize_target(target)?;
+
+        // An empty deployments set authorizes all deployments
+        if !self.deployments.is_empty() {
+            if let Some(deployment) = deployments
+                .iter()
+                .find(|deployment| !self.deployments.contains(deployment))
+            {
+                return Err(AuthError::DeploymentNotAuthorized(*deployment));
+            }
+        }
+
+        if let Some(max) = self.max_deployments_per_query {
+            if deployments.len() > max {
+                return Err(AuthError::TooManyDeployments {
+                    count: deployments.len(),
+                    max,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the query origin domain is authorized.
+    fn authorize_origin(&self, origin: &str) -> Result<(), AuthError> {
+        let domains = self.domains.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        if !common::is_domain_authorized(&domains, origin) {
+            return Err(AuthError::DomainNotAuthorized(origin.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Check if the query target subgraph or deployment is authorized.
+    fn authorize_target(&self, target: &QueryTarget) -> Result<(), AuthError> {
+        match target {
+            QueryTarget::Subgraph(subgraph) => {
+                if !common::is_subgraph_authorized(&self.subgraphs, subgraph) {
+                    return Err(AuthError::SubgraphNotAuthorized(*subgraph));
+                }
+            }
+            QueryTarget::Deployment(deployment) => {
+                if !common::is_deployment_authorized(&self.deployments, deployment) {
+                    return Err(AuthError::DeploymentNotAuthorized(*deployment));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check if each query of a batch is authorized.
+///
+/// The origin domain is checked once for the whole batch, and each target's subgraph or
+/// deployment scope individually. One result is returned per target, in order, so the
+/// authorized queries of a partially authorized batch can still be served.
+pub fn authorize_batch(
+    auth: &QueryAuthorization,
+    targets: &[QueryTarget],
+    origin: &str,
+) -> Vec<Result<(), AuthError>> {
+    if let Err(err) = auth.authorize_origin(origin) {
+        return vec![Err(err); targets.len()];
+    }
+    targets
+        .iter()
+        .map(|target| auth.authorize_target(target))
+        .collect()
+}
+
+/// Check if the query target is within the key's authorized scopes, cross-checking the network
+/// subgraph-deployment mapping.
+///
+/// An authorized subgraph implicitly authorizes its current deployments, so a key listing a
+/// subgraph is not denied when the query addresses one of the subgraph's deployments by ID. As with
+/// the [`common`] checks, an empty authorized set does not restrict its scope.
+pub fn validate_scope_consistency(
+    authorized_subgraphs: &[SubgraphId],
+    authorized_deployments: &[DeploymentId],
+    network: &GraphNetwork,
+    target: &QueryTarget,
+) -> Result<(), AuthError> {
+    match target {
+        QueryTarget::Subgraph(subgraph) => {
+            if !common::is_subgraph_authorized(authorized_subgraphs, subgraph) {
+                return Err(AuthError::SubgraphNotAuthorized(*subgraph));
+            }
+        }
+        QueryTarget::Deployment(deployment) => {
+            if common::is_deployment_authorized(authorized_deployments, deployment) {
+                return Ok(());
+            }
+
+            // Check if any of the deployment's subgraphs is authorized
+            let authorized_via_subgraph = !authorized_subgraphs.is_empty()
+                && common::is_deployment_authorized_via_subgraphs(
+                    authorized_subgraphs,
+                    deployment,
+                    |deployment| {
+                        network
+                            .deployment_by_id(deployment)
+                            .map(|deployment| deployment.subgraphs.iter().copied().collect())
+                            .unwrap_or_default()
+                    },
+                );
+            if !authorized_via_subgraph {
+                return Err(AuthError::DeploymentNotAuthorized(*deployment));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeSet, HashMap},
+        sync::Arc,
+    };
+
+    use assert_matches::assert_matches;
+    use eventuals::{Eventual, Ptr};
+    use thegraph_core::types::{DeploymentId, SubgraphId};
+
+    use super::{
+        authorize_batch, validate_scope_consistency, AuthError, QueryAuthorization, QueryTarget,
+    };
+    use crate::topology::network::{Deployment, GraphNetwork, Manifest, Subgraph};
+
+    /// Test utility function to create a valid `DeploymentId` with an arbitrary deployment id/ipfs hash.
+    fn test_deployment_id(deployment: &str) -> DeploymentId {
+        deployment.parse().expect("invalid deployment id/ipfs hash")
+    }
+
+    /// Test utility function to create a valid `SubgraphId` with an arbitrary address.
+    fn test_subgraph_id(address: &str) -> SubgraphId {
+        address.parse().expect("invalid subgraph id")
+    }
+
+    #[test]
+    fn authorize_with_empty_scopes() {
+        //* Given
+        let auth = QueryAuthorization::default();
+        let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+
+        //* When
+        let res = auth.authorize(
+            &QueryTarget::Subgraph(subgraph),
+            &[deployment],
+            "example.com",
+        );
+
+        //* Then
+        assert_matches!(res, Ok(()));
+    }
+
+    #[test]
+    fn reject_unauthorized_domain() {
+        //* Given
+        let auth = QueryAuthorization {
+            domains: vec!["*.example.com".to_string()],
+            ..Default::default()
+        };
+        let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+
+        //* When
+        let res = auth.authorize(&QueryTarget::Subgraph(subgraph), &[], "example.org");
+
+        //* Then
+        assert_matches!(res, Err(AuthError::DomainNotAuthorized(domain)) => {
+            assert_eq!(domain, "example.org");
+        });
+    }
+
+    #[test]
+    fn reject_unauthorized_subgraph() {
+        //* Given
+        let authorized = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let unauthorized = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let auth = QueryAuthorization {
+            subgraphs: vec![authorized],
+            ..Default::default()
+        };
+
+        //* When
+        let res = auth.authorize(&QueryTarget::Subgraph(unauthorized), &[], "example.com");
+
+        //* Then
+        assert_matches!(res, Err(AuthError::SubgraphNotAuthorized(subgraph)) => {
+            assert_eq!(subgraph, unauthorized);
+        });
+    }
+
+    #[test]
+    fn reject_unauthorized_target_deployment() {
+        //* Given
+        let authorized = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let unauthorized = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let auth = QueryAuthorization {
+            deployments: vec![authorized],
+            ..Default::default()
+        };
+
+        //* When
+        let res = auth.authorize(
+            &QueryTarget::Deployment(unauthorized),
+            &[unauthorized],
+            "example.com",
+        );
+
+        //* Then
+        assert_matches!(res, Err(AuthError::DeploymentNotAuthorized(deployment)) => {
+            assert_eq!(deployment, unauthorized);
+        });
+    }
+
+    #[test]
+    fn reject_unauthorized_touched_deployment() {
+        //* Given
+        let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let authorized = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let unauthorized = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let auth = QueryAuthorization {
+            deployments: vec![authorized],
+            ..Default::default()
+        };
+
+        //* When
+        let res = auth.authorize(
+            &QueryTarget::Subgraph(subgraph),
+            &[authorized, unauthorized],
+            "example.com",
+        );
+
+        //* Then
+        assert_matches!(res, Err(AuthError::DeploymentNotAuthorized(deployment)) => {
+            assert_eq!(deployment, unauthorized);
+        });
+    }
+
+    #[test]
+    fn reject_too_many_deployments() {
+        //* Given
+        let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let deployments = [
+            test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"),
+            test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3"),
+        ];
+        let auth = QueryAuthorization {
+            max_deployments_per_query: Some(1),
+            ..Default::default()
+        };
+
+        //* When
+        let res = auth.authorize(
+            &QueryTarget::Subgraph(subgraph),
+            &deployments,
+            "example.com",
+        );
+
+        //* Then
+        assert_matches!(res, Err(AuthError::TooManyDeployments { count: 2, max: 1 }));
+    }
+
+    #[test]
+    fn authorize_batch_with_one_unauthorized_item() {
+        //* Given
+        let authorized = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let unauthorized = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let auth = QueryAuthorization {
+            subgraphs: vec![authorized],
+            domains: vec!["example.com".to_string()],
+            ..Default::default()
+        };
+        let targets = [
+            QueryTarget::Subgraph(authorized),
+            QueryTarget::Subgraph(unauthorized),
+            QueryTarget::Subgraph(authorized),
+        ];
+
+        //* When
+        let res = authorize_batch(&auth, &targets, "example.com");
+
+        //* Then
+        assert_eq!(res.len(), 3);
+        assert_matches!(res[0], Ok(()));
+        assert_matches!(&res[1], Err(AuthError::SubgraphNotAuthorized(subgraph)) => {
+            assert_eq!(*subgraph, unauthorized);
+        });
+        assert_matches!(res[2], Ok(()));
+    }
+
+    /// Test utility function to create a network with a single subgraph and the given
+    /// deployments as its versions.
+    fn test_network(subgraph: SubgraphId, deployments: &[DeploymentId]) -> GraphNetwork {
+        let deployments = deployments
+            .iter()
+            .map(|id| {
+                Arc::new(Deployment {
+                    id: *id,
+                    manifest: Manifest {
+                        network: "mainnet".to_string(),
+                        min_block: 0,
+                        features: vec![],
+                    },
+                    indexers: HashMap::new(),
+                    subgraphs: BTreeSet::from([subgraph]),
+                    transferred_to_l2: false,
+                })
+            })
+            .collect::<Vec<_>>();
+        let subgraph = Subgraph {
+            versions: (0..deployments.len() as u32).collect(),
+            deployments: deployments.clone(),
+            id: subgraph,
+            l2_id: None,
+        };
+
+        GraphNetwork {
+            subgraphs: Eventual::from_value(Ptr::new(HashMap::from([(subgraph.id, subgraph)]))),
+            deployments: Eventual::from_value(Ptr::new(
+                deployments
+                    .into_iter()
+                    .map(|deployment| (deployment.id, deployment))
+                    .collect(),
+            )),
+            indexers: Eventual::from_value(Ptr::default()),
+        }
+    }
+
+    #[test]
+    fn subgraph_scope_authorizes_its_deployments() {
+        //* Given
+        let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let listed_deployment =
+            test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let other_deployment = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let network = test_network(subgraph, &[deployment]);
+
+        //* When
+        // The key lists the subgraph, and an unrelated deployment
+        let res = validate_scope_consistency(
+            &[subgraph],
+            &[listed_deployment],
+            &network,
+            &QueryTarget::Deployment(deployment),
+        );
+        let other_res = validate_scope_consistency(
+            &[subgraph],
+            &[listed_deployment],
+            &network,
+            &QueryTarget::Deployment(other_deployment),
+        );
+        // An empty authorized deployments set does not restrict the deployments
+        let unrestricted_res = validate_scope_consistency(
+            &[subgraph],
+            &[],
+            &network,
+            &QueryTarget::Deployment(other_deployment),
+        );
+
+        //* Then
+        assert_matches!(res, Ok(()));
+        assert_matches!(other_res, Err(AuthError::DeploymentNotAuthorized(deployment)) => {
+            assert_eq!(deployment, other_deployment);
+        });
+        assert_matches!(unrestricted_res, Ok(()));
+    }
+}
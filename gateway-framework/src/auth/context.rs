@@ -23,6 +23,9 @@ pub struct AuthContext {
     /// This is used to disable the payment requirement on testnets. If this is `true`, then all queries require
     /// payment, unless they are subsidized or special.
     pub payment_required: bool,
+    /// Whether requests with no `Origin` header, e.g., server-to-server requests, are authorized
+    /// when the auth token restricts the allowed domains.
+    pub allow_missing_origin: bool,
 
     // Studio API keys
     pub api_keys: watch::Receiver<HashMap<String, Arc<APIKey>>>,
@@ -58,6 +61,7 @@ impl FromRef<AuthContext> for subscriptions::AuthContext {
 impl AuthContext {
     pub fn create(
         payment_required: bool,
+        allow_missing_origin: bool,
         api_keys: watch::Receiver<HashMap<String, Arc<APIKey>>>,
         special_api_keys: HashSet<String>,
         subscriptions: watch::Receiver<HashMap<Address, Subscription>>,
@@ -67,6 +71,7 @@ impl AuthContext {
     ) -> Self {
         Self {
             payment_required,
+            allow_missing_origin,
             api_keys,
             special_api_keys: Arc::new(special_api_keys),
             special_query_key_signers: Arc::new(special_query_key_signers),
@@ -93,7 +93,10 @@ impl AuthToken {
     }
 
     /// Check if the given domain is authorized by the API key.
-    pub fn is_domain_authorized(&self, domain: &str) -> bool {
+    ///
+    /// Requests with no origin, i.e., no `Origin` header, are authorized according to the
+    /// `allow_missing_origin` policy. See [`common::is_origin_authorized`].
+    pub fn is_domain_authorized(&self, origin: Option<&str>, allow_missing_origin: bool) -> bool {
         let allowed_domains = &self
             .api_key
             .domains
@@ -101,7 +104,7 @@ impl AuthToken {
             .map(AsRef::as_ref)
             .collect::<Vec<_>>();
 
-        common::is_domain_authorized(allowed_domains, domain)
+        common::is_origin_authorized(allowed_domains, origin, allow_missing_origin)
     }
 
     /// Check if the given subgraph is authorized by the API key.
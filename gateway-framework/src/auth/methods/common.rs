@@ -1,4 +1,5 @@
 use thegraph_core::types::{DeploymentId, SubgraphId};
+use url::Url;
 
 /// Check if the given deployment is authorized.
 ///
@@ -41,31 +42,165 @@ pub fn are_subgraphs_authorized(authorized: &[SubgraphId], subgraphs: &[Subgraph
             .all(|subgraph| authorized.contains(subgraph))
 }
 
+/// Check if the given deployment is denied.
+///
+/// It checks if the given deployment is contained in the denied set. If the denied set is empty,
+/// no deployment is considered denied.
+pub fn is_deployment_denied(denied: &[DeploymentId], deployment: &DeploymentId) -> bool {
+    denied.contains(deployment)
+}
+
+/// Check if the given subgraph is denied.
+///
+/// It checks if the given subgraph is contained in the denied set. If the denied set is empty, no
+/// subgraph is considered denied.
+pub fn is_subgraph_denied(denied: &[SubgraphId], subgraph: &SubgraphId) -> bool {
+    denied.contains(subgraph)
+}
+
+/// Check if the given deployment belongs to any of the authorized subgraphs.
+///
+/// The `resolver` maps the deployment to the subgraphs it belongs to. If the authorized set is
+/// empty, any deployment is considered authorized.
+pub fn is_deployment_authorized_via_subgraphs(
+    authorized_subgraphs: &[SubgraphId],
+    deployment: &DeploymentId,
+    resolver: impl Fn(&DeploymentId) -> Vec<SubgraphId>,
+) -> bool {
+    authorized_subgraphs.is_empty()
+        || resolver(deployment)
+            .iter()
+            .any(|subgraph| authorized_subgraphs.contains(subgraph))
+}
+
 /// Check if the query origin domain is authorized.
 ///
-/// If the authorized domain starts with a `*`, it is considered a wildcard
-/// domain. This means that any origin domain that ends with the wildcard
-/// domain is considered authorized.
+/// If the authorized domain starts with a `*`, it is considered a wildcard domain. The wildcard
+/// matching is label-aware, see [`match_wildcard_domain`].
+///
+/// Domains are case-insensitive, so the authorized domains and the origin are ASCII case-folded
+/// before matching.
+///
+/// The origin can be either a bare domain, optionally with a port (e.g., `example.com:3000`), or a
+/// full origin URL as sent by browsers (e.g., `https://example.com:8443`). The authorized domains
+/// can optionally specify a port (e.g., `example.com:8443`). An authorized domain without a port
+/// matches any port.
 ///
 /// If the authorized domains set is empty, all domains are considered authorized.
 pub fn is_domain_authorized(authorized: &[&str], origin: &str) -> bool {
-    fn match_domain(pattern: &str, origin: &str) -> bool {
-        if pattern.starts_with('*') {
-            origin.ends_with(pattern.trim_start_matches('*'))
-        } else {
-            origin == pattern
+    let (origin_host, origin_port) = parse_origin(&origin.to_ascii_lowercase());
+    authorized.is_empty()
+        || authorized.iter().any(|pattern| {
+            let pattern = pattern.to_ascii_lowercase();
+            let (pattern_host, pattern_port) = split_host_port(&pattern);
+            (pattern_port.is_none() || pattern_port == origin_port)
+                && match_wildcard_domain(pattern_host, &origin_host)
+        })
+}
+
+/// Extract the host and port from the origin.
+///
+/// If the origin is a URL, e.g., `https://example.com`, the port defaults to the scheme's known
+/// default port. Otherwise, the origin is split into its host and optional port.
+fn parse_origin(origin: &str) -> (String, Option<u16>) {
+    if origin.contains("://") {
+        if let Some((host, port)) = Url::parse(origin)
+            .ok()
+            .and_then(|url| Some((url.host_str()?.to_string(), url.port_or_known_default())))
+        {
+            return (host, port);
         }
     }
+    let (host, port) = split_host_port(origin);
+    (host.to_string(), port)
+}
 
-    authorized.is_empty()
-        || authorized
+/// Split the domain into its host and optional port, e.g., `example.com:3000`.
+fn split_host_port(domain: &str) -> (&str, Option<u16>) {
+    match domain.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (domain, None),
+        },
+        None => (domain, None),
+    }
+}
+
+/// Match the origin domain against the authorized domain pattern, respecting the domain labels:
+///
+/// - `*`: any domain matches.
+/// - `*.example.com`: one or more subdomain labels of `example.com` match, e.g., `a.example.com`
+///   and `a.b.example.com`, but never the bare `example.com`, a non-subdomain like
+///   `notexample.com`, or a domain with empty labels like `.example.com`.
+/// - `*-foo.example.com`: the wildcard matches within the first label only, e.g.,
+///   `bar-foo.example.com`, but not `a.bar-foo.example.com`.
+/// - Otherwise, the domain must match exactly.
+fn match_wildcard_domain(pattern: &str, origin: &str) -> bool {
+    let suffix = match pattern.strip_prefix('*') {
+        Some("") => return true,
+        Some(suffix) => suffix,
+        None => return origin == pattern,
+    };
+    let Some(prefix) = origin.strip_suffix(suffix) else {
+        return false;
+    };
+
+    if suffix.starts_with('.') {
+        // Whole subdomain labels
+        !prefix.is_empty() && prefix.split('.').all(|label| !label.is_empty())
+    } else {
+        // Part of the first label
+        !prefix.contains('.')
+    }
+}
+
+/// Check if the query origin domain is denied.
+///
+/// The denied domains follow the [`is_domain_authorized`] matching semantics, i.e., wildcards,
+/// case-insensitivity, and ports. If the denied domains set is empty, no domain is considered
+/// denied.
+pub fn is_domain_denied(denied: &[&str], origin: &str) -> bool {
+    !denied.is_empty() && is_domain_authorized(denied, origin)
+}
+
+/// Check if the query origin domain is authorized, applying the given policy to the requests with
+/// no origin, e.g., server-to-server requests.
+///
+/// A `None` origin denotes a missing `Origin` header. If the authorized domains set is not empty,
+/// requests with no origin are authorized only if `allow_missing_origin` is set. Otherwise, the
+/// [`is_domain_authorized`] semantics apply, e.g., an explicit `null` origin (sent by sandboxed
+/// iframes and `file:` pages) must be authorized like any other origin.
+pub fn is_origin_authorized(
+    authorized: &[&str],
+    origin: Option<&str>,
+    allow_missing_origin: bool,
+) -> bool {
+    match origin {
+        None => authorized.is_empty() || allow_missing_origin,
+        Some(origin) => is_domain_authorized(authorized, origin),
+    }
+}
+
+/// Check if the query origin domain is authorized by any of the layered domain lists, e.g., an
+/// organization-level and a project-level allow-list.
+///
+/// Each layer is checked with the [`is_domain_authorized`] semantics, so an empty layer authorizes
+/// all domains. If there are no layers, all domains are considered authorized.
+pub fn is_domain_authorized_layered(layers: &[&[&str]], origin: &str) -> bool {
+    layers.is_empty()
+        || layers
             .iter()
-            .any(|pattern| match_domain(pattern, origin))
+            .any(|authorized| is_domain_authorized(authorized, origin))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::is_domain_authorized;
+    use thegraph_core::types::{DeploymentId, SubgraphId};
+
+    use super::{
+        is_deployment_authorized_via_subgraphs, is_deployment_denied, is_domain_authorized,
+        is_domain_authorized_layered, is_domain_denied, is_origin_authorized, is_subgraph_denied,
+    };
 
     #[test]
     fn authorized_domains() {
@@ -92,10 +227,22 @@ mod tests {
             ("e", false),
             ("d.e", false),
             ("z.d.e", true),
+            ("a.z.d.e", true),
+            (".d.e", false),
+            ("a..d.e", false),
+            ("zd.e", false),
             ("-foo.vercel.app", true),
             ("foo.vercel.app", false),
             ("bar-foo.vercel.app", true),
             ("bar.foo.vercel.app", false),
+            ("a.bar-foo.vercel.app", false),
+            ("evilexample.com", false),
+            ("evil-example.com", false),
+            ("Example.COM", true),
+            ("LocalHost", true),
+            ("A.Z.D.E", true),
+            ("Bar-Foo.Vercel.App", true),
+            ("D.E", false),
         ];
 
         for (input, expected) in sub_cases {
@@ -140,4 +287,283 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn layered_authorized_domains() {
+        let org_domains: &[&str] = &["example.com", "*.example.com"];
+        let project_domains: &[&str] = &["localhost", "*-foo.vercel.app"];
+        let layers = [org_domains, project_domains];
+
+        let sub_cases = [
+            ("example.com", true),
+            ("app.example.com", true),
+            ("localhost", true),
+            ("bar-foo.vercel.app", true),
+            ("badhost", false),
+            ("foo.vercel.app", false),
+        ];
+
+        for (input, expected) in sub_cases {
+            assert_eq!(
+                expected,
+                is_domain_authorized_layered(&layers, input),
+                "match '{input}'"
+            );
+        }
+
+        // The origin matches only the second layer
+        assert!(!is_domain_authorized(org_domains, "localhost"));
+        assert!(is_domain_authorized_layered(&layers, "localhost"));
+    }
+
+    #[test]
+    fn layered_authorized_domains_with_an_empty_layer() {
+        let org_domains: &[&str] = &["example.com"];
+        let project_domains: &[&str] = &[];
+
+        assert!(is_domain_authorized_layered(
+            &[org_domains, project_domains],
+            "badhost"
+        ));
+        assert!(is_domain_authorized_layered(&[], "badhost"));
+    }
+
+    #[test]
+    fn missing_origin_with_a_non_empty_authorized_domains_set() {
+        let authorized_domains = ["example.com", "*.example.com"];
+
+        // Requests with no origin are authorized only if the policy allows them
+        assert!(is_origin_authorized(&authorized_domains, None, true));
+        assert!(!is_origin_authorized(&authorized_domains, None, false));
+
+        // Requests with an origin are not affected by the policy
+        assert!(is_origin_authorized(
+            &authorized_domains,
+            Some("app.example.com"),
+            false
+        ));
+        assert!(!is_origin_authorized(
+            &authorized_domains,
+            Some("badhost"),
+            true
+        ));
+    }
+
+    #[test]
+    fn null_origin_is_not_handled_as_a_missing_origin() {
+        let authorized_domains = ["example.com", "*.example.com"];
+
+        // An explicit `null` origin is not affected by the missing origin policy
+        assert!(!is_origin_authorized(
+            &authorized_domains,
+            Some("null"),
+            true
+        ));
+        assert!(is_origin_authorized(&[], Some("null"), false));
+    }
+
+    #[test]
+    fn missing_origin_with_an_empty_authorized_domains_set() {
+        assert!(is_origin_authorized(&[], None, true));
+        assert!(is_origin_authorized(&[], None, false));
+    }
+
+    #[test]
+    fn wildcard_subdomain_pattern_matches_subdomain_labels_only() {
+        let authorized_domains = ["*.example.com"];
+
+        let sub_cases = [
+            ("example.com", false),
+            ("notexample.com", false),
+            ("evil-example.com", false),
+            (".example.com", false),
+            ("a.example.com", true),
+            ("a.z.example.com", true),
+            ("a.example.com.evil.com", false),
+        ];
+
+        for (input, expected) in sub_cases {
+            assert_eq!(
+                expected,
+                is_domain_authorized(&authorized_domains, input),
+                "match '{input}'"
+            );
+        }
+
+        // A bare wildcard matches any domain
+        assert!(is_domain_authorized(&["*"], "a.z.example.com"));
+    }
+
+    #[test]
+    fn origin_urls_and_ports() {
+        let authorized_domains = ["example.com", "*.example.org:8443"];
+
+        let sub_cases = [
+            ("https://example.com:3000", true),
+            ("https://example.com", true),
+            ("http://example.com", true),
+            ("example.com:3000", true),
+            ("https://evil.com", false),
+            ("https://app.example.org:8443", true),
+            ("app.example.org:8443", true),
+            ("https://app.example.org:3000", false),
+            ("https://app.example.org", false),
+            ("app.example.org", false),
+        ];
+
+        for (input, expected) in sub_cases {
+            assert_eq!(
+                expected,
+                is_domain_authorized(&authorized_domains, input),
+                "match '{input}'"
+            );
+        }
+
+        // The scheme's known default port matches an explicit-port pattern
+        assert!(is_domain_authorized(
+            &["example.com:443"],
+            "https://example.com"
+        ));
+        assert!(!is_domain_authorized(
+            &["example.com:443"],
+            "http://example.com"
+        ));
+    }
+
+    #[test]
+    fn mixed_case_authorized_domains() {
+        let authorized_domains = ["Example.com", "*.App.Example.ORG", "*-Foo.Vercel.app"];
+
+        let sub_cases = [
+            ("example.com", true),
+            ("EXAMPLE.COM", true),
+            ("a.app.example.org", true),
+            ("A.APP.EXAMPLE.ORG", true),
+            ("app.example.org", false),
+            ("bar-foo.vercel.app", true),
+            ("BAR-FOO.VERCEL.APP", true),
+            ("bar.foo.vercel.app", false),
+        ];
+
+        for (input, expected) in sub_cases {
+            assert_eq!(
+                expected,
+                is_domain_authorized(&authorized_domains, input),
+                "match '{input}'"
+            );
+        }
+    }
+
+    #[test]
+    fn deployment_authorized_via_subgraphs() {
+        let authorized: SubgraphId = "184ba627DB853244c9f17f3Cb4378cB8B39bf147".parse().unwrap();
+        let unauthorized: SubgraphId = "DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp"
+            .parse()
+            .unwrap();
+        let authorized_deployment: DeploymentId = "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+            .parse()
+            .unwrap();
+        let unauthorized_deployment: DeploymentId =
+            "QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3"
+                .parse()
+                .unwrap();
+        let unknown_deployment: DeploymentId = "QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw"
+            .parse()
+            .unwrap();
+
+        // A fake resolver: the authorized deployment belongs to both subgraphs
+        let resolver = |deployment: &DeploymentId| {
+            if *deployment == authorized_deployment {
+                vec![unauthorized, authorized]
+            } else if *deployment == unauthorized_deployment {
+                vec![unauthorized]
+            } else {
+                vec![]
+            }
+        };
+
+        assert!(is_deployment_authorized_via_subgraphs(
+            &[authorized],
+            &authorized_deployment,
+            resolver
+        ));
+        assert!(!is_deployment_authorized_via_subgraphs(
+            &[authorized],
+            &unauthorized_deployment,
+            resolver
+        ));
+        assert!(!is_deployment_authorized_via_subgraphs(
+            &[authorized],
+            &unknown_deployment,
+            resolver
+        ));
+
+        // An empty authorized set authorizes any deployment
+        assert!(is_deployment_authorized_via_subgraphs(
+            &[],
+            &unauthorized_deployment,
+            resolver
+        ));
+        assert!(is_deployment_authorized_via_subgraphs(
+            &[],
+            &unknown_deployment,
+            resolver
+        ));
+    }
+
+    #[test]
+    fn denied_subgraphs_and_deployments() {
+        let denied_subgraph: SubgraphId =
+            "184ba627DB853244c9f17f3Cb4378cB8B39bf147".parse().unwrap();
+        let allowed_subgraph: SubgraphId = "DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp"
+            .parse()
+            .unwrap();
+        let denied_deployment: DeploymentId = "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+            .parse()
+            .unwrap();
+        let allowed_deployment: DeploymentId = "QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3"
+            .parse()
+            .unwrap();
+
+        // An empty deny-list denies nothing
+        assert!(!is_subgraph_denied(&[], &denied_subgraph));
+        assert!(!is_deployment_denied(&[], &denied_deployment));
+
+        assert!(is_subgraph_denied(&[denied_subgraph], &denied_subgraph));
+        assert!(!is_subgraph_denied(&[denied_subgraph], &allowed_subgraph));
+        assert!(is_deployment_denied(
+            &[denied_deployment],
+            &denied_deployment
+        ));
+        assert!(!is_deployment_denied(
+            &[denied_deployment],
+            &allowed_deployment
+        ));
+    }
+
+    #[test]
+    fn denied_domains() {
+        let denied_domains = ["abuse.example.com", "*.spam.org"];
+
+        let sub_cases = [
+            ("abuse.example.com", true),
+            ("https://Abuse.Example.com:3000", true),
+            ("a.spam.org", true),
+            ("example.com", false),
+            ("spam.org", false),
+            ("", false),
+        ];
+
+        for (input, expected) in sub_cases {
+            assert_eq!(
+                expected,
+                is_domain_denied(&denied_domains, input),
+                "match '{input}'"
+            );
+        }
+
+        // An empty deny-list denies nothing
+        assert!(!is_domain_denied(&[], "abuse.example.com"));
+        assert!(!is_domain_denied(&[], ""));
+    }
 }
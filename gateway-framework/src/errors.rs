@@ -67,4 +67,7 @@ pub enum UnavailableReason {
     /// The indexer did not have a block required by the query.
     #[error("missing block")]
     MissingBlock,
+    /// The indexer is being drained, it is not sent new queries.
+    #[error("drained")]
+    Drained,
 }
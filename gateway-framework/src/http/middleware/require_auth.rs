@@ -153,11 +153,17 @@ where
             ),
         };
 
-        // Check if the request origin domain is authorized
-        let origin = req.headers().typed_get::<Origin>().unwrap_or(Origin::NULL);
-        tracing::debug!(domain = %origin.hostname());
-
-        if !auth_token.is_domain_authorized(origin.hostname()) {
+        // Check if the request origin domain is authorized. The full serialized origin, e.g.,
+        // `https://example.com:8443`, is checked so port-qualified authorized domains can match.
+        // An explicit `null` origin is checked as is, only a missing `Origin` header is subject to
+        // the missing origin policy.
+        let origin = req
+            .headers()
+            .typed_get::<Origin>()
+            .map(|origin| origin.to_string());
+        tracing::debug!(?origin);
+
+        if !auth_token.is_domain_authorized(origin.as_deref(), self.ctx.allow_missing_origin) {
             // If the request origin domain is not allowed, return an error response
             return ResponseFuture::error(graphql::error_response(Error::Auth(anyhow::anyhow!(
                 "domain not authorized by user"
@@ -230,6 +236,7 @@ mod tests {
     fn test_auth_ctx(key: Option<&str>) -> AuthContext {
         let mut ctx = AuthContext {
             payment_required: false,
+            allow_missing_origin: false,
             api_keys: watch::channel(Default::default()).1,
             special_api_keys: Default::default(),
             special_query_key_signers: Default::default(),
@@ -251,6 +258,30 @@ mod tests {
         ctx
     }
 
+    /// Create a test auth context with an API key authorized for the given domains.
+    fn test_auth_ctx_with_domains(key: &str, domains: &[&str]) -> AuthContext {
+        let mut ctx = test_auth_ctx(None);
+        ctx.api_keys = watch::channel(HashMap::from([(
+            key.into(),
+            Arc::new(api_keys::APIKey {
+                key: key.into(),
+                max_budget_usd: Some(NotNan::new(1e3).unwrap()),
+                domains: domains.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            }),
+        )]))
+        .1;
+        ctx
+    }
+
+    /// Create a test request with an `Authorization` header and the given `Origin` header.
+    fn test_req_with_origin(token: &str, origin: &'static str) -> http::Request<()> {
+        let mut req = test_req_with_auth_header(token);
+        req.headers_mut()
+            .insert(http::header::ORIGIN, http::HeaderValue::from_static(origin));
+        req
+    }
+
     /// Create a test request without an `Authorization` header or `AuthToken` extension.
     fn test_req_unauthenticated() -> http::Request<()> {
         http::Request::builder().body(()).unwrap()
@@ -525,4 +556,128 @@ mod tests {
         });
         assert_matches!(r.extensions().get::<QuerySettings>(), Some(_));
     }
+
+    /// A port-qualified authorized domain should authorize the requests from the same origin port.
+    #[tokio::test]
+    async fn port_qualified_domain_authorizes_the_origin_port() {
+        //* Given
+        let api_key = "0123456789abcdef0123456789abcdef";
+        let auth_ctx = test_auth_ctx_with_domains(api_key, &["example.com:8443"]);
+
+        let (mut svc, mut handle) =
+            tower_test::mock::spawn_layer(RequireAuthorizationLayer::new(auth_ctx));
+
+        let req = test_req_with_origin(api_key, "https://example.com:8443");
+
+        //* When
+        // The service must be ready before calling it
+        handle.allow(1);
+        assert_ready_ok!(svc.poll_ready());
+
+        // Call the wrapped service
+        svc.call(req);
+
+        let (r, _) = handle
+            .next_request()
+            .await
+            .expect("service received a request");
+
+        //* Then
+        assert_matches!(
+            r.extensions().get::<AuthToken>(),
+            Some(AuthToken::ApiKey(_))
+        );
+    }
+
+    /// A port-qualified authorized domain should reject the requests from other origin ports.
+    #[tokio::test]
+    async fn port_qualified_domain_rejects_other_origin_ports() {
+        //* Given
+        let api_key = "0123456789abcdef0123456789abcdef";
+        let auth_ctx = test_auth_ctx_with_domains(api_key, &["example.com:8443"]);
+
+        let (mut svc, mut handle) =
+            tower_test::mock::spawn_layer(RequireAuthorizationLayer::new(auth_ctx));
+
+        let req = test_req_with_origin(api_key, "https://example.com");
+
+        //* When
+        // The service must be ready before calling it
+        handle.allow(1);
+        assert_ready_ok!(svc.poll_ready());
+
+        // Call the wrapped service and await the response
+        let res = svc.call(req).await;
+
+        //* Then
+        assert_matches!(res, Ok(mut res) => {
+            assert_matches!(deserialize_graphql_response_body::<()>(res.body_mut()).await, Ok(res_body) => {
+                assert_eq!(res_body.errors.len(), 1);
+                assert_eq!(res_body.errors[0].message, "auth error: domain not authorized by user");
+            });
+        });
+    }
+
+    /// An explicit `null` origin should not be handled as a missing origin.
+    #[tokio::test]
+    async fn null_origin_is_rejected_when_missing_origins_are_allowed() {
+        //* Given
+        let api_key = "0123456789abcdef0123456789abcdef";
+        let mut auth_ctx = test_auth_ctx_with_domains(api_key, &["example.com"]);
+        auth_ctx.allow_missing_origin = true;
+
+        let (mut svc, mut handle) =
+            tower_test::mock::spawn_layer(RequireAuthorizationLayer::new(auth_ctx));
+
+        let req = test_req_with_origin(api_key, "null");
+
+        //* When
+        // The service must be ready before calling it
+        handle.allow(1);
+        assert_ready_ok!(svc.poll_ready());
+
+        // Call the wrapped service and await the response
+        let res = svc.call(req).await;
+
+        //* Then
+        assert_matches!(res, Ok(mut res) => {
+            assert_matches!(deserialize_graphql_response_body::<()>(res.body_mut()).await, Ok(res_body) => {
+                assert_eq!(res_body.errors.len(), 1);
+                assert_eq!(res_body.errors[0].message, "auth error: domain not authorized by user");
+            });
+        });
+    }
+
+    /// A missing `Origin` header should be authorized when missing origins are allowed.
+    #[tokio::test]
+    async fn missing_origin_is_authorized_when_missing_origins_are_allowed() {
+        //* Given
+        let api_key = "0123456789abcdef0123456789abcdef";
+        let mut auth_ctx = test_auth_ctx_with_domains(api_key, &["example.com"]);
+        auth_ctx.allow_missing_origin = true;
+
+        let (mut svc, mut handle) =
+            tower_test::mock::spawn_layer(RequireAuthorizationLayer::new(auth_ctx));
+
+        let req = test_req_with_auth_header(api_key);
+
+        //* When
+        // The service must be ready before calling it
+        handle.allow(1);
+        assert_ready_ok!(svc.poll_ready());
+
+        // Call the wrapped service
+        svc.call(req);
+
+        let (r, _) = handle
+            .next_request()
+            .await
+            .expect("service received a request");
+
+        //* Then
+        assert_matches!(
+            r.extensions().get::<AuthToken>(),
+            Some(AuthToken::ApiKey(_))
+        );
+    }
 }
@@ -1,14 +1,21 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
 
-use alloy_primitives::{Address, BlockNumber};
-use eventuals::{self, Eventual, EventualExt as _, EventualWriter, Ptr};
-use serde::Deserialize;
+use alloy_primitives::{Address, BlockNumber, B256};
+use eventuals::{Eventual, EventualWriter, Ptr};
+use gateway_common::file_reloader;
+use itertools::Itertools as _;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
 use serde_with::serde_as;
-use thegraph_core::{
-    client as subgraph_client,
-    types::{DeploymentId, SubgraphId},
-};
-use tokio::sync::Mutex;
+use thegraph_core::types::{DeploymentId, SubgraphId};
+use thegraph_graphql_http::http::response::ResponseBody;
+use url::Url;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,6 +23,23 @@ pub struct Subgraph {
     pub id: SubgraphId,
     pub id_on_l2: Option<SubgraphId>,
     pub versions: Vec<SubgraphVersion>,
+    /// All the subgraph versions point to deployments transferred to L2.
+    ///
+    /// Not part of the network subgraph response, it is derived from the versions once the
+    /// subgraphs are fetched.
+    #[serde(skip)]
+    pub fully_transferred: bool,
+}
+
+impl Subgraph {
+    /// Check if all the subgraph versions point to deployments transferred to L2.
+    fn is_fully_transferred(&self) -> bool {
+        !self.versions.is_empty()
+            && self
+                .versions
+                .iter()
+                .all(|version| version.subgraph_deployment.transferred_to_l2)
+    }
 }
 
 #[serde_as]
@@ -25,11 +49,17 @@ pub struct Manifest {
     pub network: Option<String>,
     #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
     pub start_block: Option<BlockNumber>,
+    /// The subgraph features declared by the deployment manifest, e.g., `fullTextSearch`,
+    /// `grafting` or `nonFatalErrors`. Empty if the manifest omits them.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnNull")]
+    pub features: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubgraphVersion {
+    pub version: u32,
     pub subgraph_deployment: SubgraphDeployment,
 }
 
@@ -46,7 +76,7 @@ pub struct SubgraphDeployment {
 }
 
 #[serde_as]
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Allocation {
     pub id: Address,
@@ -56,53 +86,269 @@ pub struct Allocation {
 }
 
 #[serde_as]
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Indexer {
     pub id: Address,
     pub url: Option<String>,
-    #[serde_as(as = "serde_with::DisplayFromStr")]
-    pub staked_tokens: u128,
+    /// The indexer's staked tokens. `None` if the value is malformed.
+    #[serde_as(as = "serde_with::DefaultOnError<Option<serde_with::DisplayFromStr>>")]
+    pub staked_tokens: Option<u128>,
+}
+
+/// The network subgraph auth token.
+///
+/// The token is shared between the [`Client`] and its owner, so it can be rotated at runtime. An
+/// in-flight poll may use the old token, the next poll will use the new one.
+#[derive(Clone, Debug, Default)]
+pub struct AuthToken(Arc<RwLock<Option<String>>>);
+
+impl AuthToken {
+    pub fn new(token: Option<String>) -> Self {
+        Self(Arc::new(RwLock::new(token)))
+    }
+
+    /// Replace the auth token used by subsequent network subgraph requests.
+    pub fn set(&self, token: Option<String>) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = token;
+    }
+
+    pub fn get(&self) -> Option<String> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Create a new [`AuthToken`] read from the given file, e.g., a mounted secret.
+    ///
+    /// The file holds the token, surrounding whitespace is trimmed. If the file is missing or
+    /// empty, no token is used.
+    pub fn from_file(path: &Path) -> Self {
+        let token = Self::default();
+        reload_from_file(&token.0, path);
+        token
+    }
+
+    /// Spawn a background task reloading the token whenever the given file changes, checking the
+    /// file modification time every `interval`.
+    ///
+    /// The task stops once the `shutdown` future completes, or all the token handles are dropped.
+    pub fn spawn_file_reloader(
+        &self,
+        path: PathBuf,
+        interval: Duration,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) {
+        let token = Arc::downgrade(&self.0);
+
+        file_reloader::spawn_file_reloader(path, interval, shutdown, move |path| {
+            // If all the token handles were dropped, stop reloading
+            let Some(token) = token.upgrade() else {
+                return false;
+            };
+            reload_from_file(&token, path);
+            true
+        });
+    }
 }
 
+/// Reload the auth token from the file. See [`AuthToken::from_file`].
+///
+/// If the file cannot be read, a warning is logged and the current token is kept.
+fn reload_from_file(token: &RwLock<Option<String>>, path: &Path) {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        // A missing file means no token
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            tracing::warn!(path = %path.display(), error = %err, "failed to read network subgraph auth token file");
+            return;
+        }
+    };
+
+    let contents = contents.trim();
+    tracing::debug!(path = %path.display(), "network subgraph auth token reloaded");
+    *token.write().unwrap_or_else(PoisonError::into_inner) =
+        (!contents.is_empty()).then(|| contents.to_string());
+}
+
+/// The default maximum size of a network subgraph response page body, 64 MiB.
+pub const DEFAULT_MAX_RESPONSE_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// The default number of subgraphs per network subgraph query page.
+pub const DEFAULT_PAGE_SIZE: usize = 200;
+
+/// The default maximum number of active allocations fetched per subgraph deployment along with the
+/// subgraphs.
+///
+/// The allocations of the deployments reaching this limit are fetched in a separate paginated
+/// query, see [`Client::create`].
+pub const DEFAULT_ALLOCATIONS_LIMIT: usize = 100;
+
+/// The default network subgraph polling interval.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The default maximum network subgraph polling interval, after consecutive poll failures.
+pub const DEFAULT_MAX_POLL_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
 pub struct Client {
-    subgraph_client: subgraph_client::Client,
+    http_client: reqwest::Client,
+    subgraph_url: Url,
+    auth_token: AuthToken,
+    /// The maximum size of a response page body. Polls receiving larger bodies are aborted.
+    max_response_body_size: usize,
+    /// The number of subgraphs per query page.
+    page_size: usize,
+    /// The maximum number of active allocations fetched per subgraph deployment.
+    allocations_limit: usize,
+    /// The base polling interval.
+    poll_interval: Duration,
+    /// The maximum polling interval, after consecutive poll failures.
+    max_poll_interval: Duration,
+    /// The latest block number the network subgraph responded with. Used to avoid going back in
+    /// time when switching between network subgraph indexers.
+    latest_block: Option<BlockNumber>,
     subgraphs: EventualWriter<Ptr<Vec<Subgraph>>>,
+    /// The network subgraph block number the latest successful poll was served at.
+    subgraphs_block: EventualWriter<BlockNumber>,
     // TODO: remove when L2 subgraph transfer support is on mainnet network subgraphs
     l2_transfer_support: bool,
+    /// The number of consecutive failed polls. Used to back off the polling interval during
+    /// network subgraph outages.
+    consecutive_poll_failures: u32,
 }
 
 impl Client {
+    /// Create the network subgraph client and spawn the subgraphs polling task.
+    ///
+    /// Returns the polled subgraphs and, in parallel, the network subgraph block number each
+    /// successful poll was served at. The block allows correlating the network topology with the
+    /// on-chain state, e.g., when debugging reorg-related topology flaps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `page_size` or the `allocations_limit` is zero, or if the `max_poll_interval`
+    /// is shorter than the `poll_interval`.
+    ///
+    /// The deployments reaching the `allocations_limit` may have more active allocations than
+    /// fetched along with the subgraphs. Their remaining allocations are fetched in a separate
+    /// query, paginated by allocation ID.
+    #[allow(clippy::too_many_arguments)]
     pub async fn create(
-        subgraph_client: subgraph_client::Client,
+        http_client: reqwest::Client,
+        subgraph_url: Url,
+        auth_token: AuthToken,
+        max_response_body_size: usize,
+        page_size: usize,
+        allocations_limit: usize,
+        poll_interval: Duration,
+        max_poll_interval: Duration,
         l2_transfer_support: bool,
-    ) -> Eventual<Ptr<Vec<Subgraph>>> {
+    ) -> (Eventual<Ptr<Vec<Subgraph>>>, Eventual<BlockNumber>) {
         let (subgraphs_tx, subgraphs_rx) = Eventual::new();
-        let client = Arc::new(Mutex::new(Client {
-            subgraph_client,
-            subgraphs: subgraphs_tx,
+        let (subgraphs_block_tx, subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            http_client,
+            subgraph_url,
+            auth_token,
+            max_response_body_size,
+            page_size,
+            allocations_limit,
+            poll_interval,
+            max_poll_interval,
+            subgraphs_tx,
+            subgraphs_block_tx,
             l2_transfer_support,
-        }));
+        );
 
         // 4e072dfe-5cb3-4f86-80f6-b64afeb9dcb2
-        eventuals::timer(Duration::from_secs(30))
-            .pipe_async(move |_| {
-                let client = client.clone();
-                async move {
-                    let mut client = client.lock().await;
-                    if let Err(poll_subgraphs_err) = client.poll_subgraphs().await {
-                        tracing::error!(%poll_subgraphs_err);
-                    }
-                }
-            })
-            .forever();
+        tokio::spawn(async move {
+            loop {
+                client.poll().await;
+                tokio::time::sleep(client.poll_delay()).await;
+            }
+        });
 
-        subgraphs_rx
+        (subgraphs_rx, subgraphs_block_rx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        http_client: reqwest::Client,
+        subgraph_url: Url,
+        auth_token: AuthToken,
+        max_response_body_size: usize,
+        page_size: usize,
+        allocations_limit: usize,
+        poll_interval: Duration,
+        max_poll_interval: Duration,
+        subgraphs: EventualWriter<Ptr<Vec<Subgraph>>>,
+        subgraphs_block: EventualWriter<BlockNumber>,
+        l2_transfer_support: bool,
+    ) -> Self {
+        assert!(page_size > 0, "network subgraph page size must be non-zero");
+        assert!(
+            allocations_limit > 0,
+            "network subgraph allocations limit must be non-zero"
+        );
+        assert!(
+            max_poll_interval >= poll_interval,
+            "network subgraph max poll interval must not be shorter than the poll interval"
+        );
+
+        Self {
+            http_client,
+            subgraph_url,
+            auth_token,
+            max_response_body_size,
+            page_size,
+            allocations_limit,
+            poll_interval,
+            max_poll_interval,
+            latest_block: None,
+            subgraphs,
+            subgraphs_block,
+            l2_transfer_support,
+            consecutive_poll_failures: 0,
+        }
+    }
+
+    /// Poll the network subgraph, keeping track of the consecutive failures.
+    ///
+    /// On failure, no update is written, and the error is logged. Discarded empty updates count as
+    /// failures.
+    async fn poll(&mut self) {
+        match self.poll_subgraphs().await {
+            Ok(()) => self.consecutive_poll_failures = 0,
+            Err(poll_subgraphs_err) => {
+                self.consecutive_poll_failures = self.consecutive_poll_failures.saturating_add(1);
+                tracing::error!(%poll_subgraphs_err, failures = self.consecutive_poll_failures);
+            }
+        }
+    }
+
+    /// The delay until the next poll.
+    ///
+    /// The delay is the base polling interval, doubled for each consecutive failed poll, and capped
+    /// at the maximum polling interval. It is reset to the base interval on the first successful
+    /// poll.
+    fn poll_delay(&self) -> Duration {
+        let backoff = 2_u32.saturating_pow(self.consecutive_poll_failures);
+        self.poll_interval
+            .saturating_mul(backoff)
+            .min(self.max_poll_interval)
+    }
+
+    /// Replace the auth token used by the next network subgraph poll.
+    pub fn set_auth_token(&self, token: Option<String>) {
+        self.auth_token.set(token);
     }
 
     #[allow(clippy::obfuscated_if_else)]
     async fn poll_subgraphs(&mut self) -> Result<(), String> {
         // last allocation is latest by indexing: 9936786a-e286-45f3-9190-8409d8389e88
+        let allocations_limit = self.allocations_limit;
         let query = format!(
             r#"
             subgraphs(
@@ -118,14 +364,16 @@ impl Client {
                 id
                 {}
                 versions(orderBy: version, orderDirection: asc) {{
+                    version
                     subgraphDeployment {{
                         ipfsHash
                         manifest {{
                             network
                             startBlock
+                            features
                         }}
                         indexerAllocations(
-                            first: 100
+                            first: {allocations_limit}
                             orderBy: createdAt, orderDirection: asc
                             where: {{ status: Active }}
                         ) {{
@@ -151,16 +399,1035 @@ impl Client {
                 .unwrap_or(""),
         );
 
-        let subgraphs = self
-            .subgraph_client
-            .paginated_query::<Subgraph>(query, 200)
+        let mut block_hash = None;
+        let mut subgraphs = self
+            .paginated_query::<Subgraph>(&query, self.page_size, &mut block_hash)
             .await?;
 
         if subgraphs.is_empty() {
             return Err("Discarding empty update (subgraph_deployments)".to_string());
         }
 
+        let truncated = truncated_deployments(&subgraphs, allocations_limit);
+        if !truncated.is_empty() {
+            match self
+                .fetch_deployments_allocations(&truncated, &mut block_hash)
+                .await
+            {
+                Ok(allocations) => merge_allocations(&mut subgraphs, allocations),
+                Err(err) => warn_truncated_allocations(&subgraphs, &truncated, &err),
+            }
+        }
+
+        // Annotate the fully transferred subgraphs, so downstream code need not recompute it
+        for subgraph in &mut subgraphs {
+            subgraph.fully_transferred = subgraph.is_fully_transferred();
+        }
+
         self.subgraphs.write(Ptr::new(subgraphs));
+
+        // All the pages were served at the latest block, see `paginated_query`
+        if let Some(block) = self.latest_block {
+            self.subgraphs_block.write(block);
+        }
         Ok(())
     }
+
+    /// Fetch all the active allocations of the given deployments, paginating by allocation ID.
+    ///
+    /// The allocations are fetched at the given block, i.e., the block the subgraphs were fetched
+    /// at. See [`Client::paginated_query`].
+    async fn fetch_deployments_allocations(
+        &mut self,
+        deployments: &[DeploymentId],
+        block_hash: &mut Option<B256>,
+    ) -> Result<Vec<DeploymentAllocation>, String> {
+        let deployments = serde_json::to_string(
+            &deployments
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+        )
+        .map_err(|err| err.to_string())?;
+        let query = format!(
+            r#"
+            allocations(
+                block: $block
+                orderBy: id, orderDirection: asc
+                first: $first
+                where: {{
+                    id_gt: $last
+                    status: Active
+                    subgraphDeployment_: {{ ipfsHash_in: {deployments} }}
+                }}
+            ) {{
+                id
+                allocatedTokens
+                createdAt
+                indexer {{
+                    id
+                    url
+                    stakedTokens
+                }}
+                subgraphDeployment {{
+                    ipfsHash
+                }}
+            }}
+        "#
+        );
+
+        self.paginated_query(&query, self.page_size, block_hash)
+            .await
+    }
+
+    /// Perform a paginated query against the network subgraph.
+    ///
+    /// All pages are queried at the same block. If no `block_hash` is given, it is set to the block
+    /// of the first page, so subsequent queries can be made at the same block. The query must
+    /// paginate by `id`, using the `$block`, `$first` and `$last` variables.
+    async fn paginated_query<T: DeserializeOwned>(
+        &mut self,
+        query: &str,
+        page_size: usize,
+        block_hash: &mut Option<B256>,
+    ) -> Result<Vec<T>, String> {
+        let query = format!(
+            r#"
+            query ($block: Block_height!, $first: Int!, $last: String!) {{
+                meta: _meta(block: $block) {{ block {{ number hash }} }}
+                results: {query}
+            }}
+            "#
+        );
+
+        let mut last_id = String::new();
+        let mut results: Vec<serde_json::Value> = Vec::new();
+        loop {
+            let block = match *block_hash {
+                None => json!({ "number_gte": self.latest_block.unwrap_or(0) }),
+                Some(hash) => json!({ "hash": hash }),
+            };
+            let variables = json!({ "block": block, "first": page_size, "last": last_id });
+            let page = self.query_page(&query, variables).await?;
+
+            if block_hash.is_none() {
+                *block_hash = Some(page.meta.block.hash);
+                self.latest_block = Some(page.meta.block.number);
+            }
+
+            let page_len = page.results.len();
+            if let Some(last) = page.results.last() {
+                last_id = last
+                    .get("id")
+                    .and_then(serde_json::Value::as_str)
+                    .ok_or_else(|| "page result missing id".to_string())?
+                    .to_string();
+            }
+            results.extend(page.results);
+
+            if page_len < page_size {
+                break;
+            }
+        }
+
+        results
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<T>, _>>()
+            .map_err(|err| format!("failed to deserialize page result: {err}"))
+    }
+
+    /// Query a single page, enforcing the maximum response body size.
+    async fn query_page(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<PageResponse, String> {
+        let mut request = self
+            .http_client
+            .post(self.subgraph_url.clone())
+            .json(&json!({ "query": query, "variables": variables }));
+        if let Some(auth_token) = self.auth_token.get() {
+            request = request.bearer_auth(auth_token);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| err.to_string())?;
+        let body = read_body_with_limit(response, self.max_response_body_size).await?;
+
+        let response: ResponseBody<PageResponse> =
+            serde_json::from_slice(&body).map_err(|err| err.to_string())?;
+        match response.data {
+            Some(data) if response.errors.is_empty() => Ok(data),
+            _ => Err(response
+                .errors
+                .into_iter()
+                .map(|err| err.message)
+                .collect::<Vec<_>>()
+                .join(", ")),
+        }
+    }
+}
+
+/// Read the response body in chunks, aborting as soon as it exceeds the given maximum size.
+///
+/// The body is never buffered beyond the limit, whether the response announces its length or is
+/// chunk-encoded.
+async fn read_body_with_limit(
+    mut response: reqwest::Response,
+    max_size: usize,
+) -> Result<Vec<u8>, String> {
+    let size_exceeded = || format!("response body exceeds the maximum size of {max_size} bytes");
+    if response.content_length().unwrap_or(0) > max_size as u64 {
+        return Err(size_exceeded());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+        if body.len() + chunk.len() > max_size {
+            return Err(size_exceeded());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// An active allocation, with the deployment it is allocated to. See
+/// [`Client::fetch_deployments_allocations`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeploymentAllocation {
+    #[serde(flatten)]
+    allocation: Allocation,
+    /// The allocation creation timestamp.
+    created_at: u64,
+    subgraph_deployment: DeploymentRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeploymentRef {
+    #[serde(rename = "ipfsHash")]
+    id: DeploymentId,
+}
+
+/// Get the deployments whose allocations were probably truncated, i.e., deployments with as many
+/// active allocations as the allocations limit.
+fn truncated_deployments(subgraphs: &[Subgraph], allocations_limit: usize) -> Vec<DeploymentId> {
+    subgraphs
+        .iter()
+        .flat_map(|subgraph| &subgraph.versions)
+        .map(|version| &version.subgraph_deployment)
+        .filter(|deployment| deployment.allocations.len() >= allocations_limit)
+        .map(|deployment| deployment.id)
+        .unique()
+        .collect()
+}
+
+/// Merge the paginated allocations into the deployments' allocations.
+///
+/// The allocations fetched along with the subgraphs are kept in place, and the missing ones are
+/// appended in creation order, so the allocations stay ordered oldest first:
+/// 9936786a-e286-45f3-9190-8409d8389e88
+fn merge_allocations(subgraphs: &mut [Subgraph], mut allocations: Vec<DeploymentAllocation>) {
+    allocations.sort_by_key(|allocation| allocation.created_at);
+    let mut allocations_by_deployment: HashMap<DeploymentId, Vec<Allocation>> = HashMap::new();
+    for allocation in allocations {
+        allocations_by_deployment
+            .entry(allocation.subgraph_deployment.id)
+            .or_default()
+            .push(allocation.allocation);
+    }
+
+    let deployments = subgraphs
+        .iter_mut()
+        .flat_map(|subgraph| &mut subgraph.versions)
+        .map(|version| &mut version.subgraph_deployment);
+    for deployment in deployments {
+        let Some(allocations) = allocations_by_deployment.get(&deployment.id) else {
+            continue;
+        };
+        let fetched = deployment
+            .allocations
+            .iter()
+            .map(|allocation| allocation.id)
+            .collect::<HashSet<_>>();
+        deployment.allocations.extend(
+            allocations
+                .iter()
+                .filter(|allocation| !fetched.contains(&allocation.id))
+                .cloned(),
+        );
+    }
+}
+
+/// Warn about the deployments whose allocations were probably truncated, if fetching their
+/// remaining allocations failed.
+///
+/// Truncated allocations distort the indexers' allocated tokens and largest allocation, and so the
+/// indexer selection. The indexers whose allocations are missing are unknown, so the warning
+/// names the indexers whose allocations were fetched. A single warning is emitted per deployment.
+fn warn_truncated_allocations(subgraphs: &[Subgraph], truncated: &[DeploymentId], error: &str) {
+    let deployments = subgraphs
+        .iter()
+        .flat_map(|subgraph| &subgraph.versions)
+        .map(|version| &version.subgraph_deployment)
+        .filter(|deployment| truncated.contains(&deployment.id))
+        .unique_by(|deployment| deployment.id);
+    for deployment in deployments {
+        let indexers = deployment
+            .allocations
+            .iter()
+            .map(|allocation| allocation.indexer.id)
+            .unique()
+            .collect::<Vec<_>>();
+        tracing::warn!(
+            deployment = %deployment.id,
+            ?indexers,
+            allocations = deployment.allocations.len(),
+            %error,
+            "failed to fetch the deployment remaining allocations, allocations may be truncated"
+        );
+    }
+}
+
+#[derive(Deserialize)]
+struct PageResponse {
+    meta: PageMeta,
+    results: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct PageMeta {
+    block: PageBlock,
+}
+
+#[derive(Deserialize)]
+struct PageBlock {
+    number: BlockNumber,
+    hash: B256,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fmt::Write as _,
+        io::{BufRead as _, BufReader, Read as _, Write as _},
+        net::TcpListener,
+        sync::{mpsc, Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use alloy_primitives::Address;
+    use eventuals::Eventual;
+    use serde_json::json;
+    use tracing::{field::Field, Event, Level, Subscriber};
+    use tracing_subscriber::{
+        field::Visit,
+        layer::{Context, SubscriberExt as _},
+        Layer,
+    };
+
+    use super::{
+        read_body_with_limit, AuthToken, Client, Indexer, Manifest, DEFAULT_ALLOCATIONS_LIMIT,
+        DEFAULT_MAX_POLL_INTERVAL, DEFAULT_MAX_RESPONSE_BODY_SIZE, DEFAULT_PAGE_SIZE,
+        DEFAULT_POLL_INTERVAL,
+    };
+
+    /// Spawn a mock HTTP server responding with the given body. It reports the `Authorization`
+    /// header and the body of each received request.
+    fn spawn_mock_server(
+        response_body: String,
+    ) -> (url::Url, mpsc::Receiver<(Option<String>, String)>) {
+        spawn_sequenced_mock_server(vec![response_body])
+    }
+
+    /// Spawn a mock HTTP server responding with the given bodies in order, repeating the last one
+    /// once all were served. See [`spawn_mock_server`].
+    fn spawn_sequenced_mock_server(
+        response_bodies: Vec<String>,
+    ) -> (url::Url, mpsc::Receiver<(Option<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let url = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for (idx, stream) in listener.incoming().enumerate() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+
+                let mut authorization = None;
+                let mut content_length = 0;
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("authorization") {
+                            authorization = Some(value.trim().to_string());
+                        }
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                let mut request_body = vec![0; content_length];
+                let _ = reader.read_exact(&mut request_body);
+                let _ = tx.send((
+                    authorization,
+                    String::from_utf8_lossy(&request_body).into_owned(),
+                ));
+
+                let response_body = &response_bodies[idx.min(response_bodies.len() - 1)];
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    response_body.len(),
+                    response_body
+                );
+            }
+        });
+
+        (url, rx)
+    }
+
+    #[test]
+    fn malformed_indexer_stake_is_deserialized_as_none() {
+        //* Given
+        let indexer = |staked_tokens: &str| {
+            json!({
+                "id": "0x0101010101010101010101010101010101010101",
+                "url": "https://indexer.example.com/",
+                "stakedTokens": staked_tokens,
+            })
+        };
+
+        //* When
+        let valid: Indexer = serde_json::from_value(indexer("100")).expect("valid indexer");
+        let malformed: Indexer =
+            serde_json::from_value(indexer("not-a-number")).expect("valid indexer");
+
+        //* Then
+        assert_eq!(valid.staked_tokens, Some(100));
+        assert_eq!(malformed.staked_tokens, None);
+    }
+
+    #[tokio::test]
+    async fn auth_token_change_is_used_by_next_poll() {
+        //* Given
+        let (url, requests) = spawn_mock_server(r#"{"errors":[{"message":"mock"}]}"#.to_string());
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url,
+            AuthToken::new(Some("old-token".to_string())),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+
+        let _ = client.poll_subgraphs().await;
+        let (first_request_auth, _) = requests.recv().expect("request received");
+
+        //* When
+        client.set_auth_token(Some("new-token".to_string()));
+        let _ = client.poll_subgraphs().await;
+        let (second_request_auth, _) = requests.recv().expect("request received");
+
+        //* Then
+        assert_eq!(first_request_auth.as_deref(), Some("Bearer old-token"));
+        assert_eq!(second_request_auth.as_deref(), Some("Bearer new-token"));
+    }
+
+    #[tokio::test]
+    async fn oversized_response_body_is_rejected() {
+        //* Given
+        let oversized_body = format!(r#"{{"errors":[{{"message":"{}"}}]}}"#, "x".repeat(4096));
+        let (url, _requests) = spawn_mock_server(oversized_body);
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url,
+            AuthToken::default(),
+            1024,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+
+        //* When
+        let result = client.poll_subgraphs().await;
+
+        //* Then
+        assert_eq!(
+            result,
+            Err("response body exceeds the maximum size of 1024 bytes".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_chunked_response_body_is_rejected() {
+        //* Given
+        // A server streaming a chunk-encoded body, without announcing its length
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n"
+                );
+                let chunk = "x".repeat(512);
+                for _ in 0..8 {
+                    let _ = write!(stream, "{:x}\r\n{chunk}\r\n", chunk.len());
+                }
+                let _ = write!(stream, "0\r\n\r\n");
+            }
+        });
+        let response = reqwest::Client::new()
+            .get(url)
+            .send()
+            .await
+            .expect("response received");
+
+        //* When
+        let result = read_body_with_limit(response, 1024).await;
+
+        //* Then
+        assert_eq!(
+            result,
+            Err("response body exceeds the maximum size of 1024 bytes".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn fully_transferred_subgraphs_are_annotated() {
+        //* Given
+        let version = |version: u32, deployment: &str, transferred_to_l2: bool| {
+            json!({
+                "version": version,
+                "subgraphDeployment": {
+                    "ipfsHash": deployment,
+                    "indexerAllocations": [],
+                    "manifest": null,
+                    "transferredToL2": transferred_to_l2,
+                },
+            })
+        };
+        let response_body = json!({
+            "data": {
+                "meta": { "block": { "number": 1, "hash": format!("0x{}", "00".repeat(32)) } },
+                "results": [
+                    {
+                        "id": "184ba627DB853244c9f17f3Cb4378cB8B39bf147",
+                        "versions": [
+                            version(0, "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH", true),
+                            version(1, "QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3", true),
+                        ],
+                    },
+                    {
+                        "id": "DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp",
+                        "versions": [
+                            version(0, "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH", true),
+                            version(1, "QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw", false),
+                        ],
+                    },
+                ],
+            },
+        });
+        let (url, _requests) = spawn_mock_server(response_body.to_string());
+        let (subgraphs_tx, subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url,
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            true,
+        );
+
+        //* When
+        let result = client.poll_subgraphs().await;
+
+        //* Then
+        assert_eq!(result, Ok(()));
+        let subgraphs = subgraphs_rx.value().await.expect("subgraphs written");
+        assert_eq!(subgraphs.len(), 2);
+        assert!(subgraphs[0].fully_transferred);
+        assert!(!subgraphs[1].fully_transferred);
+    }
+
+    #[tokio::test]
+    async fn polled_block_is_captured() {
+        //* Given
+        let response_body = json!({
+            "data": {
+                "meta": { "block": { "number": 42, "hash": format!("0x{}", "00".repeat(32)) } },
+                "results": [
+                    {
+                        "id": "184ba627DB853244c9f17f3Cb4378cB8B39bf147",
+                        "versions": [
+                            {
+                                "version": 0,
+                                "subgraphDeployment": {
+                                    "ipfsHash": "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH",
+                                    "indexerAllocations": [],
+                                    "manifest": null,
+                                },
+                            },
+                        ],
+                    },
+                ],
+            },
+        });
+        let (url, _requests) = spawn_mock_server(response_body.to_string());
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url,
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+
+        //* When
+        let result = client.poll_subgraphs().await;
+
+        //* Then
+        assert_eq!(result, Ok(()));
+        let block = subgraphs_block_rx.value().await.expect("block written");
+        assert_eq!(block, 42);
+    }
+
+    #[tokio::test]
+    async fn consecutive_poll_failures_back_off() {
+        //* Given
+        let page = |results: serde_json::Value| {
+            json!({
+                "data": {
+                    "meta": { "block": { "number": 1, "hash": format!("0x{}", "00".repeat(32)) } },
+                    "results": results,
+                },
+            })
+            .to_string()
+        };
+        let poll_interval = Duration::from_secs(10);
+        let max_poll_interval = Duration::from_secs(60);
+
+        // Empty updates are discarded, and count as failures
+        let (empty_url, _requests) = spawn_mock_server(page(json!([])));
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            empty_url,
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            poll_interval,
+            max_poll_interval,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+
+        //* When
+        let mut delays = vec![client.poll_delay()];
+        for _ in 0..3 {
+            client.poll().await;
+            delays.push(client.poll_delay());
+        }
+
+        let (url, _requests) = spawn_mock_server(page(json!([{
+            "id": "184ba627DB853244c9f17f3Cb4378cB8B39bf147",
+            "versions": [],
+        }])));
+        client.subgraph_url = url;
+        client.poll().await;
+        let delay_after_success = client.poll_delay();
+
+        //* Then
+        assert_eq!(delays[0], poll_interval);
+        for (prev, next) in delays.iter().zip(delays.iter().skip(1)) {
+            assert!(next > prev);
+        }
+        assert_eq!(delays[2], poll_interval * 4);
+        // The delay is capped at the maximum polling interval
+        assert_eq!(delays[3], max_poll_interval);
+        assert_eq!(delay_after_success, poll_interval);
+    }
+
+    #[test]
+    #[should_panic(expected = "network subgraph page size must be non-zero")]
+    fn zero_page_size_is_rejected() {
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        Client::new(
+            reqwest::Client::new(),
+            "http://localhost:8000/".parse().unwrap(),
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            0,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "network subgraph allocations limit must be non-zero")]
+    fn zero_allocations_limit_is_rejected() {
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        Client::new(
+            reqwest::Client::new(),
+            "http://localhost:8000/".parse().unwrap(),
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            0,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "network subgraph max poll interval must not be shorter than the poll interval"
+    )]
+    fn max_poll_interval_shorter_than_the_poll_interval_is_rejected() {
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        Client::new(
+            reqwest::Client::new(),
+            "http://localhost:8000/".parse().unwrap(),
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+    }
+
+    #[tokio::test]
+    async fn configured_page_size_and_allocations_limit_are_used() {
+        //* Given
+        let response_body = json!({
+            "data": {
+                "meta": { "block": { "number": 1, "hash": format!("0x{}", "00".repeat(32)) } },
+                "results": [
+                    {
+                        "id": "184ba627DB853244c9f17f3Cb4378cB8B39bf147",
+                        "versions": [],
+                    },
+                ],
+            },
+        });
+        let (url, requests) = spawn_mock_server(response_body.to_string());
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url,
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            5,
+            7,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+
+        //* When
+        let result = client.poll_subgraphs().await;
+
+        //* Then
+        assert_eq!(result, Ok(()));
+        let (_, request_body) = requests.recv().expect("request received");
+        let request: serde_json::Value =
+            serde_json::from_str(&request_body).expect("valid request body");
+        assert_eq!(request["variables"]["first"], 5);
+        let query = request["query"].as_str().expect("query");
+        assert!(query.contains("first: 7"));
+        assert!(!query.contains(&format!("first: {DEFAULT_ALLOCATIONS_LIMIT}")));
+    }
+
+    /// A tracing layer collecting the fields of the emitted warnings.
+    #[derive(Clone, Default)]
+    struct WarningsLayer(Arc<Mutex<Vec<String>>>);
+
+    impl<S: Subscriber> Layer<S> for WarningsLayer {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            if *event.metadata().level() != Level::WARN {
+                return;
+            }
+            let mut fields = FieldsVisitor::default();
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+    }
+
+    #[derive(Default)]
+    struct FieldsVisitor(String);
+
+    impl Visit for FieldsVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            let _ = write!(self.0, "{}={:?} ", field.name(), value);
+        }
+    }
+
+    /// Test helper to build a network subgraph allocation.
+    fn test_allocation(idx: usize) -> serde_json::Value {
+        let indexers = [
+            "0x0101010101010101010101010101010101010101",
+            "0x0202020202020202020202020202020202020202",
+        ];
+        json!({
+            "id": format!("0x{idx:040x}"),
+            "allocatedTokens": "1",
+            "indexer": {
+                "id": indexers[idx % indexers.len()],
+                "url": "https://indexer.example.com/",
+                "stakedTokens": "1",
+            },
+        })
+    }
+
+    /// Test helper to build a subgraphs page, with a deployment referenced by two versions, and
+    /// as many allocations as the allocations limit.
+    fn test_truncated_subgraphs_page() -> String {
+        let allocations = (0..DEFAULT_ALLOCATIONS_LIMIT)
+            .map(test_allocation)
+            .collect::<Vec<_>>();
+        let version = |version: u32| {
+            json!({
+                "version": version,
+                "subgraphDeployment": {
+                    "ipfsHash": "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH",
+                    "indexerAllocations": allocations,
+                    "manifest": null,
+                },
+            })
+        };
+        json!({
+            "data": {
+                "meta": { "block": { "number": 1, "hash": format!("0x{}", "00".repeat(32)) } },
+                "results": [
+                    {
+                        "id": "184ba627DB853244c9f17f3Cb4378cB8B39bf147",
+                        "versions": [version(0), version(1)],
+                    },
+                ],
+            },
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn allocations_at_the_limit_are_paginated() {
+        //* Given
+        let layer = WarningsLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+
+        // The deployment has 2 more active allocations than fetched along with the subgraphs. They
+        // were created after the already fetched ones, in the reverse order of their IDs.
+        let allocations_page = json!({
+            "data": {
+                "meta": { "block": { "number": 1, "hash": format!("0x{}", "00".repeat(32)) } },
+                "results": (0..DEFAULT_ALLOCATIONS_LIMIT + 2)
+                    .map(|idx| {
+                        let mut allocation = test_allocation(idx);
+                        allocation["createdAt"] = match idx {
+                            idx if idx < DEFAULT_ALLOCATIONS_LIMIT => json!(idx),
+                            idx => json!(3 * DEFAULT_ALLOCATIONS_LIMIT - idx),
+                        };
+                        allocation["subgraphDeployment"] =
+                            json!({ "ipfsHash": "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH" });
+                        allocation
+                    })
+                    .collect::<Vec<_>>(),
+            },
+        });
+        let (url, requests) = spawn_sequenced_mock_server(vec![
+            test_truncated_subgraphs_page(),
+            allocations_page.to_string(),
+        ]);
+        let (subgraphs_tx, subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url,
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+
+        //* When
+        let result = client.poll_subgraphs().await;
+
+        //* Then
+        assert_eq!(result, Ok(()));
+        assert!(layer.0.lock().unwrap().is_empty());
+
+        // The allocations query is made at the subgraphs block, for the truncated deployment only
+        let _ = requests.recv().expect("subgraphs request received");
+        let (_, allocations_request) = requests.recv().expect("allocations request received");
+        assert!(allocations_request.contains("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"));
+        assert!(allocations_request.contains(&format!(r#""hash":"0x{}""#, "00".repeat(32))));
+
+        // The remaining allocations are appended to all the deployment versions, oldest first
+        let remaining_allocations = [DEFAULT_ALLOCATIONS_LIMIT + 1, DEFAULT_ALLOCATIONS_LIMIT]
+            .map(|idx| format!("0x{idx:040x}").parse::<Address>().unwrap());
+        let subgraphs = subgraphs_rx.value().await.expect("subgraphs written");
+        for version in &subgraphs[0].versions {
+            let allocations = &version.subgraph_deployment.allocations;
+            assert_eq!(allocations.len(), DEFAULT_ALLOCATIONS_LIMIT + 2);
+            assert_eq!(
+                allocations[DEFAULT_ALLOCATIONS_LIMIT..]
+                    .iter()
+                    .map(|allocation| allocation.id)
+                    .collect::<Vec<_>>(),
+                remaining_allocations
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn allocations_at_the_limit_are_warned_about_if_pagination_fails() {
+        //* Given
+        let layer = WarningsLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+
+        let (url, _requests) = spawn_sequenced_mock_server(vec![
+            test_truncated_subgraphs_page(),
+            r#"{"errors":[{"message":"mock"}]}"#.to_string(),
+        ]);
+        let (subgraphs_tx, _subgraphs_rx) = Eventual::new();
+        let (subgraphs_block_tx, _subgraphs_block_rx) = Eventual::new();
+        let mut client = Client::new(
+            reqwest::Client::new(),
+            url,
+            AuthToken::default(),
+            DEFAULT_MAX_RESPONSE_BODY_SIZE,
+            DEFAULT_PAGE_SIZE,
+            DEFAULT_ALLOCATIONS_LIMIT,
+            DEFAULT_POLL_INTERVAL,
+            DEFAULT_MAX_POLL_INTERVAL,
+            subgraphs_tx,
+            subgraphs_block_tx,
+            false,
+        );
+
+        //* When
+        let result = client.poll_subgraphs().await;
+
+        //* Then
+        // The truncated allocations are kept
+        assert_eq!(result, Ok(()));
+        // A single warning for the deployment, even if referenced by multiple versions
+        let warnings = layer.0.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"));
+        assert!(warnings[0].contains("0x0101010101010101010101010101010101010101"));
+        assert!(warnings[0].contains(&format!("allocations={DEFAULT_ALLOCATIONS_LIMIT}")));
+    }
+
+    #[test]
+    fn auth_token_is_read_from_file() {
+        //* Given
+        let path = std::env::temp_dir().join(format!(
+            "network-subgraph-auth-token-{}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&path, "file-token\n").unwrap();
+
+        //* When
+        let auth_token = AuthToken::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let missing_file_auth_token = AuthToken::from_file(&path);
+
+        //* Then
+        assert_eq!(auth_token.get().as_deref(), Some("file-token"));
+        assert_eq!(missing_file_auth_token.get(), None);
+    }
+
+    #[test]
+    fn manifest_features_are_deserialized() {
+        //* Given
+        let with_features = json!({
+            "network": "mainnet",
+            "startBlock": "42",
+            "features": ["fullTextSearch", "grafting"],
+        });
+        let without_features = json!({ "network": "mainnet", "startBlock": null });
+        let null_features = json!({ "network": "mainnet", "features": null });
+
+        //* When
+        let with_features: Manifest =
+            serde_json::from_value(with_features).expect("valid manifest");
+        let without_features: Manifest =
+            serde_json::from_value(without_features).expect("valid manifest");
+        let null_features: Manifest =
+            serde_json::from_value(null_features).expect("valid manifest");
+
+        //* Then
+        assert_eq!(with_features.features, ["fullTextSearch", "grafting"]);
+        assert_eq!(with_features.start_block, Some(42));
+        assert!(without_features.features.is_empty());
+        assert!(null_features.features.is_empty());
+    }
 }
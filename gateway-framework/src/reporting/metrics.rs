@@ -18,6 +18,10 @@ pub struct Metrics {
     pub partial_voucher: ResponseMetrics,
     pub voucher: ResponseMetrics,
     pub blocks_per_minute: IntGaugeVec,
+    pub indexer_processing_stage_duration: HistogramVec,
+    pub indexer_processing_duration: HistogramVec,
+    pub indexers_filtered: IntCounterVec,
+    pub indexer_poi_collisions: IntCounterVec,
 }
 
 impl Metrics {
@@ -50,6 +54,30 @@ impl Metrics {
                 &["chain"]
             )
             .unwrap(),
+            indexer_processing_stage_duration: register_histogram_vec!(
+                "gw_indexer_processing_stage_duration",
+                "network topology indexer processing stage duration",
+                &["stage"]
+            )
+            .unwrap(),
+            indexer_processing_duration: register_histogram_vec!(
+                "gw_indexer_processing_duration",
+                "network topology per-indexer processing duration",
+                &["indexer"]
+            )
+            .unwrap(),
+            indexers_filtered: register_int_counter_vec!(
+                "gw_indexers_filtered",
+                "network topology indexers filtered-out count",
+                &["reason"]
+            )
+            .unwrap(),
+            indexer_poi_collisions: register_int_counter_vec!(
+                "gw_indexer_poi_collisions",
+                "network topology indexers reporting the same POI for distinct deployments count",
+                &["indexer"]
+            )
+            .unwrap(),
         }
     }
 }
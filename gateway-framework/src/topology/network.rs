@@ -1,6 +1,9 @@
 use std::{
-    collections::{BTreeSet, HashMap},
-    sync::Arc,
+    collections::{hash_map::Entry, BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use alloy_primitives::Address;
@@ -8,16 +11,26 @@ use eventuals::{Eventual, EventualExt, Ptr};
 use futures::future::join_all;
 use gateway_common::types::Indexing;
 use itertools::Itertools;
+use serde::Deserialize;
 use thegraph_core::types::{DeploymentId, SubgraphId};
 use tokio::sync::Mutex;
 use url::Url;
 
 use crate::{ip_blocker::IpBlocker, network::network_subgraph};
 
+/// The default maximum amount of tokens (in GRT wei) an indexer can have allocated to a
+/// deployment: 20B GRT, well beyond the GRT total supply.
+///
+/// Allocations above this amount indicate corrupt data, and are treated as invalid.
+pub const DEFAULT_MAX_ALLOCATED_TOKENS: u128 = 20_000_000_000 * 10_u128.pow(18);
+
 /// Deployment manifest information needed for the gateway to work.
 pub struct Manifest {
     pub network: String,
     pub min_block: u64,
+    /// The subgraph features declared by the manifest, e.g., `fullTextSearch`. Used to reject
+    /// queries requiring features the deployment lacks.
+    pub features: Vec<String>,
 }
 
 pub struct Indexer {
@@ -49,6 +62,8 @@ impl Indexer {
 pub struct Subgraph {
     /// Subgraph versions, in ascending order
     pub deployments: Vec<Arc<Deployment>>,
+    /// Subgraph version numbers, matching the `deployments` order
+    pub versions: Vec<u32>,
     pub id: SubgraphId,
     /// Indicates that the subgraph has been transferred to L2, and should not be served directly by
     /// this gateway.
@@ -63,8 +78,8 @@ pub struct Deployment {
     pub indexers: HashMap<Address, Arc<Indexer>>,
     /// A deployment may be associated with multiple subgraphs.
     pub subgraphs: BTreeSet<SubgraphId>,
-    /// Indicates that the deployment should not be served directly by this gateway. This will
-    /// always be false when `allocations > 0`.
+    /// Indicates that the deployment should not be served directly by this gateway. See
+    /// [`L2TransferPolicy`] for how it interacts with the deployment's allocations.
     pub transferred_to_l2: bool,
 }
 
@@ -74,6 +89,58 @@ pub struct Allocation {
     pub indexer: Arc<Indexer>,
 }
 
+/// The treatment of indexers without staked tokens.
+///
+/// Indexers can't be legitimately allocated without stake, so a zero stake indicates corrupt
+/// data. Indexers with a malformed staked tokens value are ignored regardless of this policy.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ZeroStakePolicy {
+    /// Ignore the indexer's allocations.
+    #[default]
+    Drop,
+    /// Keep the indexer's allocations.
+    Keep,
+}
+
+/// The treatment of deployments flagged as transferred to L2 that still have active allocations.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum L2TransferPolicy {
+    /// Keep serving the deployment until all its allocations are closed.
+    #[default]
+    ServeUntilDrained,
+    /// Stop serving the deployment as soon as it is flagged as transferred.
+    StopOnFlag,
+}
+
+/// The network topology build progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildProgress {
+    /// The number of subgraphs processed so far.
+    pub subgraphs_processed: usize,
+    /// The total number of subgraphs to process.
+    pub subgraphs_total: usize,
+    /// The number of subgraph deployments built so far.
+    pub deployments_built: usize,
+}
+
+/// A callback reporting the network topology build progress, invoked after each processed
+/// subgraph with monotonically increasing counts.
+pub type BuildProgressCallback = Arc<dyn Fn(BuildProgress) + Send + Sync>;
+
+/// A hook selecting the representative allocation of an indexer on a deployment.
+///
+/// An indexer may have multiple active allocations on a deployment, collapsed into a single
+/// logical allocation (see [`Deployment::indexers`]). The hook is called with the deployment ID,
+/// the indexer ID, and the indexer's allocation IDs in fetch order, i.e., oldest first. It returns
+/// the index of the allocation the logical allocation is represented by, e.g., the allocation
+/// whose indexing is the most progressed.
+///
+/// If the hook returns `None`, or an out of bounds index, the latest allocation is selected.
+pub type AllocationSelector =
+    Arc<dyn Fn(&DeploymentId, &Address, &[Address]) -> Option<usize> + Send + Sync>;
+
 /// Representation of the graph network being used to serve queries
 #[derive(Clone)]
 pub struct GraphNetwork {
@@ -86,13 +153,33 @@ impl GraphNetwork {
     pub async fn new(
         subgraphs: Eventual<Ptr<Vec<network_subgraph::Subgraph>>>,
         ip_blocker: IpBlocker,
+        max_allocated_tokens: u128,
+        zero_stake_policy: ZeroStakePolicy,
+        l2_transfer_policy: L2TransferPolicy,
+        on_progress: Option<BuildProgressCallback>,
+        allocation_selector: Option<AllocationSelector>,
     ) -> Self {
         let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(ip_blocker.into()));
 
         // Create a lookup table for subgraphs, keyed by their ID.
         // Invalid URL indexers are filtered out. See ref: 7f2f89aa-24c9-460b-ab1e-fc94697c4f4
-        let subgraphs = subgraphs.map(move |subgraphs| async move {
-            Ptr::new(Self::subgraphs(&subgraphs, ip_blocker).await)
+        let subgraphs = subgraphs.map(move |subgraphs| {
+            let on_progress = on_progress.clone();
+            let allocation_selector = allocation_selector.clone();
+            async move {
+                Ptr::new(
+                    Self::subgraphs(
+                        &subgraphs,
+                        ip_blocker,
+                        max_allocated_tokens,
+                        zero_stake_policy,
+                        l2_transfer_policy,
+                        on_progress.as_ref(),
+                        allocation_selector.as_ref(),
+                    )
+                    .await,
+                )
+            }
         });
 
         // Create a lookup table for deployments, keyed by their ID (which is also their IPFS hash).
@@ -131,41 +218,115 @@ impl GraphNetwork {
     async fn subgraphs(
         subgraphs: &[network_subgraph::Subgraph],
         ip_blocker: &'static Mutex<IpBlocker>,
+        max_allocated_tokens: u128,
+        zero_stake_policy: ZeroStakePolicy,
+        l2_transfer_policy: L2TransferPolicy,
+        on_progress: Option<&BuildProgressCallback>,
+        allocation_selector: Option<&AllocationSelector>,
     ) -> HashMap<SubgraphId, Subgraph> {
-        join_all(subgraphs.iter().map(|subgraph| async move {
+        // Indexers with a malformed stake are ignored. Warn once per indexer, instead of once per
+        // allocation.
+        let malformed_stake_indexers = subgraphs
+            .iter()
+            .flat_map(|subgraph| &subgraph.versions)
+            .flat_map(|version| &version.subgraph_deployment.allocations)
+            .filter(|allocation| allocation.indexer.staked_tokens.is_none())
+            .map(|allocation| allocation.indexer.id)
+            .collect::<BTreeSet<_>>();
+        for indexer in malformed_stake_indexers {
+            tracing::warn!(
+                ?indexer,
+                "malformed indexer staked tokens, ignoring indexer"
+            );
+        }
+
+        let subgraphs_total = subgraphs.len();
+        let subgraphs_processed = &AtomicUsize::new(0);
+        let deployments_built = &AtomicUsize::new(0);
+
+        let subgraphs = join_all(subgraphs.iter().map(|subgraph| async move {
             let id = subgraph.id;
-            let deployments = join_all(
-                subgraph
-                    .versions
-                    .iter()
-                    .map(|version| Self::deployment(subgraphs, version, ip_blocker)),
-            )
-            .await
-            .into_iter()
-            .flatten()
-            .collect();
+            let (versions, deployments): (Vec<_>, Vec<_>) =
+                join_all(subgraph.versions.iter().map(|version| async {
+                    let deployment = Self::deployment(
+                        subgraphs,
+                        version,
+                        ip_blocker,
+                        max_allocated_tokens,
+                        zero_stake_policy,
+                        l2_transfer_policy,
+                        allocation_selector,
+                    )
+                    .await?;
+                    Some((version.version, deployment))
+                }))
+                .await
+                .into_iter()
+                .flatten()
+                .unzip();
+
+            if let Some(on_progress) = on_progress {
+                let deployments_built = deployments_built
+                    .fetch_add(deployments.len(), Ordering::Relaxed)
+                    + deployments.len();
+                let subgraphs_processed = subgraphs_processed.fetch_add(1, Ordering::Relaxed) + 1;
+                on_progress(BuildProgress {
+                    subgraphs_processed,
+                    subgraphs_total,
+                    deployments_built,
+                });
+            }
+
             let subgraph = Subgraph {
                 deployments,
+                versions,
                 id,
                 l2_id: subgraph.id_on_l2,
             };
             (id, subgraph)
         }))
-        .await
-        .into_iter()
-        .collect()
+        .await;
+
+        let mut result: HashMap<SubgraphId, Subgraph> = HashMap::new();
+        for (id, subgraph) in subgraphs {
+            // Subgraphs without any valid deployment cannot serve queries.
+            if subgraph.deployments.is_empty() {
+                continue;
+            }
+
+            // The network subgraph may return duplicate subgraph entries (e.g., during reorgs).
+            // Keep the entry with more, or newer, versions, so the outcome does not depend on the
+            // response order.
+            match result.entry(id) {
+                Entry::Vacant(entry) => {
+                    entry.insert(subgraph);
+                }
+                Entry::Occupied(mut entry) => {
+                    tracing::warn!(subgraph = %id, "duplicate subgraph in network response");
+                    if version_rank(&subgraph) > version_rank(entry.get()) {
+                        entry.insert(subgraph);
+                    }
+                }
+            }
+        }
+        result
     }
 
     async fn deployment(
         subgraphs: &[network_subgraph::Subgraph],
         version: &network_subgraph::SubgraphVersion,
         ip_blocker: &'static Mutex<IpBlocker>,
+        max_allocated_tokens: u128,
+        zero_stake_policy: ZeroStakePolicy,
+        l2_transfer_policy: L2TransferPolicy,
+        allocation_selector: Option<&AllocationSelector>,
     ) -> Option<Arc<Deployment>> {
         let id = version.subgraph_deployment.id;
         let manifest = version.subgraph_deployment.manifest.as_ref()?;
         let manifest = Manifest {
             network: manifest.network.as_ref()?.clone(),
             min_block: manifest.start_block.unwrap_or(0),
+            features: manifest.features.clone(),
         };
         let subgraphs = subgraphs
             .iter()
@@ -188,13 +349,27 @@ impl GraphNetwork {
                 // 7f2f89aa-24c9-460b-ab1e-fc94697c4f4
                 let url = allocation.indexer.url.as_ref()?.parse().ok()?;
 
+                // If the indexer's stake is malformed, the allocation is ignored. The indexer is
+                // reported once per network update, see `GraphNetwork::subgraphs`.
+                let staked_tokens = allocation.indexer.staked_tokens?;
+
+                // An allocation by an indexer without stake indicates corrupt data
+                if staked_tokens == 0 && zero_stake_policy == ZeroStakePolicy::Drop {
+                    tracing::warn!(
+                        indexer = ?allocation.indexer.id,
+                        deployment = %id,
+                        "indexer without staked tokens, ignoring allocation"
+                    );
+                    return None;
+                }
+
                 let id = allocation.indexer.id;
                 Some((
                     id,
                     Indexer {
                         id,
                         url,
-                        staked_tokens: allocation.indexer.staked_tokens,
+                        staked_tokens,
                         largest_allocation: allocation.id,
                         allocated_tokens: allocation.allocated_tokens,
                     },
@@ -202,10 +377,37 @@ impl GraphNetwork {
             })
             .into_group_map() // TODO: remove need for itertools here: https://github.com/rust-lang/rust/issues/80552
             .into_iter()
-            .filter_map(|(_, mut allocations)| {
-                let total_allocation = allocations.iter().map(|a| a.allocated_tokens).sum();
-                // last allocation is latest: 9936786a-e286-45f3-9190-8409d8389e88
-                let mut indexer = allocations.pop()?;
+            .filter_map(|(indexer, mut allocations)| {
+                let total_allocation = allocations
+                    .iter()
+                    .map(|a| a.allocated_tokens)
+                    .fold(0, u128::saturating_add);
+                // An implausibly large allocation indicates corrupt data, drop the indexing.
+                if total_allocation > max_allocated_tokens {
+                    tracing::warn!(
+                        ?indexer,
+                        deployment = %id,
+                        allocated_tokens = total_allocation,
+                        "allocated tokens above the maximum, ignoring indexing"
+                    );
+                    return None;
+                }
+                // The allocations are fetched oldest first, so the last allocation is the latest:
+                // 9936786a-e286-45f3-9190-8409d8389e88
+                // Unless overridden by the allocation selector, the latest allocation represents
+                // the indexer's logical allocation.
+                let latest = allocations.len().checked_sub(1)?;
+                let selected = allocation_selector
+                    .and_then(|select| {
+                        let allocation_ids = allocations
+                            .iter()
+                            .map(|allocation| allocation.largest_allocation)
+                            .collect::<Vec<_>>();
+                        select(&id, &indexer, &allocation_ids)
+                    })
+                    .filter(|selected| *selected < allocations.len())
+                    .unwrap_or(latest);
+                let mut indexer = allocations.swap_remove(selected);
                 indexer.allocated_tokens = total_allocation;
                 Some(indexer)
             })
@@ -225,8 +427,13 @@ impl GraphNetwork {
         indexers.retain(|indexer, _| !blocked.contains(indexer));
 
         // abf62a6d-c071-4507-b528-ddc8e250127a
-        let transferred_to_l2 = version.subgraph_deployment.transferred_to_l2
-            && version.subgraph_deployment.allocations.is_empty();
+        let transferred_to_l2 = match l2_transfer_policy {
+            L2TransferPolicy::ServeUntilDrained => {
+                version.subgraph_deployment.transferred_to_l2
+                    && version.subgraph_deployment.allocations.is_empty()
+            }
+            L2TransferPolicy::StopOnFlag => version.subgraph_deployment.transferred_to_l2,
+        };
 
         Some(Arc::new(Deployment {
             id,
@@ -247,6 +454,86 @@ impl GraphNetwork {
         self.deployments.value_immediate()?.get(id).cloned()
     }
 
+    /// Get the total staked tokens (in GRT wei) across the network indexers.
+    ///
+    /// Each indexer is counted once, even if it is allocated to multiple deployments.
+    pub fn total_staked_tokens(&self) -> u128 {
+        self.indexers
+            .value_immediate()
+            .map(|indexers| {
+                indexers
+                    .values()
+                    .map(|indexer| indexer.staked_tokens)
+                    .fold(0, u128::saturating_add)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Get the number of distinct indexers in the network.
+    pub fn indexer_count(&self) -> usize {
+        self.indexers
+            .value_immediate()
+            .map(|indexers| indexers.len())
+            .unwrap_or(0)
+    }
+
+    /// Get the number of distinct deployments in the network.
+    pub fn deployment_count(&self) -> usize {
+        self.deployments
+            .value_immediate()
+            .map(|deployments| deployments.len())
+            .unwrap_or(0)
+    }
+
+    /// Get the total allocated tokens (in GRT wei) across the deployment's indexers, if the
+    /// deployment exists.
+    pub fn allocated_tokens_for_deployment(&self, id: &DeploymentId) -> Option<u128> {
+        let deployment = self.deployment_by_id(id)?;
+        Some(
+            deployment
+                .indexers
+                .values()
+                .map(|indexer| indexer.allocated_tokens)
+                .fold(0, u128::saturating_add),
+        )
+    }
+
+    /// Get the indexer by URL, if it exists.
+    ///
+    /// The URLs are compared ignoring trailing slashes. URLs are not guaranteed to be unique per
+    /// indexer: if multiple indexers share the URL, the first match, ordered by the indexer ID, is
+    /// returned.
+    pub fn indexer_by_url(&self, url: &Url) -> Option<Arc<Indexer>> {
+        let url = url.as_str().trim_end_matches('/');
+        self.indexers
+            .value_immediate()?
+            .values()
+            .filter(|indexer| indexer.url.as_str().trim_end_matches('/') == url)
+            .min_by_key(|indexer| indexer.id)
+            .cloned()
+    }
+
+    /// Get the subgraphs referencing the deployment, ordered by their ID.
+    ///
+    /// Subgraph IDs referenced by the deployment but missing from the subgraphs map are skipped. If
+    /// the deployment is unknown, an empty list is returned.
+    pub fn subgraphs_for_deployment(&self, id: &DeploymentId) -> Vec<Subgraph> {
+        let deployment = match self.deployment_by_id(id) {
+            Some(deployment) => deployment,
+            None => return vec![],
+        };
+        let subgraphs = match self.subgraphs.value_immediate() {
+            Some(subgraphs) => subgraphs,
+            None => return vec![],
+        };
+
+        deployment
+            .subgraphs
+            .iter()
+            .filter_map(|subgraph_id| subgraphs.get(subgraph_id).cloned())
+            .collect()
+    }
+
     // Get then indexer data for some deployment.
     pub fn indexing(&self, indexing: &Indexing) -> Option<Arc<Indexer>> {
         self.deployments
@@ -256,4 +543,678 @@ impl GraphNetwork {
             .get(&indexing.indexer)
             .cloned()
     }
+
+    /// Get the indexers serving any of the subgraph's deployments, deduplicated and ordered by
+    /// their ID.
+    ///
+    /// An indexer allocated to multiple deployments is reported with its allocation info on the
+    /// newest of them. If the subgraph is unknown, an empty list is returned.
+    pub fn indexers_for_subgraph(&self, id: &SubgraphId) -> Vec<Arc<Indexer>> {
+        let subgraph = match self.subgraph_by_id(id) {
+            Some(subgraph) => subgraph,
+            None => return vec![],
+        };
+
+        let mut indexers: HashMap<Address, Arc<Indexer>> = HashMap::new();
+        // Subgraph deployments are in ascending version order, walk them from the newest
+        for deployment in subgraph.deployments.iter().rev() {
+            for (indexer_id, indexer) in &deployment.indexers {
+                indexers
+                    .entry(*indexer_id)
+                    .or_insert_with(|| indexer.clone());
+            }
+        }
+
+        indexers
+            .into_values()
+            .sorted_by_key(|indexer| indexer.id)
+            .collect()
+    }
+}
+
+/// Rank the subgraph entries by their number of versions, then by their newest version. Entries
+/// with the same versions are ranked by their indexers' addresses, so the ranking is total.
+fn version_rank(subgraph: &Subgraph) -> (usize, Option<u32>, Vec<Address>) {
+    let indexers = subgraph
+        .deployments
+        .iter()
+        .flat_map(|deployment| deployment.indexers.keys().copied())
+        .sorted()
+        .collect();
+    (
+        subgraph.versions.len(),
+        subgraph.versions.iter().max().copied(),
+        indexers,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test utility function to create a valid `SubgraphId` with an arbitrary address.
+    fn test_subgraph_id(address: &str) -> SubgraphId {
+        address.parse().expect("invalid subgraph id")
+    }
+
+    #[tokio::test]
+    async fn subgraph_without_versions_is_dropped() {
+        //* Given
+        let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(
+            IpBlocker::new(None)
+                .expect("failed to create IP blocker")
+                .into(),
+        ));
+        let subgraphs = [network_subgraph::Subgraph {
+            id: test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147"),
+            id_on_l2: None,
+            versions: vec![],
+            fully_transferred: false,
+        }];
+
+        //* When
+        let subgraphs = GraphNetwork::subgraphs(
+            &subgraphs,
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::default(),
+            None,
+            None,
+        )
+        .await;
+
+        //* Then
+        assert!(subgraphs.is_empty());
+    }
+
+    /// Test utility function to create a fetched subgraph with the given version numbers.
+    fn test_subgraph(id: SubgraphId, versions: &[u32]) -> network_subgraph::Subgraph {
+        network_subgraph::Subgraph {
+            id,
+            id_on_l2: None,
+            versions: versions
+                .iter()
+                .map(|version| network_subgraph::SubgraphVersion {
+                    version: *version,
+                    subgraph_deployment: network_subgraph::SubgraphDeployment {
+                        id: "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+                            .parse()
+                            .expect("invalid deployment id/ipfs hash"),
+                        allocations: vec![],
+                        manifest: Some(network_subgraph::Manifest {
+                            network: Some("mainnet".to_string()),
+                            start_block: None,
+                            features: vec![],
+                        }),
+                        transferred_to_l2: false,
+                    },
+                })
+                .collect(),
+            fully_transferred: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_subgraph_keeps_the_entry_with_more_versions() {
+        //* Given
+        let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(
+            IpBlocker::new(None)
+                .expect("failed to create IP blocker")
+                .into(),
+        ));
+        let id = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let subgraphs = [test_subgraph(id, &[0, 1]), test_subgraph(id, &[0])];
+        let reversed_subgraphs = [test_subgraph(id, &[0]), test_subgraph(id, &[0, 1])];
+
+        //* When
+        let subgraphs = GraphNetwork::subgraphs(
+            &subgraphs,
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::default(),
+            None,
+            None,
+        )
+        .await;
+        let reversed_subgraphs = GraphNetwork::subgraphs(
+            &reversed_subgraphs,
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::default(),
+            None,
+            None,
+        )
+        .await;
+
+        //* Then
+        assert_eq!(subgraphs.len(), 1);
+        assert_eq!(subgraphs[&id].versions, [0, 1]);
+        assert_eq!(reversed_subgraphs.len(), 1);
+        assert_eq!(reversed_subgraphs[&id].versions, [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn duplicate_subgraph_with_the_same_versions_is_resolved_by_indexer_address() {
+        //* Given
+        let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(
+            IpBlocker::new(None)
+                .expect("failed to create IP blocker")
+                .into(),
+        ));
+        let id = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let subgraph_allocated_by = |indexer: Address| {
+            let mut subgraph = test_subgraph(id, &[0]);
+            subgraph.versions[0].subgraph_deployment.allocations =
+                vec![network_subgraph::Allocation {
+                    id: Address::ZERO,
+                    indexer: network_subgraph::Indexer {
+                        id: indexer,
+                        url: Some("https://indexer.example.com/".to_string()),
+                        staked_tokens: Some(100_000),
+                    },
+                    allocated_tokens: 1,
+                }];
+            subgraph
+        };
+        let (lower, higher) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let subgraphs = [subgraph_allocated_by(lower), subgraph_allocated_by(higher)];
+        let reversed_subgraphs = [subgraph_allocated_by(higher), subgraph_allocated_by(lower)];
+
+        //* When
+        let subgraphs = GraphNetwork::subgraphs(
+            &subgraphs,
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::default(),
+            None,
+            None,
+        )
+        .await;
+        let reversed_subgraphs = GraphNetwork::subgraphs(
+            &reversed_subgraphs,
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::default(),
+            None,
+            None,
+        )
+        .await;
+
+        //* Then
+        // The outcome does not depend on the response order
+        let indexers = |subgraphs: &HashMap<SubgraphId, Subgraph>| {
+            subgraphs[&id].deployments[0]
+                .indexers
+                .keys()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(indexers(&subgraphs), [higher]);
+        assert_eq!(indexers(&reversed_subgraphs), [higher]);
+    }
+
+    #[tokio::test]
+    async fn build_progress_is_reported_with_increasing_counts() {
+        //* Given
+        let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(
+            IpBlocker::new(None)
+                .expect("failed to create IP blocker")
+                .into(),
+        ));
+        let subgraphs = [
+            test_subgraph(
+                test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147"),
+                &[0, 1],
+            ),
+            test_subgraph(
+                test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp"),
+                &[0],
+            ),
+        ];
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let on_progress: BuildProgressCallback = {
+            let reports = reports.clone();
+            Arc::new(move |progress| reports.lock().unwrap().push(progress))
+        };
+
+        //* When
+        let with_progress = GraphNetwork::subgraphs(
+            &subgraphs,
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::default(),
+            Some(&on_progress),
+            None,
+        )
+        .await;
+        let without_progress = GraphNetwork::subgraphs(
+            &subgraphs,
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::default(),
+            None,
+            None,
+        )
+        .await;
+
+        //* Then
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 2);
+        for (prev, next) in reports.iter().zip(reports.iter().skip(1)) {
+            assert!(next.subgraphs_processed > prev.subgraphs_processed);
+            assert!(next.deployments_built >= prev.deployments_built);
+        }
+        assert_eq!(
+            reports.last(),
+            Some(&BuildProgress {
+                subgraphs_processed: 2,
+                subgraphs_total: 2,
+                deployments_built: 3,
+            })
+        );
+
+        // The progress reporting does not change the result
+        let mut with_progress_ids = with_progress.keys().collect::<Vec<_>>();
+        let mut without_progress_ids = without_progress.keys().collect::<Vec<_>>();
+        with_progress_ids.sort();
+        without_progress_ids.sort();
+        assert_eq!(with_progress_ids, without_progress_ids);
+    }
+
+    /// Test utility function to create a deployment allocated by the given indexers.
+    fn test_deployment(id: &str, indexers: &[Address]) -> Arc<Deployment> {
+        Arc::new(Deployment {
+            id: id.parse().expect("invalid deployment id/ipfs hash"),
+            manifest: Manifest {
+                network: "mainnet".to_string(),
+                min_block: 0,
+                features: vec![],
+            },
+            indexers: indexers
+                .iter()
+                .map(|id| {
+                    let indexer = Indexer {
+                        id: *id,
+                        url: "https://indexer.example.com/".parse().unwrap(),
+                        staked_tokens: 1,
+                        largest_allocation: Address::ZERO,
+                        allocated_tokens: 1,
+                    };
+                    (*id, Arc::new(indexer))
+                })
+                .collect(),
+            subgraphs: Default::default(),
+            transferred_to_l2: false,
+        })
+    }
+
+    #[test]
+    fn indexers_for_subgraph_are_deduplicated() {
+        //* Given
+        let id = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let shared = Address::repeat_byte(1);
+        let only_v0 = Address::repeat_byte(2);
+        let only_v1 = Address::repeat_byte(3);
+        let subgraph = Subgraph {
+            deployments: vec![
+                test_deployment(
+                    "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH",
+                    &[shared, only_v0],
+                ),
+                test_deployment(
+                    "QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3",
+                    &[shared, only_v1],
+                ),
+            ],
+            versions: vec![0, 1],
+            id,
+            l2_id: None,
+        };
+        let network = GraphNetwork {
+            subgraphs: Eventual::from_value(Ptr::new(HashMap::from([(id, subgraph)]))),
+            deployments: Eventual::from_value(Ptr::default()),
+            indexers: Eventual::from_value(Ptr::default()),
+        };
+
+        //* When
+        let indexers = network.indexers_for_subgraph(&id);
+        let unknown_subgraph_indexers = network.indexers_for_subgraph(&test_subgraph_id(
+            "DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp",
+        ));
+
+        //* Then
+        let indexers = indexers
+            .iter()
+            .map(|indexer| indexer.id)
+            .collect::<Vec<_>>();
+        assert_eq!(indexers, [shared, only_v0, only_v1]);
+        assert!(unknown_subgraph_indexers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn zero_stake_indexer_is_dropped() {
+        //* Given
+        let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(
+            IpBlocker::new(None)
+                .expect("failed to create IP blocker")
+                .into(),
+        ));
+        let allocation =
+            |indexer: Address, staked_tokens: Option<u128>| network_subgraph::Allocation {
+                id: Address::ZERO,
+                indexer: network_subgraph::Indexer {
+                    id: indexer,
+                    url: Some("https://indexer.example.com/".to_string()),
+                    staked_tokens,
+                },
+                allocated_tokens: 1,
+            };
+        let mut subgraph = test_subgraph(
+            test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147"),
+            &[0],
+        );
+        subgraph.versions[0].subgraph_deployment.allocations = vec![
+            allocation(Address::repeat_byte(1), Some(100_000)),
+            allocation(Address::repeat_byte(2), Some(0)),
+            allocation(Address::repeat_byte(3), None),
+        ];
+        let subgraphs = [subgraph];
+
+        //* When
+        let deployment = GraphNetwork::deployment(
+            &subgraphs,
+            &subgraphs[0].versions[0],
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::Drop,
+            L2TransferPolicy::default(),
+            None,
+        )
+        .await
+        .expect("valid deployment");
+        let deployment_keeping_zero_stake = GraphNetwork::deployment(
+            &subgraphs,
+            &subgraphs[0].versions[0],
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::Keep,
+            L2TransferPolicy::default(),
+            None,
+        )
+        .await
+        .expect("valid deployment");
+
+        //* Then
+        assert!(deployment.indexers.contains_key(&Address::repeat_byte(1)));
+        assert!(!deployment.indexers.contains_key(&Address::repeat_byte(2)));
+        assert!(!deployment.indexers.contains_key(&Address::repeat_byte(3)));
+
+        // The malformed stake indexer is ignored regardless of the policy
+        assert_eq!(
+            deployment_keeping_zero_stake
+                .indexers
+                .keys()
+                .collect::<BTreeSet<_>>(),
+            BTreeSet::from([&Address::repeat_byte(1), &Address::repeat_byte(2)])
+        );
+    }
+
+    #[tokio::test]
+    async fn flagged_but_allocated_deployment_follows_the_l2_transfer_policy() {
+        //* Given
+        let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(
+            IpBlocker::new(None)
+                .expect("failed to create IP blocker")
+                .into(),
+        ));
+        let mut subgraph = test_subgraph(
+            test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147"),
+            &[0],
+        );
+        let deployment = &mut subgraph.versions[0].subgraph_deployment;
+        deployment.transferred_to_l2 = true;
+        deployment.allocations = vec![network_subgraph::Allocation {
+            id: Address::ZERO,
+            indexer: network_subgraph::Indexer {
+                id: Address::repeat_byte(1),
+                url: Some("https://indexer.example.com/".to_string()),
+                staked_tokens: Some(100_000),
+            },
+            allocated_tokens: 1,
+        }];
+        let subgraphs = [subgraph];
+
+        //* When
+        let serve_until_drained = GraphNetwork::deployment(
+            &subgraphs,
+            &subgraphs[0].versions[0],
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::ServeUntilDrained,
+            None,
+        )
+        .await
+        .expect("valid deployment");
+        let stop_on_flag = GraphNetwork::deployment(
+            &subgraphs,
+            &subgraphs[0].versions[0],
+            ip_blocker,
+            DEFAULT_MAX_ALLOCATED_TOKENS,
+            ZeroStakePolicy::default(),
+            L2TransferPolicy::StopOnFlag,
+            None,
+        )
+        .await
+        .expect("valid deployment");
+
+        //* Then
+        assert!(!serve_until_drained.transferred_to_l2);
+        assert!(stop_on_flag.transferred_to_l2);
+    }
+
+    #[test]
+    fn subgraphs_for_deployment_skips_missing_subgraphs() {
+        //* Given
+        let first_id = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+        let second_id = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let missing_id = test_subgraph_id("EMRitnR1t3drKrDQSmJMSmHBPB2sGotgZE12DzWNezDn");
+
+        let mut deployment = test_deployment("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH", &[]);
+        Arc::get_mut(&mut deployment).unwrap().subgraphs =
+            BTreeSet::from([first_id, second_id, missing_id]);
+        let subgraph = |id: SubgraphId| Subgraph {
+            deployments: vec![deployment.clone()],
+            versions: vec![0],
+            id,
+            l2_id: None,
+        };
+
+        let network = GraphNetwork {
+            subgraphs: Eventual::from_value(Ptr::new(HashMap::from([
+                (first_id, subgraph(first_id)),
+                (second_id, subgraph(second_id)),
+            ]))),
+            deployments: Eventual::from_value(Ptr::new(HashMap::from([(
+                deployment.id,
+                deployment.clone(),
+            )]))),
+            indexers: Eventual::from_value(Ptr::default()),
+        };
+
+        //* When
+        let subgraphs = network.subgraphs_for_deployment(&deployment.id);
+        let unknown_deployment_subgraphs = network.subgraphs_for_deployment(
+            &"QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3"
+                .parse()
+                .expect("invalid deployment id/ipfs hash"),
+        );
+
+        //* Then
+        let mut expected = vec![first_id, second_id];
+        expected.sort();
+        let subgraphs = subgraphs
+            .iter()
+            .map(|subgraph| subgraph.id)
+            .collect::<Vec<_>>();
+        assert_eq!(subgraphs, expected);
+        assert!(unknown_deployment_subgraphs.is_empty());
+    }
+
+    #[test]
+    fn indexer_by_url_ignores_trailing_slashes() {
+        //* Given
+        let indexer = |id: Address, url: &str| {
+            let indexer = Indexer {
+                id,
+                url: url.parse().unwrap(),
+                staked_tokens: 1,
+                largest_allocation: Address::ZERO,
+                allocated_tokens: 1,
+            };
+            (id, Arc::new(indexer))
+        };
+        let first = Address::repeat_byte(1);
+        let second = Address::repeat_byte(2);
+
+        let network = GraphNetwork {
+            subgraphs: Eventual::from_value(Ptr::default()),
+            deployments: Eventual::from_value(Ptr::default()),
+            indexers: Eventual::from_value(Ptr::new(HashMap::from([
+                indexer(first, "https://first.example.com/"),
+                indexer(second, "https://second.example.com/graph/"),
+            ]))),
+        };
+
+        //* When
+        let first_match = network.indexer_by_url(&"https://first.example.com".parse().unwrap());
+        let second_match =
+            network.indexer_by_url(&"https://second.example.com/graph".parse().unwrap());
+        let unknown_match =
+            network.indexer_by_url(&"https://unknown.example.com/".parse().unwrap());
+
+        //* Then
+        assert_eq!(first_match.map(|indexer| indexer.id), Some(first));
+        assert_eq!(second_match.map(|indexer| indexer.id), Some(second));
+        assert!(unknown_match.is_none());
+    }
+
+    #[tokio::test]
+    async fn latest_allocation_represents_the_indexer_unless_overridden() {
+        //* Given
+        let ip_blocker: &'static Mutex<IpBlocker> = Box::leak(Box::new(
+            IpBlocker::new(None)
+                .expect("failed to create IP blocker")
+                .into(),
+        ));
+        let indexer = Address::repeat_byte(1);
+        let allocation = |id: Address| network_subgraph::Allocation {
+            id,
+            indexer: network_subgraph::Indexer {
+                id: indexer,
+                url: Some("https://indexer.example.com/".to_string()),
+                staked_tokens: Some(100_000),
+            },
+            allocated_tokens: 1,
+        };
+        let (oldest, middle, latest) = (
+            Address::repeat_byte(10),
+            Address::repeat_byte(11),
+            Address::repeat_byte(12),
+        );
+        let mut subgraph = test_subgraph(
+            test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147"),
+            &[0],
+        );
+        // The allocations are fetched oldest first
+        subgraph.versions[0].subgraph_deployment.allocations =
+            vec![allocation(oldest), allocation(middle), allocation(latest)];
+        let subgraphs = [subgraph];
+
+        let select_oldest: AllocationSelector = Arc::new(|_, _, _| Some(0));
+        let select_out_of_bounds: AllocationSelector = Arc::new(|_, _, ids| Some(ids.len()));
+
+        //* When
+        let mut representatives = Vec::new();
+        for allocation_selector in [None, Some(&select_oldest), Some(&select_out_of_bounds)] {
+            let deployment = GraphNetwork::deployment(
+                &subgraphs,
+                &subgraphs[0].versions[0],
+                ip_blocker,
+                DEFAULT_MAX_ALLOCATED_TOKENS,
+                ZeroStakePolicy::default(),
+                L2TransferPolicy::default(),
+                allocation_selector,
+            )
+            .await
+            .expect("valid deployment");
+            representatives.push(deployment.indexers[&indexer].clone());
+        }
+
+        //* Then
+        let allocations = representatives
+            .iter()
+            .map(|indexer| indexer.largest_allocation)
+            .collect::<Vec<_>>();
+        assert_eq!(allocations, [latest, oldest, latest]);
+        // The allocated tokens are summed, whatever the representative allocation
+        assert!(representatives
+            .iter()
+            .all(|indexer| indexer.allocated_tokens == 3));
+    }
+
+    #[test]
+    fn network_aggregates_do_not_double_count_indexers() {
+        //* Given
+        let shared = Address::repeat_byte(1);
+        let only_first = Address::repeat_byte(2);
+        let first = test_deployment(
+            "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH",
+            &[shared, only_first],
+        );
+        let second = test_deployment("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3", &[shared]);
+
+        // Each indexer has 1 staked token and 1 allocated token per deployment
+        let indexers = [&first, &second]
+            .into_iter()
+            .flat_map(|deployment| &deployment.indexers)
+            .map(|(id, indexer)| (*id, indexer.clone()))
+            .collect::<HashMap<_, _>>();
+        let network = GraphNetwork {
+            subgraphs: Eventual::from_value(Ptr::default()),
+            deployments: Eventual::from_value(Ptr::new(HashMap::from([
+                (first.id, first.clone()),
+                (second.id, second.clone()),
+            ]))),
+            indexers: Eventual::from_value(Ptr::new(indexers)),
+        };
+
+        //* When
+        let total_staked_tokens = network.total_staked_tokens();
+        let indexer_count = network.indexer_count();
+        let deployment_count = network.deployment_count();
+
+        //* Then
+        assert_eq!(total_staked_tokens, 2);
+        assert_eq!(indexer_count, 2);
+        assert_eq!(deployment_count, 2);
+        assert_eq!(network.allocated_tokens_for_deployment(&first.id), Some(2));
+        assert_eq!(network.allocated_tokens_for_deployment(&second.id), Some(1));
+        assert_eq!(
+            network.allocated_tokens_for_deployment(
+                &"QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw"
+                    .parse()
+                    .expect("invalid deployment id/ipfs hash")
+            ),
+            None
+        );
+    }
 }
@@ -27,6 +27,10 @@ pub struct BlockRequirements {
     pub number_gte: Option<BlockNumber>,
     /// does the query benefit from using the latest block (contains NumberGTE or Unconstrained)
     pub latest: bool,
+    /// does the query pin a block hash that could not be resolved to a block number. Indexers only
+    /// report block numbers, so the query is routed without guaranteeing the selected indexers
+    /// have the pinned block.
+    pub hash_pinned_unverifiable: bool,
 }
 
 pub fn resolve_block_requirements(
@@ -48,6 +52,11 @@ pub fn resolve_block_requirements(
         })
         .max();
 
+    let hash_pinned_unverifiable = constraints.iter().any(|c| match c {
+        BlockConstraint::Hash(hash) => chain.find(&UnresolvedBlock::WithHash(*hash)).is_none(),
+        _ => false,
+    });
+
     let exact_constraints: Vec<u64> = constraints
         .iter()
         .filter_map(|c| match c {
@@ -62,6 +71,14 @@ pub fn resolve_block_requirements(
     let min_block = exact_constraints.iter().min().cloned();
     let max_block = exact_constraints.iter().max().cloned();
 
+    // A query pinning the genesis block can never be served by a deployment starting at a later
+    // block. This is often a client bug, so report it distinctly.
+    if manifest_min_block > 0 && exact_constraints.contains(&0) {
+        return Err(Error::BadQuery(anyhow!(
+            "requested genesis block, but deployment starts at block {manifest_min_block}"
+        )));
+    }
+
     // Reject queries for blocks before the minimum start block in the manifest, but only if the
     // constraint is for an exact block. For example, we always want to allow `block_gte: 0`.
     let request_contains_invalid_blocks = exact_constraints
@@ -79,6 +96,7 @@ pub fn resolve_block_requirements(
         range: min_block.map(|min| (min, max_block.unwrap())),
         number_gte,
         latest,
+        hash_pinned_unverifiable,
     })
 }
 
@@ -466,6 +484,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn hash_pinned_query_is_flagged_unverifiable() {
+        let hash: BlockHash = bytes_from_id(54321).into();
+        let query = format!("{{ a(block:{{hash:{:?}}}) {{ id }} }}", hash.to_string());
+        let context = Context::new(&query, "").unwrap();
+
+        let mut chain = Chain::default();
+        let requirements = resolve_block_requirements(&chain, &context, 0).unwrap();
+        assert!(requirements.hash_pinned_unverifiable);
+        assert!(!requirements.latest);
+        assert_eq!(requirements.range, None);
+
+        chain.insert(
+            Block {
+                hash,
+                number: 123,
+                timestamp: unix_timestamp() / 1_000,
+            },
+            Address::default(),
+        );
+        let requirements = resolve_block_requirements(&chain, &context, 0).unwrap();
+        assert!(!requirements.hash_pinned_unverifiable);
+        assert_eq!(requirements.range, Some((123, 123)));
+    }
+
+    #[test]
+    fn genesis_pinned_query_before_the_start_block_is_rejected() {
+        let context = Context::new("{ a(block:{number:0}) { id } }", "").unwrap();
+        let chain = Chain::default();
+
+        let error = resolve_block_requirements(&chain, &context, 100)
+            .expect_err("genesis block before the start block");
+        assert_eq!(
+            error.to_string(),
+            "bad query: requested genesis block, but deployment starts at block 100"
+        );
+
+        let requirements = resolve_block_requirements(&chain, &context, 0).unwrap();
+        assert_eq!(requirements.range, Some((0, 0)));
+    }
+
     #[test]
     fn query_contains_introspection() {
         let query = "{ __schema { queryType { name } } }";
@@ -506,6 +565,7 @@ mod tests {
                     latest: true,
                     number_gte: None,
                     range: Some((123, 123)),
+                    hash_pinned_unverifiable: false,
                 },
                 "{\n  bundle0: bundle(block: { hash: \"0x0000000000000000000000000000000000000000000000000000000000000000\" }, id: \"1\") {\n    ethPriceUSD\n  }\n  bundle1: bundle(block: { hash: \"0x0000000000000000000000000000000000000000000000000000000000000001\" }, id: \"1\") {\n    ethPriceUSD\n  }\n  _gateway_probe_: _meta { block { hash number timestamp } }\n}\n",
             ),
@@ -517,6 +577,7 @@ mod tests {
                     latest: true,
                     number_gte: None,
                     range: Some((125, 125)),
+                    hash_pinned_unverifiable: false,
                 },
                 "{\n  bundle0: bundle(block: { number: 125 }, id: \"1\") {\n    ethPriceUSD\n  }\n  _gateway_probe_: _meta { block { hash number timestamp } }\n}\n",
             ),
@@ -526,6 +587,7 @@ mod tests {
                     latest: true,
                     number_gte: Some(125),
                     range: None,
+                    hash_pinned_unverifiable: false,
                 },
                 "{\n  bundle(block: { number_gte: 125 }) {\n    ethPriceUSD\n  }\n  _gateway_probe_: _meta { block { hash number timestamp } }\n}\n",
             ),
@@ -539,6 +601,7 @@ mod tests {
                     latest: true,
                     number_gte: None,
                     range: None,
+                    hash_pinned_unverifiable: false,
                 },
                 "query GetTopSales {\n  events(block: { hash: \"0x0000000000000000000000000000000000000000000000000000000000000001\" }, where: {type: \"Sale\"}, first: 1, orderBy: value, orderDirection: desc) {\n    type\n  }\n  _gateway_probe_: _meta { block { hash number timestamp } }\n}\n",
             ),
@@ -558,6 +621,7 @@ mod tests {
                     latest: true,
                     number_gte: None,
                     range: None,
+                    hash_pinned_unverifiable: false,
                 },
                 "fragment Foo on Delegation {\n  id\n}\n{\n  delegations(block: { hash: \"0x0000000000000000000000000000000000000000000000000000000000000001\" }, first: 1) {\n    delegator\n    ...Foo\n  }\n  _gateway_probe_: _meta { block { hash number timestamp } }\n}\n",
             ),
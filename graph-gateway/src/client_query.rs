@@ -1,6 +1,6 @@
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
-    sync::Arc,
+    sync::{Arc, PoisonError},
     time::{Duration, Instant},
 };
 
@@ -37,8 +37,7 @@ use headers::ContentType;
 use indexer_selection::{ArrayVec, Candidate, Normalized};
 use num_traits::cast::ToPrimitive as _;
 use ordered_float::NotNan;
-use prost::bytes::Buf;
-use rand::{rngs::SmallRng, Rng as _, SeedableRng as _};
+use rand::{rngs::SmallRng, Rng, SeedableRng as _};
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 use thegraph_core::types::{attestation, DeploymentId};
@@ -54,8 +53,11 @@ use self::{
 use crate::{
     block_constraints::{resolve_block_requirements, rewrite_query, BlockRequirements},
     indexer_client::{check_block_error, IndexerClient, ResponsePayload},
+    network::{
+        indexer_drain_set::DrainSet, indexer_score::CandidateRanking, indexer_tiers::IndexerTiers,
+    },
+    query_limits::dedupe_selections,
     reports::{self, serialize_attestation},
-    sql_constraints::{validate_query, SqlFieldBehavior},
     unattestable_errors::{miscategorized_attestable, miscategorized_unattestable},
 };
 
@@ -102,13 +104,17 @@ pub async fn handle_query(
                 return Err(Error::Auth(anyhow!("Subgraph not authorized by user")));
             }
 
-            resolve_subgraph_deployments(&ctx.network, &selector)?
+            resolve_subgraph_deployments(&ctx.network, &selector, ctx.subgraph_version_fallback)?
         }
         QuerySelector::Deployment(_) => {
             // Authorization is based on the "authorized subgraphs" allowlist. We need to resolve
             // the subgraph deployments to check if any of the deployment's subgraphs are
             // authorized, otherwise return an error.
-            let (deployments, subgraph) = resolve_subgraph_deployments(&ctx.network, &selector)?;
+            let (deployments, subgraph) = resolve_subgraph_deployments(
+                &ctx.network,
+                &selector,
+                ctx.subgraph_version_fallback,
+            )?;
 
             // If none of the deployment's subgraphs are authorized, return an error.
             let deployment_subgraphs = deployments
@@ -191,9 +197,14 @@ pub async fn handle_query(
 
 /// Given a query selector, resolve the subgraph deployments for the query. If the selector is a subgraph ID, return
 /// the subgraph's deployment instances. If the selector is a deployment ID, return the deployment instance.
+///
+/// If `fallback_to_prior_version` is set and the subgraph's latest version deployment is not
+/// servable, the newest servable deployment is treated as the subgraph's current version, i.e.,
+/// the newer unservable versions are dropped.
 fn resolve_subgraph_deployments(
     network: &GraphNetwork,
     selector: &QuerySelector,
+    fallback_to_prior_version: bool,
 ) -> Result<(Vec<Arc<Deployment>>, Option<Subgraph>), Error> {
     match selector {
         QuerySelector::Subgraph(subgraph_id) => {
@@ -202,17 +213,26 @@ fn resolve_subgraph_deployments(
                 .subgraph_by_id(subgraph_id)
                 .ok_or_else(|| Error::SubgraphNotFound(anyhow!("{subgraph_id}")))?;
 
-            // Get the subgraph's chain (from the last of its deployments)
+            // Get the subgraph's current version. If the fallback is enabled, this is the newest
+            // servable version, if any.
+            let current = subgraph
+                .deployments
+                .iter()
+                .rposition(|deployment| {
+                    !fallback_to_prior_version || check_servable(deployment).is_ok()
+                })
+                .unwrap_or(subgraph.deployments.len().saturating_sub(1));
+
+            // Get the subgraph's chain (from its current version deployment)
             let subgraph_chain = subgraph
                 .deployments
-                .last()
+                .get(current)
                 .map(|deployment| deployment.manifest.network.clone())
                 .ok_or_else(|| Error::SubgraphNotFound(anyhow!("no matching deployments")))?;
 
             // Get the subgraph's deployments. Make sure we only select from deployments indexing
             // the same chain. This simplifies dealing with block constraints later
-            let versions = subgraph
-                .deployments
+            let versions = subgraph.deployments[..=current]
                 .iter()
                 .filter(|deployment| deployment.manifest.network == subgraph_chain)
                 .cloned()
@@ -231,6 +251,73 @@ fn resolve_subgraph_deployments(
     }
 }
 
+/// Given a query selector, resolve the set of deployments a query would touch.
+///
+/// If the selector is a subgraph ID, return the subgraph's latest version deployment, or the
+/// deployment of the pinned version, if any. If the selector is a deployment ID, the version pin
+/// is ignored and the deployment is returned. In both cases, the deployment must be servable by
+/// this gateway, i.e., it must not be transferred to L2 and must have at least one indexer.
+pub fn resolve_query_deployments(
+    network: &GraphNetwork,
+    selector: &QuerySelector,
+    version_pin: Option<u32>,
+) -> Result<Vec<DeploymentId>, Error> {
+    let deployment =
+        match selector {
+            QuerySelector::Subgraph(subgraph_id) => {
+                let subgraph = network
+                    .subgraph_by_id(subgraph_id)
+                    .ok_or_else(|| Error::SubgraphNotFound(anyhow!("{subgraph_id}")))?;
+
+                match version_pin {
+                    Some(version) => subgraph
+                        .versions
+                        .iter()
+                        .position(|v| *v == version)
+                        .map(|index| subgraph.deployments[index].clone())
+                        .ok_or_else(|| {
+                            Error::SubgraphNotFound(anyhow!("version not found: {version}"))
+                        })?,
+                    None => subgraph.deployments.last().cloned().ok_or_else(|| {
+                        Error::SubgraphNotFound(anyhow!("no matching deployments"))
+                    })?,
+                }
+            }
+            QuerySelector::Deployment(deployment_id) => {
+                network.deployment_by_id(deployment_id).ok_or_else(|| {
+                    Error::SubgraphNotFound(anyhow!("deployment not found: {deployment_id}"))
+                })?
+            }
+        };
+
+    check_servable(&deployment)?;
+    Ok(vec![deployment.id])
+}
+
+/// Check the deployment is servable by this gateway, i.e., it is not transferred to L2 and has at
+/// least one indexer.
+fn check_servable(deployment: &Deployment) -> Result<(), Error> {
+    if deployment.transferred_to_l2 {
+        return Err(Error::SubgraphNotFound(anyhow!(
+            "deployment transferred to L2: {}",
+            deployment.id
+        )));
+    }
+    if deployment.indexers.is_empty() {
+        return Err(Error::NoIndexers);
+    }
+    Ok(())
+}
+
+/// Decode the raw client query body.
+///
+/// The body must be valid UTF-8, invalid byte sequences are rejected instead of being lossily
+/// replaced, as that could alter the query checked by the downstream validations.
+fn parse_query_body(bytes: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(bytes)
+        .map_err(|err| Error::BadQuery(anyhow!("invalid UTF-8 query body: {err}")))
+}
+
 async fn handle_client_query_inner(
     ctx: &Context,
     query_settings: Option<QuerySettings>,
@@ -246,8 +333,9 @@ async fn handle_client_query_inner(
     let manifest_min_block = deployments.last().unwrap().manifest.min_block;
     let chain = ctx.chains.chain(&subgraph_chain).await;
 
+    let payload = parse_query_body(&payload)?;
     let payload: QueryBody =
-        serde_json::from_reader(payload.reader()).map_err(|err| Error::BadQuery(err.into()))?;
+        serde_json::from_str(payload).map_err(|err| Error::BadQuery(err.into()))?;
 
     let mut indexer_errors: BTreeMap<Address, IndexerError> = Default::default();
 
@@ -275,6 +363,11 @@ async fn handle_client_query_inner(
         }
         true
     });
+    remove_drained_indexings(
+        &mut available_indexers,
+        &ctx.indexer_drain_set,
+        &mut indexer_errors,
+    );
     if available_indexers.is_empty() {
         return Err(Error::NoIndexers);
     }
@@ -286,7 +379,12 @@ async fn handle_client_query_inner(
         .unwrap_or_default();
     let context = AgoraContext::new(&payload.query, &variables)
         .map_err(|err| Error::BadQuery(anyhow!("{err}")))?;
-    validate_query(&context, SqlFieldBehavior::RejectSql)?;
+    ctx.query_validator.validate(&payload.query, &context)?;
+    let context = if ctx.query_limits.dedupe_selections {
+        dedupe_selections(context)
+    } else {
+        context
+    };
 
     tracing::info!(
         target: CLIENT_REQUEST_TARGET,
@@ -374,20 +472,23 @@ async fn handle_client_query_inner(
         }
     }
 
-    let mut rng = SmallRng::from_entropy();
-    if tracing::enabled!(tracing::Level::TRACE) {
-        tracing::trace!(?candidates);
-    } else if rng.gen_bool(0.001) {
-        tracing::debug!(?candidates);
-    }
-
     if candidates.is_empty() {
         tracing::debug!(?indexer_errors);
         return Err(Error::BadIndexers(indexer_errors));
     }
 
-    let selected_candidates: ArrayVec<&Candidate, SELECTION_LIMIT> =
-        indexer_selection::select(&candidates);
+    let selected_candidates = {
+        let mut rng = ctx
+            .selection_rng
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        select_candidates(
+            &mut *rng,
+            &mut candidates,
+            &ctx.indexer_tiers,
+            ctx.indexer_ranking.as_ref(),
+        )
+    };
     let selections_len = selected_candidates.len();
     let mut selections: Vec<Selection> = Default::default();
     for candidate in selected_candidates {
@@ -480,6 +581,7 @@ async fn handle_client_query_inner(
             target: INDEXER_REQUEST_TARGET,
             "indexer_request",
             indexer = ?selection.indexing.indexer,
+            hash_pinned_unverifiable = block_requirements.hash_pinned_unverifiable,
         );
         let receipt_signer = ctx.receipt_signer;
         tokio::spawn(
@@ -528,6 +630,71 @@ async fn handle_client_query_inner(
     Err(Error::BadIndexers(indexer_errors))
 }
 
+/// Create the RNG driving the randomized steps of the indexer selection.
+///
+/// The RNG is created once and shared by all the queries. If a seed is given, the RNG is seeded
+/// with it so the sequence of selections can be reproduced. Otherwise, it is seeded from the OS
+/// entropy source.
+pub fn selection_rng(seed: Option<u64>) -> SmallRng {
+    match seed {
+        Some(seed) => SmallRng::seed_from_u64(seed),
+        None => SmallRng::from_entropy(),
+    }
+}
+
+/// Remove the indexings of the drained indexers from the available ones, recording the drained
+/// indexers' errors.
+///
+/// Drained indexers have zero selection weight, i.e., they are never selected. They are removed
+/// before the candidates are prepared, so a deployment served only by drained indexers has no
+/// available indexers.
+fn remove_drained_indexings(
+    available_indexers: &mut BTreeSet<Indexing>,
+    drain_set: &DrainSet,
+    indexer_errors: &mut BTreeMap<Address, IndexerError>,
+) {
+    available_indexers.retain(|indexing| {
+        if drain_set.contains(&indexing.indexer) {
+            indexer_errors.insert(
+                indexing.indexer,
+                IndexerError::Unavailable(UnavailableReason::Drained),
+            );
+            return false;
+        }
+        true
+    });
+}
+
+/// Select the indexers to query among the candidates.
+///
+/// The randomized steps on the gateway side, e.g., the sampled candidates logging, draw from the
+/// given RNG. Note that `indexer_selection::select` takes no RNG, so the seed does not cover any
+/// randomness internal to the selection algorithm.
+///
+/// The candidates' selection weight, their slashable GRT, is scaled by their indexer's tier
+/// weight. If a ranking is given, only its top-ranked candidates are considered.
+fn select_candidates<'c>(
+    rng: &mut impl Rng,
+    candidates: &'c mut Vec<Candidate>,
+    tiers: &IndexerTiers,
+    ranking: Option<&CandidateRanking>,
+) -> ArrayVec<&'c Candidate, SELECTION_LIMIT> {
+    if tracing::enabled!(tracing::Level::TRACE) {
+        tracing::trace!(?candidates);
+    } else if rng.gen_bool(0.001) {
+        tracing::debug!(?candidates);
+    }
+
+    for candidate in candidates.iter_mut() {
+        let weight = tiers.weight(&candidate.indexer);
+        candidate.slashable_grt = (candidate.slashable_grt as f64 * weight) as u64;
+    }
+    if let Some(ranking) = ranking {
+        ranking.retain_top(candidates);
+    }
+    indexer_selection::select(candidates)
+}
+
 #[allow(clippy::too_many_arguments)]
 fn prepare_candidate(
     network: &GraphNetwork,
@@ -867,6 +1034,7 @@ mod tests {
         fn test_auth_ctx(key: Option<&str>) -> AuthContext {
             let mut ctx = AuthContext {
                 payment_required: false,
+                allow_missing_origin: false,
                 api_keys: watch::channel(Default::default()).1,
                 special_api_keys: Default::default(),
                 special_query_key_signers: Default::default(),
@@ -1042,4 +1210,441 @@ mod tests {
             });
         }
     }
+
+    mod parse_query_body {
+        use assert_matches::assert_matches;
+        use gateway_framework::errors::Error;
+
+        use super::super::parse_query_body;
+
+        #[test]
+        fn accept_valid_utf8_body() {
+            //* Given
+            let body = r#"{"query": "{ tokens(where: { name: \"é\" }) { id } }"}"#.as_bytes();
+
+            //* When
+            let res = parse_query_body(body);
+
+            //* Then
+            assert_matches!(res, Ok(query) => {
+                assert_eq!(query.as_bytes(), body);
+            });
+        }
+
+        #[test]
+        fn reject_invalid_utf8_body() {
+            //* Given
+            let body = b"{\"query\": \"{ s\xffql { id } }\"}";
+
+            //* When
+            let res = parse_query_body(body);
+
+            //* Then
+            assert_matches!(res, Err(Error::BadQuery(_)));
+        }
+    }
+
+    mod resolve_query_deployments {
+        use std::{
+            collections::{BTreeSet, HashMap},
+            sync::Arc,
+        };
+
+        use alloy_primitives::Address;
+        use assert_matches::assert_matches;
+        use eventuals::{Eventual, Ptr};
+        use gateway_framework::{
+            errors::Error,
+            topology::network::{Deployment, GraphNetwork, Indexer, Manifest, Subgraph},
+        };
+        use thegraph_core::types::{DeploymentId, SubgraphId};
+
+        use super::super::{
+            resolve_query_deployments, resolve_subgraph_deployments, QuerySelector,
+        };
+
+        /// Test utility function to create a valid `DeploymentId` with an arbitrary deployment id/ipfs hash.
+        fn test_deployment_id(deployment: &str) -> DeploymentId {
+            deployment.parse().expect("invalid deployment id/ipfs hash")
+        }
+
+        /// Test utility function to create a valid `SubgraphId` with an arbitrary address.
+        fn test_subgraph_id(address: &str) -> SubgraphId {
+            address.parse().expect("invalid subgraph id")
+        }
+
+        /// Create a test deployment, allocated by a single indexer.
+        fn test_deployment(id: DeploymentId, subgraph: SubgraphId) -> Arc<Deployment> {
+            let indexer = Arc::new(Indexer {
+                id: Address::repeat_byte(1),
+                url: "https://indexer.example.com/".parse().unwrap(),
+                staked_tokens: 1,
+                largest_allocation: Address::repeat_byte(2),
+                allocated_tokens: 1,
+            });
+            Arc::new(Deployment {
+                id,
+                manifest: Manifest {
+                    network: "mainnet".to_string(),
+                    min_block: 0,
+                    features: vec![],
+                },
+                indexers: HashMap::from([(indexer.id, indexer)]),
+                subgraphs: BTreeSet::from([subgraph]),
+                transferred_to_l2: false,
+            })
+        }
+
+        /// Create a test network with a single subgraph and the given versions.
+        fn test_network(
+            subgraph: SubgraphId,
+            versions: Vec<(u32, Arc<Deployment>)>,
+        ) -> GraphNetwork {
+            let (versions, deployments): (Vec<_>, Vec<_>) = versions.into_iter().unzip();
+            let subgraph = Subgraph {
+                deployments: deployments.clone(),
+                versions,
+                id: subgraph,
+                l2_id: None,
+            };
+            let indexers = deployments
+                .iter()
+                .flat_map(|deployment| &deployment.indexers)
+                .map(|(id, indexer)| (*id, indexer.clone()))
+                .collect::<HashMap<_, _>>();
+            let deployments = deployments
+                .into_iter()
+                .map(|deployment| (deployment.id, deployment))
+                .collect::<HashMap<_, _>>();
+
+            GraphNetwork {
+                subgraphs: Eventual::from_value(Ptr::new(HashMap::from([(subgraph.id, subgraph)]))),
+                deployments: Eventual::from_value(Ptr::new(deployments)),
+                indexers: Eventual::from_value(Ptr::new(indexers)),
+            }
+        }
+
+        #[test]
+        fn subgraph_resolves_to_latest_version() {
+            //* Given
+            let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+            let v0 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+            let v1 = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+            let network = test_network(
+                subgraph,
+                vec![
+                    (0, test_deployment(v0, subgraph)),
+                    (1, test_deployment(v1, subgraph)),
+                ],
+            );
+
+            //* When
+            let res = resolve_query_deployments(&network, &QuerySelector::Subgraph(subgraph), None);
+
+            //* Then
+            assert_matches!(res, Ok(deployments) => {
+                assert_eq!(deployments, vec![v1]);
+            });
+        }
+
+        #[test]
+        fn subgraph_resolves_to_pinned_version() {
+            //* Given
+            let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+            let v0 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+            let v1 = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+            let network = test_network(
+                subgraph,
+                vec![
+                    (0, test_deployment(v0, subgraph)),
+                    (1, test_deployment(v1, subgraph)),
+                ],
+            );
+            let selector = QuerySelector::Subgraph(subgraph);
+
+            //* When
+            let pinned = resolve_query_deployments(&network, &selector, Some(0));
+            let missing = resolve_query_deployments(&network, &selector, Some(2));
+
+            //* Then
+            assert_matches!(pinned, Ok(deployments) => {
+                assert_eq!(deployments, vec![v0]);
+            });
+            assert_matches!(missing, Err(Error::SubgraphNotFound(_)));
+        }
+
+        #[test]
+        fn deployment_resolves_to_itself() {
+            //* Given
+            let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+            let v0 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+            let v1 = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+            let mut transferred = test_deployment(v1, subgraph);
+            Arc::get_mut(&mut transferred).unwrap().transferred_to_l2 = true;
+            let network = test_network(
+                subgraph,
+                vec![(0, test_deployment(v0, subgraph)), (1, transferred)],
+            );
+
+            //* When
+            let servable =
+                resolve_query_deployments(&network, &QuerySelector::Deployment(v0), None);
+            let not_servable =
+                resolve_query_deployments(&network, &QuerySelector::Deployment(v1), None);
+
+            //* Then
+            assert_matches!(servable, Ok(deployments) => {
+                assert_eq!(deployments, vec![v0]);
+            });
+            assert_matches!(not_servable, Err(Error::SubgraphNotFound(_)));
+        }
+
+        #[test]
+        fn subgraph_falls_back_to_prior_servable_version() {
+            //* Given
+            let subgraph = test_subgraph_id("184ba627DB853244c9f17f3Cb4378cB8B39bf147");
+            let v0 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+            let v1 = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+            let mut unservable = test_deployment(v1, subgraph);
+            Arc::get_mut(&mut unservable).unwrap().indexers.clear();
+            let network = test_network(
+                subgraph,
+                vec![(0, test_deployment(v0, subgraph)), (1, unservable)],
+            );
+            let selector = QuerySelector::Subgraph(subgraph);
+
+            //* When
+            let strict = resolve_subgraph_deployments(&network, &selector, false);
+            let fallback = resolve_subgraph_deployments(&network, &selector, true);
+
+            //* Then
+            assert_matches!(strict, Ok((deployments, _)) => {
+                let ids = deployments.iter().map(|d| d.id).collect::<Vec<_>>();
+                assert_eq!(ids, vec![v0, v1]);
+            });
+            assert_matches!(fallback, Ok((deployments, _)) => {
+                let ids = deployments.iter().map(|d| d.id).collect::<Vec<_>>();
+                assert_eq!(ids, vec![v0]);
+            });
+        }
+    }
+
+    mod select_candidates {
+        use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+        use alloy_primitives::Address;
+        use gateway_common::types::Indexing;
+        use gateway_framework::errors::{IndexerError, UnavailableReason};
+        use indexer_selection::{Candidate, Normalized, Performance};
+        use rand::Rng as _;
+        use thegraph_core::types::DeploymentId;
+
+        use super::super::{remove_drained_indexings, select_candidates, selection_rng};
+        use crate::network::{
+            indexer_drain_set::DrainSet,
+            indexer_score::{CandidateRanking, ScoreWeights},
+            indexer_tiers::IndexerTiers,
+        };
+
+        /// Test utility function to create a valid `DeploymentId` with an arbitrary deployment id/ipfs hash.
+        fn test_deployment_id(deployment: &str) -> DeploymentId {
+            deployment.parse().expect("invalid deployment id/ipfs hash")
+        }
+
+        /// Create a test candidate for the given indexer.
+        fn test_candidate(indexer: u8, fee: f64) -> Candidate {
+            Candidate {
+                indexer: Address::repeat_byte(indexer),
+                deployment: test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"),
+                url: format!("https://indexer-{indexer}.example.com/")
+                    .parse()
+                    .unwrap(),
+                perf: Performance::default().expected_performance(),
+                fee: Normalized::new(fee).unwrap(),
+                seconds_behind: indexer as u32,
+                slashable_grt: 100_000 * indexer as u64,
+                versions_behind: 0,
+                zero_allocation: false,
+            }
+        }
+
+        /// Create a list of test candidates.
+        fn test_candidates() -> Vec<Candidate> {
+            (1..=5)
+                .map(|indexer| test_candidate(indexer, indexer as f64 / 10.0))
+                .collect()
+        }
+
+        #[test]
+        fn same_seed_and_candidates_produce_identical_selections() {
+            //* Given
+            let mut rng_a = selection_rng(Some(42));
+            let mut rng_b = selection_rng(Some(42));
+
+            //* When
+            let selections_a = (0..10)
+                .map(|_| {
+                    select_candidates(
+                        &mut rng_a,
+                        &mut test_candidates(),
+                        &IndexerTiers::default(),
+                        None,
+                    )
+                    .into_iter()
+                    .map(|candidate| candidate.indexer)
+                    .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let selections_b = (0..10)
+                .map(|_| {
+                    select_candidates(
+                        &mut rng_b,
+                        &mut test_candidates(),
+                        &IndexerTiers::default(),
+                        None,
+                    )
+                    .into_iter()
+                    .map(|candidate| candidate.indexer)
+                    .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            //* Then
+            assert_eq!(selections_a, selections_b);
+            assert_eq!(rng_a.gen::<u64>(), rng_b.gen::<u64>());
+        }
+
+        #[test]
+        fn drained_indexer_indexings_are_not_available() {
+            //* Given
+            let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+            let drained = Address::repeat_byte(1);
+            let available = Address::repeat_byte(2);
+            let mut available_indexers = [drained, available]
+                .into_iter()
+                .map(|indexer| Indexing {
+                    indexer,
+                    deployment,
+                })
+                .collect::<BTreeSet<_>>();
+            let mut indexer_errors = BTreeMap::new();
+
+            let drain_set = DrainSet::default();
+            drain_set.drain(drained);
+
+            //* When
+            remove_drained_indexings(&mut available_indexers, &drain_set, &mut indexer_errors);
+
+            //* Then
+            assert_eq!(
+                available_indexers,
+                BTreeSet::from([Indexing {
+                    indexer: available,
+                    deployment,
+                }])
+            );
+            assert_eq!(
+                indexer_errors,
+                BTreeMap::from([(
+                    drained,
+                    IndexerError::Unavailable(UnavailableReason::Drained)
+                )])
+            );
+        }
+
+        #[test]
+        fn fully_drained_deployment_has_no_available_indexers() {
+            //* Given
+            let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+            let indexers = [Address::repeat_byte(1), Address::repeat_byte(2)];
+            let mut available_indexers = indexers
+                .into_iter()
+                .map(|indexer| Indexing {
+                    indexer,
+                    deployment,
+                })
+                .collect::<BTreeSet<_>>();
+            let mut indexer_errors = BTreeMap::new();
+
+            let drain_set = DrainSet::new(indexers.into_iter().collect());
+
+            //* When
+            remove_drained_indexings(&mut available_indexers, &drain_set, &mut indexer_errors);
+
+            //* Then
+            // The query fails with no available indexers, instead of selecting no candidates
+            assert!(available_indexers.is_empty());
+            assert_eq!(indexer_errors.len(), 2);
+        }
+
+        #[test]
+        fn higher_tier_indexer_is_selected_more_often() {
+            //* Given
+            let lower = Address::repeat_byte(1);
+            let higher = Address::repeat_byte(2);
+
+            // Candidates with equal stake, differing only in their tier
+            let candidates = || {
+                let mut lower_candidate = test_candidate(1, 0.2);
+                let mut higher_candidate = test_candidate(2, 0.2);
+                lower_candidate.seconds_behind = 0;
+                higher_candidate.seconds_behind = 0;
+                lower_candidate.slashable_grt = 100_000;
+                higher_candidate.slashable_grt = 100_000;
+                vec![lower_candidate, higher_candidate]
+            };
+            let tiers = IndexerTiers::new(HashMap::from([(higher, 2)]), 2.0);
+
+            let mut rng = selection_rng(Some(42));
+
+            //* When
+            let first_selected = (0..100)
+                .filter_map(|_| {
+                    select_candidates(&mut rng, &mut candidates(), &tiers, None)
+                        .first()
+                        .map(|candidate| candidate.indexer)
+                })
+                .collect::<Vec<_>>();
+
+            //* Then
+            let higher_count = first_selected.iter().filter(|&&i| i == higher).count();
+            let lower_count = first_selected.iter().filter(|&&i| i == lower).count();
+            assert_eq!(tiers.tier(&lower), 0);
+            assert!(higher_count > lower_count);
+        }
+
+        #[test]
+        fn freshness_weighted_ranking_only_considers_the_caught_up_indexer() {
+            //* Given
+            let caught_up = Address::repeat_byte(1);
+
+            // The most staked candidate is the most lagging one
+            let mut candidates = test_candidates();
+            for candidate in candidates.iter_mut() {
+                candidate.seconds_behind = 10 * (candidate.seconds_behind - 1);
+            }
+            let ranking = CandidateRanking {
+                weights: ScoreWeights {
+                    stake: 1.0,
+                    freshness: 10.0,
+                    latency: 1.0,
+                },
+                limit: 1,
+            };
+
+            //* When
+            let selected = select_candidates(
+                &mut selection_rng(Some(42)),
+                &mut candidates,
+                &IndexerTiers::default(),
+                Some(&ranking),
+            )
+            .into_iter()
+            .map(|candidate| candidate.indexer)
+            .collect::<Vec<_>>();
+
+            //* Then
+            assert_eq!(selected, [caught_up]);
+        }
+    }
 }
@@ -1,4 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 
 use alloy_primitives::Address;
 use alloy_sol_types::Eip712Domain;
@@ -13,10 +16,18 @@ use gateway_framework::{
     topology::network::GraphNetwork,
 };
 use ordered_float::NotNan;
+use rand::rngs::SmallRng;
 use tokio::sync::watch;
 use url::Url;
 
-use crate::indexer_client::IndexerClient;
+use crate::{
+    indexer_client::IndexerClient,
+    network::{
+        indexer_drain_set::DrainSet, indexer_score::CandidateRanking, indexer_tiers::IndexerTiers,
+    },
+    query_limits::QueryLimits,
+    query_validator::QueryValidator,
+};
 
 #[derive(Clone)]
 pub struct Context {
@@ -33,4 +44,19 @@ pub struct Context {
     pub attestation_domain: &'static Eip712Domain,
     pub bad_indexers: &'static HashSet<Address>,
     pub indexings_blocklist: Eventual<Ptr<HashSet<Indexing>>>,
+    pub query_limits: QueryLimits,
+    /// The client queries preflight checks.
+    pub query_validator: QueryValidator,
+    /// The indexer selection RNG, shared by all the queries.
+    ///
+    /// See [`crate::client_query::selection_rng`].
+    pub selection_rng: Arc<Mutex<SmallRng>>,
+    /// Indexers being drained, i.e., not selected for new queries.
+    pub indexer_drain_set: DrainSet,
+    /// Operator-defined indexer tiers, scaling the indexers' selection weight.
+    pub indexer_tiers: IndexerTiers,
+    /// The composite score ranking limiting the candidates considered for selection.
+    pub indexer_ranking: Option<CandidateRanking>,
+    /// Resolve subgraph queries to the newest servable version if the latest one is not servable.
+    pub subgraph_version_fallback: bool,
 }
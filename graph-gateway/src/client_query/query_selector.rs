@@ -7,7 +7,7 @@ use axum::{
     http::request::Parts,
     response::IntoResponse,
 };
-use gateway_framework::{errors::Error, graphql};
+use gateway_framework::{auth::authorization::QueryTarget, errors::Error, graphql};
 use thegraph_core::types::{DeploymentId, SubgraphId};
 
 /// Rejection type for the query selector extractor, [`QuerySelector`].
@@ -50,6 +50,15 @@ impl std::fmt::Display for QuerySelector {
     }
 }
 
+impl From<&QuerySelector> for QueryTarget {
+    fn from(value: &QuerySelector) -> Self {
+        match value {
+            QuerySelector::Deployment(id) => QueryTarget::Deployment(*id),
+            QuerySelector::Subgraph(id) => QueryTarget::Subgraph(*id),
+        }
+    }
+}
+
 #[async_trait]
 impl<S> FromRequestParts<S> for QuerySelector
 where
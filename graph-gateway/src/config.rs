@@ -1,8 +1,9 @@
 //! The Graph Gateway configuration.
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     fmt::{self, Display},
+    num::NonZeroUsize,
     path::PathBuf,
 };
 
@@ -11,7 +12,9 @@ use custom_debug::CustomDebug;
 use gateway_framework::{
     auth::methods::api_keys::APIKey,
     config::{Hidden, HiddenSecretKey},
+    topology::network::{L2TransferPolicy, ZeroStakePolicy},
 };
+use graph_gateway::{network::indexer_score::CandidateRanking, query_limits::QueryLimits};
 use secp256k1::SecretKey;
 use semver::Version;
 use serde::Deserialize;
@@ -22,6 +25,10 @@ use url::Url;
 #[serde_as]
 #[derive(CustomDebug, Deserialize)]
 pub struct Config {
+    /// Authorize the requests with no `Origin` header, e.g., server-to-server requests, when the
+    /// auth token restricts the allowed domains (default: false)
+    #[serde(default)]
+    pub allow_missing_origin: bool,
     #[serde(default)]
     pub api_keys: Option<ApiKeys>,
     pub attestations: AttestationConfig,
@@ -32,6 +39,10 @@ pub struct Config {
     /// Chain aliases
     #[serde(default)]
     pub chain_aliases: BTreeMap<String, String>,
+    /// List of indexer addresses to drain. Drained indexers stay in the network topology, but no
+    /// new queries are sent to them.
+    #[serde(default)]
+    pub drained_indexers: Vec<Address>,
     /// Ethereum RPC provider, or fixed exchange rate for testing
     pub exchange_rate_provider: ExchangeRateProvider,
     /// The Gateway unique identifier. This ID is used to identify the Gateway in the network
@@ -42,6 +53,17 @@ pub struct Config {
     pub gateway_id: Option<String>,
     /// Graph network environment identifier, inserted into Kafka messages
     pub graph_env_id: String,
+    /// Rank the query candidates by a composite score of their stake, freshness, and latency, and
+    /// only consider the top-ranked ones for selection. If not set, all the candidates are
+    /// considered.
+    #[serde(default)]
+    pub indexer_ranking: Option<CandidateRanking>,
+    /// Multiplier applied to the indexers' selection weight per tier (default: 2.0)
+    pub indexer_tier_multiplier: Option<f64>,
+    /// Operator-defined indexer tiers, independent of stake. Each indexer's selection weight is
+    /// scaled by the tier multiplier raised to its tier. Indexers not listed get tier 0.
+    #[serde(default)]
+    pub indexer_tiers: HashMap<Address, i32>,
     /// File path of CSV containing rows of `IpNetwork,Country`
     pub ip_blocker_db: Option<PathBuf>,
     /// IP rate limit in requests per second
@@ -51,6 +73,18 @@ pub struct Config {
     pub kafka: KafkaConfig,
     /// Format log output as JSON
     pub log_json: bool,
+    /// Maximum amount of tokens (in GRT wei) an indexer can have allocated to a deployment. Above
+    /// this amount, the allocation is considered corrupt and the indexing is ignored
+    /// (default: 20B GRT)
+    pub max_allocated_tokens: Option<u128>,
+    /// Treatment of indexers without (or with malformed) staked tokens: `drop` or `keep`
+    /// (default: drop)
+    #[serde(default)]
+    pub zero_stake_policy: ZeroStakePolicy,
+    /// Treatment of deployments flagged as transferred to L2 that are still allocated:
+    /// `serve_until_drained` or `stop_on_flag` (default: serve_until_drained)
+    #[serde(default)]
+    pub l2_transfer_policy: L2TransferPolicy,
     /// L2 gateway to forward client queries to
     #[debug(with = fmt_optional_url)]
     #[serde_as(as = "Option<DisplayFromStr>")]
@@ -65,6 +99,20 @@ pub struct Config {
     #[debug(with = Display::fmt)]
     #[serde_as(as = "DisplayFromStr")]
     pub network_subgraph: Url,
+    /// File holding the network subgraph auth token. The file is reloaded when changed, so the
+    /// token can be rotated without restarting the gateway.
+    pub network_subgraph_auth_token_file: Option<PathBuf>,
+    /// Maximum size, in bytes, of a network subgraph response page (default: 64 MiB)
+    pub network_subgraph_max_response_body_size: Option<usize>,
+    /// Number of subgraphs per network subgraph query page (default: 200)
+    pub network_subgraph_page_size: Option<NonZeroUsize>,
+    /// Maximum number of active allocations fetched per subgraph deployment (default: 100)
+    pub network_subgraph_allocations_limit: Option<NonZeroUsize>,
+    /// Network subgraph polling interval in seconds (default: 30 seconds)
+    pub network_subgraph_poll_interval: Option<u64>,
+    /// Maximum network subgraph polling interval in seconds, the polling interval is doubled on
+    /// each consecutive poll failure up to this value (default: 10 minutes)
+    pub network_subgraph_max_poll_interval: Option<u64>,
     /// Check payment state of client (disable for testnets)
     pub payment_required: bool,
     /// POI blocklist
@@ -78,8 +126,19 @@ pub struct Config {
     pub port_metrics: u16,
     /// Target for indexer fees paid per request
     pub query_fees_target: f64,
+    /// Limits applied to the client queries
+    #[serde(default)]
+    pub query_limits: QueryLimits,
     /// Scalar TAP config (receipt signing)
     pub scalar: Scalar,
+    /// Seed for the RNG of the randomized steps of the indexer selection, created once at startup.
+    /// Given the same seed and sequence of queries, the same random draws are made. If not set, a
+    /// random seed is used.
+    pub selection_seed: Option<u64>,
+    /// Resolve subgraph queries to the newest version with indexers if the latest version has none,
+    /// or is transferred to L2 (default: false)
+    #[serde(default)]
+    pub subgraph_version_fallback: bool,
     /// Subscriptions configuration
     pub subscriptions: Option<Subscriptions>,
 }
@@ -3,6 +3,8 @@ pub use urls::*;
 pub mod cost_models;
 pub mod indexing;
 pub mod indexing_statuses;
+#[cfg(feature = "grpc-status")]
+pub mod indexing_statuses_grpc;
 pub mod public_poi;
 mod urls;
 pub mod version;
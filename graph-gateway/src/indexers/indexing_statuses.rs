@@ -1,12 +1,18 @@
 use alloy_primitives::BlockNumber;
-use anyhow::{bail, ensure};
+use anyhow::{anyhow, ensure};
 use futures::future::join_all;
 use indoc::formatdoc;
 use itertools::Itertools;
+use reqwest::StatusCode;
 use serde::Deserialize;
 use serde_with::serde_as;
 use thegraph_core::types::DeploymentId;
-use thegraph_graphql_http::http_client::ReqwestExt as _;
+
+/// The indexer status endpoint is not supported, i.e., it responded with `404 Not Found` or
+/// `426 Upgrade Required`.
+#[derive(Debug, thiserror::Error)]
+#[error("status endpoint not supported: {0}")]
+pub struct UnsupportedStatusEndpoint(pub StatusCode);
 
 pub async fn query(
     client: &reqwest::Client,
@@ -27,21 +33,13 @@ pub async fn query(
                 }}
             }}"#
         };
-        client.post(status_url.clone()).send_graphql(query)
+        send_query(client, status_url.clone(), query)
     });
-    let results: Vec<Result<IndexingStatusesResponse, String>> = join_all(queries)
-        .await
-        .into_iter()
-        .map(|r| match r {
-            Ok(Ok(response)) => Ok(response),
-            Ok(Err(err)) => Err(err.to_string()),
-            Err(err) => Err(err.to_string()),
-        })
-        .collect();
+    let results: Vec<anyhow::Result<IndexingStatusesResponse>> = join_all(queries).await;
     ensure!(!results.is_empty(), "no results");
     if results.iter().all(|r| r.is_err()) {
         let error = results.into_iter().find_map(|r| r.err()).unwrap();
-        bail!("{error}");
+        return Err(error);
     }
     let indexing_statuses: Vec<IndexingStatusResponse> = results
         .into_iter()
@@ -50,6 +48,53 @@ pub async fn query(
     Ok(indexing_statuses)
 }
 
+/// Send the indexing statuses GraphQL query to the indexer status endpoint.
+///
+/// If the endpoint is not supported, an [`UnsupportedStatusEndpoint`] error is returned, so the
+/// caller can fall back to another status API.
+async fn send_query(
+    client: &reqwest::Client,
+    status_url: reqwest::Url,
+    query: String,
+) -> anyhow::Result<IndexingStatusesResponse> {
+    let response = client
+        .post(status_url)
+        .json(&serde_json::json!({ "query": query }))
+        .send()
+        .await?;
+    if matches!(
+        response.status(),
+        StatusCode::NOT_FOUND | StatusCode::UPGRADE_REQUIRED
+    ) {
+        return Err(UnsupportedStatusEndpoint(response.status()).into());
+    }
+
+    let response = response
+        .error_for_status()?
+        .json::<GraphQlResponse<IndexingStatusesResponse>>()
+        .await?;
+    response.data.ok_or_else(|| {
+        let errors = response
+            .errors
+            .into_iter()
+            .map(|err| err.message)
+            .join("; ");
+        anyhow!("graphql error: {errors}")
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct IndexingStatusesResponse {
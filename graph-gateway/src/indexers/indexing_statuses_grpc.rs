@@ -0,0 +1,172 @@
+//! The indexer status gRPC API client.
+//!
+//! Some indexers expose their indexing statuses over gRPC instead of the HTTP status endpoint. The
+//! API is defined in `proto/indexer_status.proto`.
+//!
+//! The unary calls are framed by hand over an HTTP/2 client, see [`client`], instead of depending
+//! on a full gRPC stack.
+
+use anyhow::{anyhow, bail, ensure};
+use prost::Message as _;
+use reqwest::header::CONTENT_TYPE;
+use thegraph_core::types::DeploymentId;
+
+use super::indexing_statuses::{BlockStatus, ChainStatus, IndexingStatusResponse};
+
+/// The `IndexingStatuses` method path.
+pub const INDEXING_STATUSES_PATH: &str =
+    "/graphprotocol.indexer.status.v1.IndexerStatus/IndexingStatuses";
+
+/// The length of the gRPC message frame header: a compression flag byte, and the message length
+/// as a big-endian `u32`.
+const FRAME_HEADER_LEN: usize = 5;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IndexingStatusesRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub deployments: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IndexingStatusesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub statuses: Vec<IndexingStatus>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IndexingStatus {
+    #[prost(string, tag = "1")]
+    pub deployment: String,
+    #[prost(message, repeated, tag = "2")]
+    pub chains: Vec<GrpcChainStatus>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GrpcChainStatus {
+    #[prost(string, tag = "1")]
+    pub network: String,
+    #[prost(uint64, optional, tag = "2")]
+    pub latest_block: Option<u64>,
+    #[prost(uint64, optional, tag = "3")]
+    pub earliest_block: Option<u64>,
+}
+
+impl TryFrom<IndexingStatus> for IndexingStatusResponse {
+    type Error = anyhow::Error;
+
+    fn try_from(status: IndexingStatus) -> Result<Self, Self::Error> {
+        Ok(IndexingStatusResponse {
+            subgraph: status.deployment.parse()?,
+            chains: status
+                .chains
+                .into_iter()
+                .map(|chain| ChainStatus {
+                    network: chain.network,
+                    latest_block: chain.latest_block.map(|number| BlockStatus { number }),
+                    earliest_block: chain.earliest_block.map(|number| BlockStatus { number }),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Create the HTTP/2 client for the gRPC calls.
+///
+/// The indexers' gRPC endpoints are reached over cleartext HTTP/2 without an upgrade, so the
+/// client assumes HTTP/2 with prior knowledge.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .http2_prior_knowledge()
+        .build()
+        .expect("valid gRPC client")
+}
+
+/// Query the indexing statuses of the given deployments over the indexer status gRPC API.
+pub async fn query(
+    client: &reqwest::Client,
+    indexer_url: &reqwest::Url,
+    deployments: &[DeploymentId],
+) -> anyhow::Result<Vec<IndexingStatusResponse>> {
+    let url = indexer_url.join(INDEXING_STATUSES_PATH)?;
+    let request = IndexingStatusesRequest {
+        deployments: deployments.iter().map(ToString::to_string).collect(),
+    };
+
+    let response = client
+        .post(url)
+        .header(CONTENT_TYPE, "application/grpc")
+        .header("te", "trailers")
+        .body(encode_frame(&request))
+        .send()
+        .await?;
+    ensure!(
+        response.status().is_success(),
+        "unexpected status: {}",
+        response.status()
+    );
+
+    // Errors are reported as trailers-only responses, i.e., in the headers
+    if let Some(status) = response.headers().get("grpc-status") {
+        if status != "0" {
+            let message = response
+                .headers()
+                .get("grpc-message")
+                .and_then(|message| message.to_str().ok())
+                .unwrap_or_default();
+            bail!("grpc status {status:?}: {message}");
+        }
+    }
+
+    let body = response.bytes().await?;
+    let response = IndexingStatusesResponse::decode(decode_frame(&body)?)?;
+    response
+        .statuses
+        .into_iter()
+        .map(IndexingStatusResponse::try_from)
+        .collect()
+}
+
+/// Frame the message as an uncompressed gRPC message.
+pub fn encode_frame(message: &impl prost::Message) -> Vec<u8> {
+    let message = message.encode_to_vec();
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + message.len());
+    frame.push(0);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message);
+    frame
+}
+
+/// Get the message of an uncompressed gRPC message frame.
+pub fn decode_frame(frame: &[u8]) -> anyhow::Result<&[u8]> {
+    ensure!(frame.len() >= FRAME_HEADER_LEN, "missing message frame");
+    ensure!(frame[0] == 0, "compressed messages are not supported");
+    let len = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]) as usize;
+    frame[FRAME_HEADER_LEN..]
+        .get(..len)
+        .ok_or_else(|| anyhow!("truncated message frame"))
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message as _;
+
+    use super::{decode_frame, encode_frame, IndexingStatusesRequest};
+
+    #[test]
+    fn message_frames_round_trip() {
+        //* Given
+        let request = IndexingStatusesRequest {
+            deployments: vec!["QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH".to_string()],
+        };
+
+        //* When
+        let frame = encode_frame(&request);
+        let decoded = decode_frame(&frame)
+            .map(IndexingStatusesRequest::decode)
+            .expect("valid frame");
+
+        //* Then
+        assert_eq!(decoded.expect("valid message"), request);
+        assert!(decode_frame(&frame[..frame.len() - 1]).is_err());
+    }
+}
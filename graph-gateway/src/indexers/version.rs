@@ -1,18 +1,43 @@
+use std::collections::HashSet;
+
 use semver::Version;
 use serde::Deserialize;
+use serde_with::serde_as;
 use thegraph_graphql_http::http_client::ReqwestExt as _;
 
 pub async fn query_indexer_service_version(
     client: &reqwest::Client,
     version_url: reqwest::Url,
 ) -> anyhow::Result<Version> {
+    let response = query_indexer_service_version_info(client, version_url).await?;
+    Ok(response.version)
+}
+
+/// Query the indexer service `/version` endpoint, including the features the indexer advertises.
+pub async fn query_indexer_service_version_info(
+    client: &reqwest::Client,
+    version_url: reqwest::Url,
+) -> anyhow::Result<IndexerServiceVersion> {
     let response = client
         .get(version_url)
         .send()
         .await?
-        .json::<IndexerVersion>()
+        .json::<IndexerServiceVersion>()
         .await?;
-    Ok(response.version)
+    Ok(response)
+}
+
+/// The indexer service `/version` endpoint response.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+pub struct IndexerServiceVersion {
+    /// The indexer service version.
+    pub version: Version,
+    /// The features the indexer advertises support for, e.g., `sql`. Empty if the indexer service
+    /// omits them.
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DefaultOnNull")]
+    pub features: HashSet<String>,
 }
 
 pub async fn query_graph_node_version(
@@ -52,4 +77,26 @@ mod tests {
         //// Then
         assert_eq!(version.version, Version::new(0, 1, 0));
     }
+
+    #[test]
+    fn deserialize_indexer_service_version_features_json() {
+        //* Given
+        let with_features = r#"{ "version": "1.0.0", "features": ["sql"] }"#;
+        let without_features = r#"{ "version": "1.0.0" }"#;
+        let null_features = r#"{ "version": "1.0.0", "features": null }"#;
+
+        //* When
+        let with_features: IndexerServiceVersion =
+            serde_json::from_str(with_features).expect("valid version response");
+        let without_features: IndexerServiceVersion =
+            serde_json::from_str(without_features).expect("valid version response");
+        let null_features: IndexerServiceVersion =
+            serde_json::from_str(null_features).expect("valid version response");
+
+        //* Then
+        assert_eq!(with_features.version, Version::new(1, 0, 0));
+        assert_eq!(with_features.features, HashSet::from(["sql".to_string()]));
+        assert!(without_features.features.is_empty());
+        assert!(null_features.features.is_empty());
+    }
 }
@@ -4,6 +4,8 @@ pub mod indexer_client;
 pub mod indexers;
 pub mod indexings_blocklist;
 pub mod network;
+pub mod query_limits;
+pub mod query_validator;
 pub mod reports;
 pub mod sql_constraints;
 pub mod subgraph_studio;
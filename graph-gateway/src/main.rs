@@ -4,8 +4,9 @@ use std::{
     fs::read_to_string,
     io::Write as _,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    num::NonZeroUsize,
     path::PathBuf,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -44,7 +45,7 @@ use gateway_framework::{
     },
     scalar::{self, ReceiptSigner},
     subscriptions::subgraph as subscriptions_subgraph,
-    topology::network::{Deployment, GraphNetwork},
+    topology::network::{BuildProgress, Deployment, GraphNetwork, DEFAULT_MAX_ALLOCATED_TOKENS},
 };
 use graph_gateway::{
     client_query::{self, context::Context},
@@ -52,6 +53,10 @@ use graph_gateway::{
     indexers,
     indexers::indexing,
     indexings_blocklist::{self, indexings_blocklist},
+    network::{
+        indexer_drain_set::DrainSet,
+        indexer_tiers::{IndexerTiers, DEFAULT_TIER_MULTIPLIER},
+    },
     reports::{report_client_query, report_indexer_query},
     subgraph_studio,
 };
@@ -133,11 +138,45 @@ async fn main() {
         ExchangeRateProvider::Rpc(url) => exchange_rate::grt_per_usd(url).await.unwrap(),
     };
 
-    let network_subgraph_client =
-        subgraph_client::Client::new(http_client.clone(), config.network_subgraph.clone());
-    let subgraphs =
-        network_subgraph::Client::create(network_subgraph_client, config.l2_gateway.is_some())
-            .await;
+    let network_subgraph_auth_token = match &config.network_subgraph_auth_token_file {
+        Some(path) => {
+            let auth_token = network_subgraph::AuthToken::from_file(path);
+            auth_token.spawn_file_reloader(
+                path.clone(),
+                Duration::from_secs(30),
+                await_shutdown_signals(),
+            );
+            auth_token
+        }
+        None => network_subgraph::AuthToken::default(),
+    };
+    let (subgraphs, subgraphs_block) = network_subgraph::Client::create(
+        http_client.clone(),
+        config.network_subgraph.clone(),
+        network_subgraph_auth_token,
+        config
+            .network_subgraph_max_response_body_size
+            .unwrap_or(network_subgraph::DEFAULT_MAX_RESPONSE_BODY_SIZE),
+        config
+            .network_subgraph_page_size
+            .map_or(network_subgraph::DEFAULT_PAGE_SIZE, NonZeroUsize::get),
+        config.network_subgraph_allocations_limit.map_or(
+            network_subgraph::DEFAULT_ALLOCATIONS_LIMIT,
+            NonZeroUsize::get,
+        ),
+        config
+            .network_subgraph_poll_interval
+            .map_or(network_subgraph::DEFAULT_POLL_INTERVAL, Duration::from_secs),
+        config.network_subgraph_max_poll_interval.map_or(
+            network_subgraph::DEFAULT_MAX_POLL_INTERVAL,
+            Duration::from_secs,
+        ),
+        config.l2_gateway.is_some(),
+    )
+    .await;
+    subgraphs_block
+        .pipe(|block| tracing::debug!(block, "network subgraph polled"))
+        .forever();
 
     let attestation_domain: &'static Eip712Domain =
         Box::leak(Box::new(attestation::eip712_domain(
@@ -147,7 +186,29 @@ async fn main() {
         )));
 
     let ip_blocker = IpBlocker::new(config.ip_blocker_db.as_deref()).unwrap();
-    let network = GraphNetwork::new(subgraphs, ip_blocker).await;
+    let network = GraphNetwork::new(
+        subgraphs,
+        ip_blocker,
+        config
+            .max_allocated_tokens
+            .unwrap_or(DEFAULT_MAX_ALLOCATED_TOKENS),
+        config.zero_stake_policy,
+        config.l2_transfer_policy,
+        Some(Arc::new(|progress: BuildProgress| {
+            if progress.subgraphs_processed % 1000 == 0
+                || progress.subgraphs_processed == progress.subgraphs_total
+            {
+                tracing::info!(
+                    subgraphs_processed = progress.subgraphs_processed,
+                    subgraphs_total = progress.subgraphs_total,
+                    deployments_built = progress.deployments_built,
+                    "network topology build progress"
+                );
+            }
+        })),
+        None,
+    )
+    .await;
 
     // Indexer blocklist
     // Periodically check the defective POIs list against the network indexers and update the
@@ -208,6 +269,7 @@ async fn main() {
 
     let auth_service = init_auth_service(
         config.payment_required,
+        config.allow_missing_origin,
         http_client.clone(),
         config.api_keys,
         http_client.clone(),
@@ -235,6 +297,20 @@ async fn main() {
         attestation_domain,
         bad_indexers,
         indexings_blocklist,
+        query_validator: config.query_limits.validator(),
+        query_limits: config.query_limits,
+        selection_rng: Arc::new(Mutex::new(client_query::selection_rng(
+            config.selection_seed,
+        ))),
+        indexer_drain_set: DrainSet::new(config.drained_indexers.into_iter().collect()),
+        indexer_tiers: IndexerTiers::new(
+            config.indexer_tiers,
+            config
+                .indexer_tier_multiplier
+                .unwrap_or(DEFAULT_TIER_MULTIPLIER),
+        ),
+        indexer_ranking: config.indexer_ranking,
+        subgraph_version_fallback: config.subgraph_version_fallback,
     };
 
     // Host metrics on a separate server with a port that isn't open to public requests.
@@ -442,6 +518,7 @@ fn graphql_error_response<S: ToString>(message: S) -> json::JsonResponse {
 /// This functions awaits the completion of the initial API keys and subscriptions fetches.
 async fn init_auth_service(
     payment_required: bool,
+    allow_missing_origin: bool,
     api_keys_http_client: reqwest::Client,
     api_keys: Option<ApiKeys>,
     subscriptions_http_client: reqwest::Client,
@@ -484,6 +561,7 @@ async fn init_auth_service(
 
     AuthContext::create(
         payment_required,
+        allow_missing_origin,
         api_keys_ev,
         special_api_keys,
         subscriptions_ev,
@@ -11,13 +11,18 @@ pub use snapshot::{
 };
 
 pub mod indexer_addr_blocklist;
+pub mod indexer_drain_set;
 pub mod indexer_host_blocklist;
 pub mod indexer_host_resolver;
 pub mod indexer_indexing_cost_model_compiler;
 pub mod indexer_indexing_cost_model_resolver;
 pub mod indexer_indexing_poi_blocklist;
+pub mod indexer_indexing_poi_reference;
 pub mod indexer_indexing_poi_resolver;
 pub mod indexer_indexing_progress_resolver;
+mod indexer_request_span;
+pub mod indexer_score;
+pub mod indexer_tiers;
 pub mod indexer_version_resolver;
 pub mod internal;
 mod service;
@@ -1,25 +1,162 @@
 //! Blocklist for indexer addresses.
 //!
-//! This is an implementation of a static address-based blocklist for indexers.
+//! This is an implementation of an address-based blocklist for indexers. The blocklist is either
+//! static, or backed by a file that can be reloaded without restarting the gateway.
+//!
+//! The blocklist file holds either a JSON array of addresses, or one address per line. Blank lines
+//! and lines starting with `#` are ignored.
 
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use alloy_primitives::Address;
-use gateway_common::blocklist::{Blocklist, Result as BlocklistResult};
+use gateway_common::{
+    blocklist::{Blocklist, Result as BlocklistResult},
+    file_reloader,
+};
 
 /// A blocklist for indexer addresses.
+///
+/// Cloning the blocklist returns a handle to the same underlying set, so reloads are visible to
+/// all the handles.
 #[derive(Debug, Clone, Default)]
 pub struct AddrBlocklist {
-    blocklist: HashSet<Address>,
+    blocklist: Arc<RwLock<HashSet<Address>>>,
+    /// The file backing the blocklist, if any.
+    path: Option<PathBuf>,
 }
 
 impl AddrBlocklist {
     /// Create a new [`AddrBlocklist`].
     pub fn new(conf: HashSet<Address>) -> Self {
-        Self { blocklist: conf }
+        Self {
+            blocklist: Arc::new(RwLock::new(conf)),
+            path: None,
+        }
+    }
+
+    /// Create a new [`AddrBlocklist`] backed by the given file.
+    ///
+    /// If the file is missing or empty, the blocklist has no entries. See [`Self::reload`].
+    pub fn from_file(path: impl Into<PathBuf>) -> Self {
+        let blocklist = Self {
+            blocklist: Default::default(),
+            path: Some(path.into()),
+        };
+        blocklist.reload();
+        blocklist
+    }
+
+    /// The number of blocked addresses.
+    pub fn len(&self) -> usize {
+        self.blocklist.read().unwrap().len()
+    }
+
+    /// Whether the blocklist has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.blocklist.read().unwrap().is_empty()
+    }
+
+    /// Reload the blocklist from its backing file, atomically replacing the blocked addresses.
+    ///
+    /// - If the blocklist is not backed by a file: nothing is done.
+    /// - If the file is missing: the blocklist is emptied.
+    /// - If the file cannot be read or is not a valid JSON array: a warning is logged and the
+    ///   current entries are kept.
+    /// - Malformed entries are skipped with a warning.
+    pub fn reload(&self) {
+        if let Some(path) = &self.path {
+            reload_from_file(&self.blocklist, path);
+        }
+    }
+
+    /// Spawn a background task reloading the blocklist whenever its backing file changes, checking
+    /// the file modification time every `interval`.
+    ///
+    /// The task stops once the `shutdown` future completes, or all the blocklist handles are
+    /// dropped. If the blocklist is not backed by a file, no task is spawned.
+    pub fn spawn_file_reloader(
+        &self,
+        interval: Duration,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let blocklist = Arc::downgrade(&self.blocklist);
+
+        file_reloader::spawn_file_reloader(path, interval, shutdown, move |path| {
+            // If all the blocklist handles were dropped, stop reloading
+            let Some(blocklist) = blocklist.upgrade() else {
+                return false;
+            };
+            reload_from_file(&blocklist, path);
+            true
+        });
     }
 }
 
+/// Reload the blocked addresses from the file. See [`AddrBlocklist::reload`].
+fn reload_from_file(blocklist: &RwLock<HashSet<Address>>, path: &Path) {
+    let addresses = match std::fs::read_to_string(path) {
+        Ok(contents) => match parse_blocklist_file(&contents) {
+            Ok(addresses) => addresses,
+            Err(err) => {
+                tracing::warn!(path = %path.display(), error = %err, "invalid address blocklist file");
+                return;
+            }
+        },
+        // A missing file means no blocklist
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+        Err(err) => {
+            tracing::warn!(path = %path.display(), error = %err, "failed to read address blocklist file");
+            return;
+        }
+    };
+
+    tracing::debug!(path = %path.display(), len = addresses.len(), "address blocklist reloaded");
+    *blocklist.write().unwrap() = addresses;
+}
+
+/// Parse the blocklist file contents, either a JSON array of addresses or one address per line.
+///
+/// Malformed entries are skipped with a warning.
+fn parse_blocklist_file(contents: &str) -> anyhow::Result<HashSet<Address>> {
+    let entries = if contents.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<serde_json::Value>>(contents)?
+            .into_iter()
+            .map(|entry| match entry {
+                serde_json::Value::String(entry) => entry,
+                entry => entry.to_string(),
+            })
+            .collect::<Vec<_>>()
+    } else {
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(ToString::to_string)
+            .collect()
+    };
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| match entry.parse::<Address>() {
+            Ok(addr) => Some(addr),
+            Err(err) => {
+                tracing::warn!(%entry, error = %err, "skipping malformed address blocklist entry");
+                None
+            }
+        })
+        .collect())
+}
+
 impl Blocklist for AddrBlocklist {
     type Resource<'a> = &'a Address;
 
@@ -28,10 +165,64 @@ impl Blocklist for AddrBlocklist {
     /// If the address is in the blocklist, return [`Result::Blocked`], otherwise return
     /// [`Result::Allowed`].
     fn check(&self, addr: &Address) -> BlocklistResult {
-        if self.blocklist.contains(addr) {
+        if self.blocklist.read().unwrap().contains(addr) {
             BlocklistResult::Blocked
         } else {
             BlocklistResult::Allowed
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use alloy_primitives::Address;
+    use gateway_common::blocklist::Blocklist as _;
+
+    use super::AddrBlocklist;
+
+    /// Test utility function to get a unique blocklist file path in the temp directory.
+    fn test_blocklist_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("addr-blocklist-{}-{name}.txt", std::process::id()))
+    }
+
+    #[test]
+    fn reloaded_blocklist_blocks_the_added_address() {
+        //* Given
+        let path = test_blocklist_path("reload");
+        let blocked = Address::repeat_byte(1);
+        let added = Address::repeat_byte(2);
+        std::fs::write(&path, format!("{blocked}\n")).unwrap();
+
+        let blocklist = AddrBlocklist::from_file(&path);
+        assert!(blocklist.check(&blocked).is_blocked());
+        assert!(blocklist.check(&added).is_allowed());
+
+        //* When
+        std::fs::write(&path, format!("{blocked}\nnot-an-address\n{added}\n")).unwrap();
+        blocklist.reload();
+
+        //* Then
+        // The malformed entry is skipped, the other entries are kept
+        assert_eq!(blocklist.len(), 2);
+        assert!(blocklist.check(&blocked).is_blocked());
+        assert!(blocklist.check(&added).is_blocked());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_blocklist_file_blocks_nothing() {
+        //* Given
+        let path = test_blocklist_path("missing");
+        let _ = std::fs::remove_file(&path);
+
+        //* When
+        let blocklist = AddrBlocklist::from_file(&path);
+
+        //* Then
+        assert!(blocklist.is_empty());
+        assert!(blocklist.check(&Address::repeat_byte(1)).is_allowed());
+    }
+}
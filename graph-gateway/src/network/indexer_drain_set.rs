@@ -0,0 +1,51 @@
+//! Set of indexers being drained.
+//!
+//! A drained indexer stays in the network topology, but it gets zero selection weight: new client
+//! queries are not sent to it while the in-flight ones complete. This allows taking an indexer out
+//! of rotation without the abrupt client errors a blocklist would cause.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
+
+use alloy_primitives::Address;
+
+/// A shared set of drained indexer addresses.
+///
+/// Cloning the set returns a handle to the same underlying set, so changes are visible to all the
+/// selectors holding a handle.
+#[derive(Debug, Clone, Default)]
+pub struct DrainSet(Arc<RwLock<HashSet<Address>>>);
+
+impl DrainSet {
+    /// Create a new [`DrainSet`] with the given drained indexers.
+    pub fn new(indexers: HashSet<Address>) -> Self {
+        Self(Arc::new(RwLock::new(indexers)))
+    }
+
+    /// Start draining the indexer.
+    pub fn drain(&self, indexer: Address) {
+        self.0.write().unwrap().insert(indexer);
+    }
+
+    /// Stop draining the indexer, bringing it back into rotation.
+    pub fn undrain(&self, indexer: &Address) {
+        self.0.write().unwrap().remove(indexer);
+    }
+
+    /// Check if the indexer is being drained.
+    pub fn contains(&self, indexer: &Address) -> bool {
+        self.0.read().unwrap().contains(indexer)
+    }
+
+    /// Get the number of indexers being drained.
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap().len()
+    }
+
+    /// Check if no indexer is being drained.
+    pub fn is_empty(&self) -> bool {
+        self.0.read().unwrap().is_empty()
+    }
+}
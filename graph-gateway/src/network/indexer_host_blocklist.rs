@@ -4,8 +4,12 @@
 //! The blocklist is loaded from a CSV file containing rows of `IpNetwork,Country`. Indexer URLs
 //! are resolved to IP addresses using a DNS resolver, and then checked against the blocklist. The
 //! result is cached so that subsequent calls with the same URL will return the same result.
+//!
+//! Additionally, entire hosting providers can be blocked by their Autonomous System Number (ASN).
+//! The resolved IP addresses are mapped to their ASN using an [`AsnTable`], loaded from a CSV file
+//! containing rows of `IpNetwork,ASN`.
 
-use std::{collections::HashSet, fs, net::IpAddr, path::Path};
+use std::{cmp::Reverse, collections::HashSet, fs, net::IpAddr, path::Path};
 
 use anyhow::Context as _;
 use gateway_common::blocklist::{Blocklist, Result as BlocklistResult};
@@ -22,6 +26,148 @@ pub fn load_ip_blocklist_conf(db_path: &Path) -> anyhow::Result<HashSet<IpNetwor
         .collect())
 }
 
+/// Load the IP-to-ASN lookup table from a CSV file.
+///
+/// The CSV file should contain rows of `IpNetwork,ASN`. See [`AsnTable::from_csv`].
+pub fn load_asn_table(db_path: &Path) -> anyhow::Result<AsnTable> {
+    let db = fs::read_to_string(db_path).context("ASN table DB")?;
+    Ok(AsnTable::from_csv(&db))
+}
+
+/// An IP-to-ASN lookup table.
+///
+/// The IP networks are flattened at load time into a sorted table of disjoint address ranges,
+/// each mapped to the ASN of the most specific network covering it, so lookups are a binary
+/// search. IPv4 addresses are mapped into the IPv6 address space, i.e., `::ffff:0:0/96`.
+#[derive(Debug, Clone, Default)]
+pub struct AsnTable {
+    /// The disjoint address ranges, sorted by their first address: `(first, last, asn)`.
+    ranges: Vec<(u128, u128, u32)>,
+    /// The number of IP networks the table was created from.
+    networks: usize,
+}
+
+impl AsnTable {
+    /// Create a new [`AsnTable`] from the given IP networks and their ASNs.
+    pub fn new(entries: impl IntoIterator<Item = (IpNetwork, u32)>) -> Self {
+        let mut networks = entries
+            .into_iter()
+            .map(|(network, asn)| {
+                let (first, last) = network_range(network);
+                (first, last, asn)
+            })
+            .collect::<Vec<_>>();
+
+        // CIDR networks are either disjoint or nested. Sort the enclosing networks before the
+        // networks nested in them.
+        networks.sort_by_key(|(first, last, _)| (*first, Reverse(*last)));
+
+        let mut ranges = Vec::new();
+        // The networks enclosing the current one, the innermost last
+        let mut enclosing: Vec<(u128, u128, u32)> = Vec::new();
+        // The first address not yet covered by the ranges, if any
+        let mut next: Option<u128> = Some(0);
+        for &(first, last, asn) in &networks {
+            // The enclosing networks ending before this network cover the rest of their addresses
+            while let Some(&(_, enclosing_last, enclosing_asn)) = enclosing.last() {
+                if enclosing_last >= first {
+                    break;
+                }
+                push_range(&mut ranges, &mut next, enclosing_last, enclosing_asn);
+                enclosing.pop();
+            }
+
+            // The innermost enclosing network covers the addresses up to this network
+            if let (Some(&(_, _, enclosing_asn)), Some(before)) =
+                (enclosing.last(), first.checked_sub(1))
+            {
+                push_range(&mut ranges, &mut next, before, enclosing_asn);
+            }
+
+            next = Some(first);
+            enclosing.push((first, last, asn));
+        }
+        while let Some((_, last, asn)) = enclosing.pop() {
+            push_range(&mut ranges, &mut next, last, asn);
+        }
+
+        Self {
+            ranges,
+            networks: networks.len(),
+        }
+    }
+
+    /// Parse the lookup table from CSV rows of `IpNetwork,ASN`.
+    ///
+    /// The ASN can be prefixed by `AS`, e.g., `AS15169`. Malformed rows, e.g., a header row, are
+    /// skipped.
+    pub fn from_csv(csv: &str) -> Self {
+        Self::new(csv.lines().filter_map(|line| {
+            let (network, asn) = line.split_once(',')?;
+            let asn = asn.trim();
+            let asn = asn
+                .strip_prefix("AS")
+                .or_else(|| asn.strip_prefix("as"))
+                .unwrap_or(asn);
+            Some((network.trim().parse().ok()?, asn.parse().ok()?))
+        }))
+    }
+
+    /// The number of IP networks in the table.
+    pub fn len(&self) -> usize {
+        self.networks
+    }
+
+    /// Whether the table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.networks == 0
+    }
+
+    /// Get the ASN of the IP address, i.e., the ASN of the most specific network containing it.
+    ///
+    /// IPv4-mapped IPv6 addresses (e.g., `::ffff:10.0.0.1`) are also looked up in the IPv4
+    /// networks.
+    pub fn lookup(&self, addr: IpAddr) -> Option<u32> {
+        let addr = address_key(addr);
+        let idx = self.ranges.partition_point(|(first, _, _)| *first <= addr);
+        let (_, last, asn) = self.ranges.get(idx.checked_sub(1)?)?;
+        (addr <= *last).then_some(*asn)
+    }
+}
+
+/// Get the first and last addresses of the IP network, IPv4 networks mapped into the IPv6 address
+/// space.
+fn network_range(network: IpNetwork) -> (u128, u128) {
+    let (addr, prefix) = match network {
+        IpNetwork::V4(network) => (
+            u128::from(network.network().to_ipv6_mapped()),
+            u32::from(network.prefix()) + 96,
+        ),
+        IpNetwork::V6(network) => (u128::from(network.network()), u32::from(network.prefix())),
+    };
+    let host_mask = u128::MAX.checked_shr(prefix).unwrap_or(0);
+    (addr & !host_mask, addr | host_mask)
+}
+
+/// Get the IP address as a lookup key, IPv4 addresses mapped into the IPv6 address space.
+fn address_key(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u128::from(addr.to_ipv6_mapped()),
+        IpAddr::V6(addr) => u128::from(addr),
+    }
+}
+
+/// Push the range from the next uncovered address up to `last`, if not empty, and advance the next
+/// uncovered address past it.
+fn push_range(ranges: &mut Vec<(u128, u128, u32)>, next: &mut Option<u128>, last: u128, asn: u32) {
+    if let Some(first) = *next {
+        if first <= last {
+            ranges.push((first, last, asn));
+            *next = last.checked_add(1);
+        }
+    }
+}
+
 /// An IP blocklist for indexers.
 ///
 /// This blocklist is used to block certain indexers based on the host IP address. The blocklist is
@@ -29,16 +175,66 @@ pub fn load_ip_blocklist_conf(db_path: &Path) -> anyhow::Result<HashSet<IpNetwor
 ///
 /// Indexer URLs are resolved to IP addresses, which are then checked against the blocklist. The
 /// result is cached so that subsequent calls with the same URL will return the same result.
+///
+/// Optionally, the IP addresses are also checked against a set of blocked ASNs. See
+/// [`HostBlocklist::with_blocked_asns`].
 #[derive(Debug, Clone, Default)]
 pub struct HostBlocklist {
     conf: HashSet<IpNetwork>,
+    /// The IP-to-ASN lookup table.
+    asn_table: AsnTable,
+    /// The blocked ASNs.
+    blocked_asns: HashSet<u32>,
 }
 
 impl HostBlocklist {
     /// Create a new [`HostBlocklist`] from the given blocklist.
     pub fn new(conf: HashSet<IpNetwork>) -> Self {
         tracing::debug!(blocked_networks = conf.len());
-        Self { conf }
+        Self {
+            conf,
+            ..Default::default()
+        }
+    }
+
+    /// Block the IP addresses belonging to the given ASNs, as mapped by the given lookup table.
+    ///
+    /// The ASN blocklist is checked independently of the IP networks blocklist, an address is
+    /// blocked if either of them matches.
+    pub fn with_blocked_asns(mut self, asn_table: AsnTable, blocked_asns: HashSet<u32>) -> Self {
+        tracing::debug!(
+            asn_table_networks = asn_table.len(),
+            blocked_asns = blocked_asns.len()
+        );
+        self.asn_table = asn_table;
+        self.blocked_asns = blocked_asns;
+        self
+    }
+
+    /// The number of blocked IP networks.
+    pub fn len(&self) -> usize {
+        self.conf.len()
+    }
+
+    /// The number of blocked ASNs.
+    pub fn asn_len(&self) -> usize {
+        self.blocked_asns.len()
+    }
+
+    /// Whether the blocklist has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.conf.is_empty() && self.blocked_asns.is_empty()
+    }
+
+    /// Check if the IP address belongs to any of the blocked ASNs.
+    fn is_asn_blocked(&self, addr: IpAddr) -> bool {
+        if self.blocked_asns.is_empty() {
+            return false;
+        }
+
+        self.asn_table
+            .lookup(addr)
+            .is_some_and(|asn| self.blocked_asns.contains(&asn))
     }
 }
 
@@ -49,15 +245,127 @@ impl Blocklist for HostBlocklist {
     ///
     /// If any of the resolved IP addresses are blocked, the function will return
     /// [`Result::Blocked`], otherwise it will return [`Result::Allowed`].
+    ///
+    /// IPv4-mapped IPv6 addresses (e.g., `::ffff:10.0.0.1`) are also checked against the blocked
+    /// IPv4 networks.
+    ///
+    /// The IP addresses belonging to any of the blocked ASNs are also blocked.
     fn check(&self, addrs: &[IpAddr]) -> BlocklistResult {
-        // Check if any of the IP addresses are contained in any of the blocked networks
-        if addrs
-            .iter()
-            .any(|addr| self.conf.iter().any(|net| net.contains(*addr)))
-        {
+        // Check if any of the IP addresses are contained in any of the blocked networks, or
+        // belong to any of the blocked ASNs
+        if addrs.iter().any(|addr| {
+            self.conf
+                .iter()
+                .any(|net| net.contains(*addr) || net.contains(addr.to_canonical()))
+                || self.is_asn_blocked(*addr)
+        }) {
             BlocklistResult::Blocked
         } else {
             BlocklistResult::Allowed
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, net::IpAddr};
+
+    use gateway_common::blocklist::Blocklist as _;
+
+    use super::{AsnTable, HostBlocklist};
+
+    #[test]
+    fn ipv6_addresses_are_checked_against_ipv6_networks() {
+        //* Given
+        let blocklist = HostBlocklist::new(HashSet::from([
+            "2001:db8::/32".parse().expect("valid network"),
+            "10.0.0.0/8".parse().expect("valid network"),
+        ]));
+
+        let blocked: IpAddr = "2001:db8::1".parse().unwrap();
+        let mapped: IpAddr = "::ffff:10.0.0.1".parse().unwrap();
+        let allowed: IpAddr = "2001:db9::1".parse().unwrap();
+
+        //* When
+        let blocked_result = blocklist.check(&[blocked]);
+        let mapped_result = blocklist.check(&[mapped]);
+        let allowed_result = blocklist.check(&[allowed]);
+
+        //* Then
+        assert!(blocked_result.is_blocked());
+        assert!(mapped_result.is_blocked());
+        assert!(allowed_result.is_allowed());
+    }
+
+    #[test]
+    fn asn_table_maps_addresses_to_the_most_specific_network() {
+        //* Given
+        let table = AsnTable::from_csv(
+            "network,asn\n10.0.0.0/8,AS100\n10.1.0.0/16,200\n2001:db8::/32,AS300\nnot-a-network,400\n",
+        );
+
+        //* When
+        let broad = table.lookup("10.2.0.1".parse().unwrap());
+        let specific = table.lookup("10.1.0.1".parse().unwrap());
+        let ipv6 = table.lookup("2001:db8::1".parse().unwrap());
+        let unknown = table.lookup("192.168.0.1".parse().unwrap());
+
+        //* Then
+        // The header and malformed rows are skipped
+        assert_eq!(table.len(), 3);
+        assert_eq!(broad, Some(100));
+        assert_eq!(specific, Some(200));
+        assert_eq!(ipv6, Some(300));
+        assert_eq!(unknown, None);
+    }
+
+    #[test]
+    fn addresses_in_blocked_asns_are_blocked() {
+        //* Given
+        let table = AsnTable::from_csv("10.0.0.0/8,AS100\n10.1.0.0/16,AS200\n");
+        let blocklist = HostBlocklist::new(HashSet::from(["192.168.0.0/16"
+            .parse()
+            .expect("valid network")]))
+        .with_blocked_asns(table, HashSet::from([200]));
+
+        let asn_blocked: IpAddr = "10.1.0.1".parse().unwrap();
+        let network_blocked: IpAddr = "192.168.0.1".parse().unwrap();
+        let allowed: IpAddr = "10.2.0.1".parse().unwrap();
+        let unknown_asn: IpAddr = "172.16.0.1".parse().unwrap();
+
+        //* When
+        let asn_blocked_result = blocklist.check(&[asn_blocked]);
+        let network_blocked_result = blocklist.check(&[network_blocked]);
+        let allowed_result = blocklist.check(&[allowed]);
+        let unknown_asn_result = blocklist.check(&[unknown_asn]);
+
+        //* Then
+        assert!(asn_blocked_result.is_blocked());
+        assert!(network_blocked_result.is_blocked());
+        assert!(allowed_result.is_allowed());
+        assert!(unknown_asn_result.is_allowed());
+    }
+
+    #[test]
+    fn asn_table_maps_addresses_in_nested_networks() {
+        //* Given
+        let table = AsnTable::from_csv(
+            "0.0.0.0/0,AS1\n10.0.0.0/8,AS100\n10.1.0.0/16,AS200\n10.1.1.0/24,AS300\n10.3.0.0/16,AS400\n",
+        );
+
+        //* When
+        let lookup = |addr: &str| table.lookup(addr.parse().unwrap());
+
+        //* Then
+        assert_eq!(lookup("10.1.1.1"), Some(300));
+        assert_eq!(lookup("::ffff:10.1.1.1"), Some(300));
+        assert_eq!(lookup("10.1.2.1"), Some(200));
+        assert_eq!(lookup("10.2.0.1"), Some(100));
+        assert_eq!(lookup("10.3.255.255"), Some(400));
+        assert_eq!(lookup("10.4.0.1"), Some(100));
+        assert_eq!(lookup("11.0.0.1"), Some(1));
+        assert_eq!(lookup("255.255.255.255"), Some(1));
+        // The IPv4 networks do not cover the IPv6 addresses
+        assert_eq!(lookup("2001:db8::1"), None);
+    }
+}
@@ -2,9 +2,14 @@
 //!
 //! This module provides a resolver for URL hosts. The resolver caches the results of host
 //! resolution to avoid repeated DNS lookups.
+//!
+//! Domains are resolved to both their IPv4 (A records) and IPv6 (AAAA records) addresses, so the
+//! host blocklist is checked against all the addresses an indexer is reachable at.
 use std::{borrow::Borrow, collections::HashMap, net::IpAddr, time::Duration};
 
-use hickory_resolver::{error::ResolveError, TokioAsyncResolver as DnsResolver};
+use hickory_resolver::{
+    config::LookupIpStrategy, error::ResolveError, system_conf, TokioAsyncResolver as DnsResolver,
+};
 use url::{Host, Url};
 
 /// The default timeout for the indexer host resolution.
@@ -39,6 +44,14 @@ impl ResolutionError {
     }
 }
 
+/// Create a DNS resolver based on the system configuration, looking up both the IPv4 and IPv6
+/// addresses of the domains.
+fn system_dns_resolver() -> Result<DnsResolver, ResolveError> {
+    let (config, mut opts) = system_conf::read_system_conf()?;
+    opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+    Ok(DnsResolver::tokio(config, opts))
+}
+
 /// A resolver for URL hosts.
 ///
 /// This resolver caches the results of host resolution to avoid repeated DNS lookups.
@@ -54,7 +67,7 @@ impl HostResolver {
     /// If a DNS resolver based on system configuration cannot be created, an error is returned.
     pub fn new() -> anyhow::Result<Self> {
         Ok(Self {
-            inner: DnsResolver::tokio_from_system_conf()?,
+            inner: system_dns_resolver()?,
             cache: Default::default(),
             timeout: DEFAULT_INDEXER_HOST_RESOLUTION_TIMEOUT,
         })
@@ -65,12 +78,18 @@ impl HostResolver {
     /// If a DNS resolver based on system configuration cannot be created, an error is returned.
     pub fn with_timeout(timeout: Duration) -> anyhow::Result<Self> {
         Ok(Self {
-            inner: DnsResolver::tokio_from_system_conf()?,
+            inner: system_dns_resolver()?,
             cache: Default::default(),
             timeout,
         })
     }
 
+    /// Cache the given resolution of the host, e.g., to resolve test hosts without DNS lookups.
+    #[cfg(test)]
+    pub fn insert_cached(&mut self, host: &str, addrs: Vec<IpAddr>) {
+        self.cache.insert(host.to_string(), Ok(addrs));
+    }
+
     /// Resolve the IP address of the given domain with a timeout.
     async fn resolve_domain(&mut self, domain: &str) -> Result<Vec<IpAddr>, ResolutionError> {
         tokio::time::timeout(self.timeout, self.inner.lookup_ip(domain))
@@ -82,8 +101,9 @@ impl HostResolver {
 
     /// Resolve the IP address of the given URL.
     ///
-    /// The URL is resolved to an IP address. The result is cached so that subsequent calls with the
-    /// same URL will return the same result.
+    /// The URL is resolved to all its IP addresses, e.g., all the A and AAAA records of a
+    /// round-robin DNS domain, in no particular order. The result is cached so that subsequent
+    /// calls with the same URL will return the same result.
     pub async fn resolve_url<U: Borrow<Url>>(
         &mut self,
         url: U,
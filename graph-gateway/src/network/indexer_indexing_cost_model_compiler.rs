@@ -4,27 +4,105 @@
 //! compilation results are cached, so if the same cost model source is compiled multiple times,
 //! the compilation result is returned from the cache.
 //!
+//! The cache entries are keyed by the indexer's address and URL, so if an indexer changes its URL,
+//! the cost models compiled from the old URL's sources are not served anymore.
+//!
 //! By default, the cost model compilation cache entries expire after 12 hours.
+//!
+//! Many indexers serve identical cost model sources, so the successfully compiled cost models are
+//! also kept in a bounded LRU cache keyed by the cost model source, shared across
+//! indexers. On a hit, the previously compiled cost model is returned instead of compiling the
+//! source again.
 
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
-use cost_model::{CompileError, CostModel};
+use alloy_primitives::Address;
+use cost_model::{CompileError, Context, CostModel};
 use eventuals::Ptr;
 use gateway_common::ttl_hash_map::TtlHashMap;
+use num_traits::cast::ToPrimitive as _;
+use url::Url;
 
 use crate::indexers::cost_models::CostModelSource;
 
 /// Default time-to-live for the cost model compilation cache entries: 12 hours.
 const DEFAULT_COMPILATION_CACHE_TTL: Duration = Duration::from_secs(12 * 60 * 60);
 
+/// Default capacity of the compiled cost models LRU cache: 1024 entries.
+pub const DEFAULT_COMPILED_MODELS_CACHE_CAPACITY: usize = 1024;
+
 /// Internal representation of a cost model source to be used as a key in the compilation cache
 /// hashmap.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct CostModelSrc {
+    indexer: Address,
+    indexer_url: Url,
     model: String,
     variables: Option<String>,
 }
 
+/// The cost model source, independent of the indexer serving it, used as a key in the compiled
+/// cost models cache.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct CompiledModelKey {
+    model: String,
+    variables: Option<String>,
+}
+
+/// A bounded cache of compiled cost models, keyed by their source, that evicts the least recently
+/// used entry when full.
+struct CompiledModelsCache {
+    capacity: usize,
+    /// Monotonic counter used to track the entries' last use.
+    tick: u64,
+    entries: HashMap<CompiledModelKey, (u64, Ptr<CostModel>)>,
+}
+
+impl CompiledModelsCache {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Get the compiled cost model, marking the entry as the most recently used.
+    fn get(&mut self, key: &CompiledModelKey) -> Option<Ptr<CostModel>> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(last_used, model)| {
+            *last_used = tick;
+            model.clone()
+        })
+    }
+
+    /// Insert the compiled cost model, evicting the least recently used entry if full.
+    fn insert(&mut self, key: CompiledModelKey, model: Ptr<CostModel>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (last_used, _))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                self.entries.remove(&lru);
+            }
+        }
+
+        self.tick += 1;
+        self.entries.insert(key, (self.tick, model));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
 /// Error type for cost model compilation.
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum CompilationError {
@@ -57,6 +135,7 @@ impl From<CompileError> for CompilationError {
 /// Resolve the indexers' cost models sources and compile them into cost models.
 pub struct CostModelCompiler {
     cache: TtlHashMap<CostModelSrc, Result<Ptr<CostModel>, CompilationError>>,
+    compiled: CompiledModelsCache,
 }
 
 impl Default for CostModelCompiler {
@@ -65,6 +144,7 @@ impl Default for CostModelCompiler {
     fn default() -> Self {
         Self {
             cache: TtlHashMap::with_ttl(DEFAULT_COMPILATION_CACHE_TTL),
+            compiled: CompiledModelsCache::with_capacity(DEFAULT_COMPILED_MODELS_CACHE_CAPACITY),
         }
     }
 }
@@ -74,14 +154,35 @@ impl CostModelCompiler {
     pub fn new(cache_ttl: Duration) -> Self {
         Self {
             cache: TtlHashMap::with_ttl(cache_ttl),
+            compiled: CompiledModelsCache::with_capacity(DEFAULT_COMPILED_MODELS_CACHE_CAPACITY),
         }
     }
 
-    /// Compile a cost model from sources.
+    /// Sets the capacity of the compiled cost models LRU cache. A capacity of 0 disables it.
+    ///
+    /// Defaults to [`DEFAULT_COMPILED_MODELS_CACHE_CAPACITY`].
+    pub fn with_compiled_models_cache_capacity(mut self, capacity: usize) -> Self {
+        self.compiled = CompiledModelsCache::with_capacity(capacity);
+        self
+    }
+
+    /// Get the capacity of the compiled cost models LRU cache.
+    pub fn compiled_models_cache_capacity(&self) -> usize {
+        self.compiled.capacity
+    }
+
+    /// Compile a cost model from sources fetched from the given indexer.
     ///
     /// The compilation result is cached, so if the same cost model source is compiled multiple
-    /// times, the compilation result is returned from the cache.
-    pub fn compile(&mut self, src: CostModelSource) -> Result<Ptr<CostModel>, CompilationError> {
+    /// times for the same indexer and URL, the compilation result is returned from the cache.
+    /// Identical sources served by different indexers are compiled only once, as long as the
+    /// compiled cost model is still in the LRU cache.
+    pub fn compile(
+        &mut self,
+        indexer: Address,
+        indexer_url: &Url,
+        src: CostModelSource,
+    ) -> Result<Ptr<CostModel>, CompilationError> {
         // Check the cost model source size
         if src.model.len() > (1 << 16) {
             return Err(CompilationError::CostModelTooLarge(src.model.len()));
@@ -89,6 +190,8 @@ impl CostModelCompiler {
 
         // Construct the cost model source representation
         let src = CostModelSrc {
+            indexer,
+            indexer_url: indexer_url.clone(),
             model: src.model,
             variables: src.variables,
         };
@@ -99,10 +202,23 @@ impl CostModelCompiler {
             Some(compilation_result) => compilation_result.clone(),
             None => {
                 let compilation_sources = src.clone();
-                let compilation_result =
-                    CostModel::compile(&src.model, &src.variables.unwrap_or_default())
-                        .map(Ptr::new)
-                        .map_err(Into::into);
+                let key = CompiledModelKey {
+                    model: src.model.clone(),
+                    variables: src.variables.clone(),
+                };
+                let compilation_result = match self.compiled.get(&key) {
+                    Some(model) => Ok(model),
+                    None => {
+                        let result =
+                            CostModel::compile(&src.model, &src.variables.unwrap_or_default())
+                                .map(Ptr::new)
+                                .map_err(CompilationError::from);
+                        if let Ok(model) = &result {
+                            self.compiled.insert(key, model.clone());
+                        }
+                        result
+                    }
+                };
 
                 // Cache the compilation result
                 self.cache
@@ -113,3 +229,187 @@ impl CostModelCompiler {
         }
     }
 }
+
+/// Query matching no cost model statement other than the `default` one.
+const DEFAULT_FEE_PROBE_QUERY: &str = "{ _gateway_probe_ }";
+
+/// Extract the cost model's global default fee (in GRT wei), i.e., the fee of a query matching
+/// only the `default` statement.
+///
+/// This is the minimum fee the indexer advertises, so budget filtering can compare against it
+/// without evaluating the cost model for every query. If the cost model has no `default`
+/// statement, `None` is returned.
+pub fn default_fee(model: &CostModel) -> Option<u128> {
+    let context = Context::new(DEFAULT_FEE_PROBE_QUERY, "").ok()?;
+    model.cost_with_context(&context).ok()?.to_u128()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::Address;
+    use url::Url;
+
+    use super::{default_fee, CostModelCompiler, CostModelSrc};
+    use crate::indexers::cost_models::CostModelSource;
+
+    /// Test helper to create a cost model source.
+    fn test_cost_model_source() -> CostModelSource {
+        CostModelSource {
+            deployment: "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+                .parse()
+                .expect("valid deployment id"),
+            model: "default => 0.00001;".to_string(),
+            variables: None,
+        }
+    }
+
+    #[test]
+    fn indexer_url_change_invalidates_cached_cost_model() {
+        //* Given
+        let mut compiler = CostModelCompiler::default();
+        let indexer = Address::repeat_byte(1);
+        let old_url: Url = "https://old.indexer.example.com/".parse().unwrap();
+        let new_url: Url = "https://new.indexer.example.com/".parse().unwrap();
+
+        let cache_key = |url: &Url| {
+            let src = test_cost_model_source();
+            CostModelSrc {
+                indexer,
+                indexer_url: url.clone(),
+                model: src.model,
+                variables: src.variables,
+            }
+        };
+
+        compiler
+            .compile(indexer, &old_url, test_cost_model_source())
+            .expect("valid cost model");
+        compiler
+            .compile(indexer, &old_url, test_cost_model_source())
+            .expect("valid cost model");
+        assert_eq!(compiler.cache.len(), 1);
+
+        //* When
+        compiler
+            .compile(indexer, &new_url, test_cost_model_source())
+            .expect("valid cost model");
+
+        //* Then
+        // The cost model was compiled again for the new URL, instead of being served from the
+        // old URL's cache entry
+        assert_eq!(compiler.cache.len(), 2);
+        assert!(compiler.cache.get(&cache_key(&old_url)).is_some());
+        assert!(compiler.cache.get(&cache_key(&new_url)).is_some());
+    }
+
+    #[test]
+    fn default_fee_is_extracted_from_the_default_statement() {
+        //* Given
+        let mut compiler = CostModelCompiler::default();
+        let indexer = Address::repeat_byte(1);
+        let url: Url = "https://indexer.example.com/".parse().unwrap();
+
+        let model = compiler
+            .compile(indexer, &url, test_cost_model_source())
+            .expect("valid cost model");
+        let doubled_model = compiler
+            .compile(
+                indexer,
+                &url,
+                CostModelSource {
+                    model: "default => 0.00002;".to_string(),
+                    ..test_cost_model_source()
+                },
+            )
+            .expect("valid cost model");
+
+        //* When
+        let fee = default_fee(&model);
+        let doubled_fee = default_fee(&doubled_model);
+
+        //* Then
+        let fee = fee.expect("default fee");
+        assert!(fee > 0);
+        assert_eq!(doubled_fee, Some(2 * fee));
+    }
+
+    #[test]
+    fn identical_source_compilation_is_skipped_across_indexers() {
+        //* Given
+        let mut compiler = CostModelCompiler::default();
+        let url: Url = "https://indexer.example.com/".parse().unwrap();
+
+        let model = compiler
+            .compile(Address::repeat_byte(1), &url, test_cost_model_source())
+            .expect("valid cost model");
+
+        //* When
+        let other_model = compiler
+            .compile(Address::repeat_byte(2), &url, test_cost_model_source())
+            .expect("valid cost model");
+
+        //* Then
+        // The second indexer got the previously compiled cost model instance
+        assert_eq!(compiler.cache.len(), 2);
+        assert_eq!(compiler.compiled.len(), 1);
+        assert!(std::ptr::eq(&*model, &*other_model));
+    }
+
+    #[test]
+    fn least_recently_used_compiled_model_is_evicted() {
+        //* Given
+        let mut compiler = CostModelCompiler::default().with_compiled_models_cache_capacity(2);
+        let indexer = Address::repeat_byte(1);
+        let url: Url = "https://indexer.example.com/".parse().unwrap();
+        let source = |fee: &str| CostModelSource {
+            model: format!("default => {fee};"),
+            ..test_cost_model_source()
+        };
+
+        let first = compiler
+            .compile(indexer, &url, source("0.00001"))
+            .expect("valid cost model");
+        compiler
+            .compile(indexer, &url, source("0.00002"))
+            .expect("valid cost model");
+
+        //* When
+        compiler
+            .compile(indexer, &url, source("0.00003"))
+            .expect("valid cost model");
+        let first_recompiled = compiler
+            .compile(Address::repeat_byte(2), &url, source("0.00001"))
+            .expect("valid cost model");
+
+        //* Then
+        // The first cost model was evicted, so it was compiled again
+        assert_eq!(compiler.compiled.len(), 2);
+        assert!(!std::ptr::eq(&*first, &*first_recompiled));
+    }
+
+    #[test]
+    fn sources_differing_only_in_variables_are_compiled_separately() {
+        //* Given
+        let mut compiler = CostModelCompiler::default();
+        let url: Url = "https://indexer.example.com/".parse().unwrap();
+        let source = |variables: &str| CostModelSource {
+            model: "default => $fee;".to_string(),
+            variables: Some(variables.to_string()),
+            ..test_cost_model_source()
+        };
+
+        let model = compiler
+            .compile(Address::repeat_byte(1), &url, source(r#"{"fee": 0.00001}"#))
+            .expect("valid cost model");
+
+        //* When
+        let other_model = compiler
+            .compile(Address::repeat_byte(2), &url, source(r#"{"fee": 0.00002}"#))
+            .expect("valid cost model");
+
+        //* Then
+        // Each source got its own compiled cost model instance
+        assert_eq!(compiler.compiled.len(), 2);
+        assert!(!std::ptr::eq(&*model, &*other_model));
+    }
+}
@@ -7,6 +7,7 @@ use std::{collections::HashMap, time::Duration};
 use thegraph_core::types::DeploymentId;
 use url::Url;
 
+use super::indexer_request_span::{instrument_request, RequestError};
 use crate::{indexers, indexers::cost_models::CostModelSource};
 
 /// The default timeout for the indexer indexings' cost model resolution.
@@ -24,6 +25,15 @@ pub enum ResolutionError {
     Timeout,
 }
 
+impl RequestError for ResolutionError {
+    fn status(&self) -> &'static str {
+        match self {
+            ResolutionError::FetchError(_) => "error",
+            ResolutionError::Timeout => "timeout",
+        }
+    }
+}
+
 /// Resolve the indexers' cost models sources and compile them into cost models.
 pub struct CostModelResolver {
     client: reqwest::Client,
@@ -50,14 +60,25 @@ impl CostModelResolver {
         indexings: &[DeploymentId],
     ) -> Result<Vec<CostModelSource>, ResolutionError> {
         let indexer_cost_url = indexers::cost_url(url);
-        tokio::time::timeout(
-            self.timeout,
-            // TODO: Handle the different errors once the indexers client module reports them
-            indexers::cost_models::query(&self.client, indexer_cost_url, indexings),
-        )
+
+        let span = tracing::debug_span!(
+            "indexer cost models request",
+            url = %indexer_cost_url,
+            deployments = indexings.len(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        instrument_request(span, async {
+            tokio::time::timeout(
+                self.timeout,
+                // TODO: Handle the different errors once the indexers client module reports them
+                indexers::cost_models::query(&self.client, indexer_cost_url, indexings),
+            )
+            .await
+            .map_err(|_| ResolutionError::Timeout)
+            .and_then(|res| res.map_err(ResolutionError::FetchError))
+        })
         .await
-        .map_err(|_| ResolutionError::Timeout)?
-        .map_err(ResolutionError::FetchError)
     }
 
     /// Fetches the cost model sources for the given deployments from the indexer.
@@ -82,3 +103,32 @@ impl CostModelResolver {
         Ok(sources)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use thegraph_core::types::DeploymentId;
+    use url::Url;
+
+    use super::CostModelResolver;
+    use crate::network::indexer_request_span::tests::request_span_status;
+
+    #[tokio::test]
+    async fn cost_models_request_is_instrumented() {
+        //* Given
+        let resolver = CostModelResolver::new(reqwest::Client::new());
+        let url: Url = "http://127.0.0.1:1/".parse().expect("valid url");
+        let deployment: DeploymentId = "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+            .parse()
+            .expect("valid deployment id");
+
+        //* When
+        let status = request_span_status(
+            "indexer cost models request",
+            resolver.resolve_cost_model(&url, &[deployment]),
+        )
+        .await;
+
+        //* Then
+        assert_eq!(status.as_deref(), Some("error"));
+    }
+}
@@ -6,37 +6,81 @@
 //!
 //! The blocklist caches the blocklist state for each indexer, so that subsequent checks against the
 //! same indexer are fast. The cached entries are considered expired after a given TTL.
+//!
+//! New blocked POIs can be appended at runtime, e.g., mid-incident, without restarting the
+//! gateway. See [`PoiBlocklist::append`].
+//!
+//! Additionally, an opt-in heuristic flags indexers reporting the same POI for distinct
+//! deployments, see [`PoiCollisionPolicy`].
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, PoisonError, RwLock},
+};
 
 use alloy_primitives::BlockNumber;
 use gateway_common::blocklist::Result as BlocklistResult;
 use itertools::Itertools;
+use serde::Serialize;
 use thegraph_core::types::{DeploymentId, ProofOfIndexing};
 
 use crate::indexers::public_poi::ProofOfIndexingInfo;
 
 /// A blocklist based on the Proof of Indexing (POI) of indexers.
+///
+/// Cloning the blocklist returns a handle to the same underlying entries, so the appended entries
+/// are visible to all the handles.
+#[derive(Debug, Clone, Default)]
 pub struct PoiBlocklist {
-    blocklist: HashMap<DeploymentId, HashSet<ProofOfIndexingInfo>>,
+    blocklist: Arc<RwLock<HashMap<DeploymentId, HashSet<ProofOfIndexingInfo>>>>,
 }
 
 impl PoiBlocklist {
     /// Create a new indexer POI blocklist with the given configuration.
     pub fn new(conf: HashSet<ProofOfIndexingInfo>) -> Self {
+        let blocklist = Self::default();
+        blocklist.append(conf);
+        blocklist
+    }
+
+    /// Append the given entries to the blocklist.
+    ///
+    /// The appended entries are picked up by the next POI check, i.e., the next network topology
+    /// update. Entries affecting deployments no indexer serves have no effect until an indexer
+    /// serves them.
+    pub fn append(&self, entries: impl IntoIterator<Item = ProofOfIndexingInfo>) {
+        // The entries are inserted one by one, so a writer panicking midway leaves a valid
+        // blocklist. Recover from the lock poisoning instead of propagating the panic.
+        let mut blocklist = self
+            .blocklist
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+
         // Group the blocked POI info by deployment ID
-        let mut conf_map = HashMap::new();
-        for info in conf {
-            let deployment_id = info.deployment_id;
-            conf_map
-                .entry(deployment_id)
-                .or_insert_with(HashSet::new)
+        for info in entries {
+            blocklist
+                .entry(info.deployment_id)
+                .or_default()
                 .insert(info);
         }
+    }
 
-        Self {
-            blocklist: conf_map,
-        }
+    /// The number of blocked POIs, across all deployments.
+    pub fn len(&self) -> usize {
+        self.blocklist
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .map(HashSet::len)
+            .sum()
+    }
+
+    /// Whether the blocklist has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.blocklist
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
     }
 
     /// Get a list of POIs metadata that are affected.
@@ -47,10 +91,14 @@ impl PoiBlocklist {
         &self,
         deployments: &[DeploymentId],
     ) -> Vec<(DeploymentId, BlockNumber)> {
+        let blocklist = self
+            .blocklist
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
         deployments
             .iter()
             .flat_map(|deployment_id| {
-                self.blocklist
+                blocklist
                     .get(deployment_id)
                     .into_iter()
                     .flat_map(|pois| pois.iter().map(|poi_info| poi_info.meta()))
@@ -80,7 +128,12 @@ impl PoiBlocklist {
         block_number: BlockNumber,
         poi: ProofOfIndexing,
     ) -> BlocklistResult {
-        match self.blocklist.get(&deployment_id) {
+        match self
+            .blocklist
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&deployment_id)
+        {
             None => BlocklistResult::Allowed,
             Some(blocked_pois) => {
                 // Check if the POI is blocked
@@ -97,3 +150,160 @@ impl PoiBlocklist {
         }
     }
 }
+
+/// The policy applied to indexers reporting the same POI for distinct deployments.
+///
+/// Distinct deployments are expected to have distinct POIs, so a collision is a strong signal of
+/// an indexer faking its POIs. This is a heuristic, disabled by default.
+///
+/// The check runs over a sample of each indexer's POIs. The indexers reporting colliding POIs are
+/// counted in the `gw_indexer_poi_collisions` metric.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum PoiCollisionPolicy {
+    /// Do not check for POI collisions.
+    #[default]
+    Disabled,
+    /// Record the colliding POIs, but keep the indexer.
+    Flag,
+    /// Block the indexer.
+    Block,
+}
+
+/// Find the POIs reported for more than one distinct deployment.
+///
+/// Returns the colliding POIs, each with the list of deployments it was reported for.
+pub fn find_poi_collisions(
+    pois: &HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>,
+) -> HashMap<ProofOfIndexing, Vec<DeploymentId>> {
+    let mut deployments_by_poi: HashMap<ProofOfIndexing, Vec<DeploymentId>> = HashMap::new();
+    for ((deployment_id, _), poi) in pois {
+        let deployments = deployments_by_poi.entry(*poi).or_default();
+        if !deployments.contains(deployment_id) {
+            deployments.push(*deployment_id);
+        }
+    }
+
+    deployments_by_poi.retain(|_, deployments| deployments.len() > 1);
+    deployments_by_poi
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use thegraph_core::types::{DeploymentId, ProofOfIndexing};
+
+    use super::{find_poi_collisions, PoiBlocklist, ProofOfIndexingInfo};
+
+    /// Test utility function to create a valid `DeploymentId` with an arbitrary deployment id/ipfs hash.
+    fn test_deployment_id(deployment: &str) -> DeploymentId {
+        deployment.parse().expect("invalid deployment id/ipfs hash")
+    }
+
+    #[test]
+    fn poi_reported_for_distinct_deployments_is_flagged() {
+        //* Given
+        let deployment_a = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_b = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let duplicated_poi = ProofOfIndexing::from([1u8; 32]);
+
+        let pois = HashMap::from([
+            ((deployment_a, 100), duplicated_poi),
+            ((deployment_a, 200), ProofOfIndexing::from([2u8; 32])),
+            ((deployment_b, 100), duplicated_poi),
+        ]);
+
+        //* When
+        let collisions = find_poi_collisions(&pois);
+
+        //* Then
+        assert_eq!(collisions.len(), 1);
+        let mut deployments = collisions
+            .get(&duplicated_poi)
+            .expect("colliding poi")
+            .clone();
+        deployments.sort_by_key(|deployment| deployment.to_string());
+        let mut expected = vec![deployment_a, deployment_b];
+        expected.sort_by_key(|deployment| deployment.to_string());
+        assert_eq!(deployments, expected);
+    }
+
+    #[test]
+    fn poi_reported_for_the_same_deployment_is_not_flagged() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let poi = ProofOfIndexing::from([1u8; 32]);
+
+        let pois = HashMap::from([((deployment, 100), poi), ((deployment, 200), poi)]);
+
+        //* When
+        let collisions = find_poi_collisions(&pois);
+
+        //* Then
+        assert!(collisions.is_empty());
+    }
+
+    #[test]
+    fn append_recovers_from_a_poisoned_lock() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let blocklist = PoiBlocklist::new(HashSet::new());
+
+        // Poison the lock by panicking while holding it
+        let handle = blocklist.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = handle.blocklist.write().unwrap();
+            panic!("poison the blocklist lock");
+        })
+        .join();
+        assert!(blocklist.blocklist.is_poisoned());
+
+        //* When
+        blocklist.append([ProofOfIndexingInfo {
+            proof_of_indexing: ProofOfIndexing::from([1u8; 32]),
+            deployment_id: deployment,
+            block_number: 100,
+        }]);
+
+        //* Then
+        assert_eq!(blocklist.len(), 1);
+        assert_eq!(blocklist.affected_pois_metadata(&[deployment]).len(), 1);
+    }
+
+    #[test]
+    fn appended_pois_affect_the_served_deployments() {
+        //* Given
+        let served = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let unserved = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let poi = ProofOfIndexing::from([1u8; 32]);
+
+        let blocklist = PoiBlocklist::new(HashSet::new());
+        let handle = blocklist.clone();
+
+        //* When
+        handle.append([ProofOfIndexingInfo {
+            proof_of_indexing: poi,
+            deployment_id: unserved,
+            block_number: 100,
+        }]);
+        let unserved_affected = blocklist.affected_pois_metadata(&[served]);
+
+        handle.append([ProofOfIndexingInfo {
+            proof_of_indexing: poi,
+            deployment_id: served,
+            block_number: 200,
+        }]);
+        let served_affected = blocklist.affected_pois_metadata(&[served]);
+
+        //* Then
+        // Appending a POI of a deployment not served is a no-op for the indexer
+        assert!(unserved_affected.is_empty());
+        assert_eq!(served_affected, [(served, 200)]);
+        assert_eq!(blocklist.len(), 2);
+        assert!(blocklist
+            .check(HashMap::from([((served, 200), poi)]))
+            .get(&served)
+            .expect("checked deployment")
+            .is_blocked());
+    }
+}
@@ -0,0 +1,115 @@
+//! Trusted reference Proof of Indexing (POI) sources.
+//!
+//! Instead of blocking a static set of known-bad POIs (see
+//! [`PoiBlocklist`](super::indexer_indexing_poi_blocklist::PoiBlocklist)), the indexers' reported
+//! POIs can be compared against trusted reference POIs, e.g., the POIs reported by a local trusted
+//! indexer or loaded from a file. The reference POIs come from a pluggable [`PoiReferenceSource`].
+//!
+//! The POIs stage resolves the indexers' POIs at the reference blocks, and blocks the indexings
+//! whose POIs do not match the reference ones.
+
+use std::collections::HashMap;
+
+use alloy_primitives::BlockNumber;
+use gateway_common::blocklist::Result as BlocklistResult;
+use thegraph_core::types::{DeploymentId, ProofOfIndexing};
+
+/// A source of trusted reference POIs.
+pub trait PoiReferenceSource: Send + Sync {
+    /// Get the trusted POI of the deployment at the given block number, if known.
+    fn reference_poi(
+        &self,
+        deployment: &DeploymentId,
+        block: BlockNumber,
+    ) -> Option<ProofOfIndexing>;
+
+    /// Get the block numbers the source has trusted POIs for, for the given deployment.
+    ///
+    /// The indexers' POIs are resolved at these blocks to be checked against the source.
+    fn reference_blocks(&self, deployment: &DeploymentId) -> Vec<BlockNumber>;
+}
+
+/// An in-memory reference source, e.g., loaded from a file.
+impl PoiReferenceSource for HashMap<(DeploymentId, BlockNumber), ProofOfIndexing> {
+    fn reference_poi(
+        &self,
+        deployment: &DeploymentId,
+        block: BlockNumber,
+    ) -> Option<ProofOfIndexing> {
+        self.get(&(*deployment, block)).copied()
+    }
+
+    fn reference_blocks(&self, deployment: &DeploymentId) -> Vec<BlockNumber> {
+        self.keys()
+            .filter(|(id, _)| id == deployment)
+            .map(|(_, block)| *block)
+            .collect()
+    }
+}
+
+/// Check the reported POIs against the reference source.
+///
+/// - If the source has no reference POI for the deployment and block: the indexing is ALLOWED.
+/// - If the reported POI matches the reference POI: the indexing is ALLOWED.
+/// - If any reported POI for the deployment does not match: the indexing is BLOCKED.
+pub fn check_against_reference(
+    source: &dyn PoiReferenceSource,
+    pois: &HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>,
+) -> HashMap<DeploymentId, BlocklistResult> {
+    let mut result = HashMap::new();
+    for ((deployment, block), poi) in pois {
+        let state = match source.reference_poi(deployment, *block) {
+            Some(reference) if reference != *poi => BlocklistResult::Blocked,
+            _ => BlocklistResult::Allowed,
+        };
+
+        // A single mismatching POI blocks the deployment
+        let entry = result
+            .entry(*deployment)
+            .or_insert(BlocklistResult::Allowed);
+        if state.is_blocked() {
+            *entry = state;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use thegraph_core::types::{DeploymentId, ProofOfIndexing};
+
+    use super::check_against_reference;
+
+    /// Test utility function to create a valid `DeploymentId` with an arbitrary deployment id/ipfs hash.
+    fn test_deployment_id(deployment: &str) -> DeploymentId {
+        deployment.parse().expect("invalid deployment id/ipfs hash")
+    }
+
+    #[test]
+    fn reported_pois_are_checked_against_the_reference_source() {
+        //* Given
+        let matching = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let mismatching = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let unknown = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+
+        let source = HashMap::from([
+            ((matching, 100), ProofOfIndexing::from([1u8; 32])),
+            ((mismatching, 100), ProofOfIndexing::from([2u8; 32])),
+        ]);
+        let pois = HashMap::from([
+            ((matching, 100), ProofOfIndexing::from([1u8; 32])),
+            ((mismatching, 100), ProofOfIndexing::from([3u8; 32])),
+            ((unknown, 100), ProofOfIndexing::from([4u8; 32])),
+        ]);
+
+        //* When
+        let result = check_against_reference(&source, &pois);
+
+        //* Then
+        assert!(result[&matching].is_allowed());
+        assert!(result[&mismatching].is_blocked());
+        assert!(result[&unknown].is_allowed());
+    }
+}
@@ -4,15 +4,25 @@
 //! results of these requests to avoid making the same request multiple times.
 //!
 //! The cache has a TTL of 20 minutes. Entries are considered expired after this time causing the
-//! resolver to fetch the public POIs of the indexer again.
+//! resolver to fetch the public POIs of the indexer again. Requesting POIs not covered by the cached
+//! entry, e.g., after new POIs are blocked, also causes the resolver to fetch them again.
+//!
+//! Concurrent resolutions for the same indexer and POIs metadata share a single in-flight request,
+//! and its result, be it a success or a failure.
 
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use alloy_primitives::BlockNumber;
 use gateway_common::ttl_hash_map::TtlHashMap;
 use thegraph_core::types::{DeploymentId, ProofOfIndexing};
+use tokio::sync::OnceCell;
 use url::Url;
 
+use super::indexer_request_span::{instrument_request, RequestError};
 use crate::indexers;
 
 /// The default TTL for cache entries is 20 minutes. Entries are considered expired after this time.
@@ -25,17 +35,43 @@ pub const DEFAULT_INDEXER_INDEXING_POIS_RESOLUTION_TIMEOUT: Duration = Duration:
 const POIS_QUERY_BATCH_SIZE: usize = 10;
 
 /// Error that can occur during POI resolution.
-#[derive(Debug, thiserror::Error)]
+#[derive(Clone, Debug, thiserror::Error)]
 pub enum ResolutionError {
     /// Resolution timed out.
     #[error("timeout")]
     Timeout,
 }
 
+impl RequestError for ResolutionError {
+    fn status(&self) -> &'static str {
+        match self {
+            ResolutionError::Timeout => "timeout",
+        }
+    }
+}
+
+/// The public POIs of an indexer, along with the POIs metadata they were requested for.
+type CachedPois = (
+    HashSet<(DeploymentId, BlockNumber)>,
+    HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>,
+);
+
+/// The in-flight POIs requests key, i.e., the indexer status URL and the requested POIs metadata.
+type InFlightKey = (Url, BTreeSet<(DeploymentId, BlockNumber)>);
+
+/// An in-flight POIs request, shared by the concurrent resolutions waiting for it.
+///
+/// The request result is shared, so a failed request is not retried by each waiting resolution.
+type InFlightRequest =
+    Arc<OnceCell<Result<HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>, ResolutionError>>>;
+
 /// A resolver for the Proof of Indexing (POI) of indexers.
 pub struct PoiResolver {
     client: reqwest::Client,
-    cache: TtlHashMap<Url, HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>>,
+    cache: Mutex<TtlHashMap<Url, CachedPois>>,
+    /// The in-flight POIs requests. Concurrent resolutions for the same key wait for the in-flight
+    /// request instead of querying the indexer again.
+    in_flight: Mutex<HashMap<InFlightKey, InFlightRequest>>,
     timeout: Duration,
 }
 
@@ -50,7 +86,8 @@ impl PoiResolver {
     pub fn new(client: reqwest::Client) -> Self {
         Self {
             client,
-            cache: TtlHashMap::with_ttl(DEFAULT_CACHE_TLL),
+            cache: Mutex::new(TtlHashMap::with_ttl(DEFAULT_CACHE_TLL)),
+            in_flight: Default::default(),
             timeout: DEFAULT_INDEXER_INDEXING_POIS_RESOLUTION_TIMEOUT,
         }
     }
@@ -59,55 +96,163 @@ impl PoiResolver {
     pub fn with_timeout(client: reqwest::Client, timeout: Duration) -> Self {
         Self {
             client,
-            cache: TtlHashMap::with_ttl(DEFAULT_CACHE_TLL),
+            cache: Mutex::new(TtlHashMap::with_ttl(DEFAULT_CACHE_TLL)),
+            in_flight: Default::default(),
             timeout,
         }
     }
 
+    /// Get the POIs resolution timeout.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
     /// Fetch the public POIs of the indexer based on the given POIs metadata.
     async fn fetch_indexer_public_pois(
         &self,
         indexer_status_url: Url,
         pois: &[(DeploymentId, BlockNumber)],
     ) -> Result<HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>, ResolutionError> {
-        // TODO: Handle the different errors once the indexers client module reports them
-        tokio::time::timeout(
-            self.timeout,
-            indexers::public_poi::merge_queries(
-                self.client.clone(),
-                indexer_status_url,
-                pois,
-                POIS_QUERY_BATCH_SIZE,
-            ),
-        )
+        let span = tracing::debug_span!(
+            "indexer public pois request",
+            url = %indexer_status_url,
+            deployments = pois.len(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        instrument_request(span, async {
+            // TODO: Handle the different errors once the indexers client module reports them
+            tokio::time::timeout(
+                self.timeout,
+                indexers::public_poi::merge_queries(
+                    self.client.clone(),
+                    indexer_status_url,
+                    pois,
+                    POIS_QUERY_BATCH_SIZE,
+                ),
+            )
+            .await
+            .map_err(|_| ResolutionError::Timeout)
+        })
         .await
-        .map_err(|_| ResolutionError::Timeout)
     }
 
     /// Resolve the public POIs of the indexer based on the given POIs metadata.
     ///
-    /// If the public POIs of the indexer are already in the cache, the resolver returns them.
+    /// If the public POIs of the indexer are already in the cache, and they were requested for
+    /// all the given POIs metadata, the resolver returns them. If a request for the same indexer
+    /// and POIs metadata is in-flight, the resolver waits for it.
     pub async fn resolve(
-        &mut self,
+        &self,
         url: &Url,
         pois: &[(DeploymentId, BlockNumber)],
     ) -> Result<HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>, ResolutionError> {
         let indexer_status_url = indexers::status_url(url);
 
         // Check if the indexer public POIs are already in the cache
-        match self.cache.get(&indexer_status_url) {
-            Some(pois) => Ok(pois.clone()),
-            None => {
+        if let Some(cached) = self.get_cached(&indexer_status_url, pois) {
+            return Ok(cached);
+        }
+
+        // Join the in-flight request for the same indexer and POIs metadata, if any
+        let key = (
+            indexer_status_url.clone(),
+            pois.iter().copied().collect::<BTreeSet<_>>(),
+        );
+        let request = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+
+            // Check the cache again, as the in-flight request may have completed since the
+            // previous check
+            if let Some(cached) = self.get_cached(&indexer_status_url, pois) {
+                return Ok(cached);
+            }
+
+            in_flight.entry(key.clone()).or_default().clone()
+        };
+
+        let result = request
+            .get_or_init(|| async {
                 // Fetch the public POIs of the indexer
-                let pois = self
+                let resolved = self
                     .fetch_indexer_public_pois(indexer_status_url.clone(), pois)
                     .await?;
 
                 // Insert the public POIs into the cache
-                self.cache.insert(indexer_status_url, pois.clone());
+                let requested = pois.iter().copied().collect();
+                self.cache
+                    .lock()
+                    .unwrap()
+                    .insert(indexer_status_url, (requested, resolved.clone()));
 
-                Ok(pois)
-            }
+                Ok(resolved)
+            })
+            .await
+            .clone();
+
+        // The request completed, the later resolutions are served from the cache
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight
+            .get(&key)
+            .is_some_and(|pending| Arc::ptr_eq(pending, &request))
+        {
+            in_flight.remove(&key);
         }
+
+        result
+    }
+
+    /// Get the cached public POIs of the indexer, if they were requested for all the given POIs
+    /// metadata.
+    fn get_cached(
+        &self,
+        indexer_status_url: &Url,
+        pois: &[(DeploymentId, BlockNumber)],
+    ) -> Option<HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>> {
+        let cache = self.cache.lock().unwrap();
+        let (requested, cached) = cache.get(indexer_status_url)?;
+        pois.iter()
+            .all(|meta| requested.contains(meta))
+            .then(|| cached.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use thegraph_core::types::DeploymentId;
+    use tokio::net::TcpListener;
+    use url::Url;
+
+    use super::PoiResolver;
+    use crate::network::indexer_request_span::tests::request_span_status;
+
+    #[tokio::test]
+    async fn public_pois_request_is_instrumented() {
+        //* Given
+        // A server accepting connections, but never responding
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind listener");
+        let url: Url = format!("http://{}/status", listener.local_addr().unwrap())
+            .parse()
+            .expect("valid url");
+
+        let resolver =
+            PoiResolver::with_timeout(reqwest::Client::new(), Duration::from_millis(100));
+        let deployment: DeploymentId = "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+            .parse()
+            .expect("valid deployment id");
+
+        //* When
+        let status = request_span_status(
+            "indexer public pois request",
+            resolver.fetch_indexer_public_pois(url, &[(deployment, 100)]),
+        )
+        .await;
+
+        //* Then
+        assert_eq!(status.as_deref(), Some("timeout"));
     }
 }
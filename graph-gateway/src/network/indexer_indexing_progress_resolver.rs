@@ -1,16 +1,24 @@
 //! A resolver that fetches the indexing statuses of deployments from an indexer's status URL.
 
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use alloy_primitives::BlockNumber;
 use thegraph_core::types::DeploymentId;
 use url::Url;
 
+use super::indexer_request_span::{instrument_request, RequestError};
 use crate::{indexers, indexers::indexing_statuses::IndexingStatusResponse};
 
 /// The timeout for the indexer's indexing progress resolution.
 pub const DEFAULT_INDEXER_INDEXING_PROGRESS_RESOLUTION_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// How long the indexings progress of an indexer is retained after its last resolution.
+const INDEXER_PROGRESS_RETENTION: Duration = Duration::from_secs(30 * 60);
+
 /// An error that occurred while resolving the indexing statuses of deployments.
 // TODO: Differentiate deserialization errors from resolver errors
 #[derive(Debug, thiserror::Error)]
@@ -26,6 +34,15 @@ pub enum ResolutionError {
     Timeout,
 }
 
+impl RequestError for ResolutionError {
+    fn status(&self) -> &'static str {
+        match self {
+            ResolutionError::FetchError(_) => "error",
+            ResolutionError::Timeout => "timeout",
+        }
+    }
+}
+
 /// The indexing progress information of a deployment on a chain.
 #[derive(Debug)]
 pub struct IndexingProgressInfo {
@@ -35,12 +52,51 @@ pub struct IndexingProgressInfo {
     pub latest_block: BlockNumber,
     /// The earliest block number indexed by the indexer.
     pub min_block: Option<BlockNumber>,
+    /// The last time the indexer's latest block was observed advancing, i.e., when its current
+    /// value was first observed. `None` if the indexing was resolved for the first time, as there
+    /// is no previous block to compare against.
+    pub last_progress_at: Option<Instant>,
+    /// The latency of the indexer's status request the progress was resolved with.
+    pub latency: Duration,
+}
+
+impl IndexingProgressInfo {
+    /// Whether the indexing latest block has not advanced for longer than the given threshold,
+    /// e.g., the indexer's node is stuck.
+    pub fn is_stuck(&self, threshold: Duration) -> bool {
+        is_stuck(self.last_progress_at, threshold)
+    }
+}
+
+/// Whether an indexing last observed advancing at `last_progress_at` has not advanced for longer
+/// than the given threshold.
+///
+/// An indexing whose progress is unknown, i.e., resolved for the first time, is not stuck.
+pub fn is_stuck(last_progress_at: Option<Instant>, threshold: Duration) -> bool {
+    last_progress_at.is_some_and(|at| at.elapsed() > threshold)
+}
+
+/// The latest block observed for each of an indexer's indexings.
+struct IndexerProgress {
+    /// The latest block observed for each indexing, and when it was first observed.
+    indexings: HashMap<DeploymentId, (BlockNumber, Instant)>,
+    /// The last time the indexer's indexings progress was resolved.
+    resolved_at: Instant,
 }
 
 /// A resolver that fetches the indexing statuses of deployments from an indexer's status URL.
+///
+/// With the `grpc-status` feature, the resolver falls back to the indexer status gRPC API if the
+/// indexer does not support the HTTP status endpoint.
 pub struct IndexingProgressResolver {
     client: reqwest::Client,
+    /// The HTTP/2 client for the indexer status gRPC API.
+    #[cfg(feature = "grpc-status")]
+    grpc_client: reqwest::Client,
     timeout: Duration,
+    /// The indexings progress observed for each indexer, used to track when the indexings last
+    /// progressed.
+    progress: Mutex<HashMap<Url, IndexerProgress>>,
 }
 
 impl IndexingProgressResolver {
@@ -48,13 +104,22 @@ impl IndexingProgressResolver {
     pub fn new(client: reqwest::Client) -> Self {
         Self {
             client,
+            #[cfg(feature = "grpc-status")]
+            grpc_client: indexers::indexing_statuses_grpc::client(),
             timeout: DEFAULT_INDEXER_INDEXING_PROGRESS_RESOLUTION_TIMEOUT,
+            progress: Default::default(),
         }
     }
 
     /// Creates a new [`IndexingProgressResolver`] with the given timeout.
     pub fn with_timeout(client: reqwest::Client, timeout: Duration) -> Self {
-        Self { client, timeout }
+        Self {
+            client,
+            #[cfg(feature = "grpc-status")]
+            grpc_client: indexers::indexing_statuses_grpc::client(),
+            timeout,
+            progress: Default::default(),
+        }
     }
 
     /// Resolves the indexer indexing progress for the given deployments
@@ -64,29 +129,84 @@ impl IndexingProgressResolver {
         indexings: &[DeploymentId],
     ) -> Result<Vec<IndexingStatusResponse>, ResolutionError> {
         let indexer_status_url = indexers::status_url(url);
-        tokio::time::timeout(
-            self.timeout,
-            // TODO: Handle the different errors once the indexers client module reports them
-            indexers::indexing_statuses::query(&self.client, indexer_status_url, indexings),
-        )
+
+        let span = tracing::debug_span!(
+            "indexer indexing progress request",
+            url = %indexer_status_url,
+            deployments = indexings.len(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        let result = instrument_request(span, async {
+            tokio::time::timeout(
+                self.timeout,
+                // TODO: Handle the different errors once the indexers client module reports them
+                indexers::indexing_statuses::query(&self.client, indexer_status_url, indexings),
+            )
+            .await
+            .map_err(|_| ResolutionError::Timeout)
+            .and_then(|res| res.map_err(ResolutionError::FetchError))
+        })
+        .await;
+
+        // If the HTTP status endpoint is not supported, fall back to the gRPC status API
+        #[cfg(feature = "grpc-status")]
+        if let Err(ResolutionError::FetchError(err)) = &result {
+            if err
+                .downcast_ref::<indexers::indexing_statuses::UnsupportedStatusEndpoint>()
+                .is_some()
+            {
+                return self.resolve_indexing_progress_grpc(url, indexings).await;
+            }
+        }
+
+        result
+    }
+
+    /// Resolves the indexer indexing progress for the given deployments over the indexer status
+    /// gRPC API.
+    #[cfg(feature = "grpc-status")]
+    async fn resolve_indexing_progress_grpc(
+        &self,
+        url: &Url,
+        indexings: &[DeploymentId],
+    ) -> Result<Vec<IndexingStatusResponse>, ResolutionError> {
+        let span = tracing::debug_span!(
+            "indexer indexing progress grpc request",
+            url = %url,
+            deployments = indexings.len(),
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        instrument_request(span, async {
+            tokio::time::timeout(
+                self.timeout,
+                indexers::indexing_statuses_grpc::query(&self.grpc_client, url, indexings),
+            )
+            .await
+            .map_err(|_| ResolutionError::Timeout)
+            .and_then(|res| res.map_err(ResolutionError::FetchError))
+        })
         .await
-        .map_err(|_| ResolutionError::Timeout)?
-        .map_err(ResolutionError::FetchError)
     }
 
     /// Resolves the indexing statuses of the given deployments.
     ///
     /// The resolver fetches the indexing statuses from the indexer status URL.
     ///
-    /// Returns a map of deployment IDs to their indexing statuses.
+    /// Returns a map of deployment IDs to their indexing statuses. The latest block of each
+    /// indexing is compared against the one resolved in the previous call to track when the
+    /// indexing last progressed.
     pub async fn resolve(
         &self,
         url: &Url,
         indexer_deployments: &[DeploymentId],
     ) -> Result<HashMap<DeploymentId, IndexingProgressInfo>, ResolutionError> {
+        let start = Instant::now();
         let progress = self
             .resolve_indexing_progress(url, indexer_deployments)
             .await?;
+        let latency = start.elapsed();
 
         let progress = progress
             .into_iter()
@@ -101,15 +221,356 @@ impl IndexingProgressResolver {
 
                 Some((
                     status.subgraph,
+                    (status_chain, status_latest_block, status_min_block),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let mut last_progress = self.track_progress(
+            url,
+            progress
+                .iter()
+                .map(|(deployment, (_, latest_block, _))| (*deployment, *latest_block)),
+            Instant::now(),
+        );
+        let progress = progress
+            .into_iter()
+            .map(|(deployment, (chain, latest_block, min_block))| {
+                (
+                    deployment,
                     IndexingProgressInfo {
-                        chain: status_chain,
-                        latest_block: status_latest_block,
-                        min_block: status_min_block,
+                        chain,
+                        latest_block,
+                        min_block,
+                        last_progress_at: last_progress.remove(&deployment).flatten(),
+                        latency,
                     },
-                ))
+                )
             })
             .collect::<HashMap<_, _>>();
 
         Ok(progress)
     }
+
+    /// Resolves the latest block indexed for each of the given deployments.
+    ///
+    /// Unlike [`IndexingProgressResolver::resolve`], the indexings progress is not tracked, so it
+    /// can be resolved for a subset of the indexer's deployments.
+    pub async fn resolve_latest_blocks(
+        &self,
+        url: &Url,
+        indexer_deployments: &[DeploymentId],
+    ) -> Result<HashMap<DeploymentId, BlockNumber>, ResolutionError> {
+        let progress = self
+            .resolve_indexing_progress(url, indexer_deployments)
+            .await?;
+
+        Ok(progress
+            .into_iter()
+            .filter_map(|status| {
+                // Only consider the first chain status, if has no chains
+                let chain = status.chains.into_iter().next()?;
+                let latest_block = chain.latest_block?.number;
+                Some((status.subgraph, latest_block))
+            })
+            .collect())
+    }
+
+    /// Record the indexer's indexings latest block and return the last time each was observed
+    /// advancing, if known.
+    ///
+    /// Only the indexings in the given set are retained for the indexer, and the indexers not
+    /// resolved within the retention period are evicted.
+    fn track_progress(
+        &self,
+        url: &Url,
+        latest_blocks: impl IntoIterator<Item = (DeploymentId, BlockNumber)>,
+        now: Instant,
+    ) -> HashMap<DeploymentId, Option<Instant>> {
+        let mut progress = self.progress.lock().unwrap();
+        progress.retain(|_, indexer| {
+            now.saturating_duration_since(indexer.resolved_at) <= INDEXER_PROGRESS_RETENTION
+        });
+
+        let previous = progress
+            .remove(url)
+            .map(|indexer| indexer.indexings)
+            .unwrap_or_default();
+
+        let mut indexings = HashMap::new();
+        let mut last_progress = HashMap::new();
+        for (deployment, latest_block) in latest_blocks {
+            let (entry, last_progress_at) = match previous.get(&deployment) {
+                None => ((latest_block, now), None),
+                Some((block, _)) if latest_block > *block => ((latest_block, now), Some(now)),
+                Some((block, since)) => ((*block, *since), Some(*since)),
+            };
+            indexings.insert(deployment, entry);
+            last_progress.insert(deployment, last_progress_at);
+        }
+
+        progress.insert(
+            url.clone(),
+            IndexerProgress {
+                indexings,
+                resolved_at: now,
+            },
+        );
+
+        last_progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use thegraph_core::types::DeploymentId;
+    use url::Url;
+
+    use super::{IndexingProgressInfo, IndexingProgressResolver, INDEXER_PROGRESS_RETENTION};
+    use crate::network::indexer_request_span::tests::request_span_status;
+
+    /// Test helper to get a [`DeploymentId`] from a string.
+    fn test_deployment_id(deployment: &str) -> DeploymentId {
+        deployment.parse().expect("invalid deployment id/ipfs hash")
+    }
+
+    /// Test helper to get an instant in the past.
+    fn instant_ago(duration: Duration) -> Instant {
+        Instant::now()
+            .checked_sub(duration)
+            .expect("instant out of range")
+    }
+
+    #[test]
+    fn indexing_not_advancing_across_refreshes_is_stuck() {
+        //* Given
+        let resolver = IndexingProgressResolver::new(reqwest::Client::new());
+        let url: Url = "https://indexer.example.com/".parse().unwrap();
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+
+        let first_refresh = instant_ago(Duration::from_secs(60));
+        let second_refresh = Instant::now();
+
+        //* When
+        let first = resolver.track_progress(&url, [(deployment, 100)], first_refresh);
+        let second = resolver.track_progress(&url, [(deployment, 100)], second_refresh);
+
+        //* Then
+        assert_eq!(first[&deployment], None);
+        assert_eq!(second[&deployment], Some(first_refresh));
+
+        let info = IndexingProgressInfo {
+            chain: "mainnet".to_string(),
+            latest_block: 100,
+            min_block: None,
+            last_progress_at: second[&deployment],
+            latency: Duration::from_millis(100),
+        };
+        assert!(info.is_stuck(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn indexing_advancing_across_refreshes_is_not_stuck() {
+        //* Given
+        let resolver = IndexingProgressResolver::new(reqwest::Client::new());
+        let url: Url = "https://indexer.example.com/".parse().unwrap();
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+
+        let first_refresh = instant_ago(Duration::from_secs(60));
+        let second_refresh = Instant::now();
+
+        //* When
+        resolver.track_progress(&url, [(deployment, 100)], first_refresh);
+        let second = resolver.track_progress(&url, [(deployment, 101)], second_refresh);
+
+        //* Then
+        assert_eq!(second[&deployment], Some(second_refresh));
+
+        let info = IndexingProgressInfo {
+            chain: "mainnet".to_string(),
+            latest_block: 101,
+            min_block: None,
+            last_progress_at: second[&deployment],
+            latency: Duration::from_millis(100),
+        };
+        assert!(!info.is_stuck(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn indexing_resolved_once_is_not_stuck() {
+        //* Given
+        let info = IndexingProgressInfo {
+            chain: "mainnet".to_string(),
+            latest_block: 100,
+            min_block: None,
+            last_progress_at: None,
+            latency: Duration::from_millis(100),
+        };
+
+        //* Then
+        assert!(!info.is_stuck(Duration::ZERO));
+    }
+
+    #[test]
+    fn unreported_indexings_and_stale_indexers_are_evicted() {
+        //* Given
+        let resolver = IndexingProgressResolver::new(reqwest::Client::new());
+        let url: Url = "https://indexer.example.com/".parse().unwrap();
+        let other_url: Url = "https://other-indexer.example.com/".parse().unwrap();
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let other_deployment = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+
+        let first_refresh = Instant::now();
+        let second_refresh = first_refresh + Duration::from_secs(60);
+        let later_refresh = second_refresh + INDEXER_PROGRESS_RETENTION + Duration::from_secs(1);
+
+        //* When
+        resolver.track_progress(
+            &url,
+            [(deployment, 100), (other_deployment, 100)],
+            first_refresh,
+        );
+        resolver.track_progress(&other_url, [(deployment, 100)], first_refresh);
+        resolver.track_progress(&url, [(deployment, 100)], second_refresh);
+        let after_unreported = resolver.track_progress(
+            &url,
+            [(deployment, 100), (other_deployment, 100)],
+            second_refresh,
+        );
+        let after_stale = resolver.track_progress(&url, [(deployment, 100)], later_refresh);
+
+        //* Then
+        // The indexing not reported in the previous refresh was forgotten
+        assert_eq!(after_unreported[&deployment], Some(first_refresh));
+        assert_eq!(after_unreported[&other_deployment], None);
+        // The indexings of the indexer not resolved within the retention period were evicted
+        assert_eq!(after_stale[&deployment], None);
+        let progress = resolver.progress.lock().unwrap();
+        assert!(!progress.contains_key(&other_url));
+        assert!(progress.contains_key(&url));
+    }
+
+    #[tokio::test]
+    async fn indexing_progress_request_is_instrumented() {
+        //* Given
+        let resolver = IndexingProgressResolver::new(reqwest::Client::new());
+        let url: Url = "http://127.0.0.1:1/".parse().expect("valid url");
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+
+        //* When
+        let status = request_span_status(
+            "indexer indexing progress request",
+            resolver.resolve(&url, &[deployment]),
+        )
+        .await;
+
+        //* Then
+        assert_eq!(status.as_deref(), Some("error"));
+    }
+
+    /// Test utility function to spawn a mock indexer not supporting the HTTP status endpoint, and
+    /// serving the given deployments' latest block over the indexer status gRPC API.
+    #[cfg(feature = "grpc-status")]
+    async fn spawn_grpc_status_server(latest_blocks: Vec<(DeploymentId, u64)>) -> Url {
+        use hyper::{body::Bytes, http};
+        use prost::Message as _;
+        use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        use crate::indexers::indexing_statuses_grpc::{
+            decode_frame, encode_frame, GrpcChainStatus, IndexingStatus, IndexingStatusesRequest,
+            IndexingStatusesResponse, INDEXING_STATUSES_PATH,
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let latest_blocks = latest_blocks.clone();
+                tokio::spawn(async move {
+                    // The HTTP/1.1 status requests are answered with a `404 Not Found`
+                    let mut preface = [0u8; 3];
+                    socket.peek(&mut preface).await.unwrap();
+                    if &preface != b"PRI" {
+                        let mut buf = [0u8; 4096];
+                        let _ = socket.read(&mut buf).await;
+                        let _ = socket
+                            .write_all(
+                                b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                            )
+                            .await;
+                        return;
+                    }
+
+                    let mut connection = h2::server::handshake(socket).await.unwrap();
+                    while let Some(request) = connection.accept().await {
+                        let (request, mut respond) = request.unwrap();
+                        assert_eq!(request.uri().path(), INDEXING_STATUSES_PATH);
+
+                        let mut body = request.into_body();
+                        let mut frame = Vec::new();
+                        while let Some(chunk) = body.data().await {
+                            let chunk = chunk.unwrap();
+                            let _ = body.flow_control().release_capacity(chunk.len());
+                            frame.extend_from_slice(&chunk);
+                        }
+                        let request =
+                            IndexingStatusesRequest::decode(decode_frame(&frame).unwrap()).unwrap();
+
+                        let response = IndexingStatusesResponse {
+                            statuses: latest_blocks
+                                .iter()
+                                .filter(|(deployment, _)| {
+                                    request.deployments.contains(&deployment.to_string())
+                                })
+                                .map(|(deployment, latest_block)| IndexingStatus {
+                                    deployment: deployment.to_string(),
+                                    chains: vec![GrpcChainStatus {
+                                        network: "mainnet".to_string(),
+                                        latest_block: Some(*latest_block),
+                                        earliest_block: Some(1),
+                                    }],
+                                })
+                                .collect(),
+                        };
+
+                        let head = http::Response::builder()
+                            .header("content-type", "application/grpc")
+                            .body(())
+                            .unwrap();
+                        let mut send = respond.send_response(head, false).unwrap();
+                        send.send_data(Bytes::from(encode_frame(&response)), false)
+                            .unwrap();
+                        let mut trailers = http::HeaderMap::new();
+                        trailers.insert("grpc-status", http::HeaderValue::from_static("0"));
+                        send.send_trailers(trailers).unwrap();
+                    }
+                });
+            }
+        });
+
+        format!("http://{addr}/").parse().unwrap()
+    }
+
+    #[cfg(feature = "grpc-status")]
+    #[tokio::test]
+    async fn progress_is_resolved_over_grpc_if_the_status_endpoint_is_not_supported() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let url = spawn_grpc_status_server(vec![(deployment, 100)]).await;
+
+        let resolver = IndexingProgressResolver::new(reqwest::Client::new());
+
+        //* When
+        let res = resolver.resolve(&url, &[deployment]).await;
+
+        //* Then
+        let progress = res.expect("progress resolved over grpc");
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[&deployment].chain, "mainnet");
+        assert_eq!(progress[&deployment].latest_block, 100);
+        assert_eq!(progress[&deployment].min_block, Some(1));
+    }
 }
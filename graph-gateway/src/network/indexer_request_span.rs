@@ -0,0 +1,175 @@
+//! Instrumentation of the resolvers' indexer requests.
+//!
+//! Each resolver request runs within its own span, a child of the indexer processing span. The
+//! span is expected to declare the empty `status` and `latency_ms` fields, recorded once the
+//! request completes.
+
+use std::{future::Future, time::Instant};
+
+use tracing::{Instrument as _, Span};
+
+/// A resolver request error, labeled in the request span `status` field.
+pub trait RequestError {
+    /// The request span `status` field label of the error.
+    fn status(&self) -> &'static str;
+}
+
+/// Run the resolver request within the given span, recording the request status and latency.
+///
+/// The status is `ok` if the request succeeded, otherwise the error [`RequestError::status`].
+pub async fn instrument_request<T, E: RequestError>(
+    span: Span,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let start = Instant::now();
+    let result = request.instrument(span.clone()).await;
+
+    let status = match &result {
+        Ok(_) => "ok",
+        Err(err) => err.status(),
+    };
+    span.record("status", status)
+        .record("latency_ms", start.elapsed().as_millis() as u64);
+
+    result
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{
+        collections::HashMap,
+        fmt,
+        future::Future,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{Context, SubscriberExt as _},
+        registry::LookupSpan,
+        Layer,
+    };
+
+    use super::{instrument_request, RequestError};
+
+    /// The spans collected by the [`RequestSpansLayer`].
+    #[derive(Default)]
+    struct CollectedSpans {
+        /// The created spans' names and their recorded `status` field, in creation order.
+        spans: Vec<(&'static str, Option<String>)>,
+        /// The index of each live span. Span IDs are reused once the spans are closed.
+        ids: HashMap<span::Id, usize>,
+    }
+
+    /// A tracing layer collecting the created spans' names and their recorded `status` field.
+    #[derive(Clone, Default)]
+    struct RequestSpansLayer(Arc<Mutex<CollectedSpans>>);
+
+    impl RequestSpansLayer {
+        /// The `status` field recorded for the span with the given name, if any.
+        fn status(&self, name: &str) -> Option<String> {
+            let collected = self.0.lock().unwrap();
+            collected
+                .spans
+                .iter()
+                .find(|(span_name, _)| *span_name == name)
+                .and_then(|(_, status)| status.clone())
+        }
+    }
+
+    /// A visitor extracting the `status` field value.
+    struct StatusVisitor<'a>(&'a mut Option<String>);
+
+    impl Visit for StatusVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "status" {
+                *self.0 = Some(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "status" {
+                *self.0 = Some(format!("{value:?}"));
+            }
+        }
+    }
+
+    impl<S> Layer<S> for RequestSpansLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, _ctx: Context<'_, S>) {
+            let mut collected = self.0.lock().unwrap();
+            let index = collected.spans.len();
+            collected.spans.push((attrs.metadata().name(), None));
+            collected.ids.insert(id.clone(), index);
+        }
+
+        fn on_record(&self, id: &span::Id, values: &span::Record<'_>, _ctx: Context<'_, S>) {
+            let mut collected = self.0.lock().unwrap();
+            let CollectedSpans { spans, ids } = &mut *collected;
+            if let Some((_, status)) = ids.get(id).and_then(|index| spans.get_mut(*index)) {
+                values.record(&mut StatusVisitor(status));
+            }
+        }
+    }
+
+    /// Test utility function to run the given resolver request, returning the `status` field
+    /// recorded in the request span with the given name. If no such span was created, i.e., the
+    /// request was not instrumented with [`instrument_request`], `None` is returned.
+    pub async fn request_span_status<T>(
+        name: &str,
+        request: impl Future<Output = T>,
+    ) -> Option<String> {
+        let layer = RequestSpansLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+
+        request.await;
+        layer.status(name)
+    }
+
+    /// A test request error.
+    struct TestError;
+
+    impl RequestError for TestError {
+        fn status(&self) -> &'static str {
+            "test_error"
+        }
+    }
+
+    #[tokio::test]
+    async fn request_status_is_recorded_in_its_span() {
+        //* Given
+        let layer = RequestSpansLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+
+        let ok_span = tracing::debug_span!(
+            "ok request",
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        let failed_span = tracing::debug_span!(
+            "failed request",
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+
+        //* When
+        let ok = instrument_request(ok_span, async { Ok::<_, TestError>(()) }).await;
+        let failed = instrument_request(failed_span, async { Err::<(), _>(TestError) }).await;
+
+        //* Then
+        assert!(ok.is_ok());
+        assert!(failed.is_err());
+        assert_eq!(layer.status("ok request").as_deref(), Some("ok"));
+        assert_eq!(
+            layer.status("failed request").as_deref(),
+            Some("test_error")
+        );
+    }
+}
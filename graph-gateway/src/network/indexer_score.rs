@@ -0,0 +1,212 @@
+//! Composite indexer selection score.
+//!
+//! Instead of chaining boolean filters, the candidates are ranked by a single score blending their
+//! stake, freshness (blocks behind the chain head), and latency. The weight of each factor is
+//! configurable.
+
+use alloy_primitives::Address;
+use indexer_selection::Candidate;
+use serde::Deserialize;
+
+/// The composite score ranking of the query candidates.
+///
+/// Only the top-ranked candidates are considered by the indexer selection.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CandidateRanking {
+    /// The weights of the score factors.
+    #[serde(default)]
+    pub weights: ScoreWeights,
+    /// The number of top-ranked candidates considered by the indexer selection.
+    pub limit: usize,
+}
+
+impl CandidateRanking {
+    /// Keep only the top-ranked candidates, in their original order.
+    pub fn retain_top(&self, candidates: &mut Vec<Candidate>) {
+        if candidates.len() <= self.limit {
+            return;
+        }
+
+        let inputs = candidates
+            .iter()
+            .map(|candidate| ScoreInput {
+                indexer: candidate.indexer,
+                staked_tokens: candidate.slashable_grt as u128,
+                seconds_behind: candidate.seconds_behind,
+                latency_ms: candidate.perf.latency_success_ms as u32,
+            })
+            .collect::<Vec<_>>();
+        let scores = scores(&inputs, &self.weights);
+
+        let mut ranked = (0..candidates.len()).collect::<Vec<_>>();
+        ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        let mut top = vec![false; candidates.len()];
+        for index in ranked.into_iter().take(self.limit) {
+            top[index] = true;
+        }
+
+        let mut top = top.into_iter();
+        candidates.retain(|_| top.next().unwrap_or(false));
+    }
+}
+
+/// The weights of each factor of the composite score.
+///
+/// The weights are relative to each other, e.g., `{ stake: 1, freshness: 2, latency: 1 }`
+/// makes freshness count twice as much as each of the other factors.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ScoreWeights {
+    /// The weight of the indexer's stake.
+    pub stake: f64,
+    /// The weight of the indexing freshness, i.e., how close to the chain head the indexing is.
+    pub freshness: f64,
+    /// The weight of the indexer's latency.
+    pub latency: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            stake: 1.0,
+            freshness: 1.0,
+            latency: 1.0,
+        }
+    }
+}
+
+/// The score factors of a candidate.
+#[derive(Clone, Debug)]
+pub struct ScoreInput {
+    /// The indexer's address.
+    pub indexer: Address,
+    /// The indexer's staked tokens.
+    pub staked_tokens: u128,
+    /// How far behind the chain head the indexing is, in seconds.
+    pub seconds_behind: u32,
+    /// The indexer's expected response latency, in milliseconds.
+    pub latency_ms: u32,
+}
+
+/// Rank the candidates by their composite score, from highest to lowest.
+///
+/// Each factor is normalized to `[0, 1]` relative to the other candidates, so the score does not
+/// depend on the factors' units:
+///  - Stake: the candidate's stake over the highest stake.
+///  - Freshness: `1 / (1 + seconds_behind)`.
+///  - Latency: the lowest latency over the candidate's latency.
+///
+/// The score is the weighted average of the normalized factors.
+pub fn rank(candidates: &[ScoreInput], weights: &ScoreWeights) -> Vec<(Address, f64)> {
+    let mut ranking = candidates
+        .iter()
+        .map(|candidate| candidate.indexer)
+        .zip(scores(candidates, weights))
+        .collect::<Vec<_>>();
+    ranking.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    ranking
+}
+
+/// Compute the composite score of each candidate, in the candidates' order.
+fn scores(candidates: &[ScoreInput], weights: &ScoreWeights) -> Vec<f64> {
+    let max_stake = candidates
+        .iter()
+        .map(|candidate| candidate.staked_tokens)
+        .max()
+        .unwrap_or(0);
+    let min_latency = candidates
+        .iter()
+        .map(|candidate| candidate.latency_ms)
+        .min()
+        .unwrap_or(0);
+    let total_weight = weights.stake + weights.freshness + weights.latency;
+
+    candidates
+        .iter()
+        .map(|candidate| {
+            let stake = if max_stake == 0 {
+                0.0
+            } else {
+                candidate.staked_tokens as f64 / max_stake as f64
+            };
+            let freshness = 1.0 / (1.0 + candidate.seconds_behind as f64);
+            let latency = min_latency.max(1) as f64 / candidate.latency_ms.max(1) as f64;
+
+            if total_weight <= 0.0 {
+                0.0
+            } else {
+                (weights.stake * stake + weights.freshness * freshness + weights.latency * latency)
+                    / total_weight
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::Address;
+
+    use super::{rank, ScoreInput, ScoreWeights};
+
+    /// Test candidates: a high stake indexer lagging behind the chain head, and a low stake
+    /// indexer caught up with the chain head. Both with the same latency.
+    fn test_candidates() -> [ScoreInput; 2] {
+        [
+            ScoreInput {
+                indexer: Address::repeat_byte(1),
+                staked_tokens: 1_000_000,
+                seconds_behind: 100,
+                latency_ms: 200,
+            },
+            ScoreInput {
+                indexer: Address::repeat_byte(2),
+                staked_tokens: 100_000,
+                seconds_behind: 0,
+                latency_ms: 200,
+            },
+        ]
+    }
+
+    #[test]
+    fn ranking_follows_the_weights() {
+        //* Given
+        let candidates = test_candidates();
+        let lagging = Address::repeat_byte(1);
+        let caught_up = Address::repeat_byte(2);
+
+        let stake_weighted = ScoreWeights {
+            stake: 10.0,
+            freshness: 1.0,
+            latency: 1.0,
+        };
+        let freshness_weighted = ScoreWeights {
+            stake: 1.0,
+            freshness: 10.0,
+            latency: 1.0,
+        };
+
+        //* When
+        let stake_ranking = rank(&candidates, &stake_weighted);
+        let freshness_ranking = rank(&candidates, &freshness_weighted);
+
+        //* Then
+        assert_eq!(stake_ranking[0].0, lagging);
+        assert_eq!(freshness_ranking[0].0, caught_up);
+    }
+
+    #[test]
+    fn latency_breaks_ties() {
+        //* Given
+        let mut candidates = test_candidates();
+        candidates[1].staked_tokens = 1_000_000;
+        candidates[1].seconds_behind = 100;
+        candidates[1].latency_ms = 100;
+
+        //* When
+        let ranking = rank(&candidates, &ScoreWeights::default());
+
+        //* Then
+        assert_eq!(ranking[0].0, Address::repeat_byte(2));
+        assert!(ranking[0].1 > ranking[1].1);
+    }
+}
@@ -0,0 +1,64 @@
+//! Operator-defined indexer tiers.
+//!
+//! Operators can rank indexers in tiers (e.g., preferred partners) independently of their stake.
+//! During the indexer selection, each candidate's selection weight is scaled by the tier
+//! multiplier raised to the indexer's tier: higher tiers are favored, lower (negative) tiers are
+//! penalized.
+
+use std::{collections::HashMap, sync::Arc};
+
+use alloy_primitives::Address;
+
+/// The default multiplier applied per tier.
+pub const DEFAULT_TIER_MULTIPLIER: f64 = 2.0;
+
+/// A shared map of indexer tiers.
+///
+/// Indexers absent from the map get tier 0, i.e., their selection weight is not modified.
+#[derive(Debug, Clone)]
+pub struct IndexerTiers {
+    tiers: Arc<HashMap<Address, i32>>,
+    multiplier: f64,
+}
+
+impl Default for IndexerTiers {
+    fn default() -> Self {
+        Self::new(Default::default(), DEFAULT_TIER_MULTIPLIER)
+    }
+}
+
+impl IndexerTiers {
+    /// Create a new [`IndexerTiers`] with the given tiers and per-tier multiplier.
+    pub fn new(tiers: HashMap<Address, i32>, multiplier: f64) -> Self {
+        Self {
+            tiers: Arc::new(tiers),
+            multiplier,
+        }
+    }
+
+    /// Get the tier of the indexer. Defaults to 0 if the indexer has no tier assigned.
+    pub fn tier(&self, indexer: &Address) -> i32 {
+        self.tiers.get(indexer).copied().unwrap_or(0)
+    }
+
+    /// Get the number of indexers with a tier assigned.
+    pub fn len(&self) -> usize {
+        self.tiers.len()
+    }
+
+    /// Check if no indexer has a tier assigned.
+    pub fn is_empty(&self) -> bool {
+        self.tiers.is_empty()
+    }
+
+    /// Get the multiplier applied per tier.
+    pub fn multiplier(&self) -> f64 {
+        self.multiplier
+    }
+
+    /// Get the factor to scale the indexer's selection weight by, i.e., the tier multiplier
+    /// raised to the indexer's tier.
+    pub fn weight(&self, indexer: &Address) -> f64 {
+        self.multiplier.powi(self.tier(indexer))
+    }
+}
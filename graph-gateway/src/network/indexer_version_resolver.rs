@@ -6,19 +6,33 @@
 //!
 //! The resolver will perform better if the client provided has a connection pool with the different
 //! indexers, as it will be able to reuse already established connections.
+//!
+//! The resolved versions are cached per indexer URL for a configurable time-to-live, so indexers
+//! co-located on the same host share a single probe, and the versions are not probed again on
+//! every network topology refresh.
 
-use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use semver::Version;
+use serde::Serialize;
+use tokio::sync::OnceCell;
 use url::Url;
 
-use crate::indexers;
+use super::indexer_request_span::{instrument_request, RequestError};
+use crate::indexers::{self, version::IndexerServiceVersion};
 
 /// The default indexer version resolution timeout.
 ///
 /// This timeout is applied \*independently\* for the agent and graph node versions fetches.
 pub const DEFAULT_INDEXER_VERSION_RESOLUTION_TIMEOUT: Duration = Duration::from_millis(1_500);
 
+/// The default resolved indexer versions cache time-to-live.
+pub const DEFAULT_INDEXER_VERSION_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
 /// The error that can occur while resolving the indexer versions.
 #[derive(Debug, thiserror::Error)]
 pub enum ResolutionError {
@@ -31,12 +45,34 @@ pub enum ResolutionError {
     Timeout,
 }
 
+impl RequestError for ResolutionError {
+    fn status(&self) -> &'static str {
+        match self {
+            ResolutionError::FetchError(_) => "error",
+            ResolutionError::Timeout => "timeout",
+        }
+    }
+}
+
+/// The policy applied when an indexer's graph-node version cannot be resolved.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum GraphNodeVersionFallback {
+    /// Block the indexer, as with the agent version resolution failures.
+    Block,
+    /// Assume the indexer is on the minimum required graph-node version, i.e., the highest of its
+    /// indexings' networks minimum versions.
+    #[default]
+    AssumeMinimum,
+    /// Assume the indexer is on the given graph-node version.
+    AssumeVersion(Version),
+}
+
 /// The indexer versions resolver.
 ///
 /// The resolver is responsible for fetching the versions of the indexer agent and graph-node
 /// services. If the version takes more than the timeout to resolve, the resolver will return an
 /// error.
-// TODO: Cache the result with TTL in case the resolution fails.
+// TODO: Serve the stale cached result in case the resolution fails.
 #[derive(Clone)]
 pub struct VersionResolver {
     /// The indexer client.
@@ -50,6 +86,35 @@ pub struct VersionResolver {
     agent_version_resolution_timeout: Duration,
     /// The indexer graph-node version resolution timeout.
     graph_node_version_resolution_timeout: Duration,
+    /// The policy applied when the graph-node version cannot be resolved.
+    graph_node_version_fallback: GraphNodeVersionFallback,
+
+    /// The resolved versions cache time-to-live.
+    cache_ttl: Duration,
+    /// The resolved versions cache, keyed by indexer URL.
+    resolved: Arc<Mutex<HashMap<Url, Arc<ResolvedVersions>>>>,
+}
+
+/// The versions resolved for an indexer URL, and their resolution timestamps.
+///
+/// Only the successful resolutions are kept. Concurrent resolutions for the same URL wait for the
+/// in-flight one instead of probing the indexer again.
+#[derive(Default)]
+struct ResolvedVersions {
+    agent: OnceCell<(IndexerServiceVersion, Instant)>,
+    graph_node: OnceCell<(Version, Instant)>,
+}
+
+impl ResolvedVersions {
+    /// Whether any of the resolved versions is older than the time-to-live.
+    fn is_expired(&self, ttl: Duration) -> bool {
+        [
+            self.agent.get().map(|(_, at)| at),
+            self.graph_node.get().map(|(_, at)| at),
+        ]
+        .into_iter()
+        .any(|at| at.is_some_and(|at| at.elapsed() > ttl))
+    }
 }
 
 impl VersionResolver {
@@ -62,6 +127,9 @@ impl VersionResolver {
             client,
             agent_version_resolution_timeout: DEFAULT_INDEXER_VERSION_RESOLUTION_TIMEOUT,
             graph_node_version_resolution_timeout: DEFAULT_INDEXER_VERSION_RESOLUTION_TIMEOUT,
+            graph_node_version_fallback: GraphNodeVersionFallback::default(),
+            cache_ttl: DEFAULT_INDEXER_VERSION_CACHE_TTL,
+            resolved: Default::default(),
         }
     }
 
@@ -71,41 +139,279 @@ impl VersionResolver {
             client,
             agent_version_resolution_timeout: timeout,
             graph_node_version_resolution_timeout: timeout,
+            graph_node_version_fallback: GraphNodeVersionFallback::default(),
+            cache_ttl: DEFAULT_INDEXER_VERSION_CACHE_TTL,
+            resolved: Default::default(),
+        }
+    }
+
+    /// Sets the policy applied when the graph-node version cannot be resolved.
+    pub fn with_graph_node_version_fallback(mut self, fallback: GraphNodeVersionFallback) -> Self {
+        self.graph_node_version_fallback = fallback;
+        self
+    }
+
+    /// Get the policy applied when the graph-node version cannot be resolved.
+    pub fn graph_node_version_fallback(&self) -> &GraphNodeVersionFallback {
+        &self.graph_node_version_fallback
+    }
+
+    /// Sets the resolved versions cache time-to-live.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Get the resolved versions cache time-to-live.
+    pub fn cache_ttl(&self) -> Duration {
+        self.cache_ttl
+    }
+
+    /// Forget the expired resolved versions, e.g., at the start of a network topology refresh.
+    pub fn evict_expired(&self) {
+        self.resolved
+            .lock()
+            .unwrap()
+            .retain(|_, versions| !versions.is_expired(self.cache_ttl));
+    }
+
+    /// Get the versions resolved for the given indexer URL.
+    ///
+    /// If any of the cached versions expired, the entry is replaced by an empty one.
+    fn resolved_versions(&self, url: &Url) -> Arc<ResolvedVersions> {
+        let mut resolved = self.resolved.lock().unwrap();
+        let versions = resolved.entry(url.clone()).or_default();
+        if versions.is_expired(self.cache_ttl) {
+            *versions = Default::default();
         }
+        versions.clone()
     }
 
     /// Resolves the indexer agent version.
     ///
-    /// The version resolution time is upper-bounded by the configured timeout.
+    /// The version is served from the cache if it was resolved within the cache time-to-live.
     pub async fn resolve_agent_version(&self, url: &Url) -> Result<Version, ResolutionError> {
+        self.resolve_agent(url)
+            .await
+            .map(|response| response.version)
+    }
+
+    /// Resolves the features the indexer advertises support for.
+    ///
+    /// The features are reported by the indexer agent version endpoint, so they share its cache
+    /// entry and are not probed separately.
+    pub async fn resolve_agent_features(
+        &self,
+        url: &Url,
+    ) -> Result<HashSet<String>, ResolutionError> {
+        self.resolve_agent(url)
+            .await
+            .map(|response| response.features)
+    }
+
+    /// Resolves the indexer agent version endpoint response.
+    async fn resolve_agent(&self, url: &Url) -> Result<IndexerServiceVersion, ResolutionError> {
+        self.resolved_versions(url)
+            .agent
+            .get_or_try_init(|| async {
+                let response = self.probe_agent_version(url).await;
+                response.map(|response| (response, Instant::now()))
+            })
+            .await
+            .map(|(response, _)| response.clone())
+    }
+
+    /// Resolves the indexer graph-node version.
+    ///
+    /// The version is served from the cache if it was resolved within the cache time-to-live.
+    pub async fn resolve_graph_node_version(&self, url: &Url) -> Result<Version, ResolutionError> {
+        self.resolved_versions(url)
+            .graph_node
+            .get_or_try_init(|| async {
+                let version = self.probe_graph_node_version(url).await;
+                version.map(|version| (version, Instant::now()))
+            })
+            .await
+            .map(|(version, _)| version.clone())
+    }
+
+    /// Fetch the indexer agent version.
+    ///
+    /// The version resolution time is upper-bounded by the configured timeout.
+    async fn probe_agent_version(
+        &self,
+        url: &Url,
+    ) -> Result<IndexerServiceVersion, ResolutionError> {
         let indexer_agent_version_url = indexers::version_url(url);
 
-        tokio::time::timeout(
-            self.agent_version_resolution_timeout,
-            indexers::version::query_indexer_service_version(
-                &self.client,
-                indexer_agent_version_url,
-            ),
-        )
+        let span = tracing::debug_span!(
+            "indexer agent version request",
+            url = %indexer_agent_version_url,
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        instrument_request(span, async {
+            tokio::time::timeout(
+                self.agent_version_resolution_timeout,
+                indexers::version::query_indexer_service_version_info(
+                    &self.client,
+                    indexer_agent_version_url,
+                ),
+            )
+            .await
+            .map_err(|_| ResolutionError::Timeout)
+            .and_then(|res| res.map_err(ResolutionError::FetchError))
+        })
         .await
-        .map_err(|_| ResolutionError::Timeout)?
-        .map_err(ResolutionError::FetchError)
     }
 
-    /// Resolves the indexer graph-node version.
+    /// Fetch the indexer graph-node version.
     ///
     /// The version resolution time is upper-bounded by the configured timeout.
-    pub async fn resolve_graph_node_version(&self, url: &Url) -> Result<Version, ResolutionError> {
+    async fn probe_graph_node_version(&self, url: &Url) -> Result<Version, ResolutionError> {
         let indexer_graph_node_version_url = indexers::status_url(url);
-        tokio::time::timeout(
-            self.graph_node_version_resolution_timeout,
-            indexers::version::query_graph_node_version(
-                &self.client,
-                indexer_graph_node_version_url,
-            ),
-        )
+
+        let span = tracing::debug_span!(
+            "indexer graph-node version request",
+            url = %indexer_graph_node_version_url,
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        instrument_request(span, async {
+            tokio::time::timeout(
+                self.graph_node_version_resolution_timeout,
+                indexers::version::query_graph_node_version(
+                    &self.client,
+                    indexer_graph_node_version_url,
+                ),
+            )
+            .await
+            .map_err(|_| ResolutionError::Timeout)
+            .and_then(|res| res.map_err(ResolutionError::FetchError))
+        })
         .await
-        .map_err(|_| ResolutionError::Timeout)?
-        .map_err(ResolutionError::FetchError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use semver::Version;
+    use tokio::{
+        io::{AsyncReadExt as _, AsyncWriteExt as _},
+        net::TcpListener,
+    };
+    use url::Url;
+
+    use super::VersionResolver;
+    use crate::network::indexer_request_span::tests::request_span_status;
+
+    /// Test utility function to spawn a mock indexer reporting its agent version. It counts the
+    /// received agent version requests.
+    async fn spawn_versions_server() -> (Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener");
+        let url = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .expect("valid url");
+
+        let agent_version_requests = Arc::new(AtomicUsize::new(0));
+        let requests = agent_version_requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                // Read the request head, the agent version request has no body
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                if request.starts_with(b"GET /version") {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                }
+
+                let body = r#"{"version":"1.0.0"}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (url, agent_version_requests)
+    }
+
+    #[tokio::test]
+    async fn version_requests_are_instrumented() {
+        //* Given
+        let resolver =
+            VersionResolver::with_timeout(reqwest::Client::new(), Duration::from_millis(100));
+        let url: Url = "http://127.0.0.1:1/".parse().expect("valid url");
+
+        //* When
+        let agent_status = request_span_status(
+            "indexer agent version request",
+            resolver.resolve_agent_version(&url),
+        )
+        .await;
+        let graph_node_status = request_span_status(
+            "indexer graph-node version request",
+            resolver.resolve_graph_node_version(&url),
+        )
+        .await;
+
+        //* Then
+        assert_eq!(agent_status.as_deref(), Some("error"));
+        assert_eq!(graph_node_status.as_deref(), Some("error"));
+    }
+
+    #[tokio::test]
+    async fn versions_resolved_within_the_ttl_are_served_from_the_cache() {
+        //* Given
+        let (url, agent_version_requests) = spawn_versions_server().await;
+        let resolver = VersionResolver::new(reqwest::Client::new());
+
+        //* When
+        let first = resolver.resolve_agent_version(&url).await;
+        let second = resolver.resolve_agent_version(&url).await;
+
+        //* Then
+        assert_eq!(first.expect("agent version"), Version::new(1, 0, 0));
+        assert_eq!(second.expect("agent version"), Version::new(1, 0, 0));
+        assert_eq!(agent_version_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_versions_are_resolved_again() {
+        //* Given
+        let (url, agent_version_requests) = spawn_versions_server().await;
+        let resolver =
+            VersionResolver::new(reqwest::Client::new()).with_cache_ttl(Duration::from_millis(10));
+
+        //* When
+        resolver
+            .resolve_agent_version(&url)
+            .await
+            .expect("agent version");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        resolver
+            .resolve_agent_version(&url)
+            .await
+            .expect("agent version");
+
+        //* Then
+        assert_eq!(agent_version_requests.load(Ordering::SeqCst), 2);
     }
 }
@@ -1,12 +1,23 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    future::Future,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, BlockNumber};
 use anyhow::anyhow;
+use cost_model::CostModel;
+use eventuals::Ptr;
+use futures::StreamExt as _;
 use gateway_common::blocklist::Blocklist as _;
+use gateway_framework::reporting::{with_metric, METRICS};
 use itertools::Itertools;
 use semver::Version;
-use thegraph_core::types::SubgraphId;
-use tokio::sync::Mutex;
+use serde::Serialize;
+use thegraph_core::types::{DeploymentId, SubgraphId};
+use tokio::{sync::Mutex, time::Instant};
 use tracing::Instrument;
 use url::Url;
 use vec1::Vec1;
@@ -16,23 +27,182 @@ use self::types::{
     SubgraphVersionInfo,
 };
 use super::{
-    indexer_addr_blocklist::AddrBlocklist, indexer_host_blocklist::HostBlocklist,
-    indexer_host_resolver::HostResolver, indexer_indexing_cost_model_compiler::CostModelCompiler,
+    indexer_addr_blocklist::AddrBlocklist,
+    indexer_host_blocklist::HostBlocklist,
+    indexer_host_resolver::HostResolver,
+    indexer_indexing_cost_model_compiler::CostModelCompiler,
     indexer_indexing_cost_model_resolver::CostModelResolver,
-    indexer_indexing_poi_blocklist::PoiBlocklist, indexer_indexing_poi_resolver::PoiResolver,
+    indexer_indexing_poi_blocklist::{find_poi_collisions, PoiBlocklist, PoiCollisionPolicy},
+    indexer_indexing_poi_reference::{check_against_reference, PoiReferenceSource},
+    indexer_indexing_poi_resolver::PoiResolver,
     indexer_indexing_progress_resolver::IndexingProgressResolver,
-    indexer_version_resolver::VersionResolver, snapshot, snapshot::NetworkTopologySnapshot,
-    subgraph, subgraph::Client as SubgraphClient,
+    indexer_version_resolver::{GraphNodeVersionFallback, VersionResolver},
+    snapshot,
+    snapshot::{FetchedInfo, NetworkTopologySnapshot},
+    subgraph,
+    subgraph::Client as SubgraphClient,
 };
 
-/// The network topology fetch timeout.
+/// The default network topology fetch timeout.
+///
+/// This is the default for both the indexers and subgraphs information fetches, each fetch's
+/// timeout being applied independently.
+pub const DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The network topology fetch retry configuration.
+///
+/// A failed or timed out fetch is retried with an exponential backoff: the delay before the `n`th
+/// retry is `base_delay * 2^(n-1)`. Empty fetches are not retried, as retrying won't populate an
+/// empty network.
+///
+/// The retries are capped by the update timeout budget: the attempts are cut short at the update
+/// deadline, and no retry is made if its backoff delay ends past it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// The maximum number of fetch attempts, including the first one.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub base_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Get the delay before the given retry, starting at 1.
+    fn delay(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+}
+
+/// The policy applied when only one of the indexers and subgraphs information fetches fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PartialUpdatePolicy {
+    /// Fail the whole update, keeping the previous network topology snapshot.
+    #[default]
+    Disabled,
+    /// Update the successfully fetched half, reusing the previous snapshot's information for the
+    /// failed half. If there is no previous snapshot, or its information was fetched more than
+    /// `max_age` ago, the update fails.
+    ReusePrevious {
+        /// The maximum age of the reused information.
+        max_age: Duration,
+    },
+}
+
+/// The network topology fetch and update errors.
+///
+/// The variants are flat, i.e., [`fetch_update`] returns the actual failure condition, e.g.,
+/// [`NetworkFetchError::EmptyIndexers`], regardless of the processing step it happened at.
+#[derive(Debug, thiserror::Error)]
+pub enum NetworkFetchError {
+    /// The network topology update took longer than the update timeout.
+    #[error("network topology update timed out")]
+    UpdateTimeout,
+    /// The network topology update was cancelled, e.g., on shutdown.
+    #[error("network topology update cancelled")]
+    UpdateCancelled,
+    /// The indexers information fetch took longer than the fetch timeout.
+    #[error("indexers info fetch timed out")]
+    IndexersFetchTimeout,
+    /// The network subgraph indexers query failed.
+    #[error("indexers info fetch failed: {cause}")]
+    IndexersFetchFailed {
+        #[source]
+        cause: anyhow::Error,
+    },
+    /// The network subgraph returned no indexers.
+    #[error("empty indexers fetch")]
+    EmptyIndexers,
+    /// All the fetched indexers were filtered out, either during pre-processing or processing.
+    #[error("no valid indexers found")]
+    NoValidIndexers,
+    /// The subgraphs information fetch took longer than the fetch timeout.
+    #[error("subgraphs info fetch timed out")]
+    SubgraphsFetchTimeout,
+    /// The network subgraph subgraphs query failed.
+    #[error("subgraphs info fetch failed: {cause}")]
+    SubgraphsFetchFailed {
+        #[source]
+        cause: anyhow::Error,
+    },
+    /// The network subgraph returned no subgraphs.
+    #[error("empty subgraphs fetch")]
+    EmptySubgraphs,
+    /// All the fetched subgraphs were filtered out.
+    #[error("no valid subgraphs found")]
+    NoValidSubgraphs,
+}
+
+/// A network topology fetch attempt error.
+#[derive(Debug)]
+enum FetchError {
+    /// The fetch timed out.
+    Timeout,
+    /// The fetch failed.
+    Failed(NetworkFetchError),
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::Timeout => write!(f, "timed out"),
+            FetchError::Failed(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl FetchError {
+    /// Whether the fetch should be retried. Empty fetches are not retried.
+    fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::Timeout => true,
+            FetchError::Failed(err) => !matches!(
+                err,
+                NetworkFetchError::EmptyIndexers | NetworkFetchError::EmptySubgraphs
+            ),
+        }
+    }
+}
+
+/// The default network topology update timeout.
+///
+/// This timeout bounds the whole update, i.e., the indexers and subgraphs information fetches and
+/// the subsequent indexers processing.
+pub const DEFAULT_NETWORK_TOPOLOGY_UPDATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The default per-indexer processing deadline.
+///
+/// The deadline applies to each indexer separately, starting once the indexer is dequeued for
+/// processing. It is shorter than the default update timeout, so a slow-responding indexer is
+/// dropped from the update before the whole update times out.
+pub const DEFAULT_INDEXERS_PROCESSING_DEADLINE: Duration = Duration::from_secs(25);
+
+/// The default maximum number of indexers processed concurrently.
+///
+/// Each indexer processing issues several requests to the indexer, so the concurrency bounds the
+/// number of simultaneous connections. High enough to process all the network indexers at once.
+pub const DEFAULT_INDEXERS_PROCESSING_CONCURRENCY: usize = 1_000;
+
+/// The number of deployments per indexer whose POIs are sampled for the POI collision check.
 ///
-/// This timeout is applied independently to the indexers and subgraphs information fetches.
-const NETWORK_TOPOLOGY_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+/// The sampled deployments are the indexer's largest allocation ones.
+const POI_COLLISION_SAMPLE_SIZE: usize = 10;
 
 /// Internal types.
 pub mod types {
-    use std::{collections::HashMap, fmt::Display};
+    use std::{
+        collections::{HashMap, HashSet},
+        fmt::Display,
+        time::{Duration, Instant},
+    };
 
     use alloy_primitives::{Address, BlockNumber};
     use cost_model::CostModel;
@@ -101,11 +271,15 @@ pub mod types {
         /// The deployments are ordered from highest to lowest associated token allocation.
         //  See ref: d260724b-a445-4842-964e-fb95062c119d
         pub deployments: Vec1<DeploymentId>,
+        /// The manifest network of the indexer's deployments, if known.
+        pub deployments_network: HashMap<DeploymentId, String>,
 
         /// The indexer's "indexer service" version.
         pub indexer_agent_version: Version,
         /// The indexer's "graph node" version.
         pub graph_node_version: Version,
+        /// The features the indexer advertises support for, e.g., SQL queries.
+        pub features: HashSet<String>,
 
         /// The largest allocation per indexing.
         pub largest_allocation: HashMap<DeploymentId, Address>,
@@ -118,6 +292,27 @@ pub mod types {
         pub indexings_cost_model: HashMap<DeploymentId, Ptr<CostModel>>,
     }
 
+    impl IndexerInfo {
+        /// Get the indexer's indexing progress information for the given deployment.
+        pub fn progress_for(
+            &self,
+            deployment: &DeploymentId,
+        ) -> Option<&IndexerIndexingProgressInfo> {
+            self.indexings_progress.get(deployment)
+        }
+
+        /// Get the number of blocks the indexer's indexing of the given deployment is behind the
+        /// given chain head block.
+        ///
+        /// If the indexing is ahead of the given head, e.g., the head is stale, it is considered
+        /// `0` blocks behind. If there is no progress information for the deployment, `None` is
+        /// returned.
+        pub fn blocks_behind(&self, deployment: &DeploymentId, head: BlockNumber) -> Option<u64> {
+            self.progress_for(deployment)
+                .map(|progress| head.saturating_sub(progress.latest_block))
+        }
+    }
+
     /// Internal representation of the fetched indexer's indexing progress information.
     #[derive(Clone, Debug)]
     pub struct IndexerIndexingProgressInfo {
@@ -125,73 +320,661 @@ pub mod types {
         pub latest_block: BlockNumber,
         /// The minimum block the indexer has indexed for the deployment.
         pub min_block: Option<BlockNumber>,
+        /// The last time the indexer's latest block was observed advancing, if known.
+        pub last_progress_at: Option<Instant>,
+        /// The latency of the indexer's status request the progress was resolved with.
+        pub latency: Duration,
+    }
+}
+
+/// The indexers processing pipeline stages.
+///
+/// An indexer failing any of the stages is filtered out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FilterStage {
+    /// Check the indexer's address against the address blocklist.
+    AddrBlocklist,
+    /// Resolve the indexer's host and check it against the host blocklist.
+    HostBlocklist,
+    /// Resolve the indexer's agent and graph-node versions and check the minimum versions.
+    Version,
+    /// Resolve the indexer's public POIs and filter-out the blocked indexings.
+    PoiBlocklist,
+    /// Resolve the indexer's indexings progress.
+    IndexingProgress,
+    /// Resolve and compile the indexer's indexings cost models.
+    CostModel,
+}
+
+impl FilterStage {
+    /// The stage name, used as the stage metrics label.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterStage::AddrBlocklist => "addr_blocklist",
+            FilterStage::HostBlocklist => "host_blocklist",
+            FilterStage::Version => "version",
+            FilterStage::PoiBlocklist => "poi_blocklist",
+            FilterStage::IndexingProgress => "indexing_progress",
+            FilterStage::CostModel => "cost_model",
+        }
+    }
+}
+
+/// The reason an indexer was filtered-out of the network topology.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IndexerFilterReason {
+    /// The indexer has no URL.
+    MissingUrl,
+    /// The indexer URL is not valid, e.g., it has no host.
+    InvalidUrl,
+    /// The indexer URL scheme is not HTTP(S).
+    InvalidScheme,
+    /// The indexer has no allocations.
+    NoAllocations,
+    /// The indexer has less staked tokens than the minimum required.
+    InsufficientStake,
+    /// All the indexer's indexings were dropped.
+    NoDeployments,
+    /// The indexer address is blocked.
+    AddrBlocked,
+    /// The indexer host is blocked.
+    HostBlocked,
+    /// The indexer host could not be resolved.
+    HostUnresolvable,
+    /// The indexer agent version could not be resolved.
+    AgentVersionUnresolvable,
+    /// The indexer agent version is below the minimum required.
+    AgentVersionTooLow,
+    /// The indexer graph-node version could not be resolved, and the fallback policy blocks it.
+    GraphNodeVersionUnresolvable,
+    /// The indexer graph-node version is below the minimum required by all its indexings.
+    GraphNodeVersionTooLow,
+    /// The indexer POIs could not be resolved.
+    PoiResolutionFailed,
+    /// The indexer reported the same POI for distinct deployments.
+    PoiCollision,
+    /// All the indexer's indexings are blocked by POI.
+    AllPoisBlocked,
+    /// The indexer indexings progress could not be resolved.
+    ProgressResolutionFailed,
+    /// The indexer processing did not complete within the processing deadline.
+    ProcessingDeadlineExceeded,
+    /// All the indexer's indexings are lagging behind their chain head.
+    StaleIndexings,
+    /// The indexer is lagging behind the chain head on all its indexings, by more than the
+    /// whole-indexer lag threshold.
+    LaggingIndexer,
+}
+
+impl IndexerFilterReason {
+    /// The reason name, used as the filtered indexers metric label.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndexerFilterReason::MissingUrl => "missing_url",
+            IndexerFilterReason::InvalidUrl => "invalid_url",
+            IndexerFilterReason::InvalidScheme => "invalid_scheme",
+            IndexerFilterReason::NoAllocations => "no_allocations",
+            IndexerFilterReason::InsufficientStake => "insufficient_stake",
+            IndexerFilterReason::NoDeployments => "no_deployments",
+            IndexerFilterReason::AddrBlocked => "addr_blocked",
+            IndexerFilterReason::HostBlocked => "host_blocked",
+            IndexerFilterReason::HostUnresolvable => "host_unresolvable",
+            IndexerFilterReason::AgentVersionUnresolvable => "agent_version_unresolvable",
+            IndexerFilterReason::AgentVersionTooLow => "agent_version_too_low",
+            IndexerFilterReason::GraphNodeVersionUnresolvable => "graph_node_version_unresolvable",
+            IndexerFilterReason::GraphNodeVersionTooLow => "graph_node_version_too_low",
+            IndexerFilterReason::PoiResolutionFailed => "poi_resolution_failed",
+            IndexerFilterReason::PoiCollision => "poi_collision",
+            IndexerFilterReason::AllPoisBlocked => "all_pois_blocked",
+            IndexerFilterReason::ProgressResolutionFailed => "progress_resolution_failed",
+            IndexerFilterReason::ProcessingDeadlineExceeded => "processing_deadline_exceeded",
+            IndexerFilterReason::StaleIndexings => "stale_indexings",
+            IndexerFilterReason::LaggingIndexer => "lagging_indexer",
+        }
+    }
+
+    /// Create an [`IndexerFilterError`] with this reason.
+    fn error(self, error: anyhow::Error) -> IndexerFilterError {
+        IndexerFilterError {
+            reason: self,
+            error,
+        }
+    }
+}
+
+/// The error returned when an indexer is filtered-out of the network topology.
+#[derive(Debug, thiserror::Error)]
+#[error("{error}")]
+pub struct IndexerFilterError {
+    /// The reason the indexer was filtered-out.
+    pub reason: IndexerFilterReason,
+    error: anyhow::Error,
+}
+
+/// A summary of the last indexers processing, counting the indexers filtered-out at each
+/// processing pipeline stage, e.g., to diagnose why an indexer is missing from the network
+/// topology.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TopologyFetchReport {
+    /// The number of fetched indexers.
+    pub fetched: usize,
+    /// The number of indexers blocked by the address blocklist.
+    pub addr_blocked: usize,
+    /// The number of indexers blocked by the host blocklist, or whose host is unresolvable.
+    pub host_blocked: usize,
+    /// The number of indexers blocked by their agent or graph-node versions.
+    pub version_blocked: usize,
+    /// The number of indexers blocked by POI, or whose POIs could not be resolved.
+    pub poi_blocked: usize,
+    /// The number of indexers whose indexings progress could not be resolved, or whose indexings
+    /// are all lagging behind the chain head.
+    pub progress_failed: usize,
+    /// The number of indexers filtered-out for other reasons, e.g., insufficient stake, the
+    /// indexers cap, or the processing deadline.
+    pub other_filtered: usize,
+    /// The number of indexers making it into the network topology.
+    pub final_count: usize,
+}
+
+impl TopologyFetchReport {
+    /// Count an indexer filtered-out at the given stage. Indexers filtered-out outside the
+    /// processing pipeline stages are counted as `other_filtered`.
+    fn count_filtered(&mut self, stage: Option<FilterStage>) {
+        let counter = match stage {
+            Some(FilterStage::AddrBlocklist) => &mut self.addr_blocked,
+            Some(FilterStage::HostBlocklist) => &mut self.host_blocked,
+            Some(FilterStage::Version) => &mut self.version_blocked,
+            Some(FilterStage::PoiBlocklist) => &mut self.poi_blocked,
+            Some(FilterStage::IndexingProgress) => &mut self.progress_failed,
+            Some(FilterStage::CostModel) | None => &mut self.other_filtered,
+        };
+        *counter += 1;
+    }
+}
+
+/// Count the filtered-out indexer by reason.
+fn count_filtered_indexer(reason: IndexerFilterReason) {
+    with_metric(&METRICS.indexers_filtered, &[reason.name()], |counter| {
+        counter.inc()
+    });
+}
+
+/// The default indexers processing pipeline stages order.
+///
+/// The cheap in-memory checks run before the expensive network probes.
+pub const DEFAULT_FILTER_STAGES: [FilterStage; 6] = [
+    FilterStage::AddrBlocklist,
+    FilterStage::HostBlocklist,
+    FilterStage::Version,
+    FilterStage::PoiBlocklist,
+    FilterStage::IndexingProgress,
+    FilterStage::CostModel,
+];
+
+/// A source of the chain head block per network, e.g., the latest block reported by the network's
+/// chain head tracker.
+pub trait ChainHeadSource: Send + Sync {
+    /// Get the chain head block of the network, if known.
+    fn chain_head(&self, network: &str) -> Option<BlockNumber>;
+}
+
+/// An in-memory chain heads source, keyed by network.
+impl ChainHeadSource for HashMap<String, BlockNumber> {
+    fn chain_head(&self, network: &str) -> Option<BlockNumber> {
+        self.get(network).copied()
     }
 }
 
+/// The per-indexing staleness filter configuration.
+///
+/// The indexings more than `max_blocks_behind` blocks behind their chain head are dropped.
+#[derive(Clone)]
+pub struct StalenessFilter {
+    /// The chain heads source.
+    pub chain_heads: Arc<dyn ChainHeadSource>,
+    /// The maximum number of blocks an indexing can be behind the chain head.
+    pub max_blocks_behind: u64,
+}
+
+/// The whole-indexer lag check configuration.
+///
+/// The indexers more than `max_blocks_behind` blocks behind their chain head on all their
+/// indexings are blocked. Unlike the [`StalenessFilter`], no individual indexing is dropped.
+#[derive(Clone)]
+pub struct IndexerLagFilter {
+    /// The chain heads source.
+    pub chain_heads: Arc<dyn ChainHeadSource>,
+    /// The maximum number of blocks an indexer can be behind the chain head on all its indexings.
+    pub max_blocks_behind: u64,
+}
+
 /// Internal type holding the network service state.
 pub struct InternalState {
     pub indexer_http_client: reqwest::Client,
     pub indexer_min_agent_version: Version,
     pub indexer_min_graph_node_version: Version,
+    /// The minimum graph-node version per deployment manifest network. Networks without an entry
+    /// fall back to `indexer_min_graph_node_version`.
+    pub indexer_min_graph_node_version_per_network: HashMap<String, Version>,
     pub indexer_addr_blocklist: Option<AddrBlocklist>,
     pub indexer_host_resolver: Mutex<HostResolver>,
     pub indexer_host_blocklist: Option<HostBlocklist>,
     pub indexer_version_resolver: VersionResolver,
-    pub indexer_indexing_pois_blocklist: Option<(PoiBlocklist, Mutex<PoiResolver>)>,
+    pub indexer_indexing_pois_blocklist: Option<(PoiBlocklist, PoiResolver)>,
+    /// The trusted reference POIs the indexers' POIs are checked against. It requires the POIs
+    /// resolver, i.e., `indexer_indexing_pois_blocklist`, to be set.
+    pub indexer_indexing_poi_reference: Option<Arc<dyn PoiReferenceSource>>,
+    /// The policy applied to indexers reporting the same POI for distinct deployments.
+    pub indexer_indexing_poi_collision_policy: PoiCollisionPolicy,
     pub indexer_indexing_status_resolver: IndexingProgressResolver,
+    /// The per-indexing staleness filter, applied after resolving the indexings progress. If not
+    /// set, the indexings are not checked against the chain head.
+    pub indexer_indexing_staleness_filter: Option<StalenessFilter>,
+    /// The whole-indexer lag check, applied after resolving the indexings progress and before the
+    /// staleness filter. If not set, the indexers are not checked against the chain head.
+    pub indexer_lag_filter: Option<IndexerLagFilter>,
     pub indexer_indexing_cost_model_resolver: (CostModelResolver, Mutex<CostModelCompiler>),
+    /// The operator-defined cost models per deployment. They take precedence over the cost models
+    /// reported by the indexers.
+    pub indexer_indexing_cost_model_overrides: HashMap<DeploymentId, Ptr<CostModel>>,
+    /// The indexers processing pipeline stages, in execution order.
+    pub indexer_filter_stages: Vec<FilterStage>,
+    /// The maximum amount of tokens an indexer can have allocated to a deployment.
+    pub indexer_max_allocated_tokens: u128,
+    /// The minimum amount of tokens an indexer must have staked. Indexers below it are
+    /// filtered-out.
+    pub indexer_min_staked_tokens: u128,
+    /// The maximum number of indexers processed per update. If not set, all indexers are
+    /// processed.
+    pub max_indexers: Option<usize>,
+    /// The recognized deployment manifest networks. If not set, all networks are accepted.
+    pub known_networks: Option<HashSet<String>>,
+    /// The indexers information fetch timeout. It covers the network subgraph query only, not the
+    /// indexers processing.
+    pub indexers_fetch_timeout: Duration,
+    /// The subgraphs information fetch timeout.
+    pub subgraphs_fetch_timeout: Duration,
+    /// The indexers and subgraphs information fetches retry configuration.
+    pub fetch_retry: RetryConfig,
+    /// The overall network topology update timeout, covering the fetches and the processing.
+    pub update_timeout: Duration,
+    /// The per-indexer processing deadline, started once the indexer is dequeued for processing.
+    /// The indexers not processed by then are dropped.
+    pub indexers_processing_deadline: Duration,
+    /// The maximum number of indexers processed concurrently.
+    pub indexers_processing_concurrency: usize,
+    /// The report of the last indexers processing. See [`InternalState::last_fetch_report`].
+    pub last_fetch_report: RwLock<Option<TopologyFetchReport>>,
+}
+
+/// A serializable summary of the network service's active settings, e.g., for a diagnostics
+/// endpoint.
+///
+/// Blocklists are reported by their number of entries, not their contents. Secrets, e.g., the
+/// network subgraph auth token, are held by the subgraph client and are never reported.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EffectiveConfig {
+    pub indexer_min_agent_version: Version,
+    pub indexer_min_graph_node_version: Version,
+    pub indexer_min_graph_node_version_per_network: BTreeMap<String, Version>,
+    /// The policy applied when an indexer's graph-node version cannot be resolved.
+    pub indexer_graph_node_version_fallback: GraphNodeVersionFallback,
+    pub indexer_version_cache_ttl_ms: u128,
+    /// The number of blocked indexer addresses, if the blocklist is enabled.
+    pub indexer_addr_blocklist_len: Option<usize>,
+    /// The number of blocked IP networks, if the blocklist is enabled.
+    pub indexer_host_blocklist_len: Option<usize>,
+    /// The number of blocked ASNs, if the blocklist is enabled.
+    pub indexer_host_blocklist_asn_len: Option<usize>,
+    /// The number of blocked POIs, if the blocklist is enabled.
+    pub indexer_pois_blocklist_len: Option<usize>,
+    /// The POIs resolution timeout, if the POIs are resolved.
+    pub indexer_pois_resolution_timeout_ms: Option<u128>,
+    pub indexer_poi_collision_policy: PoiCollisionPolicy,
+    /// The staleness filter maximum blocks behind the chain head, if the filter is enabled.
+    pub indexer_indexing_max_blocks_behind: Option<u64>,
+    /// The number of deployments with an operator-defined cost model.
+    pub indexer_cost_model_overrides_len: usize,
+    /// The compiled cost models cache capacity. It is not reported if the cost model compiler is
+    /// in use, i.e., mid-update, when the summary is taken.
+    pub indexer_compiled_cost_models_cache_capacity: Option<usize>,
+    /// The number of indexers being drained.
+    pub indexer_drain_set_len: usize,
+    /// The number of indexers with a tier assigned.
+    pub indexer_tiers_len: usize,
+    pub indexer_tier_multiplier: f64,
+    /// The indexers processing pipeline stage names, in execution order.
+    pub indexer_filter_stages: Vec<&'static str>,
+    pub indexer_max_allocated_tokens: u128,
+    pub indexer_min_staked_tokens: u128,
+    pub max_indexers: Option<usize>,
+    /// The recognized deployment manifest networks, sorted. If not set, all networks are accepted.
+    pub known_networks: Option<Vec<String>>,
+    pub indexers_fetch_timeout_ms: u128,
+    pub subgraphs_fetch_timeout_ms: u128,
+    pub fetch_retry_max_attempts: u32,
+    pub fetch_retry_base_delay_ms: u128,
+    /// The maximum age of the previous snapshot's information reused on partial updates, if
+    /// partial updates are enabled. See [`PartialUpdatePolicy`].
+    pub partial_update_max_age_ms: Option<u128>,
+    pub update_timeout_ms: u128,
+    pub indexers_processing_deadline_ms: u128,
+    pub indexers_processing_concurrency: usize,
+}
+
+impl InternalState {
+    /// Get the summary of the active settings.
+    ///
+    /// The settings held by the network service, e.g., the drain set or the partial update
+    /// policy, are left to their defaults. See [`NetworkService::effective_config`].
+    ///
+    /// [`NetworkService::effective_config`]: super::service::NetworkService::effective_config
+    pub fn effective_config(&self) -> EffectiveConfig {
+        EffectiveConfig {
+            indexer_min_agent_version: self.indexer_min_agent_version.clone(),
+            indexer_min_graph_node_version: self.indexer_min_graph_node_version.clone(),
+            indexer_min_graph_node_version_per_network: self
+                .indexer_min_graph_node_version_per_network
+                .iter()
+                .map(|(network, version)| (network.clone(), version.clone()))
+                .collect(),
+            indexer_graph_node_version_fallback: self
+                .indexer_version_resolver
+                .graph_node_version_fallback()
+                .clone(),
+            indexer_version_cache_ttl_ms: self.indexer_version_resolver.cache_ttl().as_millis(),
+            indexer_addr_blocklist_len: self.indexer_addr_blocklist.as_ref().map(|bl| bl.len()),
+            indexer_host_blocklist_len: self.indexer_host_blocklist.as_ref().map(|bl| bl.len()),
+            indexer_host_blocklist_asn_len: self
+                .indexer_host_blocklist
+                .as_ref()
+                .map(|bl| bl.asn_len()),
+            indexer_pois_blocklist_len: self
+                .indexer_indexing_pois_blocklist
+                .as_ref()
+                .map(|(bl, _)| bl.len()),
+            indexer_pois_resolution_timeout_ms: self
+                .indexer_indexing_pois_blocklist
+                .as_ref()
+                .map(|(_, resolver)| resolver.timeout().as_millis()),
+            indexer_poi_collision_policy: self.indexer_indexing_poi_collision_policy,
+            indexer_indexing_max_blocks_behind: self
+                .indexer_indexing_staleness_filter
+                .as_ref()
+                .map(|filter| filter.max_blocks_behind),
+            indexer_cost_model_overrides_len: self.indexer_indexing_cost_model_overrides.len(),
+            indexer_compiled_cost_models_cache_capacity: self
+                .indexer_indexing_cost_model_resolver
+                .1
+                .try_lock()
+                .ok()
+                .map(|compiler| compiler.compiled_models_cache_capacity()),
+            indexer_filter_stages: self
+                .indexer_filter_stages
+                .iter()
+                .map(FilterStage::name)
+                .collect(),
+            indexer_max_allocated_tokens: self.indexer_max_allocated_tokens,
+            indexer_min_staked_tokens: self.indexer_min_staked_tokens,
+            max_indexers: self.max_indexers,
+            known_networks: self
+                .known_networks
+                .as_ref()
+                .map(|networks| networks.iter().cloned().sorted().collect()),
+            indexers_fetch_timeout_ms: self.indexers_fetch_timeout.as_millis(),
+            subgraphs_fetch_timeout_ms: self.subgraphs_fetch_timeout.as_millis(),
+            fetch_retry_max_attempts: self.fetch_retry.max_attempts,
+            fetch_retry_base_delay_ms: self.fetch_retry.base_delay.as_millis(),
+            update_timeout_ms: self.update_timeout.as_millis(),
+            indexers_processing_deadline_ms: self.indexers_processing_deadline.as_millis(),
+            indexers_processing_concurrency: self.indexers_processing_concurrency,
+            ..Default::default()
+        }
+    }
+
+    /// Get the report of the last indexers processing, if any. It is updated on every
+    /// [`fetch_update`] whose indexers fetch succeeds, even if no valid indexers were found.
+    pub fn last_fetch_report(&self) -> Option<TopologyFetchReport> {
+        self.last_fetch_report.read().unwrap().clone()
+    }
 }
 
 /// Fetch the network topology information from the graph network subgraph.
+///
+/// If the update, i.e., fetching and processing the information, takes longer than the
+/// configured update timeout, an error is returned. The caller must keep the previous network
+/// topology snapshot.
+///
+/// If only one of the indexers and subgraphs information fetches fails, the partial update
+/// `policy` decides whether the `previous` snapshot's information is reused for the failed half.
+/// See [`PartialUpdatePolicy`].
 pub async fn fetch_update(
     client: &Mutex<SubgraphClient>,
     state: &InternalState,
-) -> anyhow::Result<NetworkTopologySnapshot> {
+    previous: Option<&NetworkTopologySnapshot>,
+    policy: PartialUpdatePolicy,
+) -> Result<NetworkTopologySnapshot, NetworkFetchError> {
+    tokio::time::timeout(
+        state.update_timeout,
+        fetch_and_process_update(client, state, previous, policy),
+    )
+    .await
+    .map_err(|_| NetworkFetchError::UpdateTimeout)?
+}
+
+/// Fetch and process the network topology information from the graph network subgraph.
+async fn fetch_and_process_update(
+    client: &Mutex<SubgraphClient>,
+    state: &InternalState,
+    previous: Option<&NetworkTopologySnapshot>,
+    policy: PartialUpdatePolicy,
+) -> Result<NetworkTopologySnapshot, NetworkFetchError> {
+    // The fetch retries must not outlive the update timeout
+    let deadline = Instant::now() + state.update_timeout;
+
     // Fetch and pre-process the network topology information
-    let (indexers_info, subgraphs_info) = futures::future::try_join(
-        async {
-            let indexers = {
-                let mut subgraph_client = client.lock().await;
-                match tokio::time::timeout(
-                    NETWORK_TOPOLOGY_FETCH_TIMEOUT,
-                    fetch_and_pre_process_indexers_info(&mut subgraph_client),
-                )
-                .await
-                {
-                    // If the fetch timed out, return an error
-                    Err(_) => Err(anyhow!("indexers info fetch timed out")),
-                    Ok(resp) => match resp {
-                        // If the fetch failed, return an error
-                        Err(err) => Err(anyhow!("indexers info fetch failed: {err}")),
-                        Ok(resp) => Ok(resp),
-                    },
-                }
-            }?;
+    let indexers = async {
+        let indexers = fetch_with_retry("indexers", &state.fetch_retry, deadline, || async move {
+            let mut subgraph_client = client.lock().await;
+            tokio::time::timeout(
+                state.indexers_fetch_timeout,
+                fetch_and_pre_process_indexers_info(
+                    &mut subgraph_client,
+                    state.indexer_max_allocated_tokens,
+                ),
+            )
+            .await
+            .map_err(|_| FetchError::Timeout)?
+            .map_err(FetchError::Failed)
+        })
+        .await
+        .map_err(|err| match err {
+            // If the fetch timed out, return an error
+            FetchError::Timeout => NetworkFetchError::IndexersFetchTimeout,
+            // If the fetch failed, return the failure condition as is
+            FetchError::Failed(err) => err,
+        })?;
 
-            // Process the fetched network topology information
-            process_indexers_info(state, indexers).await
-        },
-        async {
+        // Process the fetched network topology information
+        process_indexers_info(state, indexers).await
+    };
+    let subgraphs = async {
+        fetch_with_retry("subgraphs", &state.fetch_retry, deadline, || async move {
             let mut subgraph_client = client.lock().await;
-            match tokio::time::timeout(
-                NETWORK_TOPOLOGY_FETCH_TIMEOUT,
-                fetch_and_pre_process_subgraphs_info(&mut subgraph_client),
+            tokio::time::timeout(
+                state.subgraphs_fetch_timeout,
+                fetch_and_pre_process_subgraphs_info(
+                    &mut subgraph_client,
+                    state.known_networks.as_ref(),
+                ),
             )
             .await
-            {
-                // If the fetch timed out, return an error
-                Err(_) => Err(anyhow!("subgraphs info fetch timed out")),
-                Ok(resp) => match resp {
-                    // If the fetch failed, return an error
-                    Err(err) => Err(anyhow!("subgraphs info fetch failed: {err}")),
-                    Ok(resp) => Ok(resp),
-                },
+            .map_err(|_| FetchError::Timeout)?
+            .map_err(FetchError::Failed)
+        })
+        .await
+        .map_err(|err| match err {
+            // If the fetch timed out, return an error
+            FetchError::Timeout => NetworkFetchError::SubgraphsFetchTimeout,
+            // If the fetch failed, return the failure condition as is
+            FetchError::Failed(err) => err,
+        })
+    };
+
+    // The fetched information is only retained in the snapshot if it can be reused on partial
+    // network topology updates
+    let snapshot = match (policy, previous) {
+        (PartialUpdatePolicy::ReusePrevious { max_age }, Some(previous)) => {
+            let (indexers_info, subgraphs_info) = futures::future::join(indexers, subgraphs).await;
+            let (indexers_info, subgraphs_info) =
+                merge_partial_update(indexers_info, subgraphs_info, previous, max_age)?;
+            snapshot::new_retaining_info(indexers_info, subgraphs_info)
+        }
+        (PartialUpdatePolicy::ReusePrevious { .. }, None) => {
+            let (indexers_info, subgraphs_info) =
+                futures::future::try_join(indexers, subgraphs).await?;
+            snapshot::new_retaining_info(
+                FetchedInfo::new(indexers_info),
+                FetchedInfo::new(subgraphs_info),
+            )
+        }
+        (PartialUpdatePolicy::Disabled, _) => {
+            let (indexers_info, subgraphs_info) =
+                futures::future::try_join(indexers, subgraphs).await?;
+            snapshot::new_from(indexers_info, subgraphs_info)
+        }
+    };
+
+    Ok(snapshot)
+}
+
+/// Merge the indexers and subgraphs information fetch results, reusing the previous snapshot's
+/// information for the failed half.
+///
+/// The previous snapshot's information is only reused if it was retained and fetched at most
+/// `max_age` ago, otherwise the failed fetch error is returned. If both fetches failed, the
+/// indexers fetch error is returned.
+fn merge_partial_update(
+    indexers_info: Result<HashMap<Address, IndexerInfo>, NetworkFetchError>,
+    subgraphs_info: Result<HashMap<SubgraphId, SubgraphInfo>, NetworkFetchError>,
+    previous: &NetworkTopologySnapshot,
+    max_age: Duration,
+) -> Result<
+    (
+        FetchedInfo<HashMap<Address, IndexerInfo>>,
+        FetchedInfo<HashMap<SubgraphId, SubgraphInfo>>,
+    ),
+    NetworkFetchError,
+> {
+    match (indexers_info, subgraphs_info) {
+        (Ok(indexers_info), Ok(subgraphs_info)) => Ok((
+            FetchedInfo::new(indexers_info),
+            FetchedInfo::new(subgraphs_info),
+        )),
+        (Ok(indexers_info), Err(err)) => {
+            let Some(subgraphs_info) = reusable_info(previous.subgraphs_info(), max_age) else {
+                return Err(err);
+            };
+            tracing::warn!(
+                error = %err,
+                age = ?subgraphs_info.age(),
+                "reusing the previous subgraphs info"
+            );
+            Ok((FetchedInfo::new(indexers_info), subgraphs_info))
+        }
+        (Err(err), Ok(subgraphs_info)) => {
+            let Some(indexers_info) = reusable_info(previous.indexers_info(), max_age) else {
+                return Err(err);
+            };
+            tracing::warn!(
+                error = %err,
+                age = ?indexers_info.age(),
+                "reusing the previous indexers info"
+            );
+            Ok((indexers_info, FetchedInfo::new(subgraphs_info)))
+        }
+        (Err(indexers_err), Err(_)) => Err(indexers_err),
+    }
+}
+
+/// Get a copy of the previous snapshot's information, if retained and fetched at most `max_age`
+/// ago.
+fn reusable_info<T: Clone>(
+    info: Option<&FetchedInfo<T>>,
+    max_age: Duration,
+) -> Option<FetchedInfo<T>> {
+    info.filter(|info| info.age() <= max_age).cloned()
+}
+
+/// Run the fetch, retrying it with an exponential backoff on failure or timeout.
+///
+/// Empty fetches are not retried. The attempts are cut short at the `deadline`, and no retry is
+/// made if its backoff delay ends past it. The number of attempts made is recorded in the fetch
+/// span.
+async fn fetch_with_retry<T, F, Fut>(
+    fetch: &'static str,
+    retry: &RetryConfig,
+    deadline: Instant,
+    mut attempt: F,
+) -> Result<T, FetchError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, FetchError>>,
+{
+    let span = tracing::debug_span!(
+        "network topology fetch",
+        fetch,
+        attempts = tracing::field::Empty,
+    );
+    async {
+        let mut attempts = 0;
+        let result = loop {
+            attempts += 1;
+            let result = tokio::time::timeout_at(deadline, attempt())
+                .await
+                .unwrap_or(Err(FetchError::Timeout));
+            match result {
+                Err(err)
+                    if err.is_retryable()
+                        && attempts < retry.max_attempts
+                        && Instant::now() + retry.delay(attempts) < deadline =>
+                {
+                    let delay = retry.delay(attempts);
+                    tracing::warn!(
+                        fetch,
+                        attempts,
+                        ?delay,
+                        error = %err,
+                        "retrying network topology fetch"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => break result,
             }
-        },
-    )
-    .await?;
+        };
+        tracing::Span::current().record("attempts", attempts);
+        result
+    }
+    .instrument(span)
+    .await
+}
 
-    Ok(snapshot::new_from(indexers_info, subgraphs_info))
+/// Fetch the network topology information, aborting the fetch if the `cancel` future completes
+/// first, e.g., on shutdown.
+///
+/// On cancellation, all the outstanding fetches and indexer probes are dropped and an error is
+/// returned. The caller must keep the previous network topology snapshot.
+pub async fn fetch_update_with_cancellation(
+    client: &Mutex<SubgraphClient>,
+    state: &InternalState,
+    previous: Option<&NetworkTopologySnapshot>,
+    policy: PartialUpdatePolicy,
+    cancel: impl Future<Output = ()>,
+) -> Result<NetworkTopologySnapshot, NetworkFetchError> {
+    tokio::select! { biased;
+        _ = cancel => Err(NetworkFetchError::UpdateCancelled),
+        update = fetch_update(client, state, previous, policy) => update,
+    }
 }
 
 /// Fetch the indexers information from the graph network subgraph and performs pre-processing
@@ -204,16 +987,20 @@ pub async fn fetch_update(
 ///
 /// Invalid info is filtered out before converting into the internal representation. If no valid
 /// indexers are found, an error is returned.
+///
+/// Indexings with more allocated tokens than `max_allocated_tokens` are considered corrupt and
+/// filtered out.
 pub async fn fetch_and_pre_process_indexers_info(
     client: &mut SubgraphClient,
-) -> anyhow::Result<HashMap<Address, IndexerInfo>> {
+    max_allocated_tokens: u128,
+) -> Result<HashMap<Address, IndexerInfo>, NetworkFetchError> {
     // Fetch the indexers information from the graph network subgraph
     let indexers = client
         .fetch_indexers()
         .await
-        .map_err(|err| anyhow!("indexers fetch failed: {err}"))?;
+        .map_err(|cause| NetworkFetchError::IndexersFetchFailed { cause })?;
     if indexers.is_empty() {
-        return Err(anyhow!("empty indexers fetch"));
+        return Err(NetworkFetchError::EmptyIndexers);
     }
 
     // Map the fetched indexers info into the internal representation
@@ -228,10 +1015,11 @@ pub async fn fetch_and_pre_process_indexers_info(
                 indexer.url = ?indexer.url,
             );
 
-            match try_into_internal_indexer_info(indexer) {
+            match try_into_internal_indexer_info(indexer, max_allocated_tokens) {
                 Ok(indexer) => Some((indexer.id, indexer)),
                 Err(err) => {
-                    tracing::debug!("filtering-out indexer: {err}");
+                    tracing::debug!(reason = err.reason.name(), "filtering-out indexer: {err}");
+                    count_filtered_indexer(err.reason);
                     None
                 }
             }
@@ -240,7 +1028,7 @@ pub async fn fetch_and_pre_process_indexers_info(
 
     // If no valid indexers are found, return an error
     if indexers.is_empty() {
-        Err(anyhow!("no valid indexers found"))
+        Err(NetworkFetchError::NoValidIndexers)
     } else {
         Ok(indexers)
     }
@@ -256,16 +1044,20 @@ pub async fn fetch_and_pre_process_indexers_info(
 ///
 /// Invalid info is filtered out before converting into the internal representation. If no valid
 /// subgraphs are found, an error is returned.
+///
+/// If `known_networks` is set, deployments whose manifest network is not recognized are filtered
+/// out.
 pub async fn fetch_and_pre_process_subgraphs_info(
     client: &mut SubgraphClient,
-) -> anyhow::Result<HashMap<SubgraphId, SubgraphInfo>> {
+    known_networks: Option<&HashSet<String>>,
+) -> Result<HashMap<SubgraphId, SubgraphInfo>, NetworkFetchError> {
     // Fetch the subgraphs information from the graph network subgraph
     let subgraphs = client
         .fetch_subgraphs()
         .await
-        .map_err(|err| anyhow!("subgraphs fetch failed: {err}"))?;
+        .map_err(|cause| NetworkFetchError::SubgraphsFetchFailed { cause })?;
     if subgraphs.is_empty() {
-        return Err(anyhow!("empty subgraphs fetch"));
+        return Err(NetworkFetchError::EmptySubgraphs);
     }
 
     // Map the fetched subgraphs info into the internal representation
@@ -278,7 +1070,7 @@ pub async fn fetch_and_pre_process_subgraphs_info(
                 subgraph.id = %subgraph.id,
             )
             .entered();
-            match try_into_internal_subgraph_info(subgraph) {
+            match try_into_internal_subgraph_info(subgraph, known_networks) {
                 Ok(subgraph) => Some((subgraph.id, subgraph)),
                 Err(err) => {
                     tracing::debug!("filtering-out subgraph: {err}");
@@ -289,7 +1081,7 @@ pub async fn fetch_and_pre_process_subgraphs_info(
         .collect::<HashMap<_, _>>();
 
     if subgraphs.is_empty() {
-        Err(anyhow!("no valid subgraphs found"))
+        Err(NetworkFetchError::NoValidSubgraphs)
     } else {
         Ok(subgraphs)
     }
@@ -297,44 +1089,86 @@ pub async fn fetch_and_pre_process_subgraphs_info(
 
 /// Convert from the fetched indexer information into the internal representation.
 ///
-/// If the indexer is invalid, e.g., has no URL, an error is returned.
+/// If the indexer is invalid, e.g., has no URL, an error is returned. Indexings with more
+/// allocated tokens than `max_allocated_tokens` are dropped.
 fn try_into_internal_indexer_info(
     indexer: subgraph::types::fetch_indexers::Indexer,
-) -> anyhow::Result<IndexerInfo> {
+    max_allocated_tokens: u128,
+) -> Result<IndexerInfo, IndexerFilterError> {
     // Check if the indexer is present
-    let indexer_url = indexer.url.ok_or_else(|| anyhow!("missing URL"))?;
+    let indexer_url = indexer
+        .url
+        .ok_or_else(|| IndexerFilterReason::MissingUrl.error(anyhow!("missing URL")))?;
 
     // Parse the URL. It must have an HTTP (or HTTPS) scheme and a valid host.
     // Filter out indexers with invalid URLs.
-    let indexer_url = indexer_url
-        .parse::<Url>()
-        .map_err(|err| anyhow!("URL parsing failed: {err}"))?;
+    //
+    // Parsing also drops the port if it is the scheme's default one, so `https://x.io` and
+    // `https://x.io:443` are treated as the same indexer URL. Non-default ports are preserved.
+    let indexer_url = indexer_url.parse::<Url>().map_err(|err| {
+        IndexerFilterReason::InvalidUrl.error(anyhow!("URL parsing failed: {err}"))
+    })?;
     if !indexer_url.scheme().starts_with("http") {
-        return Err(anyhow!("invalid URL: invalid scheme"));
+        return Err(
+            IndexerFilterReason::InvalidScheme.error(anyhow!("invalid URL: invalid scheme"))
+        );
     }
     if indexer_url.host().is_none() {
-        return Err(anyhow!("invalid URL: missing host"));
+        return Err(IndexerFilterReason::InvalidUrl.error(anyhow!("invalid URL: missing host")));
     }
 
     // Check if the indexer has any allocations
     let indexer_allocations: Vec1<_> = indexer
         .allocations
         .try_into()
-        .map_err(|_| anyhow!("no allocations"))?;
+        .map_err(|_| IndexerFilterReason::NoAllocations.error(anyhow!("no allocations")))?;
+
+    // Get the total amount of tokens allocated for each indexing. Indexings with an implausibly
+    // large amount of allocated tokens indicate corrupt data, and are dropped.
+    let indexer_indexing_total_allocated_tokens = indexer_allocations
+        .iter()
+        .map(|alloc| alloc.subgraph_deployment.id)
+        .unique()
+        .map(|deployment_id| {
+            let total = indexer_allocations
+                .iter()
+                .filter_map(|alloc| {
+                    if alloc.subgraph_deployment.id == deployment_id {
+                        Some(alloc.allocated_tokens)
+                    } else {
+                        None
+                    }
+                })
+                .fold(0, u128::saturating_add);
+            (deployment_id, total)
+        })
+        .filter(|(deployment_id, total)| {
+            if *total > max_allocated_tokens {
+                tracing::warn!(
+                    deployment = %deployment_id,
+                    allocated_tokens = total,
+                    "allocated tokens above the maximum, dropping indexing"
+                );
+                return false;
+            }
+            true
+        })
+        .collect::<HashMap<_, _>>();
 
     // Get the list of unique deployment IDs the indexer is associated with.
-    // NOTE: The indexer is guaranteed to have at least one allocation and one
-    // deployment.
+    // NOTE: The indexer is guaranteed to have at least one allocation, but all its indexings
+    // may have been dropped.
     // See ref: d260724b-a445-4842-964e-fb95062c119d
     let indexer_deployment_ids: Vec1<_> = indexer_allocations
         .iter()
         .map(|alloc| alloc.subgraph_deployment.id)
         .unique()
+        .filter(|deployment_id| indexer_indexing_total_allocated_tokens.contains_key(deployment_id))
         .collect::<Vec<_>>()
         .try_into()
-        .map_err(|_| anyhow!("no deployments"))?;
+        .map_err(|_| IndexerFilterReason::NoDeployments.error(anyhow!("no deployments")))?;
 
-    // Get the largest allocation and the total amount of tokens allocated for each indexing
+    // Get the largest allocation for each indexing
     // NOTE: The allocations are ordered by `allocatedTokens` in descending order, and
     // the largest allocation is the first one.
     // See ref: d260724b-a445-4842-964e-fb95062c119d
@@ -354,20 +1188,20 @@ fn try_into_internal_indexer_info(
         })
         .collect::<HashMap<_, _>>();
 
-    let indexer_indexing_total_allocated_tokens = indexer_deployment_ids
+    // Get the manifest network of each indexing, if known
+    let indexer_indexing_networks = indexer_allocations
         .iter()
-        .map(|deployment_id| {
-            let total = indexer_allocations
-                .iter()
-                .filter_map(|alloc| {
-                    if alloc.subgraph_deployment.id == *deployment_id {
-                        Some(alloc.allocated_tokens)
-                    } else {
-                        None
-                    }
-                })
-                .sum();
-            (*deployment_id, total)
+        .filter(|alloc| {
+            indexer_indexing_total_allocated_tokens.contains_key(&alloc.subgraph_deployment.id)
+        })
+        .filter_map(|alloc| {
+            let network = alloc
+                .subgraph_deployment
+                .manifest
+                .as_ref()?
+                .network
+                .clone()?;
+            Some((alloc.subgraph_deployment.id, network))
         })
         .collect::<HashMap<_, _>>();
 
@@ -376,10 +1210,12 @@ fn try_into_internal_indexer_info(
         url: indexer_url,
         staked_tokens: indexer.staked_tokens,
         deployments: indexer_deployment_ids,
+        deployments_network: indexer_indexing_networks,
         largest_allocation: indexer_indexing_largest_allocations,
         total_allocated_tokens: indexer_indexing_total_allocated_tokens,
         indexer_agent_version: Version::new(0, 0, 0), // Placeholder
         graph_node_version: Version::new(0, 0, 0),    // Placeholder
+        features: HashSet::new(),                     // Placeholder
         indexings_progress: HashMap::new(),           // Placeholder
         indexings_cost_model: HashMap::new(),         // Placeholder
     })
@@ -387,13 +1223,33 @@ fn try_into_internal_indexer_info(
 
 /// Convert from the fetched subgraph information into the internal representation.
 ///
-/// If the subgraph is invalid, e.g., has no versions, an error is returned.
+/// If the subgraph is invalid, e.g., has no versions, an error is returned. If `known_networks` is
+/// set, versions whose deployment manifest network is not recognized are dropped.
 fn try_into_internal_subgraph_info(
     subgraph: subgraph::types::fetch_subgraphs::Subgraph,
+    known_networks: Option<&HashSet<String>>,
 ) -> anyhow::Result<SubgraphInfo> {
     let versions = subgraph
         .versions
         .into_iter()
+        .filter(|version| {
+            let network = version
+                .subgraph_deployment
+                .manifest
+                .as_ref()
+                .and_then(|manifest| manifest.network.as_ref());
+            match (known_networks, network) {
+                (Some(known_networks), Some(network)) if !known_networks.contains(network) => {
+                    tracing::warn!(
+                        deployment = %version.subgraph_deployment.id,
+                        network,
+                        "filtering-out deployment: unknown network"
+                    );
+                    false
+                }
+                _ => true,
+            }
+        })
         .map(|version| {
             let deployment = version.subgraph_deployment;
 
@@ -444,10 +1300,48 @@ fn try_into_internal_subgraph_info(
 }
 
 /// Process the fetched network topology information.
+///
+/// If the number of indexers exceeds the configured maximum, only the indexers with the most
+/// staked tokens are processed, and the rest are skipped.
+///
+/// The indexers not processed by the processing deadline are dropped, and the update proceeds with
+/// the indexers processed so far.
+///
+/// Up to the configured processing concurrency indexers are processed at once. The result does not
+/// depend on the concurrency.
+///
+/// The processing summary is recorded as the state's last fetch report. See
+/// [`InternalState::last_fetch_report`].
 pub async fn process_indexers_info(
     state: &InternalState,
     indexers: HashMap<Address, IndexerInfo>,
-) -> anyhow::Result<HashMap<Address, IndexerInfo>> {
+) -> Result<HashMap<Address, IndexerInfo>, NetworkFetchError> {
+    let mut report = TopologyFetchReport {
+        fetched: indexers.len(),
+        ..Default::default()
+    };
+
+    // Bound the processing work by keeping only the top-N indexers by staked tokens
+    let indexers = match state.max_indexers {
+        Some(max_indexers) if indexers.len() > max_indexers => {
+            tracing::warn!(
+                max_indexers,
+                skipped = indexers.len() - max_indexers,
+                "too many indexers, skipping the least staked ones"
+            );
+            report.other_filtered += indexers.len() - max_indexers;
+            indexers
+                .into_iter()
+                .sorted_by_key(|(id, indexer)| (Reverse(indexer.staked_tokens), *id))
+                .take(max_indexers)
+                .collect()
+        }
+        _ => indexers,
+    };
+
+    // Forget the expired indexers' versions, the rest are served from the resolver's cache
+    state.indexer_version_resolver.evict_expired();
+
     // Process the fetched indexers information
     let indexers_info = {
         let indexers_iter_fut = indexers.into_iter().map(move |(indexer_id, indexer)| {
@@ -462,127 +1356,255 @@ pub async fn process_indexers_info(
             tracing::trace!(parent: &indexer_span, "processing");
 
             async move {
-                let mut indexer = indexer;
-
-                // Check if the indexer's address is in the address blocklist
-                if let Err(err) =
-                    check_indexer_blocked_by_addr_blocklist(&state.indexer_addr_blocklist, &indexer)
-                {
-                    tracing::debug!("filtering-out indexer: {err}");
-                    return None;
-                }
+                // Record the indexer processing duration, up to the indexer being filtered-out
+                let timer = with_metric(
+                    &METRICS.indexer_processing_duration,
+                    &[&indexer_id.to_string()],
+                    |hist| hist.start_timer(),
+                );
 
-                // Check if the indexer's host is in the host blocklist
-                if let Err(err) = resolve_and_check_indexer_blocked_by_host_blocklist(
-                    &state.indexer_host_resolver,
-                    &state.indexer_host_blocklist,
-                    &indexer,
-                )
-                .await
-                {
-                    tracing::debug!("filtering-out indexer: {err}");
-                    return None;
-                }
+                let processing = async move {
+                    let mut indexer = indexer;
 
-                // Check if the indexer's reported versions are supported
-                if let Err(err) = resolve_and_check_indexer_blocked_by_version(
-                    &state.indexer_version_resolver,
-                    &state.indexer_min_agent_version,
-                    &state.indexer_min_graph_node_version,
-                    &mut indexer,
-                )
-                .await
-                {
-                    tracing::debug!("filtering-out indexer: {err}");
-                    return None;
-                }
+                    // Check if the indexer has enough staked tokens
+                    if let Err(err) =
+                        check_indexer_staked_tokens(state.indexer_min_staked_tokens, &indexer)
+                    {
+                        tracing::debug!(reason = err.reason.name(), "filtering-out indexer: {err}");
+                        count_filtered_indexer(err.reason);
+                        return Err(None);
+                    }
 
-                // Update the span information with the resolved versions
-                tracing::Span::current()
-                    .record(
-                        "indexer.agent_version",
-                        tracing::field::display(&indexer.indexer_agent_version),
-                    )
-                    .record(
-                        "indexer.graph_node_version",
-                        tracing::field::display(&indexer.graph_node_version),
-                    );
+                    // Run the indexer through the processing pipeline stages in the configured
+                    // order. If the indexer fails any of the stages, it must be filtered out.
+                    for stage in &state.indexer_filter_stages {
+                        // Record the stage duration, aggregated across all the indexers
+                        let _timer = with_metric(
+                            &METRICS.indexer_processing_stage_duration,
+                            &[stage.name()],
+                            |hist| hist.start_timer(),
+                        );
+                        if let Err(err) = run_filter_stage(state, *stage, &mut indexer).await {
+                            tracing::debug!(
+                                ?stage,
+                                reason = err.reason.name(),
+                                "filtering-out indexer: {err}"
+                            );
+                            count_filtered_indexer(err.reason);
+                            return Err(Some(*stage));
+                        }
+                    }
 
-                // Check if the indexer's deployments should be blocked by POI
-                // Update the indexer's deployments list to only include the deployments that are
-                // not blocked by POI. If the indexer has no deployments left, it must be ignored.
-                if let Err(err) = resolve_and_check_indexer_blocked_by_poi(
-                    &state.indexer_indexing_pois_blocklist,
-                    &mut indexer,
-                )
-                .await
-                {
-                    tracing::debug!("filtering-out indexer: {err}");
-                    return None;
-                }
+                    Ok(indexer)
+                };
 
-                // Fetch the indexer's indexing progress statuses
-                // NOTE: At this point, the indexer's deployments list should contain only the
-                //       deployment IDs that were not blocked by any blocklist.
-                if let Err(err) = resolve_indexer_indexing_progress_statuses(
-                    &state.indexer_indexing_status_resolver,
-                    &mut indexer,
+                // The deadline starts once the indexer is dequeued for processing, so the time
+                // spent waiting for a processing slot does not count against it
+                let result = match tokio::time::timeout(
+                    state.indexers_processing_deadline,
+                    processing,
                 )
                 .await
                 {
-                    tracing::debug!("filtering-out indexer: {err}");
-                    return None;
-                }
+                    Ok(result) => result,
+                    Err(_) => {
+                        let reason = IndexerFilterReason::ProcessingDeadlineExceeded;
+                        tracing::debug!(
+                            reason = reason.name(),
+                            "filtering-out indexer: processing deadline exceeded"
+                        );
+                        count_filtered_indexer(reason);
+                        Err(None)
+                    }
+                };
 
-                // Fetch the indexer's indexing statuses and cost models
-                // NOTE: At this point, the indexer's deployments list should contain only the
-                //       deployment IDs that were not blocked by any blocklist.
-                if let Err(err) = resolve_indexer_indexing_cost_models(
-                    &state.indexer_indexing_cost_model_resolver,
-                    &mut indexer,
-                )
-                .await
-                {
-                    tracing::debug!("filtering-out indexer: {err}");
-                    return None;
+                if let Some(timer) = timer {
+                    let duration = timer.stop_and_record();
+                    tracing::debug!(duration_ms = (duration * 1_000.0) as u64, "processed");
                 }
 
-                Some((indexer_id, indexer))
+                (indexer_id, result)
             }
             .instrument(indexer_span)
         });
 
-        // Wait for all the indexers to be processed
-        futures::future::join_all(indexers_iter_fut).await
+        // Wait for the indexers to be processed
+        let mut processing = futures::stream::iter(indexers_iter_fut)
+            .buffer_unordered(state.indexers_processing_concurrency.max(1));
+
+        let mut processed = Vec::new();
+        while let Some((indexer_id, indexer)) = processing.next().await {
+            match indexer {
+                Ok(indexer) => processed.push((indexer_id, indexer)),
+                Err(stage) => report.count_filtered(stage),
+            }
+        }
+        processed
     }
     .into_iter()
-    .flatten() // Filter out the `None` values
     .collect::<HashMap<_, _>>();
 
+    report.final_count = indexers_info.len();
+    tracing::debug!(?report, "indexers processed");
+    *state.last_fetch_report.write().unwrap() = Some(report);
+
     if indexers_info.is_empty() {
-        Err(anyhow!("no valid indexers found"))
+        Err(NetworkFetchError::NoValidIndexers)
     } else {
         Ok(indexers_info)
     }
 }
 
-/// Check if the indexer's address is in the address blocklist.
-///
-/// - If the address blocklist was not configured: the indexer is ALLOWED.
-/// - If the address is in the blocklist: the indexer is BLOCKED.
-fn check_indexer_blocked_by_addr_blocklist(
-    blocklist: &Option<AddrBlocklist>,
-    indexer: &IndexerInfo,
-) -> anyhow::Result<()> {
-    let blocklist = match blocklist {
-        Some(blocklist) => blocklist,
-        None => return Ok(()),
-    };
-
-    // Check if the indexer's address is in the blocklist
-    if blocklist.check(&indexer.id).is_blocked() {
-        return Err(anyhow!("indexer address blocked by blocklist"));
-    }
+/// Run the given processing pipeline stage for the indexer.
+async fn run_filter_stage(
+    state: &InternalState,
+    stage: FilterStage,
+    indexer: &mut IndexerInfo,
+) -> Result<(), IndexerFilterError> {
+    match stage {
+        // Check if the indexer's address is in the address blocklist
+        FilterStage::AddrBlocklist => {
+            check_indexer_blocked_by_addr_blocklist(&state.indexer_addr_blocklist, indexer)
+        }
+
+        // Check if the indexer's host is in the host blocklist
+        FilterStage::HostBlocklist => {
+            resolve_and_check_indexer_blocked_by_host_blocklist(
+                &state.indexer_host_resolver,
+                &state.indexer_host_blocklist,
+                indexer,
+            )
+            .await
+        }
+
+        // Check if the indexer's reported versions are supported
+        FilterStage::Version => {
+            resolve_and_check_indexer_blocked_by_version(
+                &state.indexer_version_resolver,
+                &state.indexer_min_agent_version,
+                &state.indexer_min_graph_node_version,
+                &state.indexer_min_graph_node_version_per_network,
+                indexer,
+            )
+            .await?;
+
+            // Update the span information with the resolved versions
+            tracing::Span::current()
+                .record(
+                    "indexer.agent_version",
+                    tracing::field::display(&indexer.indexer_agent_version),
+                )
+                .record(
+                    "indexer.graph_node_version",
+                    tracing::field::display(&indexer.graph_node_version),
+                );
+
+            Ok(())
+        }
+
+        // Check if the indexer's deployments should be blocked by POI
+        // Update the indexer's deployments list to only include the deployments that are
+        // not blocked by POI. If the indexer has no deployments left, it must be ignored.
+        FilterStage::PoiBlocklist => {
+            resolve_and_check_indexer_blocked_by_poi(
+                &state.indexer_indexing_pois_blocklist,
+                state.indexer_indexing_poi_reference.as_deref(),
+                indexer,
+            )
+            .await?;
+
+            // Check if the indexer reports the same POI for distinct deployments. The POIs are
+            // sampled independently of the POI blocklist affected deployments.
+            match &state.indexer_indexing_pois_blocklist {
+                Some((_, pois_resolver))
+                    if state.indexer_indexing_poi_collision_policy
+                        != PoiCollisionPolicy::Disabled =>
+                {
+                    resolve_and_check_indexer_poi_collisions(
+                        &state.indexer_indexing_status_resolver,
+                        pois_resolver,
+                        state.indexer_indexing_poi_collision_policy,
+                        indexer,
+                    )
+                    .await
+                }
+                _ => Ok(()),
+            }
+        }
+
+        // Fetch the indexer's indexing progress statuses, and drop the indexings lagging too far
+        // behind the chain head, if the staleness filter is enabled
+        // NOTE: The indexer's deployments list contains only the deployment IDs that were not
+        //       blocked by the blocklist stages run so far.
+        FilterStage::IndexingProgress => {
+            resolve_indexer_indexing_progress_statuses(
+                &state.indexer_indexing_status_resolver,
+                indexer,
+            )
+            .await?;
+
+            if let Some(filter) = &state.indexer_lag_filter {
+                check_indexer_blocked_by_lag(filter, indexer)?;
+            }
+
+            match &state.indexer_indexing_staleness_filter {
+                Some(filter) => check_indexer_indexings_staleness(filter, indexer),
+                None => Ok(()),
+            }
+        }
+
+        // Fetch the indexer's indexing cost models
+        // NOTE: The indexer's deployments list contains only the deployment IDs that were not
+        //       blocked by the blocklist stages run so far.
+        FilterStage::CostModel => {
+            resolve_indexer_indexing_cost_models(
+                &state.indexer_indexing_cost_model_resolver,
+                &state.indexer_indexing_cost_model_overrides,
+                indexer,
+            )
+            .await
+        }
+    }
+}
+
+/// Check if the indexer has enough staked tokens.
+///
+/// - If the indexer has less staked tokens than the minimum required: the indexer is BLOCKED.
+///
+/// A minimum of zero allows all the indexers.
+fn check_indexer_staked_tokens(
+    min_staked_tokens: u128,
+    indexer: &IndexerInfo,
+) -> Result<(), IndexerFilterError> {
+    if indexer.staked_tokens < min_staked_tokens {
+        return Err(IndexerFilterReason::InsufficientStake.error(anyhow!(
+            "indexer staked tokens below the minimum: {} < {min_staked_tokens}",
+            indexer.staked_tokens
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check if the indexer's address is in the address blocklist.
+///
+/// - If the address blocklist was not configured: the indexer is ALLOWED.
+/// - If the address is in the blocklist: the indexer is BLOCKED.
+fn check_indexer_blocked_by_addr_blocklist(
+    blocklist: &Option<AddrBlocklist>,
+    indexer: &IndexerInfo,
+) -> Result<(), IndexerFilterError> {
+    let blocklist = match blocklist {
+        Some(blocklist) => blocklist,
+        None => return Ok(()),
+    };
+
+    // Check if the indexer's address is in the blocklist
+    if blocklist.check(&indexer.id).is_blocked() {
+        return Err(
+            IndexerFilterReason::AddrBlocked.error(anyhow!("indexer address blocked by blocklist"))
+        );
+    }
 
     Ok(())
 }
@@ -596,13 +1618,14 @@ async fn resolve_and_check_indexer_blocked_by_host_blocklist(
     resolver: &Mutex<HostResolver>,
     blocklist: &Option<HostBlocklist>,
     indexer: &IndexerInfo,
-) -> anyhow::Result<()> {
+) -> Result<(), IndexerFilterError> {
     // Resolve the indexer's URL, if it fails (or times out), the indexer must be BLOCKED
     let mut host_resolver = resolver.lock().await;
     let resolution_result = match host_resolver.resolve_url(&indexer.url).await {
         // If the resolution failed, the indexer must be BLOCKED
         Err(err) => {
-            return Err(anyhow!("failed to resolve indexer URL: {err}"));
+            return Err(IndexerFilterReason::HostUnresolvable
+                .error(anyhow!("failed to resolve indexer URL: {err}")));
         }
         Ok(result) => result,
     };
@@ -614,7 +1637,9 @@ async fn resolve_and_check_indexer_blocked_by_host_blocklist(
     };
 
     if host_blocklist.check(&resolution_result).is_blocked() {
-        return Err(anyhow!("indexer host blocked by blocklist"));
+        return Err(
+            IndexerFilterReason::HostBlocked.error(anyhow!("indexer host blocked by blocklist"))
+        );
     }
 
     Ok(())
@@ -624,57 +1649,138 @@ async fn resolve_and_check_indexer_blocked_by_host_blocklist(
 ///
 /// - If the agent version is not resolvable: the indexer must be BLOCKED.
 /// - If the agent version is below the minimum required: the indexer must be BLOCKED.
-/// - If the graph node version is not resolvable: the indexer must be BLOCKED.
-/// - If the graph node version is below the minimum required: the indexer must be BLOCKED.
+/// - If the graph node version is not resolvable: the resolver's fallback policy applies, i.e.,
+///   the indexer is BLOCKED, or assumed to be on the minimum version or on the given version.
+/// - If the graph node version is below the minimum required by the network of an indexing: the
+///   indexing is filtered-out. If all the indexings are filtered-out, the indexer must be BLOCKED.
 async fn resolve_and_check_indexer_blocked_by_version(
     resolver: &VersionResolver,
     min_agent_version: &Version,
     min_graph_node_version: &Version,
+    min_graph_node_version_per_network: &HashMap<String, Version>,
     indexer: &mut IndexerInfo,
-) -> anyhow::Result<()> {
+) -> Result<(), IndexerFilterError> {
     // Resolve the indexer's agent version
     let agent_version = match resolver.resolve_agent_version(&indexer.url).await {
         // If the resolution failed, the indexer must be BLOCKED
         Err(err) => {
-            return Err(anyhow!("agent version resolution failed: {err}"));
+            return Err(IndexerFilterReason::AgentVersionUnresolvable
+                .error(anyhow!("agent version resolution failed: {err}")));
         }
         Ok(result) => result,
     };
 
     // Check if the indexer's agent version is supported
     if agent_version < *min_agent_version {
-        return Err(anyhow!(
+        return Err(IndexerFilterReason::AgentVersionTooLow.error(anyhow!(
             "agent version {} below the minimum required {}",
             agent_version,
             min_agent_version
-        ));
+        )));
     }
 
+    // Resolve the features the indexer advertises, served from the agent version resolution.
+    // If the indexer does not report them, assume it supports none.
+    let features = resolver
+        .resolve_agent_features(&indexer.url)
+        .await
+        .unwrap_or_default();
+
     // Resolve the indexer's graph node version, with a timeout
     let graph_node_version = match resolver.resolve_graph_node_version(&indexer.url).await {
-        // If the resolution failed, the indexer must be BLOCKED
-        Err(err) => {
-            // TODO: After more graph nodes support reporting their version,
-            //  we should assume they are on the minimum version if we can't
-            //  get the version.
-            tracing::trace!("graph-node version resolution failed: {err}");
-            min_graph_node_version.clone()
-        }
+        // If the resolution failed, apply the fallback policy
+        Err(err) => match resolver.graph_node_version_fallback() {
+            GraphNodeVersionFallback::Block => {
+                return Err(IndexerFilterReason::GraphNodeVersionUnresolvable
+                    .error(anyhow!("graph-node version resolution failed: {err}")));
+            }
+            GraphNodeVersionFallback::AssumeMinimum => {
+                tracing::trace!("graph-node version resolution failed: {err}");
+                indexer_indexings_min_graph_node_version(
+                    min_graph_node_version,
+                    min_graph_node_version_per_network,
+                    indexer,
+                )
+                .clone()
+            }
+            GraphNodeVersionFallback::AssumeVersion(version) => {
+                tracing::trace!("graph-node version resolution failed: {err}");
+                version.clone()
+            }
+        },
         Ok(result) => result,
     };
 
-    // Check if the indexer's graph node version is supported
-    if graph_node_version < *min_graph_node_version {
-        return Err(anyhow!(
-            "Graph node version {} below the minimum required {}",
-            graph_node_version,
-            min_graph_node_version
-        ));
-    }
+    // Check if the indexer's graph node version is supported by its indexings' networks
+    check_indexer_indexings_graph_node_version(
+        &graph_node_version,
+        min_graph_node_version,
+        min_graph_node_version_per_network,
+        indexer,
+    )?;
 
     // Set the indexer's versions
     indexer.indexer_agent_version = agent_version;
     indexer.graph_node_version = graph_node_version;
+    indexer.features = features;
+
+    Ok(())
+}
+
+/// Get the lowest graph node version supported by all the indexer's indexings' networks, i.e., the
+/// highest of the indexings' minimum graph node versions.
+///
+/// The minimum graph node version required for an indexing is the one configured for the
+/// deployment's manifest network, falling back to the global minimum version.
+fn indexer_indexings_min_graph_node_version<'a>(
+    min_graph_node_version: &'a Version,
+    min_graph_node_version_per_network: &'a HashMap<String, Version>,
+    indexer: &IndexerInfo,
+) -> &'a Version {
+    indexer
+        .deployments
+        .iter()
+        .map(|id| {
+            indexer
+                .deployments_network
+                .get(id)
+                .and_then(|network| min_graph_node_version_per_network.get(network))
+                .unwrap_or(min_graph_node_version)
+        })
+        .max()
+        .unwrap_or(min_graph_node_version)
+}
+
+/// Check if the indexer's graph node version is supported by each of its indexings' networks.
+///
+/// The minimum graph node version required for an indexing is the one configured for the
+/// deployment's manifest network, falling back to the global minimum version.
+///
+/// Update the indexer's deployments list to only include the deployments whose network minimum
+/// version is met. If the indexer has no deployments left, it must be BLOCKED.
+fn check_indexer_indexings_graph_node_version(
+    graph_node_version: &Version,
+    min_graph_node_version: &Version,
+    min_graph_node_version_per_network: &HashMap<String, Version>,
+    indexer: &mut IndexerInfo,
+) -> Result<(), IndexerFilterError> {
+    let deployments_network = &indexer.deployments_network;
+    indexer
+        .deployments
+        .retain(|id| {
+            let min_version = deployments_network
+                .get(id)
+                .and_then(|network| min_graph_node_version_per_network.get(network))
+                .unwrap_or(min_graph_node_version);
+            graph_node_version >= min_version
+        })
+        // If all deployments are filtered-out, the indexer must be BLOCKED
+        .map_err(|_| {
+            IndexerFilterReason::GraphNodeVersionTooLow.error(anyhow!(
+                "Graph node version {} below the minimum required by all its indexings",
+                graph_node_version
+            ))
+        })?;
 
     Ok(())
 }
@@ -682,37 +1788,56 @@ async fn resolve_and_check_indexer_blocked_by_version(
 /// Resolve and check if any of the indexer's deployments should be blocked by POI.
 ///
 /// - If the POI blocklist was not configured: the indexer must be ALLOWED.
-/// - If not indexing any of the affected deployments: the indexer must be ALLOWED.
+/// - If not indexing any of the affected deployments, nor any deployment with reference POIs: the
+///   indexer must be ALLOWED.
 /// - If there are no healthy indexings, i.e., all indexings are blocked: the indexer must be BLOCKED.
 async fn resolve_and_check_indexer_blocked_by_poi(
-    blocklist: &Option<(PoiBlocklist, Mutex<PoiResolver>)>,
+    blocklist: &Option<(PoiBlocklist, PoiResolver)>,
+    reference: Option<&dyn PoiReferenceSource>,
     indexer: &mut IndexerInfo,
-) -> anyhow::Result<()> {
+) -> Result<(), IndexerFilterError> {
     // If the POI blocklist was not configured, the indexer must be ALLOWED
     let (pois_blocklist, pois_resolver) = match blocklist {
         Some((blocklist, resolver)) => (blocklist, resolver),
         _ => return Ok(()),
     };
 
-    // Get the list of affected POIs to resolve for the indexer's deployments
+    // Get the list of affected POIs, and reference POIs, to resolve for the indexer's deployments
     // If none of the deployments are affected, the indexer must be ALLOWED
-    let indexer_affected_pois = pois_blocklist.affected_pois_metadata(&indexer.deployments);
+    let mut indexer_affected_pois = pois_blocklist.affected_pois_metadata(&indexer.deployments);
+    if let Some(reference) = reference {
+        for deployment in indexer.deployments.iter() {
+            for block in reference.reference_blocks(deployment) {
+                if !indexer_affected_pois.contains(&(*deployment, block)) {
+                    indexer_affected_pois.push((*deployment, block));
+                }
+            }
+        }
+    }
     if indexer_affected_pois.is_empty() {
         return Ok(());
     }
 
     // Resolve the indexer public POIs for the affected deployments
-    let poi_result = {
-        let mut pois_resolver = pois_resolver.lock().await;
-        pois_resolver
-            .resolve(&indexer.url, &indexer_affected_pois)
-            .await?
-    };
+    let poi_result = pois_resolver
+        .resolve(&indexer.url, &indexer_affected_pois)
+        .await
+        .map_err(|err| IndexerFilterReason::PoiResolutionFailed.error(err.into()))?;
+
+    // Check if any of the reported POIs does not match the reference POIs
+    let reference_result = reference
+        .map(|reference| check_against_reference(reference, &poi_result))
+        .unwrap_or_default();
 
     // Check if any of the reported POIs are in the blocklist. and filter out the indexings
     // Update the indexers deployments to only include the deployments that are not affected
     // i.e., keep the deployments that are not blocked by POI.
-    let check_result = pois_blocklist.check(poi_result);
+    let mut check_result = pois_blocklist.check(poi_result);
+    for (deployment, state) in reference_result {
+        if state.is_blocked() {
+            check_result.insert(deployment, state);
+        }
+    }
     indexer
         .deployments
         .retain(|id| match check_result.get(id) {
@@ -723,7 +1848,74 @@ async fn resolve_and_check_indexer_blocked_by_poi(
             }
         })
         // If all deployments are blocked, the indexer must be BLOCKED
-        .map_err(|_| anyhow!("all deployments blocked due to blocked POIs"))?;
+        .map_err(|_| {
+            IndexerFilterReason::AllPoisBlocked
+                .error(anyhow!("all deployments blocked due to blocked POIs"))
+        })?;
+
+    Ok(())
+}
+
+/// Resolve a sample of the indexer's POIs and check if it reports the same POI for distinct
+/// deployments.
+///
+/// The sample is the POIs of the indexer's largest allocation deployments, at their latest indexed
+/// block. The collisions are recorded in the POI collisions metric. If the policy is to block, an
+/// indexer with colliding POIs must be BLOCKED.
+///
+/// This is a heuristic, so failing to resolve the sample is not evidence against the indexer: the
+/// indexer is ALLOWED.
+async fn resolve_and_check_indexer_poi_collisions(
+    progress_resolver: &IndexingProgressResolver,
+    pois_resolver: &PoiResolver,
+    policy: PoiCollisionPolicy,
+    indexer: &IndexerInfo,
+) -> Result<(), IndexerFilterError> {
+    // A collision requires at least two distinct deployments
+    let sample = indexer
+        .deployments
+        .iter()
+        .take(POI_COLLISION_SAMPLE_SIZE)
+        .copied()
+        .collect::<Vec<_>>();
+    if sample.len() < 2 {
+        return Ok(());
+    }
+
+    let latest_blocks = match progress_resolver
+        .resolve_latest_blocks(&indexer.url, &sample)
+        .await
+    {
+        Ok(latest_blocks) => latest_blocks,
+        Err(err) => {
+            tracing::debug!("POI collision sample blocks resolution failed: {err}");
+            return Ok(());
+        }
+    };
+    let sample = latest_blocks.into_iter().collect::<Vec<_>>();
+    let pois = match pois_resolver.resolve(&indexer.url, &sample).await {
+        Ok(pois) => pois,
+        Err(err) => {
+            tracing::debug!("POI collision sample resolution failed: {err}");
+            return Ok(());
+        }
+    };
+
+    let collisions = find_poi_collisions(&pois);
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    tracing::warn!(?collisions, "indexer reported colliding POIs");
+    let _ = with_metric(
+        &METRICS.indexer_poi_collisions,
+        &[&indexer.id.to_string()],
+        |counter| counter.inc(),
+    );
+    if policy == PoiCollisionPolicy::Block {
+        return Err(IndexerFilterReason::PoiCollision
+            .error(anyhow!("reported POIs collide across deployments")));
+    }
 
     Ok(())
 }
@@ -732,11 +1924,12 @@ async fn resolve_and_check_indexer_blocked_by_poi(
 async fn resolve_indexer_indexing_progress_statuses(
     resolver: &IndexingProgressResolver,
     indexer: &mut IndexerInfo,
-) -> anyhow::Result<()> {
+) -> Result<(), IndexerFilterError> {
     let progress_status = match resolver.resolve(&indexer.url, &indexer.deployments).await {
         // If the resolution failed, the indexer must be BLOCKED
         Err(err) => {
-            return Err(anyhow!("indexing progress status resolution failed: {err}"));
+            return Err(IndexerFilterReason::ProgressResolutionFailed
+                .error(anyhow!("indexing progress status resolution failed: {err}")));
         }
         Ok(result) => result,
     };
@@ -754,6 +1947,8 @@ async fn resolve_indexer_indexing_progress_statuses(
                 IndexerIndexingProgressInfo {
                     latest_block: res.latest_block,
                     min_block: res.min_block,
+                    last_progress_at: res.last_progress_at,
+                    latency: res.latency,
                 },
             )
         })
@@ -765,42 +1960,2162 @@ async fn resolve_indexer_indexing_progress_statuses(
     Ok(())
 }
 
+/// Check the indexer's indexings progress against their chain head, dropping the stale ones.
+///
+/// The indexing's chain head is the head of its deployment's manifest network.
+///
+/// - If the network, its chain head, or the indexing progress is unknown: the indexing is kept.
+/// - If the indexing is more than `max_blocks_behind` blocks behind: the indexing is dropped.
+/// - If all the indexer's indexings are dropped, i.e., the indexer lags behind on all of them: the
+///   indexer must be BLOCKED.
+fn check_indexer_indexings_staleness(
+    filter: &StalenessFilter,
+    indexer: &mut IndexerInfo,
+) -> Result<(), IndexerFilterError> {
+    let fresh_deployments = indexer
+        .deployments
+        .iter()
+        .filter(|deployment| {
+            let blocks_behind = indexer
+                .deployments_network
+                .get(deployment)
+                .and_then(|network| filter.chain_heads.chain_head(network))
+                .and_then(|head| indexer.blocks_behind(deployment, head));
+            match blocks_behind {
+                Some(blocks_behind) if blocks_behind > filter.max_blocks_behind => {
+                    tracing::debug!(
+                        %deployment,
+                        blocks_behind,
+                        "dropping stale indexing"
+                    );
+                    false
+                }
+                _ => true,
+            }
+        })
+        .copied()
+        .collect::<Vec<_>>();
+
+    indexer.deployments = fresh_deployments.try_into().map_err(|_| {
+        IndexerFilterReason::StaleIndexings
+            .error(anyhow!("all indexings lagging behind the chain head"))
+    })?;
+
+    Ok(())
+}
+
+/// Check if the indexer is lagging behind the chain head on all its indexings.
+///
+/// An indexer behind on every deployment is likely misconfigured (e.g., a stalled chain
+/// provider), so it is blocked entirely. This is independent of the per-indexing staleness
+/// filter: an indexer with at least one indexing within the threshold is kept, with all its
+/// indexings.
+///
+/// Only the indexings with both a known progress and a known chain head are considered.
+///
+/// - If none of the indexer's indexings can be checked: the indexer is ALLOWED.
+/// - If any indexing is at most `max_blocks_behind` blocks behind: the indexer is ALLOWED.
+/// - If all indexings are more than `max_blocks_behind` blocks behind: the indexer is BLOCKED.
+fn check_indexer_blocked_by_lag(
+    filter: &IndexerLagFilter,
+    indexer: &IndexerInfo,
+) -> Result<(), IndexerFilterError> {
+    let blocks_behind = indexer
+        .deployments
+        .iter()
+        .filter_map(|deployment| {
+            let network = indexer.deployments_network.get(deployment)?;
+            let head = filter.chain_heads.chain_head(network)?;
+            indexer.blocks_behind(deployment, head)
+        })
+        .collect::<Vec<_>>();
+
+    let (best, worst) = match blocks_behind.iter().minmax().into_option() {
+        Some((best, worst)) => (*best, *worst),
+        None => return Ok(()),
+    };
+
+    if best > filter.max_blocks_behind {
+        return Err(IndexerFilterReason::LaggingIndexer.error(anyhow!(
+            "all indexings lagging behind the chain head (blocks behind: min {best}, max {worst})"
+        )));
+    }
+
+    Ok(())
+}
+
 /// Resolve the indexer's indexing cost models.
+///
+/// The operator-defined cost model overrides take precedence over the indexer's cost models:
+/// - If the deployment has an override: the override is used, and the indexer's cost model is
+///   neither fetched nor compiled.
+/// - Otherwise: the indexer's cost model is used, if it was resolved and compiled successfully.
 async fn resolve_indexer_indexing_cost_models(
     (resolver, compiler): &(CostModelResolver, Mutex<CostModelCompiler>),
+    overrides: &HashMap<DeploymentId, Ptr<CostModel>>,
     indexer: &mut IndexerInfo,
-) -> anyhow::Result<()> {
+) -> Result<(), IndexerFilterError> {
+    // The overridden deployments' cost models are not resolved from the indexer
+    let deployments = indexer
+        .deployments
+        .iter()
+        .filter(|deployment| !overrides.contains_key(deployment))
+        .copied()
+        .collect::<Vec<_>>();
+
     // Resolve the indexer's cost model sources
-    let indexings_cost_models = match resolver.resolve(&indexer.url, &indexer.deployments).await {
-        Err(err) => {
-            // If the resolution failed, return early
-            tracing::debug!("cost model resolution failed: {err}");
-            return Ok(());
-        }
-        Ok(result) if result.is_empty() => {
-            // If the resolution is empty, return early
-            return Ok(());
+    let indexings_cost_models = if deployments.is_empty() {
+        HashMap::new()
+    } else {
+        match resolver.resolve(&indexer.url, &deployments).await {
+            Err(err) => {
+                tracing::debug!("cost model resolution failed: {err}");
+                HashMap::new()
+            }
+            Ok(result) => result,
         }
-        Ok(result) => result,
     };
 
     // Compile the cost model sources into cost models
-    let indexings_cost_models = {
+    let mut indexings_cost_models = {
         let mut compiler = compiler.lock().await;
         indexings_cost_models
             .into_iter()
-            .filter_map(|(deployment, source)| match compiler.compile(source) {
-                Err(err) => {
-                    tracing::debug!("cost model compilation failed: {err}");
-                    None
+            .filter_map(|(deployment, source)| {
+                match compiler.compile(indexer.id, &indexer.url, source) {
+                    Err(err) => {
+                        tracing::debug!("cost model compilation failed: {err}");
+                        None
+                    }
+                    Ok(cost_model) => Some((deployment, cost_model)),
                 }
-                Ok(cost_model) => Some((deployment, cost_model)),
             })
-            .collect()
+            .collect::<HashMap<_, _>>()
     };
 
+    // Apply the overrides for the indexer's deployments
+    for deployment in indexer.deployments.iter() {
+        if let Some(cost_model) = overrides.get(deployment) {
+            indexings_cost_models.insert(*deployment, cost_model.clone());
+        }
+    }
+
     // Set the indexer's indexing cost models
     indexer.indexings_cost_model = indexings_cost_models;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        net::IpAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use alloy_primitives::Address;
+    use anyhow::anyhow;
+    use cost_model::CostModel;
+    use eventuals::Ptr;
+    use gateway_framework::reporting::METRICS;
+    use itertools::Itertools as _;
+    use semver::Version;
+    use thegraph_core::{
+        client as subgraph_client,
+        types::{DeploymentId, SubgraphId},
+    };
+    use tokio::{
+        io::{AsyncReadExt as _, AsyncWriteExt as _},
+        net::TcpListener,
+        sync::Mutex,
+    };
+    use vec1::vec1;
+
+    use super::{
+        check_indexer_blocked_by_lag, check_indexer_indexings_graph_node_version,
+        check_indexer_indexings_staleness, fetch_update, fetch_update_with_cancellation,
+        fetch_with_retry, merge_partial_update, process_indexers_info,
+        resolve_and_check_indexer_blocked_by_host_blocklist,
+        resolve_and_check_indexer_blocked_by_poi, resolve_and_check_indexer_blocked_by_version,
+        resolve_and_check_indexer_poi_collisions, resolve_indexer_indexing_cost_models,
+        run_filter_stage,
+        subgraph::types::{fetch_indexers, fetch_subgraphs},
+        subgraph::Client as SubgraphClient,
+        try_into_internal_indexer_info, try_into_internal_subgraph_info,
+        types::{IndexerIndexingProgressInfo, IndexerInfo, SubgraphInfo},
+        AddrBlocklist, CostModelCompiler, CostModelResolver, FetchError, FilterStage,
+        GraphNodeVersionFallback, HostBlocklist, HostResolver, IndexerFilterError,
+        IndexerFilterReason, IndexerLagFilter, IndexingProgressResolver, InternalState,
+        NetworkFetchError, PartialUpdatePolicy, PoiBlocklist, PoiCollisionPolicy,
+        PoiReferenceSource, PoiResolver, RetryConfig, StalenessFilter, TopologyFetchReport,
+        VersionResolver, DEFAULT_INDEXERS_PROCESSING_CONCURRENCY,
+        DEFAULT_INDEXERS_PROCESSING_DEADLINE, DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+        DEFAULT_NETWORK_TOPOLOGY_UPDATE_TIMEOUT,
+    };
+    use crate::{
+        indexers::public_poi::{ProofOfIndexing, ProofOfIndexingInfo},
+        network::{
+            indexer_indexing_cost_model_compiler::default_fee,
+            indexer_indexing_poi_resolver::ResolutionError,
+            snapshot::{self, FetchedInfo},
+        },
+    };
+
+    /// Test utility function to create a valid `DeploymentId` with an arbitrary deployment ID/ipfs hash.
+    fn test_deployment_id(deployment: &str) -> DeploymentId {
+        deployment.parse().expect("invalid deployment id/ipfs hash")
+    }
+
+    /// Test utility function to create a fetched indexer info with the given URL.
+    fn test_indexer(url: &str) -> fetch_indexers::Indexer {
+        fetch_indexers::Indexer {
+            id: Address::ZERO,
+            url: Some(url.to_string()),
+            staked_tokens: 100_000,
+            allocations: vec![fetch_indexers::Allocation {
+                id: Address::ZERO,
+                allocated_tokens: 100,
+                subgraph_deployment: fetch_indexers::SubgraphDeployment {
+                    id: test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"),
+                    manifest: None,
+                },
+            }],
+        }
+    }
+
+    #[test]
+    fn indexer_url_default_ports_are_stripped() {
+        //* Given
+        let https_indexer = test_indexer("https://indexer.example.com:443/");
+        let http_indexer = test_indexer("http://indexer.example.com:80/");
+
+        //* When
+        let https_indexer = try_into_internal_indexer_info(https_indexer, u128::MAX);
+        let http_indexer = try_into_internal_indexer_info(http_indexer, u128::MAX);
+
+        //* Then
+        let https_indexer = https_indexer.expect("valid indexer info");
+        assert_eq!(https_indexer.url.port(), None);
+        assert_eq!(https_indexer.url.as_str(), "https://indexer.example.com/");
+
+        let http_indexer = http_indexer.expect("valid indexer info");
+        assert_eq!(http_indexer.url.port(), None);
+        assert_eq!(http_indexer.url.as_str(), "http://indexer.example.com/");
+    }
+
+    #[test]
+    fn indexer_url_non_default_ports_are_kept() {
+        //* Given
+        let indexer = test_indexer("https://indexer.example.com:8000/");
+
+        //* When
+        let indexer = try_into_internal_indexer_info(indexer, u128::MAX);
+
+        //* Then
+        let indexer = indexer.expect("valid indexer info");
+        assert_eq!(indexer.url.port(), Some(8000));
+        assert_eq!(indexer.url.as_str(), "https://indexer.example.com:8000/");
+    }
+
+    #[test]
+    fn indexing_with_allocated_tokens_above_the_maximum_is_dropped() {
+        //* Given
+        let valid_deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let corrupt_deployment =
+            test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+
+        let mut indexer = test_indexer("https://indexer.example.com/");
+        indexer.allocations = vec![
+            fetch_indexers::Allocation {
+                id: Address::repeat_byte(1),
+                allocated_tokens: 1_000,
+                subgraph_deployment: fetch_indexers::SubgraphDeployment {
+                    id: corrupt_deployment,
+                    manifest: None,
+                },
+            },
+            fetch_indexers::Allocation {
+                id: Address::repeat_byte(2),
+                allocated_tokens: 100,
+                subgraph_deployment: fetch_indexers::SubgraphDeployment {
+                    id: valid_deployment,
+                    manifest: None,
+                },
+            },
+        ];
+
+        //* When
+        let indexer = try_into_internal_indexer_info(indexer, 500);
+
+        //* Then
+        let indexer = indexer.expect("valid indexer info");
+        assert_eq!(indexer.deployments.as_slice(), [valid_deployment]);
+        assert!(!indexer.largest_allocation.contains_key(&corrupt_deployment));
+        assert!(!indexer
+            .total_allocated_tokens
+            .contains_key(&corrupt_deployment));
+    }
+
+    #[test]
+    fn indexing_progress_for_present_deployment() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let mut indexer =
+            try_into_internal_indexer_info(test_indexer("https://indexer.example.com/"), u128::MAX)
+                .expect("valid indexer info");
+        indexer.indexings_progress.insert(
+            deployment,
+            IndexerIndexingProgressInfo {
+                latest_block: 90,
+                min_block: None,
+                last_progress_at: None,
+                latency: Duration::from_millis(100),
+            },
+        );
+
+        //* When
+        let progress = indexer.progress_for(&deployment);
+        let blocks_behind = indexer.blocks_behind(&deployment, 100);
+        let blocks_behind_stale_head = indexer.blocks_behind(&deployment, 80);
+
+        //* Then
+        assert_eq!(progress.map(|progress| progress.latest_block), Some(90));
+        assert_eq!(blocks_behind, Some(10));
+        assert_eq!(blocks_behind_stale_head, Some(0));
+    }
+
+    #[test]
+    fn indexing_progress_for_absent_deployment() {
+        //* Given
+        let deployment = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let indexer =
+            try_into_internal_indexer_info(test_indexer("https://indexer.example.com/"), u128::MAX)
+                .expect("valid indexer info");
+
+        //* When
+        let progress = indexer.progress_for(&deployment);
+        let blocks_behind = indexer.blocks_behind(&deployment, 100);
+
+        //* Then
+        assert!(progress.is_none());
+        assert_eq!(blocks_behind, None);
+    }
+
+    /// Test utility function to create an indexer info with the given indexings progress. The
+    /// deployments' manifest network is `mainnet`.
+    fn test_indexer_with_progress(progress: &[(DeploymentId, u64)]) -> IndexerInfo {
+        let mut indexer =
+            try_into_internal_indexer_info(test_indexer("https://indexer.example.com/"), u128::MAX)
+                .expect("valid indexer info");
+        indexer.deployments = progress
+            .iter()
+            .map(|(deployment, _)| *deployment)
+            .collect::<Vec<_>>()
+            .try_into()
+            .expect("at least one deployment");
+        indexer.indexings_progress = progress
+            .iter()
+            .map(|(deployment, latest_block)| {
+                (
+                    *deployment,
+                    IndexerIndexingProgressInfo {
+                        latest_block: *latest_block,
+                        min_block: None,
+                        last_progress_at: None,
+                        latency: Duration::from_millis(100),
+                    },
+                )
+            })
+            .collect();
+        indexer.deployments_network = progress
+            .iter()
+            .map(|(deployment, _)| (*deployment, "mainnet".to_string()))
+            .collect();
+        indexer
+    }
+
+    #[test]
+    fn stale_indexings_are_dropped() {
+        //* Given
+        let fresh = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let stale = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let unknown_head = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let filter = StalenessFilter {
+            chain_heads: Arc::new(HashMap::from([
+                ("mainnet".to_string(), 1_000),
+                ("arbitrum-one".to_string(), 2_000),
+            ])),
+            max_blocks_behind: 100,
+        };
+
+        // The deployments' networks set the chain head their progress is compared against
+        let with_networks = |mut indexer: IndexerInfo| {
+            indexer
+                .deployments_network
+                .insert(stale, "arbitrum-one".to_string());
+            indexer
+                .deployments_network
+                .insert(unknown_head, "gnosis".to_string());
+            indexer
+        };
+        let mut indexer = with_networks(test_indexer_with_progress(&[
+            (fresh, 950),
+            (stale, 1_500),
+            (unknown_head, 10),
+        ]));
+        let mut lagging = with_networks(test_indexer_with_progress(&[(stale, 1_500)]));
+
+        //* When
+        let result = check_indexer_indexings_staleness(&filter, &mut indexer);
+        let lagging_result = check_indexer_indexings_staleness(&filter, &mut lagging);
+
+        //* Then
+        // The indexings with an unknown chain head are kept
+        assert!(result.is_ok());
+        assert_eq!(indexer.deployments.as_slice(), [fresh, unknown_head]);
+
+        // If all the indexings are stale, the indexer is blocked
+        let err = lagging_result.expect_err("indexer should be blocked");
+        assert_eq!(err.reason, IndexerFilterReason::StaleIndexings);
+    }
+
+    #[test]
+    fn uniformly_lagging_indexer_is_blocked() {
+        //* Given
+        let deployment_a = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_b = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let filter = IndexerLagFilter {
+            chain_heads: Arc::new(HashMap::from([
+                ("mainnet".to_string(), 1_000),
+                ("arbitrum-one".to_string(), 2_000),
+            ])),
+            max_blocks_behind: 100,
+        };
+
+        let with_networks = |mut indexer: IndexerInfo| {
+            indexer
+                .deployments_network
+                .insert(deployment_b, "arbitrum-one".to_string());
+            indexer
+        };
+        let lagging = with_networks(test_indexer_with_progress(&[
+            (deployment_a, 500),
+            (deployment_b, 1_500),
+        ]));
+        let mixed = with_networks(test_indexer_with_progress(&[
+            (deployment_a, 500),
+            (deployment_b, 1_995),
+        ]));
+
+        //* When
+        let lagging_result = check_indexer_blocked_by_lag(&filter, &lagging);
+        let mixed_result = check_indexer_blocked_by_lag(&filter, &mixed);
+
+        //* Then
+        let err = lagging_result.expect_err("indexer should be blocked");
+        assert_eq!(err.reason, IndexerFilterReason::LaggingIndexer);
+        assert!(mixed_result.is_ok());
+    }
+
+    /// Test utility function to create a fetched subgraph info with a version per given
+    /// deployment manifest network.
+    fn test_subgraph(networks: &[&str]) -> fetch_subgraphs::Subgraph {
+        let deployments = [
+            "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH",
+            "QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3",
+        ];
+        fetch_subgraphs::Subgraph {
+            id: "184ba627DB853244c9f17f3Cb4378cB8B39bf147"
+                .parse()
+                .expect("invalid subgraph id"),
+            id_on_l2: None,
+            versions: networks
+                .iter()
+                .zip(deployments)
+                .enumerate()
+                .map(
+                    |(version, (network, deployment))| fetch_subgraphs::SubgraphVersion {
+                        version: version as u32,
+                        subgraph_deployment: fetch_subgraphs::SubgraphDeployment {
+                            id: test_deployment_id(deployment),
+                            allocations: vec![],
+                            manifest: Some(fetch_subgraphs::Manifest {
+                                network: Some(network.to_string()),
+                                start_block: None,
+                            }),
+                            transferred_to_l2: false,
+                        },
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn deployment_on_unknown_network_is_dropped() {
+        //* Given
+        let subgraph = test_subgraph(&["mainnet", "ethereumm"]);
+        let known_networks = HashSet::from(["mainnet".to_string()]);
+
+        //* When
+        let subgraph = try_into_internal_subgraph_info(subgraph, Some(&known_networks));
+
+        //* Then
+        let subgraph = subgraph.expect("valid subgraph info");
+        assert_eq!(subgraph.versions.len(), 1);
+        assert_eq!(
+            subgraph.versions[0].deployment.manifest_network.as_deref(),
+            Some("mainnet")
+        );
+    }
+
+    #[test]
+    fn deployment_networks_are_kept_without_known_networks() {
+        //* Given
+        let subgraph = test_subgraph(&["mainnet", "ethereumm"]);
+
+        //* When
+        let subgraph = try_into_internal_subgraph_info(subgraph, None);
+
+        //* Then
+        let subgraph = subgraph.expect("valid subgraph info");
+        assert_eq!(subgraph.versions.len(), 2);
+    }
+
+    #[test]
+    fn indexings_are_checked_against_their_network_min_graph_node_version() {
+        //* Given
+        let mainnet_deployment =
+            test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let arbitrum_deployment =
+            test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+
+        let mut indexer = test_indexer("https://indexer.example.com/");
+        indexer.allocations = [
+            (Address::repeat_byte(1), mainnet_deployment, "mainnet"),
+            (Address::repeat_byte(2), arbitrum_deployment, "arbitrum-one"),
+        ]
+        .into_iter()
+        .map(|(id, deployment, network)| fetch_indexers::Allocation {
+            id,
+            allocated_tokens: 100,
+            subgraph_deployment: fetch_indexers::SubgraphDeployment {
+                id: deployment,
+                manifest: Some(fetch_indexers::Manifest {
+                    network: Some(network.to_string()),
+                }),
+            },
+        })
+        .collect();
+        let indexer = try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer");
+
+        let graph_node_version = Version::new(0, 34, 0);
+        let min_graph_node_version = Version::new(0, 30, 0);
+        let min_graph_node_version_per_network =
+            HashMap::from([("arbitrum-one".to_string(), Version::new(0, 35, 0))]);
+
+        //* When
+        let mut all_indexings = indexer.clone();
+        let all_indexings_result = check_indexer_indexings_graph_node_version(
+            &graph_node_version,
+            &min_graph_node_version,
+            &min_graph_node_version_per_network,
+            &mut all_indexings,
+        );
+
+        let mut arbitrum_only = indexer;
+        arbitrum_only.deployments = vec![arbitrum_deployment]
+            .try_into()
+            .expect("at least one deployment");
+        let arbitrum_only_result = check_indexer_indexings_graph_node_version(
+            &graph_node_version,
+            &min_graph_node_version,
+            &min_graph_node_version_per_network,
+            &mut arbitrum_only,
+        );
+
+        //* Then
+        // The mainnet indexing meets the global minimum, the arbitrum one is filtered-out
+        assert!(all_indexings_result.is_ok());
+        assert_eq!(all_indexings.deployments.as_slice(), [mainnet_deployment]);
+
+        // If none of the indexings meet their network minimum, the indexer is blocked
+        assert!(arbitrum_only_result.is_err());
+    }
+
+    /// Test utility function to create a test internal state with the given blocklists and
+    /// processing pipeline stages.
+    fn test_internal_state(
+        addr_blocklist: HashSet<Address>,
+        host_blocklist: HashSet<ipnetwork::IpNetwork>,
+        filter_stages: Vec<FilterStage>,
+    ) -> InternalState {
+        let http_client = reqwest::Client::new();
+        InternalState {
+            indexer_http_client: http_client.clone(),
+            indexer_min_agent_version: Version::new(0, 0, 0),
+            indexer_min_graph_node_version: Version::new(0, 0, 0),
+            indexer_min_graph_node_version_per_network: HashMap::new(),
+            indexer_addr_blocklist: Some(AddrBlocklist::new(addr_blocklist)),
+            indexer_host_resolver: Mutex::new(
+                HostResolver::new().expect("failed to create host resolver"),
+            ),
+            indexer_host_blocklist: Some(HostBlocklist::new(host_blocklist)),
+            indexer_version_resolver: VersionResolver::new(http_client.clone()),
+            indexer_indexing_pois_blocklist: None,
+            indexer_indexing_poi_reference: None,
+            indexer_indexing_poi_collision_policy: Default::default(),
+            indexer_indexing_status_resolver: IndexingProgressResolver::new(http_client.clone()),
+            indexer_indexing_staleness_filter: None,
+            indexer_lag_filter: None,
+            indexer_indexing_cost_model_resolver: (
+                CostModelResolver::new(http_client),
+                Mutex::new(CostModelCompiler::default()),
+            ),
+            indexer_indexing_cost_model_overrides: HashMap::new(),
+            indexer_filter_stages: filter_stages,
+            indexer_max_allocated_tokens: u128::MAX,
+            indexer_min_staked_tokens: 0,
+            max_indexers: None,
+            known_networks: None,
+            indexers_fetch_timeout: DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+            subgraphs_fetch_timeout: DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+            fetch_retry: Default::default(),
+            update_timeout: DEFAULT_NETWORK_TOPOLOGY_UPDATE_TIMEOUT,
+            indexers_processing_deadline: DEFAULT_INDEXERS_PROCESSING_DEADLINE,
+            indexers_processing_concurrency: DEFAULT_INDEXERS_PROCESSING_CONCURRENCY,
+            last_fetch_report: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn reordered_filter_stages_produce_the_same_indexers() {
+        //* Given
+        let indexers = [
+            (Address::repeat_byte(1), "http://10.0.0.1:7600/"),
+            (Address::repeat_byte(2), "http://10.0.0.2:7600/"),
+            (Address::repeat_byte(3), "http://192.168.0.3:7600/"),
+        ]
+        .into_iter()
+        .map(|(id, url)| {
+            let indexer = fetch_indexers::Indexer {
+                id,
+                ..test_indexer(url)
+            };
+            let indexer =
+                try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+            (indexer.id, indexer)
+        })
+        .collect::<HashMap<_, _>>();
+
+        let addr_blocklist = HashSet::from([Address::repeat_byte(1)]);
+        let host_blocklist = HashSet::from(["192.168.0.0/16".parse().expect("valid ip network")]);
+
+        let default_order_state = test_internal_state(
+            addr_blocklist.clone(),
+            host_blocklist.clone(),
+            vec![FilterStage::AddrBlocklist, FilterStage::HostBlocklist],
+        );
+        let reordered_state = test_internal_state(
+            addr_blocklist,
+            host_blocklist,
+            vec![FilterStage::HostBlocklist, FilterStage::AddrBlocklist],
+        );
+
+        //* When
+        let default_order_result = process_indexers_info(&default_order_state, indexers.clone())
+            .await
+            .expect("valid indexers");
+        let reordered_result = process_indexers_info(&reordered_state, indexers)
+            .await
+            .expect("valid indexers");
+
+        //* Then
+        let default_order_indexers = default_order_result.keys().collect::<HashSet<_>>();
+        let reordered_indexers = reordered_result.keys().collect::<HashSet<_>>();
+        assert_eq!(default_order_indexers, reordered_indexers);
+        assert_eq!(
+            default_order_indexers,
+            HashSet::from([&Address::repeat_byte(2)])
+        );
+    }
+
+    #[tokio::test]
+    async fn indexers_above_the_cap_are_skipped_by_stake() {
+        //* Given
+        let indexers = [10_u128, 50, 30, 40, 20]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, staked_tokens)| {
+                let indexer = fetch_indexers::Indexer {
+                    id: Address::repeat_byte(idx as u8 + 1),
+                    staked_tokens,
+                    ..test_indexer("https://indexer.example.com/")
+                };
+                let indexer =
+                    try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+                (indexer.id, indexer)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+        state.max_indexers = Some(2);
+
+        //* When
+        let result = process_indexers_info(&state, indexers)
+            .await
+            .expect("valid indexers");
+
+        //* Then
+        let indexers = result.keys().collect::<HashSet<_>>();
+        assert_eq!(
+            indexers,
+            HashSet::from([&Address::repeat_byte(2), &Address::repeat_byte(4)])
+        );
+    }
+
+    #[tokio::test]
+    async fn each_filter_stage_records_a_duration_sample() {
+        //* Given
+        // An unreachable indexer, so the network probes fail fast
+        let indexer =
+            try_into_internal_indexer_info(test_indexer("http://127.0.0.1:1/"), u128::MAX)
+                .expect("valid indexer info");
+        let indexers = HashMap::from([(indexer.id, indexer)]);
+
+        let stages = [
+            FilterStage::AddrBlocklist,
+            FilterStage::HostBlocklist,
+            FilterStage::Version,
+            FilterStage::PoiBlocklist,
+            FilterStage::IndexingProgress,
+            FilterStage::CostModel,
+        ];
+        let sample_count = |stage: FilterStage| {
+            METRICS
+                .indexer_processing_stage_duration
+                .with_label_values(&[stage.name()])
+                .get_sample_count()
+        };
+
+        for stage in stages {
+            let state = test_internal_state(HashSet::new(), HashSet::new(), vec![stage]);
+            let samples_before = sample_count(stage);
+
+            //* When
+            // The processing result is irrelevant, only the recorded samples are checked
+            let _ = process_indexers_info(&state, indexers.clone()).await;
+
+            //* Then
+            assert!(
+                sample_count(stage) > samples_before,
+                "no duration sample recorded for stage {stage:?}"
+            );
+        }
+    }
+
+    /// Test utility function to spawn a server accepting connections but never responding.
+    async fn spawn_unresponsive_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener");
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        url
+    }
+
+    /// Test utility function to create a network subgraph client for a server accepting
+    /// connections but never responding.
+    async fn test_unresponsive_subgraph_client() -> Mutex<SubgraphClient> {
+        let subgraph_url = spawn_unresponsive_server()
+            .await
+            .parse()
+            .expect("valid url");
+
+        Mutex::new(SubgraphClient::new(
+            subgraph_client::Client::builder(reqwest::Client::new(), subgraph_url).build(),
+            true,
+        ))
+    }
+
+    /// Test utility function to spawn a mock indexer. The `respond` function gets the lowercased
+    /// request head and the request body, and returns the JSON response body.
+    async fn spawn_mock_indexer<F>(respond: F) -> String
+    where
+        F: Fn(&str, &str) -> String + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener");
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let respond = Arc::new(respond);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let respond = respond.clone();
+                tokio::spawn(async move {
+                    // Read the request head and body
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    let head_len = loop {
+                        let n = stream.read(&mut buf).await.unwrap_or(0);
+                        if n == 0 {
+                            return;
+                        }
+                        request.extend_from_slice(&buf[..n]);
+                        if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                            break pos + 4;
+                        }
+                    };
+                    let head = String::from_utf8_lossy(&request[..head_len]).to_lowercase();
+                    let content_length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|value| value.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    while request.len() < head_len + content_length {
+                        let n = stream.read(&mut buf).await.unwrap_or(0);
+                        if n == 0 {
+                            break;
+                        }
+                        request.extend_from_slice(&buf[..n]);
+                    }
+
+                    let body = String::from_utf8_lossy(&request[head_len..]);
+                    let body = respond(&head, &body);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        url
+    }
+
+    /// Test utility function to spawn a mock indexer reporting its versions. It counts the
+    /// received agent version requests.
+    async fn spawn_versions_server() -> (String, Arc<AtomicUsize>) {
+        let agent_version_requests = Arc::new(AtomicUsize::new(0));
+
+        let requests = agent_version_requests.clone();
+        let url = spawn_mock_indexer(move |head, _| {
+            if head.starts_with("get /version") {
+                requests.fetch_add(1, Ordering::SeqCst);
+                r#"{"version":"1.0.0"}"#.to_string()
+            } else {
+                r#"{"data":{"version":{"version":"0.35.0"}}}"#.to_string()
+            }
+        })
+        .await;
+
+        (url, agent_version_requests)
+    }
+
+    #[tokio::test]
+    async fn co_located_indexers_share_a_single_version_probe() {
+        //* Given
+        let (url, agent_version_requests) = spawn_versions_server().await;
+        let indexers = [Address::repeat_byte(1), Address::repeat_byte(2)]
+            .into_iter()
+            .map(|id| {
+                let indexer = fetch_indexers::Indexer {
+                    id,
+                    ..test_indexer(&url)
+                };
+                let indexer =
+                    try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+                (indexer.id, indexer)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let state = test_internal_state(HashSet::new(), HashSet::new(), vec![FilterStage::Version]);
+
+        //* When
+        let result = process_indexers_info(&state, indexers).await;
+
+        //* Then
+        let indexers = result.expect("valid indexers");
+        assert_eq!(indexers.len(), 2);
+        assert!(indexers
+            .values()
+            .all(|indexer| indexer.indexer_agent_version == Version::new(1, 0, 0)));
+        assert_eq!(agent_version_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn indexer_features_are_set_from_the_agent_version_response() {
+        //* Given
+        let url = spawn_mock_indexer(|head, _| {
+            if head.starts_with("get /version") {
+                r#"{"version":"1.0.0","features":["sql"]}"#.to_string()
+            } else {
+                r#"{"data":{"version":{"version":"0.35.0"}}}"#.to_string()
+            }
+        })
+        .await;
+        let indexer = try_into_internal_indexer_info(test_indexer(&url), u128::MAX)
+            .expect("valid indexer info");
+        let indexers = HashMap::from([(indexer.id, indexer)]);
+
+        let state = test_internal_state(HashSet::new(), HashSet::new(), vec![FilterStage::Version]);
+
+        //* When
+        let result = process_indexers_info(&state, indexers).await;
+
+        //* Then
+        let indexers = result.expect("valid indexers");
+        assert_eq!(indexers.len(), 1);
+        assert!(indexers
+            .values()
+            .all(|indexer| indexer.features == HashSet::from(["sql".to_string()])));
+    }
+
+    #[tokio::test]
+    async fn overridden_deployment_uses_the_override_cost_model() {
+        //* Given
+        let overridden = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let reported = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+
+        // The indexer reports the same cost model for all its deployments
+        let url = spawn_mock_indexer(move |_, _| {
+            let cost_models = [overridden, reported]
+                .iter()
+                .map(|deployment| {
+                    format!(
+                        r#"{{"deployment":"{deployment}","model":"default => 0.00002;","variables":null}}"#
+                    )
+                })
+                .join(",");
+            format!(r#"{{"data":{{"costModels":[{cost_models}]}}}}"#)
+        })
+        .await;
+
+        let mut indexer = try_into_internal_indexer_info(test_indexer(&url), u128::MAX)
+            .expect("valid indexer info");
+        indexer.deployments = vec1![overridden, reported];
+
+        let override_model =
+            Ptr::new(CostModel::compile("default => 0.00001;", "").expect("valid cost model"));
+        let override_fee = default_fee(&override_model).expect("default fee");
+        let overrides = HashMap::from([(overridden, override_model)]);
+
+        let state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+
+        //* When
+        resolve_indexer_indexing_cost_models(
+            &state.indexer_indexing_cost_model_resolver,
+            &overrides,
+            &mut indexer,
+        )
+        .await
+        .expect("cost models resolution should not fail");
+
+        //* Then
+        let fee =
+            |deployment: &DeploymentId| default_fee(&indexer.indexings_cost_model[deployment]);
+        assert_eq!(fee(&overridden), Some(override_fee));
+        assert_eq!(fee(&reported), Some(2 * override_fee));
+    }
+
+    #[tokio::test]
+    async fn cancelled_update_returns_promptly() {
+        //* Given
+        let client = test_unresponsive_subgraph_client().await;
+        let state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+
+        //* When
+        let update = fetch_update_with_cancellation(
+            &client,
+            &state,
+            None,
+            Default::default(),
+            tokio::time::sleep(Duration::from_millis(50)),
+        )
+        .await;
+
+        //* Then
+        assert!(matches!(update, Err(NetworkFetchError::UpdateCancelled)));
+    }
+
+    #[tokio::test]
+    async fn update_exceeding_the_timeout_fails() {
+        //* Given
+        let client = test_unresponsive_subgraph_client().await;
+        let mut state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+        state.update_timeout = Duration::from_millis(50);
+
+        //* When
+        let update = fetch_update(&client, &state, None, Default::default()).await;
+
+        //* Then
+        let err = update.expect_err("update should time out");
+        assert!(matches!(err, NetworkFetchError::UpdateTimeout));
+        assert_eq!(err.to_string(), "network topology update timed out");
+    }
+
+    #[tokio::test]
+    async fn fetch_exceeding_the_fetch_timeout_fails() {
+        //* Given
+        let client = test_unresponsive_subgraph_client().await;
+        let mut state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+        state.indexers_fetch_timeout = Duration::from_millis(1);
+        state.subgraphs_fetch_timeout = Duration::from_millis(1);
+
+        //* When
+        let update = fetch_update(&client, &state, None, Default::default()).await;
+
+        //* Then
+        let err = update.expect_err("fetch should time out");
+        assert!(matches!(
+            err,
+            NetworkFetchError::IndexersFetchTimeout | NetworkFetchError::SubgraphsFetchTimeout
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_is_retried_up_to_the_max_attempts() {
+        //* Given
+        let retry = RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let attempts = AtomicUsize::new(0);
+
+        //* When
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        let result = fetch_with_retry("test", &retry, deadline, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async {
+                Err::<(), _>(FetchError::Failed(NetworkFetchError::IndexersFetchFailed {
+                    cause: anyhow!("network subgraph unreachable"),
+                }))
+            }
+        })
+        .await;
+
+        //* Then
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn empty_fetch_is_not_retried() {
+        //* Given
+        let retry = RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let attempts = AtomicUsize::new(0);
+
+        //* When
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        let result = fetch_with_retry("test", &retry, deadline, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>(FetchError::Failed(NetworkFetchError::EmptyIndexers)) }
+        })
+        .await;
+
+        //* Then
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retried_fetch_returns_the_first_success() {
+        //* Given
+        let retry = RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let attempts = AtomicUsize::new(0);
+
+        //* When
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        let result = fetch_with_retry("test", &retry, deadline, || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt == 0 {
+                    Err(FetchError::Timeout)
+                } else {
+                    Ok(42)
+                }
+            }
+        })
+        .await;
+
+        //* Then
+        assert_eq!(result.expect("fetch should succeed"), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn fetch_is_not_retried_past_the_deadline() {
+        //* Given
+        let retry = RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+        };
+        let attempts = AtomicUsize::new(0);
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(500);
+
+        //* When
+        let result = fetch_with_retry("test", &retry, deadline, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>(FetchError::Timeout) }
+        })
+        .await;
+
+        //* Then
+        // The first retry backoff delay ends past the deadline
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn fetch_attempt_is_cut_short_at_the_deadline() {
+        //* Given
+        let retry = RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+        };
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(50);
+
+        //* When
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            fetch_with_retry("test", &retry, deadline, || {
+                futures::future::pending::<Result<(), FetchError>>()
+            }),
+        )
+        .await
+        .expect("fetch should stop at the deadline");
+
+        //* Then
+        assert!(matches!(result, Err(FetchError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn filtered_indexers_report_the_filter_reason() {
+        //* Given
+        let missing_url = fetch_indexers::Indexer {
+            url: None,
+            ..test_indexer("https://indexer.example.com/")
+        };
+        let invalid_scheme = test_indexer("ftp://indexer.example.com/");
+        let mut blocked = try_into_internal_indexer_info(
+            fetch_indexers::Indexer {
+                id: Address::repeat_byte(1),
+                ..test_indexer("https://indexer.example.com/")
+            },
+            u128::MAX,
+        )
+        .expect("valid indexer info");
+
+        let state = test_internal_state(
+            HashSet::from([Address::repeat_byte(1)]),
+            HashSet::new(),
+            vec![FilterStage::AddrBlocklist],
+        );
+
+        //* When
+        let missing_url_result = try_into_internal_indexer_info(missing_url, u128::MAX);
+        let invalid_scheme_result = try_into_internal_indexer_info(invalid_scheme, u128::MAX);
+        let blocked_result =
+            run_filter_stage(&state, FilterStage::AddrBlocklist, &mut blocked).await;
+
+        //* Then
+        let reason = |err: IndexerFilterError| err.reason;
+        assert_eq!(
+            missing_url_result.map_err(reason).err(),
+            Some(IndexerFilterReason::MissingUrl)
+        );
+        assert_eq!(
+            invalid_scheme_result.map_err(reason).err(),
+            Some(IndexerFilterReason::InvalidScheme)
+        );
+        assert_eq!(
+            blocked_result.map_err(reason).err(),
+            Some(IndexerFilterReason::AddrBlocked)
+        );
+    }
+
+    /// Test utility function to resolve and check the versions of a mock indexer whose
+    /// graph-node version cannot be resolved, with the given fallback policy.
+    async fn check_version_with_unresolvable_graph_node(
+        fallback: GraphNodeVersionFallback,
+    ) -> Result<IndexerInfo, IndexerFilterError> {
+        check_version_with_unresolvable_graph_node_per_network(fallback, HashMap::new()).await
+    }
+
+    /// Test utility function to resolve and check the versions of a mock indexer whose
+    /// graph-node version cannot be resolved, with the given fallback policy and per-network
+    /// minimum graph-node versions. The indexer's deployments are on the `mainnet` network.
+    async fn check_version_with_unresolvable_graph_node_per_network(
+        fallback: GraphNodeVersionFallback,
+        min_graph_node_version_per_network: HashMap<String, Version>,
+    ) -> Result<IndexerInfo, IndexerFilterError> {
+        let url = spawn_mock_indexer(|head, _| {
+            if head.starts_with("get /version") {
+                r#"{"version":"1.0.0"}"#.to_string()
+            } else {
+                "not a graph-node status response".to_string()
+            }
+        })
+        .await;
+        let mut indexer = try_into_internal_indexer_info(test_indexer(&url), u128::MAX)
+            .expect("valid indexer info");
+        for deployment in indexer.deployments.iter() {
+            indexer
+                .deployments_network
+                .insert(*deployment, "mainnet".to_string());
+        }
+        let resolver =
+            VersionResolver::new(reqwest::Client::new()).with_graph_node_version_fallback(fallback);
+
+        resolve_and_check_indexer_blocked_by_version(
+            &resolver,
+            &Version::new(0, 0, 0),
+            &Version::new(0, 30, 0),
+            &min_graph_node_version_per_network,
+            &mut indexer,
+        )
+        .await
+        .map(|_| indexer)
+    }
+
+    #[tokio::test]
+    async fn unresolvable_graph_node_version_is_blocked() {
+        //* When
+        let result =
+            check_version_with_unresolvable_graph_node(GraphNodeVersionFallback::Block).await;
+
+        //* Then
+        assert_eq!(
+            result.map_err(|err| err.reason).err(),
+            Some(IndexerFilterReason::GraphNodeVersionUnresolvable)
+        );
+    }
+
+    #[tokio::test]
+    async fn unresolvable_graph_node_version_is_assumed_minimum() {
+        //* When
+        let result =
+            check_version_with_unresolvable_graph_node(GraphNodeVersionFallback::AssumeMinimum)
+                .await;
+
+        //* Then
+        let indexer = result.expect("indexer should not be blocked");
+        assert_eq!(indexer.graph_node_version, Version::new(0, 30, 0));
+    }
+
+    #[tokio::test]
+    async fn unresolvable_graph_node_version_is_assumed_the_network_minimum() {
+        //* Given
+        let min_graph_node_version_per_network =
+            HashMap::from([("mainnet".to_string(), Version::new(0, 35, 0))]);
+
+        //* When
+        let result = check_version_with_unresolvable_graph_node_per_network(
+            GraphNodeVersionFallback::AssumeMinimum,
+            min_graph_node_version_per_network,
+        )
+        .await;
+
+        //* Then
+        // The per-network minimum is above the global minimum, the indexing must not be dropped
+        let indexer = result.expect("indexer should not be blocked");
+        assert_eq!(indexer.graph_node_version, Version::new(0, 35, 0));
+    }
+
+    #[tokio::test]
+    async fn unresolvable_graph_node_version_is_assumed_the_given_version() {
+        //* Given
+        let below_minimum = Version::new(0, 20, 0);
+        let above_minimum = Version::new(0, 35, 0);
+
+        //* When
+        let below_minimum_result = check_version_with_unresolvable_graph_node(
+            GraphNodeVersionFallback::AssumeVersion(below_minimum),
+        )
+        .await;
+        let above_minimum_result = check_version_with_unresolvable_graph_node(
+            GraphNodeVersionFallback::AssumeVersion(above_minimum.clone()),
+        )
+        .await;
+
+        //* Then
+        assert_eq!(
+            below_minimum_result.map_err(|err| err.reason).err(),
+            Some(IndexerFilterReason::GraphNodeVersionTooLow)
+        );
+        let indexer = above_minimum_result.expect("indexer should not be blocked");
+        assert_eq!(indexer.graph_node_version, above_minimum);
+    }
+
+    /// Test utility function to create the indexers and subgraphs information of a network
+    /// topology snapshot, with the indexer at the given address.
+    fn test_network_info(
+        indexer: Address,
+    ) -> (
+        HashMap<Address, IndexerInfo>,
+        HashMap<SubgraphId, SubgraphInfo>,
+    ) {
+        let mut indexer_info =
+            try_into_internal_indexer_info(test_indexer("https://indexer.example.com/"), u128::MAX)
+                .expect("valid indexer info");
+        indexer_info.id = indexer;
+        let subgraph_info = try_into_internal_subgraph_info(test_subgraph(&["mainnet"]), None)
+            .expect("valid subgraph info");
+
+        (
+            HashMap::from([(indexer, indexer_info)]),
+            HashMap::from([(subgraph_info.id, subgraph_info)]),
+        )
+    }
+
+    #[test]
+    fn partial_update_merges_the_successful_half_with_the_previous_info() {
+        //* Given
+        let (previous_indexers, previous_subgraphs) = test_network_info(Address::repeat_byte(1));
+        let previous = snapshot::new_retaining_info(
+            FetchedInfo::new(previous_indexers),
+            FetchedInfo::new(previous_subgraphs),
+        );
+        let (new_indexers, new_subgraphs) = test_network_info(Address::repeat_byte(2));
+        let max_age = Duration::from_secs(60);
+
+        //* When
+        let both = merge_partial_update(
+            Ok(new_indexers.clone()),
+            Ok(new_subgraphs.clone()),
+            &previous,
+            max_age,
+        );
+        let subgraphs_failed = merge_partial_update(
+            Ok(new_indexers.clone()),
+            Err(NetworkFetchError::SubgraphsFetchTimeout),
+            &previous,
+            max_age,
+        );
+        let indexers_failed = merge_partial_update(
+            Err(NetworkFetchError::IndexersFetchTimeout),
+            Ok(new_subgraphs),
+            &previous,
+            max_age,
+        );
+        let none = merge_partial_update(
+            Err(NetworkFetchError::IndexersFetchTimeout),
+            Err(NetworkFetchError::SubgraphsFetchTimeout),
+            &previous,
+            max_age,
+        );
+
+        //* Then
+        let (indexers, _) = both.expect("both fetches succeeded");
+        assert!(indexers.info.contains_key(&Address::repeat_byte(2)));
+
+        // The new indexers are merged with the previous subgraphs
+        let (indexers, subgraphs) = subgraphs_failed.expect("partial update");
+        let previous_subgraphs = previous.subgraphs_info().expect("retained subgraphs info");
+        assert!(indexers.info.contains_key(&Address::repeat_byte(2)));
+        assert_eq!(
+            subgraphs.info.keys().collect::<HashSet<_>>(),
+            previous_subgraphs.info.keys().collect()
+        );
+        // The reused half keeps its original fetch time
+        assert_eq!(subgraphs.fetched_at, previous_subgraphs.fetched_at);
+
+        // The previous indexers are merged with the new subgraphs
+        let (indexers, _) = indexers_failed.expect("partial update");
+        assert!(indexers.info.contains_key(&Address::repeat_byte(1)));
+        assert!(!indexers.info.contains_key(&Address::repeat_byte(2)));
+
+        // If both fetches fail, the update fails
+        let err = none.expect_err("both fetches failed");
+        assert!(matches!(err, NetworkFetchError::IndexersFetchTimeout));
+    }
+
+    #[test]
+    fn partial_update_does_not_reuse_expired_or_unretained_info() {
+        //* Given
+        let (previous_indexers, previous_subgraphs) = test_network_info(Address::repeat_byte(1));
+        let retained = snapshot::new_retaining_info(
+            FetchedInfo::new(previous_indexers.clone()),
+            FetchedInfo::new(previous_subgraphs.clone()),
+        );
+        let unretained = snapshot::new_from(previous_indexers, previous_subgraphs);
+        let (new_indexers, _) = test_network_info(Address::repeat_byte(2));
+
+        // Make sure the retained info is older than the max age
+        std::thread::sleep(Duration::from_millis(5));
+
+        //* When
+        let expired = merge_partial_update(
+            Ok(new_indexers.clone()),
+            Err(NetworkFetchError::SubgraphsFetchTimeout),
+            &retained,
+            Duration::ZERO,
+        );
+        let not_retained = merge_partial_update(
+            Ok(new_indexers),
+            Err(NetworkFetchError::SubgraphsFetchTimeout),
+            &unretained,
+            Duration::from_secs(60),
+        );
+
+        //* Then
+        assert!(unretained.subgraphs_info().is_none());
+        assert!(matches!(
+            expired,
+            Err(NetworkFetchError::SubgraphsFetchTimeout)
+        ));
+        assert!(matches!(
+            not_retained,
+            Err(NetworkFetchError::SubgraphsFetchTimeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn partial_update_without_a_previous_snapshot_fails() {
+        //* Given
+        let client = test_unresponsive_subgraph_client().await;
+        let mut state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+        state.indexers_fetch_timeout = Duration::from_millis(1);
+        state.subgraphs_fetch_timeout = Duration::from_millis(1);
+        state.fetch_retry.max_attempts = 1;
+        let policy = PartialUpdatePolicy::ReusePrevious {
+            max_age: Duration::from_secs(60),
+        };
+
+        //* When
+        let update = fetch_update(&client, &state, None, policy).await;
+
+        //* Then
+        assert!(update.is_err());
+    }
+
+    #[tokio::test]
+    async fn indexer_resolving_into_a_blocked_ipv6_network_is_blocked() {
+        //* Given
+        let resolver = Mutex::new(HostResolver::new().expect("failed to create host resolver"));
+        let blocklist = Some(HostBlocklist::new(HashSet::from(["2001:db8::/32"
+            .parse()
+            .expect("valid network")])));
+
+        let blocked =
+            try_into_internal_indexer_info(test_indexer("http://[2001:db8::1]:7600/"), u128::MAX)
+                .expect("valid indexer info");
+        let allowed =
+            try_into_internal_indexer_info(test_indexer("http://[2001:db9::1]:7600/"), u128::MAX)
+                .expect("valid indexer info");
+
+        //* When
+        let blocked_result =
+            resolve_and_check_indexer_blocked_by_host_blocklist(&resolver, &blocklist, &blocked)
+                .await;
+        let allowed_result =
+            resolve_and_check_indexer_blocked_by_host_blocklist(&resolver, &blocklist, &allowed)
+                .await;
+
+        //* Then
+        let err = blocked_result.expect_err("indexer should be blocked");
+        assert_eq!(err.reason, IndexerFilterReason::HostBlocked);
+        assert!(allowed_result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn indexer_whose_host_resolves_to_any_blocked_address_is_filtered_out() {
+        //* Given
+        let indexers = [
+            (Address::repeat_byte(1), "https://round-robin.example.com/"),
+            (Address::repeat_byte(2), "https://allowed.example.com/"),
+        ]
+        .into_iter()
+        .map(|(id, url)| {
+            let indexer = fetch_indexers::Indexer {
+                id,
+                ..test_indexer(url)
+            };
+            let indexer =
+                try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+            (indexer.id, indexer)
+        })
+        .collect::<HashMap<_, _>>();
+
+        let state = test_internal_state(
+            HashSet::new(),
+            HashSet::from(["198.51.100.0/24".parse().expect("valid ip network")]),
+            vec![FilterStage::HostBlocklist],
+        );
+
+        // The round-robin host resolves to two addresses, the blocked one being the last one
+        let allowed: IpAddr = "192.0.2.1".parse().unwrap();
+        let blocked: IpAddr = "198.51.100.1".parse().unwrap();
+        {
+            let mut resolver = state.indexer_host_resolver.lock().await;
+            resolver.insert_cached("round-robin.example.com", vec![allowed, blocked]);
+            resolver.insert_cached("allowed.example.com", vec![allowed]);
+        }
+
+        //* When
+        let result = process_indexers_info(&state, indexers)
+            .await
+            .expect("no valid indexers");
+
+        //* Then
+        let indexers = result.keys().collect::<HashSet<_>>();
+        assert_eq!(indexers, HashSet::from([&Address::repeat_byte(2)]));
+        assert_eq!(
+            state.last_fetch_report().map(|report| report.host_blocked),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn indexers_below_the_min_stake_are_filtered_out() {
+        //* Given
+        let indexers = [
+            (Address::repeat_byte(1), 50_u128),
+            (Address::repeat_byte(2), 150),
+        ]
+        .into_iter()
+        .map(|(id, staked_tokens)| {
+            let indexer = fetch_indexers::Indexer {
+                id,
+                staked_tokens,
+                ..test_indexer("https://indexer.example.com/")
+            };
+            let indexer =
+                try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+            (indexer.id, indexer)
+        })
+        .collect::<HashMap<_, _>>();
+
+        let mut state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+        state.indexer_min_staked_tokens = 100;
+
+        //* When
+        let result = process_indexers_info(&state, indexers)
+            .await
+            .expect("valid indexers");
+
+        //* Then
+        let indexers = result.keys().collect::<HashSet<_>>();
+        assert_eq!(indexers, HashSet::from([&Address::repeat_byte(2)]));
+    }
+
+    #[tokio::test]
+    async fn filtered_indexer_records_a_processing_duration_sample() {
+        //* Given
+        let indexer = fetch_indexers::Indexer {
+            id: Address::repeat_byte(0x15),
+            ..test_indexer("https://indexer.example.com/")
+        };
+        let indexer =
+            try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+        let indexers = HashMap::from([(indexer.id, indexer.clone())]);
+
+        // The indexer is filtered-out by the first stage
+        let state = test_internal_state(
+            HashSet::from([indexer.id]),
+            HashSet::new(),
+            vec![FilterStage::AddrBlocklist],
+        );
+        let sample_count = || {
+            METRICS
+                .indexer_processing_duration
+                .with_label_values(&[&indexer.id.to_string()])
+                .get_sample_count()
+        };
+        let samples_before = sample_count();
+
+        //* When
+        let result = process_indexers_info(&state, indexers).await;
+
+        //* Then
+        assert!(result.is_err());
+        assert_eq!(sample_count(), samples_before + 1);
+    }
+
+    #[tokio::test]
+    async fn indexers_not_processed_by_the_deadline_are_dropped() {
+        //* Given
+        let (responsive_url, _) = spawn_versions_server().await;
+        let stalled_url = spawn_unresponsive_server().await;
+        let indexers = [
+            (Address::repeat_byte(1), responsive_url),
+            (Address::repeat_byte(2), stalled_url),
+        ]
+        .into_iter()
+        .map(|(id, url)| {
+            let indexer = fetch_indexers::Indexer {
+                id,
+                ..test_indexer(&url)
+            };
+            let indexer =
+                try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+            (indexer.id, indexer)
+        })
+        .collect::<HashMap<_, _>>();
+
+        let mut state =
+            test_internal_state(HashSet::new(), HashSet::new(), vec![FilterStage::Version]);
+        state.indexers_processing_deadline = Duration::from_millis(500);
+
+        //* When
+        let result = process_indexers_info(&state, indexers).await;
+
+        //* Then
+        // The stalled indexer is dropped, the update proceeds with the responsive one
+        let indexers = result.expect("valid indexers");
+        assert_eq!(
+            indexers.keys().collect::<HashSet<_>>(),
+            HashSet::from([&Address::repeat_byte(1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn queued_indexers_processing_deadline_starts_when_dequeued() {
+        //* Given
+        let (responsive_url, _) = spawn_versions_server().await;
+        let stalled_url = spawn_unresponsive_server().await;
+        let indexers = [
+            (Address::repeat_byte(1), responsive_url.clone()),
+            (Address::repeat_byte(2), responsive_url),
+            (Address::repeat_byte(3), stalled_url.clone()),
+            (Address::repeat_byte(4), stalled_url),
+        ]
+        .into_iter()
+        .map(|(id, url)| {
+            let indexer = fetch_indexers::Indexer {
+                id,
+                ..test_indexer(&url)
+            };
+            let indexer =
+                try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+            (indexer.id, indexer)
+        })
+        .collect::<HashMap<_, _>>();
+
+        // The indexers are processed one at a time, so the indexers queued behind the stalled ones
+        // wait for more than a single deadline
+        let mut state =
+            test_internal_state(HashSet::new(), HashSet::new(), vec![FilterStage::Version]);
+        state.indexers_processing_concurrency = 1;
+        state.indexers_processing_deadline = Duration::from_millis(300);
+
+        //* When
+        let result = process_indexers_info(&state, indexers).await;
+
+        //* Then
+        // The responsive indexers are kept regardless of the time spent queued behind the stalled
+        // ones
+        let indexers = result.expect("valid indexers");
+        assert_eq!(
+            indexers.keys().collect::<HashSet<_>>(),
+            HashSet::from([&Address::repeat_byte(1), &Address::repeat_byte(2)])
+        );
+    }
+
+    /// Test utility function to spawn a mock indexer reporting the given agent version and POI.
+    /// If `progress` is false, the indexing statuses request is answered with an invalid response.
+    async fn spawn_reporting_indexer(
+        deployment: DeploymentId,
+        agent_version: &'static str,
+        poi: ProofOfIndexing,
+        progress: bool,
+    ) -> String {
+        spawn_mock_indexer(move |head, body| {
+            if head.starts_with("get /version") {
+                format!(r#"{{"version":"{agent_version}"}}"#)
+            } else if body.contains("publicProofsOfIndexing") {
+                format!(
+                    r#"{{"data":{{"publicProofsOfIndexing":[{{"deployment":"{deployment}","proofOfIndexing":"{poi}","block":{{"number":"100"}}}}]}}}}"#
+                )
+            } else if body.contains("indexingStatuses") {
+                if progress {
+                    format!(
+                        r#"{{"data":{{"indexingStatuses":[{{"subgraph":"{deployment}","chains":[{{"network":"mainnet","latestBlock":{{"number":"200"}},"earliestBlock":{{"number":"1"}}}}]}}]}}}}"#
+                    )
+                } else {
+                    "not an indexing statuses response".to_string()
+                }
+            } else {
+                r#"{"data":{"version":{"version":"0.35.0"}}}"#.to_string()
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn fetch_report_counts_the_indexers_filtered_at_each_stage() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let blocked_poi = ProofOfIndexing::from([1u8; 32]);
+        let valid_poi = ProofOfIndexing::from([2u8; 32]);
+
+        let indexers = [
+            // Blocked by the address blocklist
+            (1, "http://10.0.0.1:7600/".to_string()),
+            // Blocked by the host blocklist
+            (2, "http://192.168.0.2:7600/".to_string()),
+            // Blocked by the agent version
+            (
+                3,
+                spawn_reporting_indexer(deployment, "0.1.0", valid_poi, true).await,
+            ),
+            // Blocked by POI
+            (
+                4,
+                spawn_reporting_indexer(deployment, "1.0.0", blocked_poi, true).await,
+            ),
+            // Failing the indexings progress resolution
+            (
+                5,
+                spawn_reporting_indexer(deployment, "1.0.0", valid_poi, false).await,
+            ),
+            // Making it into the network topology
+            (
+                6,
+                spawn_reporting_indexer(deployment, "1.0.0", valid_poi, true).await,
+            ),
+        ]
+        .into_iter()
+        .map(|(id, url)| {
+            let indexer = fetch_indexers::Indexer {
+                id: Address::repeat_byte(id),
+                ..test_indexer(&url)
+            };
+            let indexer =
+                try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+            (indexer.id, indexer)
+        })
+        .collect::<HashMap<_, _>>();
+
+        let mut state = test_internal_state(
+            HashSet::from([Address::repeat_byte(1)]),
+            HashSet::from(["192.168.0.0/16".parse().expect("valid ip network")]),
+            vec![
+                FilterStage::AddrBlocklist,
+                FilterStage::HostBlocklist,
+                FilterStage::Version,
+                FilterStage::PoiBlocklist,
+                FilterStage::IndexingProgress,
+            ],
+        );
+        state.indexer_min_agent_version = Version::new(1, 0, 0);
+        state.indexer_indexing_pois_blocklist = Some((
+            PoiBlocklist::new(HashSet::from([ProofOfIndexingInfo {
+                proof_of_indexing: blocked_poi,
+                deployment_id: deployment,
+                block_number: 100,
+            }])),
+            PoiResolver::new(reqwest::Client::new()),
+        ));
+        assert!(state.last_fetch_report().is_none());
+
+        //* When
+        let result = process_indexers_info(&state, indexers).await;
+
+        //* Then
+        let indexers = result.expect("valid indexers");
+        assert_eq!(
+            indexers.keys().collect::<HashSet<_>>(),
+            HashSet::from([&Address::repeat_byte(6)])
+        );
+        assert_eq!(
+            state.last_fetch_report(),
+            Some(TopologyFetchReport {
+                fetched: 6,
+                addr_blocked: 1,
+                host_blocked: 1,
+                version_blocked: 1,
+                poi_blocked: 1,
+                progress_failed: 1,
+                other_filtered: 0,
+                final_count: 1,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn indexer_is_blocked_by_an_appended_poi() {
+        //* Given
+        let served = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let unserved = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let reported_poi = ProofOfIndexing::from([1u8; 32]);
+
+        let url = spawn_reporting_indexer(served, "1.0.0", reported_poi, true).await;
+        let indexer =
+            try_into_internal_indexer_info(test_indexer(&url), u128::MAX).expect("valid indexer");
+
+        let blocklist = PoiBlocklist::new(HashSet::new());
+        let handle = blocklist.clone();
+        let state = Some((blocklist, PoiResolver::new(reqwest::Client::new())));
+
+        //* When
+        // Appending a POI of a deployment the indexer does not serve is a no-op
+        handle.append([ProofOfIndexingInfo {
+            proof_of_indexing: reported_poi,
+            deployment_id: unserved,
+            block_number: 100,
+        }]);
+        let unserved_result =
+            resolve_and_check_indexer_blocked_by_poi(&state, None, &mut indexer.clone()).await;
+
+        handle.append([ProofOfIndexingInfo {
+            proof_of_indexing: reported_poi,
+            deployment_id: served,
+            block_number: 100,
+        }]);
+        let served_result =
+            resolve_and_check_indexer_blocked_by_poi(&state, None, &mut indexer.clone()).await;
+
+        //* Then
+        assert!(unserved_result.is_ok());
+        assert_eq!(
+            served_result.map_err(|err| err.reason).err(),
+            Some(IndexerFilterReason::AllPoisBlocked)
+        );
+    }
+
+    #[tokio::test]
+    async fn indexer_pois_are_checked_against_the_reference_source() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let reported_poi = ProofOfIndexing::from([1u8; 32]);
+
+        let url = spawn_reporting_indexer(deployment, "1.0.0", reported_poi, true).await;
+        let indexer =
+            try_into_internal_indexer_info(test_indexer(&url), u128::MAX).expect("valid indexer");
+
+        // Only the reference source is set, the POIs blocklist is empty
+        let state = Some((
+            PoiBlocklist::default(),
+            PoiResolver::new(reqwest::Client::new()),
+        ));
+        let matching_reference = HashMap::from([((deployment, 100), reported_poi)]);
+        let mismatching_reference =
+            HashMap::from([((deployment, 100), ProofOfIndexing::from([2u8; 32]))]);
+
+        //* When
+        let matching_result = resolve_and_check_indexer_blocked_by_poi(
+            &state,
+            Some(&matching_reference as &dyn PoiReferenceSource),
+            &mut indexer.clone(),
+        )
+        .await;
+        let mismatching_result = resolve_and_check_indexer_blocked_by_poi(
+            &state,
+            Some(&mismatching_reference as &dyn PoiReferenceSource),
+            &mut indexer.clone(),
+        )
+        .await;
+
+        //* Then
+        assert!(matching_result.is_ok());
+        assert_eq!(
+            mismatching_result.map_err(|err| err.reason).err(),
+            Some(IndexerFilterReason::AllPoisBlocked)
+        );
+    }
+
+    #[tokio::test]
+    async fn indexer_reporting_a_poi_for_distinct_deployments_is_flagged() {
+        //* Given
+        let deployment_a = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_b = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let poi = ProofOfIndexing::from([1u8; 32]);
+
+        // The indexer reports the same POI for both deployments
+        let url = spawn_mock_indexer(move |_, body| {
+            if body.contains("publicProofsOfIndexing") {
+                format!(
+                    r#"{{"data":{{"publicProofsOfIndexing":[{{"deployment":"{deployment_a}","proofOfIndexing":"{poi}","block":{{"number":"200"}}}},{{"deployment":"{deployment_b}","proofOfIndexing":"{poi}","block":{{"number":"200"}}}}]}}}}"#
+                )
+            } else {
+                format!(
+                    r#"{{"data":{{"indexingStatuses":[{{"subgraph":"{deployment_a}","chains":[{{"network":"mainnet","latestBlock":{{"number":"200"}}}}]}},{{"subgraph":"{deployment_b}","chains":[{{"network":"mainnet","latestBlock":{{"number":"200"}}}}]}}]}}}}"#
+                )
+            }
+        })
+        .await;
+        let mut indexer =
+            try_into_internal_indexer_info(test_indexer(&url), u128::MAX).expect("valid indexer");
+        indexer.id = Address::repeat_byte(0x65);
+        indexer.deployments = vec1![deployment_a, deployment_b];
+
+        let progress_resolver = IndexingProgressResolver::new(reqwest::Client::new());
+        let pois_resolver = PoiResolver::new(reqwest::Client::new());
+        let collisions_count = || {
+            METRICS
+                .indexer_poi_collisions
+                .with_label_values(&[&indexer.id.to_string()])
+                .get()
+        };
+        let collisions_count_before = collisions_count();
+
+        //* When
+        let flag_result = resolve_and_check_indexer_poi_collisions(
+            &progress_resolver,
+            &pois_resolver,
+            PoiCollisionPolicy::Flag,
+            &indexer,
+        )
+        .await;
+        let block_result = resolve_and_check_indexer_poi_collisions(
+            &progress_resolver,
+            &pois_resolver,
+            PoiCollisionPolicy::Block,
+            &indexer,
+        )
+        .await;
+
+        //* Then
+        assert!(flag_result.is_ok());
+        assert_eq!(
+            block_result.map_err(|err| err.reason).err(),
+            Some(IndexerFilterReason::PoiCollision)
+        );
+        assert_eq!(collisions_count(), collisions_count_before + 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_poi_resolutions_share_a_single_request() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let poi = ProofOfIndexing::from([1u8; 32]);
+
+        let poi_requests = Arc::new(AtomicUsize::new(0));
+        let url = {
+            let requests = poi_requests.clone();
+            spawn_mock_indexer(move |_, _| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                format!(
+                    r#"{{"data":{{"publicProofsOfIndexing":[{{"deployment":"{deployment}","proofOfIndexing":"{poi}","block":{{"number":"100"}}}}]}}}}"#
+                )
+            })
+            .await
+            .parse()
+            .expect("valid url")
+        };
+
+        let resolver = PoiResolver::new(reqwest::Client::new());
+        let pois = [(deployment, 100)];
+
+        //* When
+        let (first, second) =
+            futures::join!(resolver.resolve(&url, &pois), resolver.resolve(&url, &pois));
+
+        //* Then
+        let expected = HashMap::from([((deployment, 100), poi)]);
+        assert_eq!(first.expect("resolved pois"), expected);
+        assert_eq!(second.expect("resolved pois"), expected);
+        assert_eq!(poi_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_poi_resolutions_share_a_failed_request() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+
+        // A server accepting connections but never responding
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener");
+        let url = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .expect("valid url");
+        let connections = Arc::new(AtomicUsize::new(0));
+        tokio::spawn({
+            let connections = connections.clone();
+            async move {
+                let mut streams = Vec::new();
+                while let Ok((stream, _)) = listener.accept().await {
+                    connections.fetch_add(1, Ordering::SeqCst);
+                    streams.push(stream);
+                }
+            }
+        });
+
+        let resolver =
+            PoiResolver::with_timeout(reqwest::Client::new(), Duration::from_millis(100));
+        let pois = [(deployment, 100)];
+
+        //* When
+        let (first, second) =
+            futures::join!(resolver.resolve(&url, &pois), resolver.resolve(&url, &pois));
+
+        //* Then
+        // Both resolutions got the timeout of the single shared request
+        assert!(matches!(first, Err(ResolutionError::Timeout)));
+        assert!(matches!(second, Err(ResolutionError::Timeout)));
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn indexer_with_poi_resolution_timing_out_is_filtered_out() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let url = spawn_unresponsive_server().await;
+        let mut indexer =
+            try_into_internal_indexer_info(test_indexer(&url), u128::MAX).expect("valid indexer");
+
+        let state = Some((
+            PoiBlocklist::new(HashSet::from([ProofOfIndexingInfo {
+                proof_of_indexing: ProofOfIndexing::from([1u8; 32]),
+                deployment_id: deployment,
+                block_number: 100,
+            }])),
+            PoiResolver::with_timeout(reqwest::Client::new(), Duration::from_millis(100)),
+        ));
+
+        //* When
+        let result = resolve_and_check_indexer_blocked_by_poi(&state, None, &mut indexer).await;
+
+        //* Then
+        assert_eq!(
+            result.map_err(|err| err.reason).err(),
+            Some(IndexerFilterReason::PoiResolutionFailed)
+        );
+    }
+
+    #[tokio::test]
+    async fn processed_indexers_do_not_depend_on_the_concurrency() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let poi = ProofOfIndexing::from([1u8; 32]);
+
+        let mut indexers = HashMap::new();
+        for id in 1..=8_u8 {
+            // Every other indexer reports an agent version below the minimum
+            let agent_version = if id % 2 == 0 { "1.0.0" } else { "0.1.0" };
+            let url = spawn_reporting_indexer(deployment, agent_version, poi, true).await;
+            let indexer = fetch_indexers::Indexer {
+                id: Address::repeat_byte(id),
+                ..test_indexer(&url)
+            };
+            let indexer =
+                try_into_internal_indexer_info(indexer, u128::MAX).expect("valid indexer info");
+            indexers.insert(indexer.id, indexer);
+        }
+
+        let test_state = |concurrency: usize| {
+            let mut state = test_internal_state(
+                HashSet::new(),
+                HashSet::new(),
+                vec![FilterStage::Version, FilterStage::IndexingProgress],
+            );
+            state.indexer_min_agent_version = Version::new(1, 0, 0);
+            state.indexers_processing_concurrency = concurrency;
+            state
+        };
+        let sequential_state = test_state(1);
+        let concurrent_state = test_state(DEFAULT_INDEXERS_PROCESSING_CONCURRENCY);
+
+        //* When
+        let sequential = process_indexers_info(&sequential_state, indexers.clone())
+            .await
+            .expect("valid indexers");
+        let concurrent = process_indexers_info(&concurrent_state, indexers)
+            .await
+            .expect("valid indexers");
+
+        //* Then
+        let summary = |indexers: &HashMap<Address, IndexerInfo>| {
+            indexers
+                .iter()
+                .map(|(id, indexer)| {
+                    (
+                        *id,
+                        indexer.url.clone(),
+                        indexer.indexer_agent_version.clone(),
+                        indexer.deployments.to_vec(),
+                        indexer
+                            .indexings_progress
+                            .iter()
+                            .map(|(deployment, progress)| (*deployment, progress.latest_block))
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .sorted_by_key(|(id, ..)| *id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summary(&sequential), summary(&concurrent));
+        assert_eq!(sequential.len(), 4);
+        assert_eq!(
+            sequential_state.last_fetch_report(),
+            concurrent_state.last_fetch_report()
+        );
+    }
+
+    /// Test utility function to create a network subgraph client for a mock network subgraph
+    /// returning the given indexers. The subgraphs query is never answered.
+    async fn test_indexers_subgraph_client(indexers: serde_json::Value) -> Mutex<SubgraphClient> {
+        let pages = Arc::new(AtomicUsize::new(0));
+        let subgraph_url = spawn_mock_indexer(move |_, body| {
+            // Only the first page holds the indexers, the next pages are empty
+            let results = if body.contains("indexers(") && pages.fetch_add(1, Ordering::SeqCst) == 0
+            {
+                indexers.clone()
+            } else {
+                serde_json::json!([])
+            };
+            serde_json::json!({
+                "data": {
+                    "meta": { "block": { "number": 1, "hash": format!("0x{}", "00".repeat(32)) } },
+                    "results": results,
+                }
+            })
+            .to_string()
+        })
+        .await
+        .parse()
+        .expect("valid url");
+
+        Mutex::new(SubgraphClient::new(
+            subgraph_client::Client::builder(reqwest::Client::new(), subgraph_url).build(),
+            true,
+        ))
+    }
+
+    #[tokio::test]
+    async fn update_with_empty_or_invalid_indexers_reports_the_failure_condition() {
+        //* Given
+        let empty_client = test_indexers_subgraph_client(serde_json::json!([])).await;
+        let invalid_client = test_indexers_subgraph_client(serde_json::json!([{
+            "id": format!("{}", Address::repeat_byte(1)),
+            "url": null,
+            "stakedTokens": "100000",
+            "allocations": [],
+        }]))
+        .await;
+
+        let mut state = test_internal_state(HashSet::new(), HashSet::new(), vec![]);
+        state.fetch_retry.max_attempts = 1;
+
+        //* When
+        // The indexers fetch holds the network subgraph client first, so its failure is reported
+        let empty = fetch_update(&empty_client, &state, None, Default::default()).await;
+        let invalid = fetch_update(&invalid_client, &state, None, Default::default()).await;
+
+        //* Then
+        assert!(matches!(empty, Err(NetworkFetchError::EmptyIndexers)));
+        assert!(matches!(invalid, Err(NetworkFetchError::NoValidIndexers)));
+    }
+}
@@ -4,28 +4,46 @@
 
 use std::{
     collections::{HashMap, HashSet},
+    future::Future,
+    path::PathBuf,
+    sync::Arc,
     time::Duration,
 };
 
 use anyhow::anyhow;
+use cost_model::CostModel;
 use eventuals::{Eventual, EventualExt as _, Ptr};
-use gateway_framework::errors::Error;
+use gateway_framework::{errors::Error, topology::network::DEFAULT_MAX_ALLOCATED_TOKENS};
 use ipnetwork::IpNetwork;
 use semver::Version;
-use tokio::sync::Mutex;
+use tokio::{
+    sync::{watch, Mutex},
+    time::MissedTickBehavior,
+};
 use vec1::{vec1, Vec1};
 
 use super::{
     indexer_addr_blocklist::AddrBlocklist,
-    indexer_host_blocklist::HostBlocklist,
+    indexer_drain_set::DrainSet,
+    indexer_host_blocklist::{AsnTable, HostBlocklist},
     indexer_host_resolver::HostResolver,
     indexer_indexing_cost_model_compiler::CostModelCompiler,
     indexer_indexing_cost_model_resolver::CostModelResolver,
-    indexer_indexing_poi_blocklist::PoiBlocklist,
+    indexer_indexing_poi_blocklist::{PoiBlocklist, PoiCollisionPolicy},
+    indexer_indexing_poi_reference::PoiReferenceSource,
     indexer_indexing_poi_resolver::PoiResolver,
     indexer_indexing_progress_resolver::IndexingProgressResolver,
-    indexer_version_resolver::{VersionResolver, DEFAULT_INDEXER_VERSION_RESOLUTION_TIMEOUT},
-    internal::{fetch_update, InternalState},
+    indexer_tiers::IndexerTiers,
+    indexer_version_resolver::{
+        GraphNodeVersionFallback, VersionResolver, DEFAULT_INDEXER_VERSION_RESOLUTION_TIMEOUT,
+    },
+    internal::{
+        fetch_update_with_cancellation, ChainHeadSource, EffectiveConfig, FilterStage,
+        IndexerLagFilter, InternalState, PartialUpdatePolicy, RetryConfig, StalenessFilter,
+        TopologyFetchReport, DEFAULT_FILTER_STAGES, DEFAULT_INDEXERS_PROCESSING_CONCURRENCY,
+        DEFAULT_INDEXERS_PROCESSING_DEADLINE, DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+        DEFAULT_NETWORK_TOPOLOGY_UPDATE_TIMEOUT,
+    },
     snapshot::{
         Address, BlockNumber, DeploymentId, Indexing, IndexingId, NetworkTopologySnapshot,
         SubgraphId,
@@ -94,6 +112,11 @@ impl ResolvedSubgraphInfo {
 #[derive(Clone)]
 pub struct NetworkService {
     network: Eventual<Ptr<NetworkTopologySnapshot>>,
+    indexer_drain_set: DrainSet,
+    indexer_tiers: IndexerTiers,
+    indexer_pois_blocklist: Option<PoiBlocklist>,
+    effective_config: Arc<EffectiveConfig>,
+    state: Arc<InternalState>,
 }
 
 impl NetworkService {
@@ -106,6 +129,55 @@ impl NetworkService {
             .expect("network service not available");
     }
 
+    /// Get the latest network topology snapshot.
+    ///
+    /// Returns `None` if the first network topology update has not completed yet. See
+    /// [`NetworkService::wait_until_ready`].
+    pub fn snapshot(&self) -> Option<Ptr<NetworkTopologySnapshot>> {
+        self.network.value_immediate()
+    }
+
+    /// Get the summary of the service's active settings, e.g., for a diagnostics endpoint.
+    ///
+    /// The settings are the ones the service was spawned with, except for the drain set, which
+    /// changes at runtime.
+    pub fn effective_config(&self) -> EffectiveConfig {
+        EffectiveConfig {
+            indexer_drain_set_len: self.indexer_drain_set.len(),
+            ..(*self.effective_config).clone()
+        }
+    }
+
+    /// Get the report of the last indexers processing, e.g., to diagnose why an indexer is
+    /// missing from the network topology.
+    ///
+    /// Returns `None` if no network topology update has fetched the indexers yet.
+    pub fn last_fetch_report(&self) -> Option<TopologyFetchReport> {
+        self.state.last_fetch_report()
+    }
+
+    /// Get the set of indexers being drained.
+    ///
+    /// Drained indexers remain in the network topology, but the selectors must not send new
+    /// queries to them.
+    pub fn indexer_drain_set(&self) -> &DrainSet {
+        &self.indexer_drain_set
+    }
+
+    /// Get the operator-defined indexer tiers.
+    ///
+    /// The selectors must scale each candidate's selection weight by its indexer's tier weight.
+    pub fn indexer_tiers(&self) -> &IndexerTiers {
+        &self.indexer_tiers
+    }
+
+    /// Get the indexer POIs blocklist, if enabled.
+    ///
+    /// The entries appended to the blocklist are picked up by the next network topology update.
+    pub fn indexer_pois_blocklist(&self) -> Option<&PoiBlocklist> {
+        self.indexer_pois_blocklist.as_ref()
+    }
+
     /// Get the deployments table as an eventual.
     // TODO: For backwards-compat. Review this method and consider removing it
     //   - This method is used in the `main.rs` file to construct a map of indexings to
@@ -221,15 +293,39 @@ pub struct NetworkServiceBuilder {
     indexer_client: reqwest::Client,
     indexer_min_agent_version: Version,
     indexer_min_graph_node_version: Version,
+    indexer_min_graph_node_version_per_network: HashMap<String, Version>,
     indexer_addr_blocklist: Option<AddrBlocklist>,
+    indexer_addr_blocklist_reload_interval: Option<Duration>,
     indexer_host_resolver: HostResolver,
     indexer_host_blocklist: Option<HostBlocklist>,
+    indexer_host_asn_blocklist: Option<(AsnTable, HashSet<u32>)>,
     indexer_version_resolver: VersionResolver,
-    indexer_indexing_pois_blocklist: Option<(PoiBlocklist, PoiResolver)>,
+    indexer_indexing_pois_blocklist: Option<PoiBlocklist>,
+    indexer_indexing_poi_reference: Option<Arc<dyn PoiReferenceSource>>,
+    indexer_indexing_pois_resolution_timeout: Duration,
+    indexer_indexing_poi_collision_policy: PoiCollisionPolicy,
     indexer_indexing_status_resolver: IndexingProgressResolver,
+    indexer_indexing_staleness_filter: Option<StalenessFilter>,
+    indexer_lag_filter: Option<IndexerLagFilter>,
     indexer_indexing_cost_model_resolver: CostModelResolver,
     indexer_indexing_cost_model_compiler: CostModelCompiler,
+    indexer_indexing_cost_model_overrides: HashMap<DeploymentId, Ptr<CostModel>>,
+    indexer_filter_stages: Vec<FilterStage>,
+    indexer_max_allocated_tokens: u128,
+    indexer_min_staked_tokens: u128,
+    max_indexers: Option<usize>,
+    indexer_drain_set: DrainSet,
+    indexer_tiers: IndexerTiers,
+    known_networks: Option<HashSet<String>>,
+    shutdown_signal: Option<watch::Receiver<bool>>,
     update_interval: Duration,
+    update_timeout: Duration,
+    indexers_processing_deadline: Duration,
+    indexers_processing_concurrency: usize,
+    indexers_fetch_timeout: Duration,
+    subgraphs_fetch_timeout: Duration,
+    fetch_retry: RetryConfig,
+    partial_update_policy: PartialUpdatePolicy,
 }
 
 impl NetworkServiceBuilder {
@@ -258,15 +354,40 @@ impl NetworkServiceBuilder {
             indexer_client,
             indexer_min_agent_version: Version::new(0, 0, 0),
             indexer_min_graph_node_version: Version::new(0, 0, 0),
+            indexer_min_graph_node_version_per_network: HashMap::new(),
             indexer_addr_blocklist: None,
+            indexer_addr_blocklist_reload_interval: None,
             indexer_host_resolver,
             indexer_host_blocklist: None,
+            indexer_host_asn_blocklist: None,
             indexer_version_resolver,
             indexer_indexing_pois_blocklist: None,
+            indexer_indexing_poi_reference: None,
+            indexer_indexing_pois_resolution_timeout:
+                DEFAULT_INDEXER_INDEXING_POIS_RESOLUTION_TIMEOUT, // 5s
+            indexer_indexing_poi_collision_policy: PoiCollisionPolicy::default(),
             indexer_indexing_status_resolver,
+            indexer_indexing_staleness_filter: None,
+            indexer_lag_filter: None,
             indexer_indexing_cost_model_resolver,
             indexer_indexing_cost_model_compiler,
+            indexer_indexing_cost_model_overrides: HashMap::new(),
+            indexer_filter_stages: DEFAULT_FILTER_STAGES.to_vec(),
+            indexer_max_allocated_tokens: DEFAULT_MAX_ALLOCATED_TOKENS,
+            indexer_min_staked_tokens: 0,
+            max_indexers: None,
+            indexer_drain_set: DrainSet::default(),
+            indexer_tiers: IndexerTiers::default(),
+            known_networks: None,
+            shutdown_signal: None,
             update_interval: DEFAULT_UPDATE_INTERVAL,
+            update_timeout: DEFAULT_NETWORK_TOPOLOGY_UPDATE_TIMEOUT,
+            indexers_processing_deadline: DEFAULT_INDEXERS_PROCESSING_DEADLINE,
+            indexers_processing_concurrency: DEFAULT_INDEXERS_PROCESSING_CONCURRENCY,
+            indexers_fetch_timeout: DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+            subgraphs_fetch_timeout: DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+            fetch_retry: RetryConfig::default(),
+            partial_update_policy: PartialUpdatePolicy::default(),
         }
     }
 
@@ -276,6 +397,63 @@ impl NetworkServiceBuilder {
         self
     }
 
+    /// Sets the overall timeout of each network topology update, covering the information
+    /// fetches and the indexers processing.
+    pub fn with_update_timeout(mut self, update_timeout: Duration) -> Self {
+        self.update_timeout = update_timeout;
+        self
+    }
+
+    /// Sets the per-indexer processing deadline.
+    ///
+    /// The deadline starts once the indexer is dequeued for processing, see
+    /// [`Self::with_indexers_processing_concurrency`]. The indexers not processed by the deadline,
+    /// e.g., slow-responding indexers, are dropped from the update, instead of stalling it.
+    pub fn with_indexers_processing_deadline(mut self, deadline: Duration) -> Self {
+        self.indexers_processing_deadline = deadline;
+        self
+    }
+
+    /// Sets the maximum number of indexers processed concurrently.
+    ///
+    /// Bounds the number of simultaneous connections to the indexers, e.g., to avoid exhausting
+    /// the indexers HTTP client connection pool. Defaults to 1000.
+    pub fn with_indexers_processing_concurrency(mut self, concurrency: usize) -> Self {
+        self.indexers_processing_concurrency = concurrency;
+        self
+    }
+
+    /// Sets the indexers information fetch timeout.
+    ///
+    /// The timeout covers the network subgraph indexers query only, not the subsequent requests to
+    /// the indexers, bounded by the [`Self::with_indexers_processing_deadline`]. Defaults to 15
+    /// seconds.
+    pub fn with_indexers_fetch_timeout(mut self, timeout: Duration) -> Self {
+        self.indexers_fetch_timeout = timeout;
+        self
+    }
+
+    /// Sets the subgraphs information fetch timeout.
+    ///
+    /// The subgraphs fetch is a single paginated query. Defaults to 15 seconds.
+    pub fn with_subgraphs_fetch_timeout(mut self, timeout: Duration) -> Self {
+        self.subgraphs_fetch_timeout = timeout;
+        self
+    }
+
+    /// Sets the indexers and subgraphs information fetches retry configuration.
+    pub fn with_fetch_retry(mut self, retry: RetryConfig) -> Self {
+        self.fetch_retry = retry;
+        self
+    }
+
+    /// Sets the policy applied when only one of the indexers and subgraphs information fetches
+    /// fails. See [`PartialUpdatePolicy`].
+    pub fn with_partial_update_policy(mut self, policy: PartialUpdatePolicy) -> Self {
+        self.partial_update_policy = policy;
+        self
+    }
+
     /// Sets the minimum agent version for indexers.
     pub fn with_indexer_min_agent_version(mut self, version: Version) -> Self {
         self.indexer_min_agent_version = version;
@@ -288,11 +466,96 @@ impl NetworkServiceBuilder {
         self
     }
 
+    /// Sets the minimum graph node version for indexers per deployment manifest network.
+    ///
+    /// Networks without an entry fall back to the minimum graph node version.
+    pub fn with_indexer_min_graph_node_version_per_network(
+        mut self,
+        versions: HashMap<String, Version>,
+    ) -> Self {
+        self.indexer_min_graph_node_version_per_network = versions;
+        self
+    }
+
+    /// Sets the policy applied when an indexer's graph-node version cannot be resolved.
+    ///
+    /// Defaults to [`GraphNodeVersionFallback::AssumeMinimum`].
+    pub fn with_indexer_graph_node_version_fallback(
+        mut self,
+        fallback: GraphNodeVersionFallback,
+    ) -> Self {
+        self.indexer_version_resolver = self
+            .indexer_version_resolver
+            .with_graph_node_version_fallback(fallback);
+        self
+    }
+
+    /// Sets the resolved indexer versions cache time-to-live.
+    ///
+    /// Defaults to 5 minutes.
+    pub fn with_indexer_version_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.indexer_version_resolver = self.indexer_version_resolver.with_cache_ttl(ttl);
+        self
+    }
+
+    /// Enables the per-indexing staleness filter.
+    ///
+    /// The indexings more than `max_blocks_behind` blocks behind their deployment network's chain
+    /// head, as reported by the `chain_heads` source, are dropped. The indexings with an unknown
+    /// chain head are kept.
+    pub fn with_indexer_indexing_staleness_filter(
+        mut self,
+        chain_heads: Arc<dyn ChainHeadSource>,
+        max_blocks_behind: u64,
+    ) -> Self {
+        self.indexer_indexing_staleness_filter = Some(StalenessFilter {
+            chain_heads,
+            max_blocks_behind,
+        });
+        self
+    }
+
+    /// Enables the whole-indexer lag check.
+    ///
+    /// The indexers more than `max_blocks_behind` blocks behind their deployment networks' chain
+    /// heads on all their indexings are filtered-out. The indexings of the indexers within the
+    /// threshold on any indexing are kept, see
+    /// [`with_indexer_indexing_staleness_filter`](Self::with_indexer_indexing_staleness_filter)
+    /// for the per-indexing pruning.
+    pub fn with_indexer_lag_filter(
+        mut self,
+        chain_heads: Arc<dyn ChainHeadSource>,
+        max_blocks_behind: u64,
+    ) -> Self {
+        self.indexer_lag_filter = Some(IndexerLagFilter {
+            chain_heads,
+            max_blocks_behind,
+        });
+        self
+    }
+
     /// Sets the indexer address blocklist.
     pub fn with_indexer_addr_blocklist(mut self, blocklist: HashSet<Address>) -> Self {
         let blocklist = AddrBlocklist::new(blocklist);
 
         self.indexer_addr_blocklist = Some(blocklist);
+        self.indexer_addr_blocklist_reload_interval = None;
+        self
+    }
+
+    /// Sets the indexer address blocklist, backed by the given file.
+    ///
+    /// The file is checked for changes every `reload_interval`, and the blocklist is reloaded
+    /// without restarting the service. See [`AddrBlocklist::from_file`].
+    pub fn with_indexer_addr_blocklist_file(
+        mut self,
+        path: impl Into<PathBuf>,
+        reload_interval: Duration,
+    ) -> Self {
+        let blocklist = AddrBlocklist::from_file(path);
+
+        self.indexer_addr_blocklist = Some(blocklist);
+        self.indexer_addr_blocklist_reload_interval = Some(reload_interval);
         self
     }
 
@@ -304,15 +567,160 @@ impl NetworkServiceBuilder {
         self
     }
 
+    /// Sets the indexer host ASNs blocklist, mapping the indexers' IP addresses to their ASNs
+    /// with the given lookup table.
+    ///
+    /// The ASNs blocklist is checked alongside the host IP networks blocklist, if any.
+    pub fn with_indexer_host_asn_blocklist(
+        mut self,
+        asn_table: AsnTable,
+        blocked_asns: HashSet<u32>,
+    ) -> Self {
+        self.indexer_host_asn_blocklist = Some((asn_table, blocked_asns));
+        self
+    }
+
     /// Sets the indexer POIs blocklist.
     pub fn with_indexer_pois_blocklist(mut self, blocklist: HashSet<ProofOfIndexingInfo>) -> Self {
-        let resolver = PoiResolver::with_timeout(
-            self.indexer_client.clone(),
-            DEFAULT_INDEXER_INDEXING_POIS_RESOLUTION_TIMEOUT, // 5s
-        );
         let blocklist = PoiBlocklist::new(blocklist);
 
-        self.indexer_indexing_pois_blocklist = Some((blocklist, resolver));
+        self.indexer_indexing_pois_blocklist = Some(blocklist);
+        self
+    }
+
+    /// Sets the trusted reference POIs source.
+    ///
+    /// The indexers' POIs are resolved at the source's reference blocks, and the indexings whose
+    /// POIs do not match the reference ones are filtered-out.
+    pub fn with_indexer_poi_reference_source(
+        mut self,
+        source: impl PoiReferenceSource + 'static,
+    ) -> Self {
+        self.indexer_indexing_poi_reference = Some(Arc::new(source));
+        self
+    }
+
+    /// Sets the indexer POIs resolution timeout.
+    ///
+    /// The indexers whose POIs are not resolved within the timeout are filtered-out. Defaults to
+    /// 5 seconds.
+    pub fn with_indexer_pois_resolution_timeout(mut self, timeout: Duration) -> Self {
+        self.indexer_indexing_pois_resolution_timeout = timeout;
+        self
+    }
+
+    /// Sets the policy applied to indexers reporting the same POI for distinct deployments.
+    ///
+    /// The check runs over a sample of each indexer's POIs, independent of the POIs blocklist.
+    pub fn with_indexer_poi_collision_policy(mut self, policy: PoiCollisionPolicy) -> Self {
+        self.indexer_indexing_poi_collision_policy = policy;
+        self
+    }
+
+    /// Sets the operator-defined cost models per deployment.
+    ///
+    /// For these deployments, the override replaces the cost models reported by the indexers.
+    pub fn with_indexer_cost_model_overrides(
+        mut self,
+        overrides: HashMap<DeploymentId, Ptr<CostModel>>,
+    ) -> Self {
+        self.indexer_indexing_cost_model_overrides = overrides;
+        self
+    }
+
+    /// Sets the capacity of the compiled cost models LRU cache.
+    ///
+    /// See [`CostModelCompiler::with_compiled_models_cache_capacity`].
+    pub fn with_indexer_cost_model_compiled_cache_capacity(mut self, capacity: usize) -> Self {
+        self.indexer_indexing_cost_model_compiler = self
+            .indexer_indexing_cost_model_compiler
+            .with_compiled_models_cache_capacity(capacity);
+        self
+    }
+
+    /// Sets the order of the indexers processing pipeline stages.
+    ///
+    /// Stages missing from the list run after the listed ones, in their default order, see
+    /// [`DEFAULT_FILTER_STAGES`].
+    ///
+    /// # Panics
+    /// The function panics if a stage is listed more than once.
+    pub fn with_indexer_filter_stages(mut self, stages: Vec<FilterStage>) -> Self {
+        let mut filter_stages = Vec::with_capacity(DEFAULT_FILTER_STAGES.len());
+        for stage in stages {
+            assert!(
+                !filter_stages.contains(&stage),
+                "duplicated indexer filter stage: {stage:?}"
+            );
+            filter_stages.push(stage);
+        }
+        for stage in DEFAULT_FILTER_STAGES {
+            if !filter_stages.contains(&stage) {
+                filter_stages.push(stage);
+            }
+        }
+
+        self.indexer_filter_stages = filter_stages;
+        self
+    }
+
+    /// Sets the maximum amount of tokens an indexer can have allocated to a deployment.
+    ///
+    /// Indexings above this amount are considered corrupt and are dropped.
+    pub fn with_indexer_max_allocated_tokens(mut self, max_allocated_tokens: u128) -> Self {
+        self.indexer_max_allocated_tokens = max_allocated_tokens;
+        self
+    }
+
+    /// Sets the minimum amount of tokens an indexer must have staked.
+    ///
+    /// Indexers below this amount are filtered-out. Defaults to zero, i.e., no minimum.
+    pub fn with_indexer_min_staked_tokens(mut self, min_staked_tokens: u128) -> Self {
+        self.indexer_min_staked_tokens = min_staked_tokens;
+        self
+    }
+
+    /// Sets the maximum number of indexers processed per network topology update.
+    ///
+    /// If exceeded, only the indexers with the most staked tokens are processed. If not set, all
+    /// indexers are processed.
+    pub fn with_max_indexers(mut self, max_indexers: usize) -> Self {
+        self.max_indexers = Some(max_indexers);
+        self
+    }
+
+    /// Sets the recognized deployment manifest networks.
+    ///
+    /// Deployments whose manifest network is not in the set are dropped. If not set, all networks
+    /// are accepted.
+    pub fn with_known_networks(mut self, networks: HashSet<String>) -> Self {
+        self.known_networks = Some(networks);
+        self
+    }
+
+    /// Sets the set of indexers being drained.
+    ///
+    /// Pass the client query path's handle, i.e., the query context's drain set, so the drains are
+    /// visible to both.
+    pub fn with_indexer_drain_set(mut self, drain_set: DrainSet) -> Self {
+        self.indexer_drain_set = drain_set;
+        self
+    }
+
+    /// Sets the indexer tiers and the multiplier applied per tier.
+    ///
+    /// Indexers absent from the tiers map get tier 0.
+    pub fn with_indexer_tiers(mut self, tiers: HashMap<Address, i32>, multiplier: f64) -> Self {
+        self.indexer_tiers = IndexerTiers::new(tiers, multiplier);
+        self
+    }
+
+    /// Sets the shutdown signal.
+    ///
+    /// Once the signal is set to `true`, the in-progress network topology update is aborted, the
+    /// previous snapshot is kept, and no further updates are run.
+    pub fn with_shutdown_signal(mut self, signal: watch::Receiver<bool>) -> Self {
+        self.shutdown_signal = Some(signal);
         self
     }
 
@@ -320,28 +728,74 @@ impl NetworkServiceBuilder {
     ///
     /// To spawn the [`NetworkService`] instance, call the [`NetworkServicePending::spawn`] method.
     pub fn build(self) -> NetworkServicePending {
+        // The reference POIs and the POI collision check samples are resolved by the POIs
+        // blocklist resolver, so an empty blocklist is set if either is enabled without a blocklist
+        let indexer_indexing_pois_blocklist = self
+            .indexer_indexing_pois_blocklist
+            .or_else(|| {
+                (self.indexer_indexing_poi_reference.is_some()
+                    || self.indexer_indexing_poi_collision_policy != PoiCollisionPolicy::Disabled)
+                    .then(PoiBlocklist::default)
+            })
+            .map(|blocklist| {
+                let resolver = PoiResolver::with_timeout(
+                    self.indexer_client.clone(),
+                    self.indexer_indexing_pois_resolution_timeout,
+                );
+                (blocklist, resolver)
+            });
+        let indexer_host_blocklist = match self.indexer_host_asn_blocklist {
+            Some((asn_table, blocked_asns)) => Some(
+                self.indexer_host_blocklist
+                    .unwrap_or_default()
+                    .with_blocked_asns(asn_table, blocked_asns),
+            ),
+            None => self.indexer_host_blocklist,
+        };
         let internal_state = InternalState {
             indexer_http_client: self.indexer_client,
             indexer_min_agent_version: self.indexer_min_agent_version,
             indexer_min_graph_node_version: self.indexer_min_graph_node_version,
+            indexer_min_graph_node_version_per_network: self
+                .indexer_min_graph_node_version_per_network,
             indexer_addr_blocklist: self.indexer_addr_blocklist,
             indexer_host_resolver: Mutex::new(self.indexer_host_resolver),
-            indexer_host_blocklist: self.indexer_host_blocklist,
+            indexer_host_blocklist,
             indexer_version_resolver: self.indexer_version_resolver,
-            indexer_indexing_pois_blocklist: self
-                .indexer_indexing_pois_blocklist
-                .map(|(bl, res)| (bl, Mutex::new(res))),
+            indexer_indexing_pois_blocklist,
+            indexer_indexing_poi_reference: self.indexer_indexing_poi_reference,
+            indexer_indexing_poi_collision_policy: self.indexer_indexing_poi_collision_policy,
             indexer_indexing_status_resolver: self.indexer_indexing_status_resolver,
+            indexer_indexing_staleness_filter: self.indexer_indexing_staleness_filter,
+            indexer_lag_filter: self.indexer_lag_filter,
             indexer_indexing_cost_model_resolver: (
                 self.indexer_indexing_cost_model_resolver,
                 Mutex::new(self.indexer_indexing_cost_model_compiler),
             ),
+            indexer_indexing_cost_model_overrides: self.indexer_indexing_cost_model_overrides,
+            indexer_filter_stages: self.indexer_filter_stages,
+            indexer_max_allocated_tokens: self.indexer_max_allocated_tokens,
+            indexer_min_staked_tokens: self.indexer_min_staked_tokens,
+            max_indexers: self.max_indexers,
+            known_networks: self.known_networks,
+            indexers_fetch_timeout: self.indexers_fetch_timeout,
+            subgraphs_fetch_timeout: self.subgraphs_fetch_timeout,
+            fetch_retry: self.fetch_retry,
+            update_timeout: self.update_timeout,
+            indexers_processing_deadline: self.indexers_processing_deadline,
+            indexers_processing_concurrency: self.indexers_processing_concurrency,
+            last_fetch_report: Default::default(),
         };
 
         NetworkServicePending {
             subgraph_client: self.subgraph_client,
             internal_state,
+            indexer_drain_set: self.indexer_drain_set,
+            indexer_tiers: self.indexer_tiers,
+            shutdown_signal: self.shutdown_signal,
             update_interval: self.update_interval,
+            partial_update_policy: self.partial_update_policy,
+            indexer_addr_blocklist_reload_interval: self.indexer_addr_blocklist_reload_interval,
         }
     }
 }
@@ -354,19 +808,63 @@ pub struct NetworkServicePending {
     update_interval: Duration,
     subgraph_client: SubgraphClient,
     internal_state: InternalState,
+    indexer_drain_set: DrainSet,
+    indexer_tiers: IndexerTiers,
+    shutdown_signal: Option<watch::Receiver<bool>>,
+    partial_update_policy: PartialUpdatePolicy,
+    indexer_addr_blocklist_reload_interval: Option<Duration>,
 }
 
 impl NetworkServicePending {
+    /// Get the summary of the service's active settings, e.g., for a diagnostics endpoint.
+    pub fn effective_config(&self) -> EffectiveConfig {
+        EffectiveConfig {
+            indexer_drain_set_len: self.indexer_drain_set.len(),
+            indexer_tiers_len: self.indexer_tiers.len(),
+            indexer_tier_multiplier: self.indexer_tiers.multiplier(),
+            partial_update_max_age_ms: match self.partial_update_policy {
+                PartialUpdatePolicy::Disabled => None,
+                PartialUpdatePolicy::ReusePrevious { max_age } => Some(max_age.as_millis()),
+            },
+            ..self.internal_state.effective_config()
+        }
+    }
+
     /// Spawns the [`NetworkService`] instance's background task and returns the service
     /// instance.
     pub fn spawn(self) -> NetworkService {
+        let effective_config = Arc::new(self.effective_config());
+        if let (Some(blocklist), Some(interval)) = (
+            &self.internal_state.indexer_addr_blocklist,
+            self.indexer_addr_blocklist_reload_interval,
+        ) {
+            let mut shutdown_signal = self.shutdown_signal.clone();
+            blocklist.spawn_file_reloader(interval, async move {
+                wait_for_shutdown(shutdown_signal.as_mut()).await
+            });
+        }
+        let indexer_pois_blocklist = self
+            .internal_state
+            .indexer_indexing_pois_blocklist
+            .as_ref()
+            .map(|(blocklist, _)| blocklist.clone());
+        let state = Arc::new(self.internal_state);
         let network = spawn_updater_task(
             self.subgraph_client,
-            self.internal_state,
+            state.clone(),
             self.update_interval,
+            self.partial_update_policy,
+            self.shutdown_signal,
         );
 
-        NetworkService { network }
+        NetworkService {
+            network,
+            indexer_drain_set: self.indexer_drain_set,
+            indexer_tiers: self.indexer_tiers,
+            indexer_pois_blocklist,
+            effective_config,
+            state,
+        }
     }
 }
 
@@ -374,21 +872,75 @@ impl NetworkServicePending {
 /// subgraph at regular intervals
 fn spawn_updater_task(
     subgraph_client: SubgraphClient,
-    state: InternalState,
+    state: Arc<InternalState>,
     update_interval: Duration,
+    partial_update_policy: PartialUpdatePolicy,
+    shutdown_signal: Option<watch::Receiver<bool>>,
 ) -> Eventual<Ptr<NetworkTopologySnapshot>> {
+    let subgraph_client = Arc::new(Mutex::new(subgraph_client));
+    let update_shutdown_signal = shutdown_signal.clone();
+
+    spawn_update_loop(
+        move |previous: Option<Ptr<NetworkTopologySnapshot>>| {
+            let subgraph_client = subgraph_client.clone();
+            let state = state.clone();
+            let mut shutdown_signal = update_shutdown_signal.clone();
+            async move {
+                // On shutdown, the in-progress fetch is aborted, keeping the previous snapshot
+                let shutdown = wait_for_shutdown(shutdown_signal.as_mut());
+                let update = fetch_update_with_cancellation(
+                    &subgraph_client,
+                    &state,
+                    previous.as_deref(),
+                    partial_update_policy,
+                    shutdown,
+                )
+                .await?;
+                anyhow::Ok(update)
+            }
+        },
+        update_interval,
+        shutdown_signal,
+    )
+}
+
+/// Spawn a background task running the network topology `update` every `update_interval`, and
+/// return the eventual holding the latest network topology snapshot.
+///
+/// The `update` gets the latest network topology snapshot, if any.
+///
+/// If an update fails or takes longer than the interval, a warning is logged and the previous
+/// snapshot is kept. Once the shutdown signal is set, no further updates are run.
+fn spawn_update_loop<F, Fut>(
+    mut update: F,
+    update_interval: Duration,
+    mut shutdown_signal: Option<watch::Receiver<bool>>,
+) -> Eventual<Ptr<NetworkTopologySnapshot>>
+where
+    F: FnMut(Option<Ptr<NetworkTopologySnapshot>>) -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<NetworkTopologySnapshot>> + Send,
+{
     let (mut eventual_writer, eventual) = Eventual::new();
 
     tokio::spawn(async move {
-        let subgraph_client = Mutex::new(subgraph_client);
+        let mut latest: Option<Ptr<NetworkTopologySnapshot>> = None;
+        let mut timer = tokio::time::interval(update_interval);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
         loop {
             // Fetch the network topology information every `update_interval` duration
+            tokio::select! { biased;
+                _ = wait_for_shutdown(shutdown_signal.as_mut()) => break,
+                _ = timer.tick() => {}
+            }
+
             // If the fetch fails or takes too long, log a warning and skip the update
             tokio::select! { biased;
-                update = fetch_update(&subgraph_client, &state) => {
+                update = update(latest.clone()) => {
                     match update {
                         Ok(network) => {
-                            eventual_writer.write(Ptr::new(network));
+                            let network = Ptr::new(network);
+                            latest = Some(network.clone());
+                            eventual_writer.write(network);
                         }
                         // If the fetch fails, log a warning and skip the update
                         Err(err) => {
@@ -401,8 +953,227 @@ fn spawn_updater_task(
                     tracing::warn!("network update fetch taking too long");
                 }
             }
+
+            if shutdown_signal
+                .as_ref()
+                .is_some_and(|signal| *signal.borrow())
+            {
+                break;
+            }
         }
     });
 
     eventual
 }
+
+/// Wait until the shutdown signal is set. If no signal is given, or the signal sender is dropped,
+/// wait forever.
+async fn wait_for_shutdown(signal: Option<&mut watch::Receiver<bool>>) {
+    if let Some(signal) = signal {
+        if signal.wait_for(|shutdown| *shutdown).await.is_ok() {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use alloy_primitives::Address;
+    use semver::Version;
+    use thegraph_core::client as subgraph_client;
+
+    use super::{
+        spawn_update_loop, DrainSet, FilterStage, GraphNodeVersionFallback, IndexerTiers,
+        NetworkService, NetworkServiceBuilder, PartialUpdatePolicy, PoiCollisionPolicy,
+        SubgraphClient, TopologyFetchReport,
+    };
+    use crate::network::snapshot;
+
+    #[tokio::test]
+    async fn service_produces_a_snapshot_after_an_update() {
+        //* Given
+        let updates = Arc::new(AtomicUsize::new(0));
+        let network = spawn_update_loop(
+            {
+                let updates = updates.clone();
+                move |_| {
+                    updates.fetch_add(1, Ordering::SeqCst);
+                    async { anyhow::Ok(snapshot::new_from(HashMap::new(), HashMap::new())) }
+                }
+            },
+            Duration::from_secs(60),
+            None,
+        );
+        let service = NetworkService {
+            network,
+            indexer_drain_set: DrainSet::default(),
+            indexer_tiers: IndexerTiers::default(),
+            indexer_pois_blocklist: None,
+            effective_config: Default::default(),
+            state: Arc::new(test_builder().build().internal_state),
+        };
+
+        //* When
+        tokio::time::timeout(Duration::from_secs(1), service.wait_until_ready())
+            .await
+            .expect("network topology snapshot not produced");
+
+        //* Then
+        assert!(service.snapshot().is_some());
+        assert_eq!(updates.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn last_fetch_report_is_read_from_the_service_state() {
+        //* Given
+        let service = test_builder().build().spawn();
+        assert!(service.last_fetch_report().is_none());
+
+        let report = TopologyFetchReport {
+            fetched: 3,
+            addr_blocked: 1,
+            final_count: 2,
+            ..Default::default()
+        };
+
+        //* When
+        *service.state.last_fetch_report.write().unwrap() = Some(report.clone());
+
+        //* Then
+        assert_eq!(service.last_fetch_report(), Some(report));
+    }
+
+    #[tokio::test]
+    async fn effective_config_reports_the_built_settings() {
+        //* Given
+        let auth_token = "secret-network-subgraph-auth-token";
+        let subgraph_client = SubgraphClient::new(
+            subgraph_client::Client::builder(
+                reqwest::Client::new(),
+                "http://localhost:8000/".parse().expect("valid url"),
+            )
+            .with_auth_token(Some(auth_token.to_string()))
+            .build(),
+            true,
+        );
+
+        let service = NetworkServiceBuilder::new(subgraph_client, reqwest::Client::new())
+            .with_indexer_min_agent_version(Version::new(1, 2, 3))
+            .with_indexer_min_graph_node_version_per_network(HashMap::from([(
+                "mainnet".to_string(),
+                Version::new(0, 35, 0),
+            )]))
+            .with_indexer_graph_node_version_fallback(GraphNodeVersionFallback::Block)
+            .with_indexer_version_cache_ttl(Duration::from_secs(60))
+            .with_indexer_addr_blocklist(HashSet::from([
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+            ]))
+            .with_indexer_pois_resolution_timeout(Duration::from_secs(3))
+            .with_indexer_poi_collision_policy(PoiCollisionPolicy::Flag)
+            .with_indexer_cost_model_compiled_cache_capacity(16)
+            .with_indexer_filter_stages(vec![FilterStage::Version])
+            .with_max_indexers(10)
+            .with_known_networks(HashSet::from(["mainnet".to_string()]))
+            .with_indexer_drain_set(DrainSet::new(HashSet::from([Address::repeat_byte(3)])))
+            .with_indexer_tiers(HashMap::from([(Address::repeat_byte(4), 1)]), 2.0)
+            .with_partial_update_policy(PartialUpdatePolicy::ReusePrevious {
+                max_age: Duration::from_secs(600),
+            })
+            .with_update_timeout(Duration::from_secs(5))
+            .build();
+
+        //* When
+        let config = service.effective_config();
+        let config_json = serde_json::to_string(&config).expect("serializable config");
+
+        //* Then
+        assert_eq!(config.indexer_min_agent_version, Version::new(1, 2, 3));
+        assert_eq!(
+            config.indexer_min_graph_node_version_per_network["mainnet"],
+            Version::new(0, 35, 0)
+        );
+        assert_eq!(
+            config.indexer_graph_node_version_fallback,
+            GraphNodeVersionFallback::Block
+        );
+        assert_eq!(config.indexer_version_cache_ttl_ms, 60_000);
+        assert_eq!(config.indexer_addr_blocklist_len, Some(2));
+        assert_eq!(config.indexer_host_blocklist_len, None);
+        assert_eq!(config.indexer_host_blocklist_asn_len, None);
+        // The POIs are resolved to check the collisions, with an empty blocklist
+        assert_eq!(config.indexer_pois_blocklist_len, Some(0));
+        assert_eq!(config.indexer_pois_resolution_timeout_ms, Some(3_000));
+        assert_eq!(
+            config.indexer_poi_collision_policy,
+            PoiCollisionPolicy::Flag
+        );
+        assert_eq!(config.indexer_compiled_cost_models_cache_capacity, Some(16));
+        assert_eq!(config.indexer_drain_set_len, 1);
+        assert_eq!(config.indexer_tiers_len, 1);
+        assert_eq!(config.indexer_filter_stages[0], "version");
+        assert_eq!(config.max_indexers, Some(10));
+        assert_eq!(config.known_networks, Some(vec!["mainnet".to_string()]));
+        assert_eq!(config.partial_update_max_age_ms, Some(600_000));
+        assert_eq!(config.update_timeout_ms, 5_000);
+        assert!(config_json.contains(r#""indexer_poi_collision_policy":"Flag""#));
+
+        // The auth token must not be reported
+        assert!(!config_json.contains(auth_token));
+    }
+
+    /// Test helper to create a network service builder, not connected to any network subgraph.
+    fn test_builder() -> NetworkServiceBuilder {
+        let subgraph_client = SubgraphClient::new(
+            subgraph_client::Client::builder(
+                reqwest::Client::new(),
+                "http://localhost:8000/".parse().expect("valid url"),
+            )
+            .build(),
+            true,
+        );
+        NetworkServiceBuilder::new(subgraph_client, reqwest::Client::new())
+    }
+
+    #[test]
+    fn unlisted_filter_stages_run_after_the_listed_ones() {
+        //* When
+        let builder = test_builder().with_indexer_filter_stages(vec![
+            FilterStage::PoiBlocklist,
+            FilterStage::AddrBlocklist,
+        ]);
+
+        //* Then
+        assert_eq!(
+            builder.indexer_filter_stages,
+            vec![
+                FilterStage::PoiBlocklist,
+                FilterStage::AddrBlocklist,
+                FilterStage::HostBlocklist,
+                FilterStage::Version,
+                FilterStage::IndexingProgress,
+                FilterStage::CostModel,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicated indexer filter stage: Version")]
+    fn duplicated_filter_stages_are_rejected() {
+        test_builder().with_indexer_filter_stages(vec![
+            FilterStage::Version,
+            FilterStage::AddrBlocklist,
+            FilterStage::Version,
+        ]);
+    }
+}
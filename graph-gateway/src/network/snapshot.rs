@@ -5,8 +5,10 @@ use std::{
     fmt::Display,
     ops::Deref,
     sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 
+use alloy_primitives::{keccak256, B256};
 pub use alloy_primitives::{Address, BlockNumber};
 use cost_model::CostModel;
 use custom_debug::CustomDebug;
@@ -15,7 +17,11 @@ use semver::Version;
 pub use thegraph_core::types::{DeploymentId, SubgraphId};
 use url::Url;
 
-use super::internal::types::{DeploymentInfo, IndexerInfo, SubgraphInfo};
+use super::{
+    indexer_indexing_cost_model_compiler::default_fee,
+    indexer_indexing_progress_resolver,
+    internal::types::{DeploymentInfo, IndexerInfo, SubgraphInfo},
+};
 
 /// The minimum indexer agent version required to support Scalar TAP.
 fn min_required_indexer_agent_version_scalar_tap_support() -> &'static Version {
@@ -57,6 +63,36 @@ pub struct Indexing {
     pub status: Option<IndexingStatus>,
     /// The indexer's indexing cost model
     pub cost_model: Option<Ptr<CostModel>>,
+    /// The indexer's indexing minimum fee (in GRT wei), i.e., the cost model's global default fee.
+    ///
+    /// Queries with a budget below this fee should not be sent to the indexer.
+    pub min_fee: Option<u128>,
+}
+
+impl Indexing {
+    /// Get the indexing's Scalar TAP receipt target, i.e., the allocation the receipts are issued
+    /// for and the address receiving the payment.
+    ///
+    /// The network subgraph does not report a dedicated payment address for the indexers, so the
+    /// indexer address is used as the payment address.
+    pub fn receipt_target(&self) -> ReceiptTarget {
+        ReceiptTarget {
+            allocation_id: self.largest_allocation,
+            indexer_address: self.id.indexer,
+            payer_address: self.id.indexer,
+        }
+    }
+}
+
+/// The Scalar TAP receipt target of an indexing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceiptTarget {
+    /// The allocation the receipts are issued for, i.e., the indexing's largest allocation.
+    pub allocation_id: Address,
+    /// The indexer's address.
+    pub indexer_address: Address,
+    /// The address receiving the payment.
+    pub payer_address: Address,
 }
 
 /// The [`IndexingStatus`] struct represents the indexer's indexing status.
@@ -66,6 +102,36 @@ pub struct IndexingStatus {
     pub latest_block: BlockNumber,
     /// The minimum block the indexer has indexed for the deployment.
     pub min_block: Option<BlockNumber>,
+    /// The last time the indexer's latest block was observed advancing, if known.
+    pub last_progress_at: Option<Instant>,
+    /// The latency of the indexer's status request the status was resolved with.
+    pub latency: Duration,
+}
+
+impl IndexingStatus {
+    /// Whether the indexing latest block has not advanced for longer than the given threshold,
+    /// e.g., the indexer's node is stuck even if its latest block looks current.
+    pub fn is_stuck(&self, threshold: Duration) -> bool {
+        indexer_indexing_progress_resolver::is_stuck(self.last_progress_at, threshold)
+    }
+}
+
+/// Check if the indexing can serve queries at the given block number.
+///
+/// The indexing covers the block if the block is within the indexing's indexed range, i.e.,
+/// between its minimum block and its latest block, bounds included. Indexings that pruned their
+/// history (e.g., the minimum block is above the deployment's manifest start block) cannot serve
+/// historical queries below their minimum block. If the indexing has no minimum block, it is
+/// considered to have indexed the deployment from its start block.
+///
+/// Indexings whose status is unknown are considered not to cover any block.
+pub fn indexing_covers_block(indexing: &Indexing, block: BlockNumber) -> bool {
+    match &indexing.status {
+        Some(status) => {
+            status.min_block.unwrap_or(BlockNumber::MIN) <= block && block <= status.latest_block
+        }
+        None => false,
+    }
 }
 
 /// The [`Indexer`] struct represents an indexer in the network topology.
@@ -91,6 +157,8 @@ pub struct Indexer {
 
     /// Whether the indexer supports using Scalar TAP.
     pub scalar_tap_support: bool,
+    /// The features the indexer advertises support for, e.g., SQL queries.
+    pub features: HashSet<String>,
 
     /// The indexer's indexings set.
     ///
@@ -153,6 +221,46 @@ pub struct Deployment {
     pub indexings: HashMap<IndexingId, Indexing>,
 }
 
+impl Deployment {
+    /// Check if at least one of the deployment's indexings is served by an indexer advertising
+    /// support for the given feature.
+    pub fn supports_feature(&self, feature: &str) -> bool {
+        self.indexings
+            .values()
+            .any(|indexing| indexing.indexer.features.contains(feature))
+    }
+
+    /// Get the suggested query timeout for the deployment, i.e., the 95th percentile of the
+    /// latencies observed for its indexings.
+    ///
+    /// If there is no latency data for the deployment, the given default timeout is returned.
+    pub fn suggested_timeout(&self, default: Duration) -> Duration {
+        let mut latencies = self
+            .indexings
+            .values()
+            .filter_map(|indexing| indexing.status.as_ref())
+            .map(|status| status.latency)
+            .collect::<Vec<_>>();
+        if latencies.is_empty() {
+            return default;
+        }
+        latencies.sort_unstable();
+
+        // Nearest-rank percentile
+        let rank = (latencies.len() * 95).div_ceil(100);
+        latencies[rank - 1]
+    }
+
+    /// Get the deployment's indexings that can serve queries at the given block number. See
+    /// [`indexing_covers_block`].
+    pub fn indexings_covering_block(&self, block: BlockNumber) -> Vec<&Indexing> {
+        self.indexings
+            .values()
+            .filter(|indexing| indexing_covers_block(indexing, block))
+            .collect()
+    }
+}
+
 /// A snapshot of the network topology.
 pub struct NetworkTopologySnapshot {
     /// Table holding the subgraph ID of the transferred subgraphs and the L2 subgraph ID.
@@ -164,6 +272,38 @@ pub struct NetworkTopologySnapshot {
     subgraphs: HashMap<SubgraphId, Subgraph>,
     /// Deployments network topology table.
     deployments: HashMap<DeploymentId, Deployment>,
+
+    /// Hash of the snapshot composition. See [`composition_hash`].
+    composition_hash: B256,
+
+    /// The indexers information the snapshot was built from, if retained.
+    indexers_info: Option<FetchedInfo<HashMap<Address, IndexerInfo>>>,
+    /// The subgraphs information the snapshot was built from, if retained.
+    subgraphs_info: Option<FetchedInfo<HashMap<SubgraphId, SubgraphInfo>>>,
+}
+
+/// Network topology information, and the time it was fetched at.
+#[derive(Clone, Debug)]
+pub struct FetchedInfo<T> {
+    /// The fetched information.
+    pub info: T,
+    /// The time the information was fetched at.
+    pub fetched_at: Instant,
+}
+
+impl<T> FetchedInfo<T> {
+    /// Wrap the information, fetched now.
+    pub fn new(info: T) -> Self {
+        Self {
+            info,
+            fetched_at: Instant::now(),
+        }
+    }
+
+    /// Get the time elapsed since the information was fetched.
+    pub fn age(&self) -> Duration {
+        self.fetched_at.elapsed()
+    }
 }
 
 impl NetworkTopologySnapshot {
@@ -191,6 +331,21 @@ impl NetworkTopologySnapshot {
         &self.deployments
     }
 
+    /// Get the deployments whose manifest start block is within the given range, bounds included.
+    ///
+    /// Deployments starting mid-chain are often re-deployments of existing subgraphs. Deployments
+    /// whose manifest has no start block are considered to start at block 0.
+    pub fn deployments_with_min_block_between(
+        &self,
+        lo: BlockNumber,
+        hi: BlockNumber,
+    ) -> Vec<&Deployment> {
+        self.deployments
+            .values()
+            .filter(|deployment| (lo..=hi).contains(&deployment.start_block))
+            .collect()
+    }
+
     /// Get the snapshot transferred subgraphs.
     pub fn transferred_subgraphs(
         &self,
@@ -202,6 +357,73 @@ impl NetworkTopologySnapshot {
     pub fn transferred_deployments(&self) -> impl Deref<Target = HashSet<DeploymentId>> + '_ {
         &self.transferred_deployments
     }
+
+    /// Get the snapshot composition hash.
+    ///
+    /// Snapshots with the same indexers, deployments, and subgraphs have the same composition
+    /// hash, so it tells whether a network topology update changed the topology composition.
+    pub fn composition_hash(&self) -> B256 {
+        self.composition_hash
+    }
+
+    /// Get the indexers information the snapshot was built from.
+    ///
+    /// Used to reuse the previous snapshot's information on partial network topology updates. The
+    /// information is only retained if the snapshot was built with [`new_retaining_info`].
+    pub fn indexers_info(&self) -> Option<&FetchedInfo<HashMap<Address, IndexerInfo>>> {
+        self.indexers_info.as_ref()
+    }
+
+    /// Get the subgraphs information the snapshot was built from.
+    ///
+    /// Used to reuse the previous snapshot's information on partial network topology updates. The
+    /// information is only retained if the snapshot was built with [`new_retaining_info`].
+    pub fn subgraphs_info(&self) -> Option<&FetchedInfo<HashMap<SubgraphId, SubgraphInfo>>> {
+        self.subgraphs_info.as_ref()
+    }
+}
+
+/// Compute the composition hash over the sorted subgraph, deployment, and indexer ID sets.
+///
+/// The hash only depends on the ID sets, not on the tables' iteration order nor on the
+/// indexings' details (e.g., their progress).
+fn composition_hash(
+    subgraphs: &HashMap<SubgraphId, Subgraph>,
+    deployments: &HashMap<DeploymentId, Deployment>,
+) -> B256 {
+    let mut subgraph_ids = subgraphs
+        .keys()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    subgraph_ids.sort_unstable();
+    let mut deployment_ids = deployments
+        .keys()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    deployment_ids.sort_unstable();
+    let mut indexer_ids = deployments
+        .values()
+        .flat_map(|deployment| deployment.indexings.keys())
+        .map(|indexing| indexing.indexer)
+        .collect::<Vec<_>>();
+    indexer_ids.sort_unstable();
+    indexer_ids.dedup();
+
+    // Each ID set is prefixed with its length and each ID is newline-terminated, so IDs can't
+    // shift between sets nor merge with each other
+    let mut buf = Vec::new();
+    for ids in [&subgraph_ids, &deployment_ids] {
+        buf.extend_from_slice(&(ids.len() as u64).to_be_bytes());
+        for id in ids {
+            buf.extend_from_slice(id.as_bytes());
+            buf.push(b'\n');
+        }
+    }
+    buf.extend_from_slice(&(indexer_ids.len() as u64).to_be_bytes());
+    for id in &indexer_ids {
+        buf.extend_from_slice(id.as_slice());
+    }
+    keccak256(buf)
 }
 
 /// Construct the [`NetworkTopologySnapshot`] from the indexers and subgraphs information.
@@ -237,6 +459,7 @@ pub fn new_from(
                     indexer_agent_version: indexer.indexer_agent_version.clone(),
                     graph_node_version: indexer.graph_node_version.clone(),
                     scalar_tap_support: indexer_scalar_tap_support,
+                    features: indexer.features.clone(),
                     indexings: indexer.deployments.iter().copied().collect(),
                     staked_tokens: indexer.staked_tokens,
                 }),
@@ -343,12 +566,17 @@ pub fn new_from(
                                 .map(|status| IndexingStatus {
                                     latest_block: status.latest_block,
                                     min_block: status.min_block,
+                                    last_progress_at: status.last_progress_at,
+                                    latency: status.latency,
                                 });
 
                             let indexing_cost_model = indexing_indexer_info
                                 .indexings_cost_model
                                 .get(&deployment_id)
                                 .cloned();
+                            let indexing_min_fee = indexing_cost_model
+                                .as_ref()
+                                .and_then(|model| default_fee(model));
 
                             let indexing_id = IndexingId {
                                 indexer: indexing_indexer_id,
@@ -362,6 +590,7 @@ pub fn new_from(
                                 indexer: indexing_indexer.clone(),
                                 status: indexing_status,
                                 cost_model: indexing_cost_model,
+                                min_fee: indexing_min_fee,
                             };
                             Some((indexing_id, indexing))
                         })
@@ -404,7 +633,8 @@ pub fn new_from(
 
             let deployment_versions_behind = 0;
             let deployment_manifest_chain = deployment.manifest_network?.clone();
-            let deployment_manifest_start_block = deployment.manifest_start_block?;
+            // If the manifest has no start block, the deployment starts at the genesis block
+            let deployment_manifest_start_block = deployment.manifest_start_block.unwrap_or(0);
 
             let deployment_indexings = deployment
                 .allocations
@@ -438,12 +668,17 @@ pub fn new_from(
                         .map(|status| IndexingStatus {
                             latest_block: status.latest_block,
                             min_block: status.min_block,
+                            last_progress_at: status.last_progress_at,
+                            latency: status.latency,
                         });
 
                     let indexing_cost_model = indexing_indexer_info
                         .indexings_cost_model
                         .get(&deployment_id)
                         .cloned();
+                    let indexing_min_fee = indexing_cost_model
+                        .as_ref()
+                        .and_then(|model| default_fee(model));
 
                     let indexing_id = IndexingId {
                         indexer: indexing_indexer_id,
@@ -457,6 +692,7 @@ pub fn new_from(
                         indexer: indexing_indexer.clone(),
                         status: indexing_status,
                         cost_model: indexing_cost_model,
+                        min_fee: indexing_min_fee,
                     };
                     Some((indexing_id, indexing))
                 })
@@ -492,14 +728,37 @@ pub fn new_from(
         })
         .collect();
 
+    let composition_hash = composition_hash(&subgraphs, &deployments);
+    tracing::info!(
+        %composition_hash,
+        subgraphs = subgraphs.len(),
+        deployments = deployments.len(),
+        "network topology snapshot built"
+    );
+
     NetworkTopologySnapshot {
         transferred_subgraphs,
         transferred_deployments,
         deployments,
         subgraphs,
+        composition_hash,
+        indexers_info: None,
+        subgraphs_info: None,
     }
 }
 
+/// Construct the [`NetworkTopologySnapshot`] from the indexers and subgraphs information,
+/// retaining the information to be reused on partial network topology updates.
+pub fn new_retaining_info(
+    indexers_info: FetchedInfo<HashMap<Address, IndexerInfo>>,
+    subgraphs_info: FetchedInfo<HashMap<SubgraphId, SubgraphInfo>>,
+) -> NetworkTopologySnapshot {
+    let mut snapshot = new_from(indexers_info.info.clone(), subgraphs_info.info.clone());
+    snapshot.indexers_info = Some(indexers_info);
+    snapshot.subgraphs_info = Some(subgraphs_info);
+    snapshot
+}
+
 /// Extracts from the subgraphs info table the subgraph IDs that:
 /// - All its versions-deployments are marked as transferred to L2.
 /// - All its versions-deployments have no allocations.
@@ -543,3 +802,272 @@ fn construct_transferred_deployments_table(
         })
         .collect::<HashSet<_>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use alloy_primitives::Address;
+    use semver::Version;
+    use thegraph_core::types::DeploymentId;
+
+    use super::{
+        composition_hash, indexing_covers_block, Deployment, Indexer, Indexing, IndexingId,
+        IndexingStatus, NetworkTopologySnapshot, ReceiptTarget,
+    };
+
+    /// Test utility function to create a valid `DeploymentId` with an arbitrary deployment ID/ipfs hash.
+    fn test_deployment_id(deployment: &str) -> DeploymentId {
+        deployment.parse().expect("invalid deployment id/ipfs hash")
+    }
+
+    /// Test utility function to create an indexing of the given deployment.
+    fn test_indexing(indexer: u8, deployment: DeploymentId, features: &[&str]) -> Indexing {
+        let indexer = Arc::new(Indexer {
+            id: Address::repeat_byte(indexer),
+            url: format!("https://indexer-{indexer}.example.com/")
+                .parse()
+                .expect("valid url"),
+            indexer_agent_version: Version::new(1, 0, 0),
+            graph_node_version: Version::new(0, 34, 0),
+            scalar_tap_support: true,
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            indexings: HashSet::from([deployment]),
+            staked_tokens: 100_000,
+        });
+
+        Indexing {
+            id: IndexingId {
+                indexer: indexer.id,
+                deployment,
+            },
+            versions_behind: 0,
+            largest_allocation: Address::repeat_byte(indexer.id[0]),
+            total_allocated_tokens: 1_000,
+            indexer,
+            status: None,
+            cost_model: None,
+            min_fee: None,
+        }
+    }
+
+    /// Test utility function to create a deployment with the given indexings.
+    fn test_deployment(id: DeploymentId, indexings: Vec<Indexing>) -> Deployment {
+        Deployment {
+            id,
+            chain: "mainnet".to_string(),
+            start_block: 0,
+            subgraphs: HashSet::new(),
+            indexings: indexings
+                .into_iter()
+                .map(|indexing| (indexing.id, indexing))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn deployment_supports_feature_if_any_indexer_advertises_it() {
+        //* Given
+        let deployment_id = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment = test_deployment(
+            deployment_id,
+            vec![
+                test_indexing(1, deployment_id, &[]),
+                test_indexing(2, deployment_id, &["sql"]),
+                test_indexing(3, deployment_id, &[]),
+            ],
+        );
+
+        //* Then
+        assert!(deployment.supports_feature("sql"));
+        assert!(!deployment.supports_feature("full-text-search"));
+    }
+
+    #[test]
+    fn suggested_timeout_is_the_p95_of_the_observed_latencies() {
+        //* Given
+        let deployment_id = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let indexings = (1..=20)
+            .map(|indexer| {
+                let mut indexing = test_indexing(indexer, deployment_id, &[]);
+                indexing.status = Some(IndexingStatus {
+                    latest_block: 100,
+                    min_block: None,
+                    last_progress_at: None,
+                    latency: Duration::from_millis(10 * indexer as u64),
+                });
+                indexing
+            })
+            .collect();
+        let deployment = test_deployment(deployment_id, indexings);
+
+        let no_data_deployment = test_deployment(
+            deployment_id,
+            vec![
+                test_indexing(1, deployment_id, &[]),
+                test_indexing(2, deployment_id, &[]),
+            ],
+        );
+
+        let default = Duration::from_secs(30);
+
+        //* When
+        let timeout = deployment.suggested_timeout(default);
+        let no_data_timeout = no_data_deployment.suggested_timeout(default);
+
+        //* Then
+        assert_eq!(timeout, Duration::from_millis(190));
+        assert_eq!(no_data_timeout, default);
+    }
+
+    #[test]
+    fn deployments_are_filtered_by_start_block_range() {
+        //* Given
+        let genesis = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let mid_chain = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let recent = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+
+        let deployments = [(genesis, 0), (mid_chain, 1_000), (recent, 5_000)]
+            .into_iter()
+            .map(|(id, start_block)| {
+                let mut deployment = test_deployment(id, vec![]);
+                deployment.start_block = start_block;
+                (id, deployment)
+            })
+            .collect::<HashMap<_, _>>();
+        let snapshot = NetworkTopologySnapshot {
+            transferred_subgraphs: HashMap::new(),
+            transferred_deployments: HashSet::new(),
+            composition_hash: composition_hash(&HashMap::new(), &deployments),
+            subgraphs: HashMap::new(),
+            deployments,
+            indexers_info: None,
+            subgraphs_info: None,
+        };
+
+        //* When
+        let mid_chain_range = snapshot.deployments_with_min_block_between(1, 1_000);
+        let genesis_range = snapshot.deployments_with_min_block_between(0, 0);
+        let empty_range = snapshot.deployments_with_min_block_between(5_001, u64::MAX);
+
+        //* Then
+        let ids = |deployments: Vec<&Deployment>| {
+            deployments
+                .into_iter()
+                .map(|deployment| deployment.id)
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(ids(mid_chain_range), HashSet::from([mid_chain]));
+        assert_eq!(ids(genesis_range), HashSet::from([genesis]));
+        assert!(empty_range.is_empty());
+    }
+
+    #[test]
+    fn indexings_are_filtered_by_the_covered_blocks() {
+        //* Given
+        let deployment_id = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let indexings = [
+            (1, Some(None)),
+            (2, Some(Some(500))),
+            (3, Some(Some(2_000))),
+            (4, None),
+        ]
+        .into_iter()
+        .map(|(indexer, min_block)| {
+            let mut indexing = test_indexing(indexer, deployment_id, &[]);
+            indexing.status = min_block.map(|min_block| IndexingStatus {
+                latest_block: 10_000,
+                min_block,
+                last_progress_at: None,
+                latency: Duration::from_millis(100),
+            });
+            indexing
+        })
+        .collect::<Vec<_>>();
+        let deployment = test_deployment(deployment_id, indexings.clone());
+
+        //* When
+        let covering_historical = deployment.indexings_covering_block(1_000);
+        let covering_recent = deployment.indexings_covering_block(5_000);
+        let covering_future = deployment.indexings_covering_block(10_001);
+
+        //* Then
+        let indexers = |indexings: Vec<&Indexing>| {
+            indexings
+                .into_iter()
+                .map(|indexing| indexing.id.indexer)
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(
+            indexers(covering_historical),
+            HashSet::from([Address::repeat_byte(1), Address::repeat_byte(2)])
+        );
+        assert_eq!(
+            indexers(covering_recent),
+            HashSet::from([
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+                Address::repeat_byte(3)
+            ])
+        );
+        assert!(covering_future.is_empty());
+
+        // The min block bound is inclusive, and unknown statuses cover nothing
+        assert!(indexing_covers_block(&indexings[2], 2_000));
+        assert!(!indexing_covers_block(&indexings[2], 1_999));
+        assert!(!indexing_covers_block(&indexings[3], 5_000));
+    }
+
+    #[test]
+    fn indexing_receipt_target() {
+        //* Given
+        let deployment_id = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let mut indexing = test_indexing(1, deployment_id, &[]);
+        indexing.largest_allocation = Address::repeat_byte(0xaa);
+
+        //* When
+        let target = indexing.receipt_target();
+
+        //* Then
+        assert_eq!(
+            target,
+            ReceiptTarget {
+                allocation_id: Address::repeat_byte(0xaa),
+                indexer_address: Address::repeat_byte(1),
+                payer_address: Address::repeat_byte(1),
+            }
+        );
+    }
+
+    #[test]
+    fn composition_hash_depends_only_on_the_composition() {
+        //* Given
+        let deployment_a = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_b = test_deployment_id("QmdveVMs7nAvdBPxNoaMMAYgNcuSroneMctZDnZUgbPPP3");
+        let deployments = |indexers: &[u8]| {
+            [deployment_a, deployment_b]
+                .into_iter()
+                .map(|id| {
+                    let indexings = indexers
+                        .iter()
+                        .map(|indexer| test_indexing(*indexer, id, &[]))
+                        .collect();
+                    (id, test_deployment(id, indexings))
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        //* When
+        let hash = composition_hash(&HashMap::new(), &deployments(&[1, 2]));
+        let same_hash = composition_hash(&HashMap::new(), &deployments(&[2, 1]));
+        let changed_hash = composition_hash(&HashMap::new(), &deployments(&[1, 2, 3]));
+
+        //* Then
+        assert_eq!(hash, same_hash);
+        assert_ne!(hash, changed_hash);
+    }
+}
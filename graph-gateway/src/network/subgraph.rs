@@ -109,6 +109,12 @@ pub mod types {
         pub struct SubgraphDeployment {
             #[serde(rename = "ipfsHash")]
             pub id: DeploymentId,
+            pub manifest: Option<Manifest>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        pub struct Manifest {
+            pub network: Option<String>,
         }
     }
 }
@@ -235,6 +241,9 @@ impl Client {
                     allocatedTokens
                     subgraphDeployment {
                         ipfsHash
+                        manifest {
+                            network
+                        }
                     }
                 }
             }
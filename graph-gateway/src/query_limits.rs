@@ -0,0 +1,930 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use cost_model::Context;
+use gateway_framework::errors::Error;
+use graphql::graphql_parser::query::{
+    Directive, FragmentDefinition, OperationDefinition, Selection, SelectionSet,
+};
+use serde::Deserialize;
+
+use crate::{
+    query_validator::QueryValidator,
+    sql_constraints::{operation_selection_set, SqlFieldBehavior},
+};
+
+/// Limits applied to the client queries' GraphQL documents.
+///
+/// All limits are disabled by default.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct QueryLimits {
+    /// Maximum number of `__typename` selections across the whole query document.
+    #[serde(default)]
+    pub max_typename_selections: Option<usize>,
+    /// Reject queries with empty selection sets, i.e., operations selecting no fields.
+    #[serde(default)]
+    pub reject_empty_selections: bool,
+    /// Allowed GraphQL directives, besides `@skip` and `@include`. If not set, all directives are
+    /// allowed.
+    #[serde(default)]
+    pub allowed_directives: Option<HashSet<String>>,
+    /// Collapse the exact duplicate sibling selections before forwarding the query to the
+    /// indexers. See [`dedupe_selections`].
+    #[serde(default)]
+    pub dedupe_selections: bool,
+    /// Field names rejected at any depth of the query, besides `sql`. See
+    /// [`validate_banned_fields`](crate::sql_constraints::validate_banned_fields).
+    #[serde(default)]
+    pub banned_fields: HashSet<String>,
+    /// Maximum nesting depth of the query fields. See [`validate_query_depth`].
+    #[serde(default)]
+    pub max_query_depth: Option<usize>,
+    /// Maximum number of selected fields across the query operations. See
+    /// [`validate_query_field_count`].
+    #[serde(default)]
+    pub max_field_count: Option<usize>,
+}
+
+impl QueryLimits {
+    /// Create the client queries preflight validator: the SQL fields and the ambiguous operations
+    /// are rejected, then the configured limits are checked.
+    pub fn validator(&self) -> QueryValidator {
+        let mut builder = QueryValidator::builder()
+            .with_sql_fields(SqlFieldBehavior::RejectSql)
+            .with_unique_operation_names();
+        if let Some(max) = self.max_typename_selections {
+            builder = builder.with_max_typename_selections(max);
+        }
+        if self.reject_empty_selections {
+            builder = builder.with_nonempty_selections();
+        }
+        if let Some(allowed) = &self.allowed_directives {
+            builder = builder.with_allowed_directives(allowed.clone());
+        }
+        if !self.banned_fields.is_empty() {
+            builder = builder.with_banned_fields(self.banned_fields.clone());
+        }
+        if let Some(max) = self.max_query_depth {
+            builder = builder.with_max_query_depth(max);
+        }
+        if let Some(max) = self.max_field_count {
+            builder = builder.with_max_field_count(max);
+        }
+        builder.build()
+    }
+}
+
+/// Reject the query if the number of `__typename` selections across the document, i.e., all
+/// operations and fragment definitions, exceeds the given maximum.
+pub fn check_typename_selections(ctx: &Context, max: usize) -> Result<(), Error> {
+    let count = document_selection_sets(ctx)
+        .map(|selection_set| count_field_selections(selection_set, "__typename"))
+        .sum::<usize>();
+    if count > max {
+        return Err(Error::BadQuery(anyhow!(
+            "query contains {count} `__typename` selections, the maximum is {max}"
+        )));
+    }
+    Ok(())
+}
+
+/// Reject the query if any operation or fragment definition selects no fields.
+///
+/// Inline fragments are walked too. Field selection sets are not checked, as leaf fields have
+/// empty selection sets.
+pub fn check_nonempty_selections(ctx: &Context) -> Result<(), Error> {
+    let has_empty_selection_set = document_selection_sets(ctx).any(|selection_set| {
+        selection_set.items.is_empty() || has_empty_inline_fragment(selection_set)
+    });
+    if has_empty_selection_set {
+        return Err(Error::BadQuery(anyhow!(
+            "query contains an empty selection set"
+        )));
+    }
+    Ok(())
+}
+
+/// Reject the query if the nesting depth of any operation's fields exceeds the given maximum.
+///
+/// The top-level fields are at depth 1. Inline fragments do not add depth, and fragment spreads
+/// are expanded in place, so the depth cannot be hidden in fragments.
+pub fn validate_query_depth(ctx: &Context, max_depth: usize) -> Result<(), Error> {
+    let mut fragments_depth = HashMap::new();
+    let depth = ctx
+        .operations
+        .iter()
+        .map(|operation| {
+            selection_set_depth(
+                operation_selection_set(operation),
+                &ctx.fragments,
+                &mut fragments_depth,
+            )
+        })
+        .max()
+        .unwrap_or(0);
+    if depth > max_depth {
+        return Err(Error::BadQuery(anyhow!(
+            "query depth is {depth}, the maximum is {max_depth}"
+        )));
+    }
+    Ok(())
+}
+
+/// Reject the query if the total number of selected fields across all operations exceeds the given
+/// maximum.
+///
+/// Every field selection counts, including aliased selections of the same field. Fragment spreads
+/// are expanded in place, i.e., a fragment's fields count once per spread, so the limit cannot be
+/// evaded by moving fields into fragments.
+pub fn validate_query_field_count(ctx: &Context, max_fields: usize) -> Result<(), Error> {
+    let mut fragments_field_count = HashMap::new();
+    let count = ctx
+        .operations
+        .iter()
+        .map(|operation| {
+            selection_set_field_count(
+                operation_selection_set(operation),
+                &ctx.fragments,
+                &mut fragments_field_count,
+            )
+        })
+        .fold(0, usize::saturating_add);
+    if count > max_fields {
+        return Err(Error::BadQuery(anyhow!(
+            "query contains {count} field selections, the maximum is {max_fields}"
+        )));
+    }
+    Ok(())
+}
+
+/// Reject the ambiguous query documents, invalid per the GraphQL spec:
+///  - Documents with more than one operation, if any of them is anonymous.
+///  - Documents with multiple operations of the same name.
+pub fn check_operation_names(ctx: &Context) -> Result<(), Error> {
+    let names = ctx
+        .operations
+        .iter()
+        .map(|operation| match operation {
+            OperationDefinition::SelectionSet(_) => None,
+            OperationDefinition::Query(query) => query.name,
+            OperationDefinition::Mutation(mutation) => mutation.name,
+            OperationDefinition::Subscription(subscription) => subscription.name,
+        })
+        .collect::<Vec<_>>();
+
+    if names.len() > 1 && names.iter().any(Option::is_none) {
+        return Err(Error::BadQuery(anyhow!(
+            "query contains an anonymous operation along with other operations"
+        )));
+    }
+    let mut seen = HashSet::new();
+    if let Some(name) = names.into_iter().flatten().find(|name| !seen.insert(*name)) {
+        return Err(Error::BadQuery(anyhow!(
+            "query contains multiple operations named `{name}`"
+        )));
+    }
+    Ok(())
+}
+
+/// The directives defined by the GraphQL spec, always allowed.
+const BUILTIN_DIRECTIVES: [&str; 2] = ["skip", "include"];
+
+/// Reject the query if it uses any directive outside the allowed set.
+///
+/// The `@skip` and `@include` directives are always allowed. The directives of the operations,
+/// fields, fragment definitions, fragment spreads, and inline fragments are checked.
+pub fn check_directives(ctx: &Context, allowed: &HashSet<String>) -> Result<(), Error> {
+    let operations_directives = ctx.operations.iter().flat_map(|operation| match operation {
+        OperationDefinition::SelectionSet(_) => [].as_slice(),
+        OperationDefinition::Query(query) => query.directives.as_slice(),
+        OperationDefinition::Mutation(mutation) => mutation.directives.as_slice(),
+        OperationDefinition::Subscription(subscription) => subscription.directives.as_slice(),
+    });
+    let fragments_directives = ctx
+        .fragments
+        .iter()
+        .flat_map(|fragment| &fragment.directives);
+    let mut selections_directives = Vec::new();
+    for selection_set in document_selection_sets(ctx) {
+        collect_directives(selection_set, &mut selections_directives);
+    }
+
+    let disallowed = operations_directives
+        .chain(fragments_directives)
+        .chain(selections_directives)
+        .find(|directive| {
+            !BUILTIN_DIRECTIVES.contains(&directive.name) && !allowed.contains(directive.name)
+        });
+    if let Some(directive) = disallowed {
+        return Err(Error::BadQuery(anyhow!(
+            "query contains the directive `@{}`, which is not allowed",
+            directive.name
+        )));
+    }
+    Ok(())
+}
+
+/// Collect the directives of the selections within the selection set, at any depth.
+fn collect_directives<'c, 'q>(
+    selection_set: &'c SelectionSet<'q, &'q str>,
+    directives: &mut Vec<&'c Directive<'q, &'q str>>,
+) {
+    for selection in &selection_set.items {
+        match selection {
+            Selection::Field(field) => {
+                directives.extend(&field.directives);
+                collect_directives(&field.selection_set, directives);
+            }
+            Selection::FragmentSpread(spread) => directives.extend(&spread.directives),
+            Selection::InlineFragment(fragment) => {
+                directives.extend(&fragment.directives);
+                collect_directives(&fragment.selection_set, directives);
+            }
+        }
+    }
+}
+
+/// Remove the exact duplicate sibling selections from the query document, i.e., the selections
+/// with the same name, alias, arguments, directives, and sub-selection.
+///
+/// Duplicate selections inflate the indexer's work and response without changing the result.
+/// Selections differing in any of the above, e.g., an aliased duplicate `b: a`, are preserved.
+pub fn dedupe_selections(mut ctx: Context) -> Context {
+    for operation in &mut ctx.operations {
+        let selection_set = match operation {
+            OperationDefinition::SelectionSet(selection_set) => selection_set,
+            OperationDefinition::Query(query) => &mut query.selection_set,
+            OperationDefinition::Mutation(mutation) => &mut mutation.selection_set,
+            OperationDefinition::Subscription(subscription) => &mut subscription.selection_set,
+        };
+        dedupe_selection_set(selection_set);
+    }
+    for fragment in &mut ctx.fragments {
+        dedupe_selection_set(&mut fragment.selection_set);
+    }
+    ctx
+}
+
+/// Remove the exact duplicate selections within the selection set, at any depth.
+///
+/// The nested selection sets are deduplicated first, so selections that only differ in their
+/// duplicate sub-selections are collapsed too. The first occurrence is kept.
+fn dedupe_selection_set<'q>(selection_set: &mut SelectionSet<'q, &'q str>) {
+    for selection in &mut selection_set.items {
+        match selection {
+            Selection::Field(field) => dedupe_selection_set(&mut field.selection_set),
+            Selection::InlineFragment(fragment) => {
+                dedupe_selection_set(&mut fragment.selection_set)
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+
+    // The selections' source positions differ, so they are compared by their serialization
+    let mut seen = HashSet::new();
+    selection_set.items.retain(|selection| {
+        let key = match selection {
+            Selection::Field(field) => field.to_string(),
+            Selection::FragmentSpread(spread) => spread.to_string(),
+            Selection::InlineFragment(fragment) => fragment.to_string(),
+        };
+        seen.insert(key)
+    });
+}
+
+/// Check if any inline fragment within the selection set, at any depth, is empty.
+fn has_empty_inline_fragment<'q>(selection_set: &SelectionSet<'q, &'q str>) -> bool {
+    selection_set.items.iter().any(|selection| match selection {
+        Selection::Field(field) => has_empty_inline_fragment(&field.selection_set),
+        Selection::InlineFragment(fragment) => {
+            fragment.selection_set.items.is_empty()
+                || has_empty_inline_fragment(&fragment.selection_set)
+        }
+        Selection::FragmentSpread(_) => false,
+    })
+}
+
+/// Get the maximum nesting depth of the fields within the selection set, expanding the fragment
+/// spreads.
+///
+/// The fragments' depths are memoized, so each fragment definition is walked once. Cyclic and
+/// undefined fragment spreads add no depth.
+fn selection_set_depth<'q>(
+    selection_set: &SelectionSet<'q, &'q str>,
+    fragments: &[FragmentDefinition<'q, &'q str>],
+    fragments_depth: &mut HashMap<&'q str, usize>,
+) -> usize {
+    selection_set
+        .items
+        .iter()
+        .map(|selection| match selection {
+            Selection::Field(field) => {
+                1 + selection_set_depth(&field.selection_set, fragments, fragments_depth)
+            }
+            Selection::InlineFragment(fragment) => {
+                selection_set_depth(&fragment.selection_set, fragments, fragments_depth)
+            }
+            Selection::FragmentSpread(spread) => {
+                if let Some(depth) = fragments_depth.get(spread.fragment_name) {
+                    return *depth;
+                }
+                // Mark the fragment as visited, so cyclic spreads terminate
+                fragments_depth.insert(spread.fragment_name, 0);
+                let depth = fragments
+                    .iter()
+                    .find(|fragment| fragment.name == spread.fragment_name)
+                    .map(|fragment| {
+                        selection_set_depth(&fragment.selection_set, fragments, fragments_depth)
+                    })
+                    .unwrap_or(0);
+                fragments_depth.insert(spread.fragment_name, depth);
+                depth
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Count the field selections within the selection set, at any depth, expanding the fragment
+/// spreads.
+///
+/// The fragments' counts are memoized, so each fragment definition is walked once. Cyclic and
+/// undefined fragment spreads count no fields. The counts saturate, as nested spreads can expand
+/// exponentially.
+fn selection_set_field_count<'q>(
+    selection_set: &SelectionSet<'q, &'q str>,
+    fragments: &[FragmentDefinition<'q, &'q str>],
+    fragments_field_count: &mut HashMap<&'q str, usize>,
+) -> usize {
+    selection_set
+        .items
+        .iter()
+        .map(|selection| match selection {
+            Selection::Field(field) => 1_usize.saturating_add(selection_set_field_count(
+                &field.selection_set,
+                fragments,
+                fragments_field_count,
+            )),
+            Selection::InlineFragment(fragment) => {
+                selection_set_field_count(&fragment.selection_set, fragments, fragments_field_count)
+            }
+            Selection::FragmentSpread(spread) => {
+                if let Some(count) = fragments_field_count.get(spread.fragment_name) {
+                    return *count;
+                }
+                // Mark the fragment as visited, so cyclic spreads terminate
+                fragments_field_count.insert(spread.fragment_name, 0);
+                let count = fragments
+                    .iter()
+                    .find(|fragment| fragment.name == spread.fragment_name)
+                    .map(|fragment| {
+                        selection_set_field_count(
+                            &fragment.selection_set,
+                            fragments,
+                            fragments_field_count,
+                        )
+                    })
+                    .unwrap_or(0);
+                fragments_field_count.insert(spread.fragment_name, count);
+                count
+            }
+        })
+        .fold(0, usize::saturating_add)
+}
+
+/// Iterate over the top-level selection sets of the query document operations and fragment
+/// definitions.
+fn document_selection_sets<'c, 'q>(
+    ctx: &'c Context<'q>,
+) -> impl Iterator<Item = &'c SelectionSet<'q, &'q str>> {
+    let operations = ctx.operations.iter().map(operation_selection_set);
+    let fragments = ctx.fragments.iter().map(|fragment| &fragment.selection_set);
+    operations.chain(fragments)
+}
+
+/// Count the field selections with the given name in the selection set, at any depth.
+///
+/// Fragment spreads are not followed, the fragment definitions are counted on their own.
+fn count_field_selections<'q>(selection_set: &SelectionSet<'q, &'q str>, name: &str) -> usize {
+    selection_set
+        .items
+        .iter()
+        .map(|selection| match selection {
+            Selection::Field(field) => {
+                usize::from(field.name == name) + count_field_selections(&field.selection_set, name)
+            }
+            Selection::InlineFragment(fragment) => {
+                count_field_selections(&fragment.selection_set, name)
+            }
+            Selection::FragmentSpread(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_context(query: &str) -> Context {
+        let variables = r#"{}"#;
+        Context::new(query, variables).unwrap()
+    }
+
+    #[test]
+    fn test_typename_selections_below_limit() {
+        let query = r#"
+            query {
+                users {
+                    __typename
+                    id
+                    name
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_typename_selections(&ctx, 3).is_ok());
+    }
+
+    #[test]
+    fn test_typename_selections_above_limit() {
+        let query = r#"
+            query {
+                __typename
+                users {
+                    __typename
+                    id
+                    tokens {
+                        __typename
+                        id
+                    }
+                    ...UserFields
+                }
+            }
+
+            fragment UserFields on User {
+                __typename
+                name
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_typename_selections(&ctx, 3).is_err());
+    }
+
+    #[test]
+    fn test_typename_selections_limit_disabled_by_default() {
+        let query = r#"
+            query {
+                __typename
+                users {
+                    __typename
+                    tokens {
+                        __typename
+                    }
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(QueryLimits::default()
+            .validator()
+            .validate(query, &ctx)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_nonempty_selections_valid_query() {
+        let query = r#"
+            query {
+                users {
+                    id
+                    ... on User {
+                        name
+                    }
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_nonempty_selections(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_nonempty_selections_fragment_spread_only_selection() {
+        let query = r#"
+            query {
+                ...QueryFields
+            }
+
+            fragment QueryFields on Query {
+                users {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_nonempty_selections(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_nonempty_selections_empty_top_level_selection() {
+        let query = r#"
+            query {
+                users {
+                    id
+                }
+            }
+        "#;
+        let mut ctx = create_context(query);
+
+        // Empty the operation's selection set after parsing, as the parser may reject it
+        let OperationDefinition::Query(operation) = &mut ctx.operations[0] else {
+            panic!("expected a query operation");
+        };
+        operation.selection_set.items.clear();
+
+        assert!(matches!(
+            check_nonempty_selections(&ctx),
+            Err(Error::BadQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_nonempty_selections_empty_inline_fragment() {
+        let query = r#"
+            query {
+                users {
+                    id
+                    ... on User {
+                        name
+                    }
+                }
+            }
+        "#;
+        let mut ctx = create_context(query);
+
+        // Empty the inline fragment's selection set after parsing, as the parser may reject it
+        let OperationDefinition::Query(operation) = &mut ctx.operations[0] else {
+            panic!("expected a query operation");
+        };
+        let Selection::Field(users) = &mut operation.selection_set.items[0] else {
+            panic!("expected a field selection");
+        };
+        let Selection::InlineFragment(fragment) = &mut users.selection_set.items[1] else {
+            panic!("expected an inline fragment");
+        };
+        fragment.selection_set.items.clear();
+
+        assert!(matches!(
+            check_nonempty_selections(&ctx),
+            Err(Error::BadQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_directives_disallowed_stream() {
+        let query = r#"
+            query {
+                users @stream(initialCount: 1) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        let result = check_directives(&ctx, &HashSet::new());
+        assert!(matches!(result, Err(Error::BadQuery(err)) if err.to_string().contains("@stream")));
+    }
+
+    #[test]
+    fn test_directives_disallowed_in_fragments() {
+        let query = r#"
+            query {
+                users {
+                    ...UserFields
+                    ... on User {
+                        name @defer
+                    }
+                }
+            }
+
+            fragment UserFields on User {
+                id
+            }
+        "#;
+        let ctx = create_context(query);
+        let result = check_directives(&ctx, &HashSet::from(["stream".to_string()]));
+        assert!(matches!(result, Err(Error::BadQuery(err)) if err.to_string().contains("@defer")));
+    }
+
+    #[test]
+    fn test_directives_skip_and_include_always_allowed() {
+        let query = r#"
+            query {
+                users @skip(if: false) {
+                    id @include(if: true)
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_directives(&ctx, &HashSet::new()).is_ok());
+    }
+
+    /// Test utility function to get the field selections (alias and name) of the query's first
+    /// operation.
+    fn operation_fields(ctx: &Context) -> Vec<(Option<&str>, &str)> {
+        operation_selection_set(&ctx.operations[0])
+            .items
+            .iter()
+            .filter_map(|selection| match selection {
+                Selection::Field(field) => Some((field.alias, field.name)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dedupe_selections_collapses_identical_selections() {
+        let ctx = create_context("{ a a a }");
+        let ctx = dedupe_selections(ctx);
+        assert_eq!(operation_fields(&ctx), [(None, "a")]);
+    }
+
+    #[test]
+    fn test_dedupe_selections_keeps_aliased_duplicates() {
+        let ctx = create_context("{ a b: a }");
+        let ctx = dedupe_selections(ctx);
+        assert_eq!(operation_fields(&ctx), [(None, "a"), (Some("b"), "a")]);
+    }
+
+    #[test]
+    fn test_dedupe_selections_compares_sub_selections() {
+        let query = r#"
+            query {
+                users { id id }
+                users { id }
+                users { name }
+            }
+        "#;
+        let ctx = create_context(query);
+        let ctx = dedupe_selections(ctx);
+        assert_eq!(operation_fields(&ctx), [(None, "users"), (None, "users")]);
+    }
+
+    #[test]
+    fn test_query_depth_at_limit() {
+        let query = r#"
+            query {
+                users {
+                    tokens {
+                        id
+                    }
+                }
+                pairs {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query_depth(&ctx, 3).is_ok());
+    }
+
+    #[test]
+    fn test_query_depth_above_limit() {
+        let query = r#"
+            query {
+                users {
+                    tokens {
+                        owner {
+                            id
+                        }
+                    }
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query_depth(&ctx, 3).is_err());
+    }
+
+    #[test]
+    fn test_query_depth_through_fragments_above_limit() {
+        let query = r#"
+            query {
+                users {
+                    ...UserFields
+                }
+            }
+
+            fragment UserFields on User {
+                ... on User {
+                    tokens {
+                        owner {
+                            id
+                        }
+                    }
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query_depth(&ctx, 4).is_ok());
+        assert!(validate_query_depth(&ctx, 3).is_err());
+    }
+
+    #[test]
+    fn test_query_depth_cyclic_fragments() {
+        let query = r#"
+            query {
+                users {
+                    ...A
+                }
+            }
+
+            fragment A on User {
+                friends {
+                    ...B
+                }
+            }
+
+            fragment B on User {
+                friends {
+                    ...A
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query_depth(&ctx, 3).is_ok());
+        assert!(validate_query_depth(&ctx, 2).is_err());
+    }
+
+    #[test]
+    fn test_field_count_below_limit() {
+        let query = r#"
+            query {
+                users {
+                    id
+                    name
+                }
+                tokens {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query_field_count(&ctx, 5).is_ok());
+    }
+
+    #[test]
+    fn test_field_count_above_limit() {
+        let query = r#"
+            query {
+                users {
+                    id
+                    name
+                }
+                tokens {
+                    id
+                    ...TokenFields
+                }
+            }
+
+            fragment TokenFields on Token {
+                symbol
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query_field_count(&ctx, 6).is_ok());
+        assert!(validate_query_field_count(&ctx, 5).is_err());
+    }
+
+    #[test]
+    fn test_field_count_with_aliases_above_limit() {
+        let query = r#"
+            query {
+                a: users {
+                    id
+                }
+                b: users {
+                    id
+                }
+                c: users {
+                    ...UserFields
+                    ...UserFields
+                }
+            }
+
+            fragment UserFields on User {
+                x: id
+                y: id
+            }
+        "#;
+        let ctx = create_context(query);
+        // 3 aliased `users`, 2 `id`, and 2 fragment spreads of 2 aliased `id` each
+        assert!(validate_query_field_count(&ctx, 9).is_ok());
+        assert!(validate_query_field_count(&ctx, 8).is_err());
+    }
+
+    #[test]
+    fn test_operation_names_single_anonymous_operation() {
+        let query = r#"
+            {
+                users {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_operation_names(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_operation_names_multiple_anonymous_operations() {
+        let query = r#"
+            {
+                users {
+                    id
+                }
+            }
+
+            query {
+                tokens {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_operation_names(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_operation_names_named_and_anonymous_operations() {
+        let query = r#"
+            query Users {
+                users {
+                    id
+                }
+            }
+
+            {
+                tokens {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_operation_names(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_operation_names_distinct_named_operations() {
+        let query = r#"
+            query Users {
+                users {
+                    id
+                }
+            }
+
+            query Tokens {
+                tokens {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_operation_names(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_operation_names_duplicate_named_operations() {
+        let query = r#"
+            query Users {
+                users {
+                    id
+                }
+            }
+
+            subscription Users {
+                users {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(check_operation_names(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_validator_rejects_duplicate_named_operations() {
+        let query = r#"
+            query Users {
+                users {
+                    id
+                }
+            }
+
+            query Users {
+                tokens {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        let result = QueryLimits::default().validator().validate(query, &ctx);
+        assert!(matches!(result, Err(Error::BadQuery(err)) if err.to_string().contains("`Users`")));
+    }
+}
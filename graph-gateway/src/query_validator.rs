@@ -0,0 +1,229 @@
+//! Composable client query preflight checks.
+//!
+//! The [`QueryValidator`] runs an ordered list of enabled [`QueryCheck`]s against the client
+//! query, so callers do not have to remember to invoke each check separately.
+
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use cost_model::Context;
+use gateway_framework::errors::Error;
+
+use crate::{
+    query_limits::{
+        check_directives, check_nonempty_selections, check_operation_names,
+        check_typename_selections, validate_query_depth, validate_query_field_count,
+    },
+    sql_constraints::{
+        validate_banned_fields, validate_query, validate_sql_input, SqlFieldBehavior,
+    },
+};
+
+/// A query preflight check.
+#[derive(Clone, Debug)]
+pub enum QueryCheck {
+    /// Reject queries larger than the given size, in bytes.
+    MaxQuerySize(usize),
+    /// Validate the query fields against the SQL field behavior. See
+    /// [`validate_query`].
+    SqlFields(SqlFieldBehavior),
+    /// Validate the SQL statements of the top-level `sql` fields. See [`validate_sql_input`].
+    MaxSqlLen(usize),
+    /// Limit the number of `__typename` selections. See [`check_typename_selections`].
+    MaxTypenameSelections(usize),
+    /// Reject empty selection sets. See [`check_nonempty_selections`].
+    NonemptySelections,
+    /// Reject the directives outside the allowed set. See [`check_directives`].
+    AllowedDirectives(HashSet<String>),
+    /// Reject the given field names, at any depth. See [`validate_banned_fields`].
+    BannedFields(HashSet<String>),
+    /// Limit the fields nesting depth. See [`validate_query_depth`].
+    MaxQueryDepth(usize),
+    /// Limit the number of field selections. See [`validate_query_field_count`].
+    MaxFieldCount(usize),
+    /// Reject the ambiguous operations. See [`check_operation_names`].
+    UniqueOperationNames,
+}
+
+impl QueryCheck {
+    /// Run the check against the raw query and its parsed document.
+    pub fn check(&self, raw: &str, ctx: &Context) -> Result<(), Error> {
+        match self {
+            QueryCheck::MaxQuerySize(max) => {
+                if raw.len() > *max {
+                    return Err(Error::BadQuery(anyhow!(
+                        "query size is {} bytes, the maximum is {max}",
+                        raw.len()
+                    )));
+                }
+                Ok(())
+            }
+            QueryCheck::SqlFields(behavior) => validate_query(ctx, *behavior),
+            QueryCheck::MaxSqlLen(max) => validate_sql_input(ctx, *max),
+            QueryCheck::MaxTypenameSelections(max) => check_typename_selections(ctx, *max),
+            QueryCheck::NonemptySelections => check_nonempty_selections(ctx),
+            QueryCheck::AllowedDirectives(allowed) => check_directives(ctx, allowed),
+            QueryCheck::BannedFields(banned) => validate_banned_fields(ctx, banned, None),
+            QueryCheck::MaxQueryDepth(max) => validate_query_depth(ctx, *max),
+            QueryCheck::MaxFieldCount(max) => validate_query_field_count(ctx, *max),
+            QueryCheck::UniqueOperationNames => check_operation_names(ctx),
+        }
+    }
+}
+
+/// Runs the enabled query preflight checks, in order.
+///
+/// To create a new [`QueryValidator`] instance, use the [`QueryValidatorBuilder`].
+#[derive(Clone, Debug, Default)]
+pub struct QueryValidator {
+    checks: Vec<QueryCheck>,
+}
+
+impl QueryValidator {
+    /// Create a new [`QueryValidatorBuilder`] with no checks enabled.
+    pub fn builder() -> QueryValidatorBuilder {
+        QueryValidatorBuilder::default()
+    }
+
+    /// The enabled checks, in execution order.
+    pub fn checks(&self) -> &[QueryCheck] {
+        &self.checks
+    }
+
+    /// Run the enabled checks against the raw query and its parsed document.
+    ///
+    /// The checks run in order, and the first failing check's error is returned.
+    pub fn validate(&self, raw: &str, ctx: &Context) -> Result<(), Error> {
+        self.checks
+            .iter()
+            .try_for_each(|check| check.check(raw, ctx))
+    }
+}
+
+/// The [`QueryValidator`] builder.
+///
+/// The checks run in the order they are enabled.
+#[derive(Clone, Debug, Default)]
+pub struct QueryValidatorBuilder {
+    checks: Vec<QueryCheck>,
+}
+
+impl QueryValidatorBuilder {
+    /// Enables the given check.
+    pub fn with_check(mut self, check: QueryCheck) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// Enables the maximum query size check.
+    pub fn with_max_query_size(self, max: usize) -> Self {
+        self.with_check(QueryCheck::MaxQuerySize(max))
+    }
+
+    /// Enables the SQL fields check with the given behavior.
+    pub fn with_sql_fields(self, behavior: SqlFieldBehavior) -> Self {
+        self.with_check(QueryCheck::SqlFields(behavior))
+    }
+
+    /// Enables the SQL statements length check.
+    pub fn with_max_sql_len(self, max: usize) -> Self {
+        self.with_check(QueryCheck::MaxSqlLen(max))
+    }
+
+    /// Enables the `__typename` selections limit check.
+    pub fn with_max_typename_selections(self, max: usize) -> Self {
+        self.with_check(QueryCheck::MaxTypenameSelections(max))
+    }
+
+    /// Enables the empty selection sets check.
+    pub fn with_nonempty_selections(self) -> Self {
+        self.with_check(QueryCheck::NonemptySelections)
+    }
+
+    /// Enables the allowed directives check.
+    pub fn with_allowed_directives(self, allowed: HashSet<String>) -> Self {
+        self.with_check(QueryCheck::AllowedDirectives(allowed))
+    }
+
+    /// Enables the banned fields check.
+    pub fn with_banned_fields(self, banned: HashSet<String>) -> Self {
+        self.with_check(QueryCheck::BannedFields(banned))
+    }
+
+    /// Enables the fields nesting depth limit check.
+    pub fn with_max_query_depth(self, max: usize) -> Self {
+        self.with_check(QueryCheck::MaxQueryDepth(max))
+    }
+
+    /// Enables the field selections limit check.
+    pub fn with_max_field_count(self, max: usize) -> Self {
+        self.with_check(QueryCheck::MaxFieldCount(max))
+    }
+
+    /// Enables the ambiguous operations check.
+    pub fn with_unique_operation_names(self) -> Self {
+        self.with_check(QueryCheck::UniqueOperationNames)
+    }
+
+    /// Builds the [`QueryValidator`] instance.
+    pub fn build(self) -> QueryValidator {
+        QueryValidator {
+            checks: self.checks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use cost_model::Context;
+    use gateway_framework::errors::Error;
+
+    use super::QueryValidator;
+    use crate::sql_constraints::SqlFieldBehavior;
+
+    #[test]
+    fn first_failing_check_error_is_returned() {
+        //* Given
+        let query = "{ __typename a { __typename } b @stream }";
+        let ctx = Context::new(query, "{}").expect("valid query");
+
+        let validator = QueryValidator::builder()
+            .with_sql_fields(SqlFieldBehavior::RejectSql)
+            .with_nonempty_selections()
+            .with_max_typename_selections(1)
+            .with_allowed_directives(HashSet::new())
+            .build();
+
+        //* When
+        let result = validator.validate(query, &ctx);
+
+        //* Then
+        // Both the `__typename` and the directives checks fail, the first one is reported
+        let err = result.expect_err("query should be rejected");
+        assert!(matches!(&err, Error::BadQuery(_)));
+        assert!(err.to_string().contains("`__typename` selections"));
+    }
+
+    #[test]
+    fn valid_query_passes_all_checks() {
+        //* Given
+        let query = "{ a { b } }";
+        let ctx = Context::new(query, "{}").expect("valid query");
+
+        let validator = QueryValidator::builder()
+            .with_max_query_size(query.len())
+            .with_sql_fields(SqlFieldBehavior::RejectSql)
+            .with_nonempty_selections()
+            .with_max_typename_selections(0)
+            .with_allowed_directives(HashSet::new())
+            .build();
+
+        //* When
+        let result = validator.validate(query, &ctx);
+
+        //* Then
+        assert!(result.is_ok());
+    }
+}
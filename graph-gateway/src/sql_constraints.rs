@@ -1,62 +1,202 @@
+use std::collections::HashSet;
+
 use anyhow::anyhow;
 use cost_model::Context;
 use gateway_framework::errors::Error;
-use graphql::graphql_parser::query::{OperationDefinition, Query, SelectionSet};
+use graphql::graphql_parser::query::{
+    FragmentDefinition, Mutation, OperationDefinition, Query, Selection, SelectionSet,
+    Subscription, Value,
+};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum SqlFieldBehavior {
     RejectSql,
     AcceptSqlOnly,
 }
 
+/// Validate the query fields against the given SQL field behavior.
+///
+/// All the operation kinds are checked: queries, mutations, and subscriptions.
+///
+/// - [`SqlFieldBehavior::RejectSql`]: the query is rejected if it contains an `sql` field at any
+///   depth, i.e., nested in another field's selection set, an inline fragment, or a spread
+///   fragment.
+/// - [`SqlFieldBehavior::AcceptSqlOnly`]: all the query top-level fields must be `sql` fields.
+///
+/// Fields are matched by their name, not their alias, so aliasing an `sql` field (e.g.,
+/// `data: sql(...)`) does not hide it.
+///
+/// The check is performed on the parsed query AST field names only. Comments, whitespace, and
+/// the contents of string values (including block strings) are not part of the field names, so
+/// they can neither hide an `sql` field nor make a non-`sql` field look like one.
 pub fn validate_query(ctx: &Context, behavior: SqlFieldBehavior) -> Result<(), Error> {
+    if let SqlFieldBehavior::RejectSql = behavior {
+        let banned_fields = HashSet::from(["sql".to_string()]);
+        return validate_banned_fields(ctx, &banned_fields, Some("Query contains SQL"));
+    }
+
     for operation in &ctx.operations {
-        match operation {
-            OperationDefinition::SelectionSet(selection_set)
-            | OperationDefinition::Query(Query { selection_set, .. }) => {
-                if !selection_set_is_valid(selection_set, behavior) {
-                    use SqlFieldBehavior::*;
-                    match behavior {
-                        RejectSql => return Err(Error::BadQuery(anyhow!("Query contains SQL"))),
-                        AcceptSqlOnly => {
-                            let invalid_fields = selection_set
-                                .items
-                                .iter()
-                                .filter_map(|selection| {
-                                    if let graphql::graphql_parser::query::Selection::Field(field) =
-                                        selection
-                                    {
-                                        return Some(field.name);
-                                    }
-                                    None
-                                })
-                                .collect::<Vec<_>>();
-                            return Err(Error::BadQuery(anyhow!(
-                                "Fields [{}] are not SQL",
-                                invalid_fields.join(", ")
-                            )));
-                        }
+        let selection_set = operation_selection_set(operation);
+        if !selection_set_is_sql_only(selection_set) {
+            let invalid_fields = selection_set
+                .items
+                .iter()
+                .filter_map(|selection| {
+                    if let Selection::Field(field) = selection {
+                        return Some(field.name);
                     }
+                    None
+                })
+                .collect::<Vec<_>>();
+            return Err(Error::BadQuery(anyhow!(
+                "Fields [{}] are not SQL",
+                invalid_fields.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Reject the query if it contains any of the banned fields at any depth, i.e., nested in another
+/// field's selection set, an inline fragment, or a spread fragment.
+///
+/// All the operation kinds are checked, and fields are matched by their name, not their alias.
+/// If set, the given error message is returned, otherwise the message names the banned field.
+pub fn validate_banned_fields(
+    ctx: &Context,
+    banned_fields: &HashSet<String>,
+    message: Option<&str>,
+) -> Result<(), Error> {
+    for operation in &ctx.operations {
+        let selection_set = operation_selection_set(operation);
+        let banned_field = find_banned_field(
+            selection_set,
+            banned_fields,
+            &ctx.fragments,
+            &mut HashSet::new(),
+        );
+        if let Some(field) = banned_field {
+            return Err(Error::BadQuery(match message {
+                Some(message) => anyhow!("{message}"),
+                None => anyhow!("Query contains the banned field `{field}`"),
+            }));
+        }
+    }
+    Ok(())
+}
+
+/// Validate the SQL statements passed to the top-level `sql` fields of the query, i.e., the
+/// `sql(input: { query: "..." })` string argument.
+///
+/// This check is only meaningful for deployments that accept SQL queries. A statement is rejected
+/// if it is missing, empty, or longer than `max_sql_len` bytes.
+///
+/// Only the query field arguments are checked. Statements passed through the request variables,
+/// e.g., `sql(input: { query: $query })`, are not part of the query, and are not checked.
+pub fn validate_sql_input(ctx: &Context, max_sql_len: usize) -> Result<(), Error> {
+    for operation in &ctx.operations {
+        for selection in &operation_selection_set(operation).items {
+            let field = match selection {
+                Selection::Field(field) if field.name == "sql" => field,
+                _ => continue,
+            };
+
+            let input = field
+                .arguments
+                .iter()
+                .find(|(name, _)| *name == "input")
+                .map(|(_, input)| input);
+            let query = match input {
+                Some(Value::Object(input)) => input.get("query"),
+                Some(Value::Variable(_)) => continue,
+                _ => None,
+            };
+            let sql = match query {
+                Some(Value::String(query)) => Some(query.as_str()),
+                Some(Value::Variable(_)) => continue,
+                _ => None,
+            };
+
+            match sql {
+                None => return Err(Error::BadQuery(anyhow!("SQL query is missing"))),
+                Some(sql) if sql.trim().is_empty() => {
+                    return Err(Error::BadQuery(anyhow!("SQL query is empty")));
+                }
+                Some(sql) if sql.len() > max_sql_len => {
+                    return Err(Error::BadQuery(anyhow!(
+                        "SQL query length exceeds the maximum of {max_sql_len} bytes"
+                    )));
                 }
+                Some(_) => {}
             }
-            _ => continue,
         }
     }
     Ok(())
 }
 
-fn selection_set_is_valid<'q>(
+/// Get the top-level selection set of the operation, whatever its kind.
+pub(crate) fn operation_selection_set<'a, 'q>(
+    operation: &'a OperationDefinition<'q, &'q str>,
+) -> &'a SelectionSet<'q, &'q str> {
+    match operation {
+        OperationDefinition::SelectionSet(selection_set)
+        | OperationDefinition::Query(Query { selection_set, .. })
+        | OperationDefinition::Mutation(Mutation { selection_set, .. })
+        | OperationDefinition::Subscription(Subscription { selection_set, .. }) => selection_set,
+    }
+}
+
+/// Check if all the selection set top-level selections are `sql` fields.
+fn selection_set_is_sql_only<'q>(selection_set: &SelectionSet<'q, &'q str>) -> bool {
+    selection_set
+        .items
+        .iter()
+        .all(|selection| matches!(selection, Selection::Field(field) if field.name == "sql"))
+}
+
+/// Find a banned field in the selection set, descending into the nested selection sets, the
+/// inline fragments, and the spread fragments' definitions.
+///
+/// The field name is checked, not its alias. Each fragment definition is visited at most once, so
+/// the traversal terminates on cyclic fragment spreads. Spreads of undefined fragments are ignored.
+fn find_banned_field<'q>(
     selection_set: &SelectionSet<'q, &'q str>,
-    behavior: SqlFieldBehavior,
-) -> bool {
-    let field_is_valid = |field_name: &str| match behavior {
-        SqlFieldBehavior::RejectSql => field_name != "sql",
-        SqlFieldBehavior::AcceptSqlOnly => field_name == "sql",
-    };
-
-    selection_set.items.iter().all(|selection| {
-        matches!(selection, graphql::graphql_parser::query::Selection::Field(field) if field_is_valid(field.name))
-    })
+    banned_fields: &HashSet<String>,
+    fragments: &[FragmentDefinition<'q, &'q str>],
+    visited_fragments: &mut HashSet<&'q str>,
+) -> Option<&'q str> {
+    selection_set
+        .items
+        .iter()
+        .find_map(|selection| match selection {
+            Selection::Field(field) if banned_fields.contains(field.name) => Some(field.name),
+            Selection::Field(field) => find_banned_field(
+                &field.selection_set,
+                banned_fields,
+                fragments,
+                visited_fragments,
+            ),
+            Selection::InlineFragment(fragment) => find_banned_field(
+                &fragment.selection_set,
+                banned_fields,
+                fragments,
+                visited_fragments,
+            ),
+            Selection::FragmentSpread(spread) => {
+                if !visited_fragments.insert(spread.fragment_name) {
+                    return None;
+                }
+                let fragment = fragments
+                    .iter()
+                    .find(|fragment| fragment.name == spread.fragment_name)?;
+                find_banned_field(
+                    &fragment.selection_set,
+                    banned_fields,
+                    fragments,
+                    visited_fragments,
+                )
+            }
+        })
 }
 
 #[cfg(test)]
@@ -351,7 +491,7 @@ mod tests {
     }
 
     #[test]
-    fn test_no_sql_single_selection_set_reject_sql_with_nested_sql_field() {
+    fn test_single_selection_set_reject_sql_with_nested_sql_field() {
         let query = r#"
             query {
                 users {
@@ -360,11 +500,11 @@ mod tests {
             }
         "#;
         let ctx = create_context(query);
-        assert!(validate_query(&ctx, reject_sql()).is_ok());
+        assert!(validate_query(&ctx, reject_sql()).is_err());
     }
 
     #[test]
-    fn test_no_sql_single_selection_set_without_query_reject_sql_with_nested_sql_field() {
+    fn test_single_selection_set_without_query_reject_sql_with_nested_sql_field() {
         let query = r#"
             {
                 users {
@@ -373,6 +513,440 @@ mod tests {
             }
         "#;
         let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_wrapped_sql_reject_sql() {
+        let query = r#"
+            {
+                wrapper {
+                    sql(input: { query: "SELECT * FROM users" }) {
+                        id
+                    }
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_aliased_sql_reject_sql() {
+        let query = r#"
+            {
+                x: sql(input: { query: "SELECT * FROM users" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_sql_alias_on_non_sql_field_reject_sql() {
+        let query = r#"
+            {
+                sql: users {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
         assert!(validate_query(&ctx, reject_sql()).is_ok());
     }
+
+    #[test]
+    fn test_aliased_sql_accept_sql_only() {
+        let query = r#"
+        {
+            x: sql(input: { query: "SELECT * FROM users" }) {
+                id
+            }
+        }
+    "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, accept_sql_only()).is_ok());
+    }
+
+    #[test]
+    fn test_sql_input_within_max_len() {
+        let query = r#"
+            query {
+                sql(input: { query: "SELECT * FROM users" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_sql_input(&ctx, 64).is_ok());
+    }
+
+    #[test]
+    fn test_sql_input_exceeds_max_len() {
+        let query = r#"
+            query {
+                sql(input: { query: "SELECT * FROM users WHERE name = 'a very long name'" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_sql_input(&ctx, 16).is_err());
+    }
+
+    #[test]
+    fn test_sql_input_empty() {
+        let query = r#"
+            {
+                sql(input: { query: "" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_sql_input(&ctx, 64).is_err());
+    }
+
+    #[test]
+    fn test_sql_input_in_variables_is_not_checked() {
+        let query = r#"
+            query ($query: String!, $input: SqlInput!) {
+                byQuery: sql(input: { query: $query }) {
+                    id
+                }
+                byInput: sql(input: $input) {
+                    id
+                }
+            }
+        "#;
+        let variables = r#"{
+            "query": "SELECT * FROM users WHERE name = 'a very long name'",
+            "input": { "query": "" }
+        }"#;
+        let ctx = Context::new(query, variables).unwrap();
+        assert!(validate_sql_input(&ctx, 16).is_ok());
+    }
+
+    #[test]
+    fn test_sql_input_missing() {
+        let query = r#"
+            {
+                sql(input: {}) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_sql_input(&ctx, 64).is_err());
+    }
+
+    #[test]
+    fn test_sql_with_interspersed_comments_reject_sql() {
+        let query = r#"
+            query {
+                # users {
+                #     id
+                # }
+                sql # comment between the field name and its arguments
+                (input: { query: "SELECT * FROM users" }) {
+                    # sql
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_sql_in_comments_only_reject_sql() {
+        let query = r#"
+            {
+                # sql(input: { query: "SELECT * FROM users" }) { id }
+                users {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_ok());
+    }
+
+    #[test]
+    fn test_sql_in_string_argument_reject_sql() {
+        let query = r#"
+            {
+                users(where: { name: "sql(input: { query: \"SELECT 1\" }) { id }" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_ok());
+    }
+
+    #[test]
+    fn test_sql_block_string_with_field_names_accept_sql_only() {
+        let query = r#"
+            {
+                sql(input: { query: """
+                    users { id name }
+                    tokens { id }
+                    SELECT * FROM users
+                """ }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, accept_sql_only()).is_ok());
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_subscription_reject_sql() {
+        let query = r#"
+            subscription {
+                sql(input: { query: "SELECT * FROM users" }) {
+                    id
+                    name
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_no_sql_subscription_reject_sql() {
+        let query = r#"
+            subscription {
+                users {
+                    id
+                    name
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_ok());
+    }
+
+    #[test]
+    fn test_mutation_reject_sql() {
+        let query = r#"
+            mutation {
+                sql(input: { query: "SELECT * FROM users" }) {
+                    id
+                    name
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_no_sql_mutation_reject_sql() {
+        let query = r#"
+            mutation {
+                users {
+                    id
+                    name
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_ok());
+    }
+
+    #[test]
+    fn test_subscription_accept_sql_only() {
+        let query = r#"
+        subscription {
+            sql(input: { query: "SELECT * FROM users" }) {
+                id
+                name
+            }
+        }
+    "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, accept_sql_only()).is_ok());
+    }
+
+    #[test]
+    fn test_with_graphql_mutation_accept_sql_only() {
+        let query = r#"
+        mutation {
+            sql(input: { query: "SELECT * FROM users" }) {
+                id
+                name
+            }
+            users {
+                id
+                name
+            }
+        }
+    "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, accept_sql_only()).is_err());
+    }
+
+    #[test]
+    fn test_subscription_sql_input_exceeds_max_len() {
+        let query = r#"
+            subscription {
+                sql(input: { query: "SELECT * FROM users WHERE name = 'a very long name'" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_sql_input(&ctx, 16).is_err());
+    }
+
+    #[test]
+    fn test_sql_in_fragment_spread_reject_sql() {
+        let query = r#"
+            query {
+                ...F
+            }
+            fragment F on Query {
+                sql(input: { query: "SELECT * FROM users" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_sql_in_inline_fragment_reject_sql() {
+        let query = r#"
+            query {
+                ... on Query {
+                    sql(input: { query: "SELECT * FROM users" }) {
+                        id
+                    }
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_no_sql_in_fragments_reject_sql() {
+        let query = r#"
+            query {
+                ...F
+                ... on Query {
+                    tokens {
+                        id
+                    }
+                }
+            }
+            fragment F on Query {
+                users {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_ok());
+    }
+
+    #[test]
+    fn test_mutually_recursive_fragments_reject_sql() {
+        let query = r#"
+            query {
+                users {
+                    ...A
+                }
+            }
+            fragment A on User {
+                id
+                friends {
+                    ...B
+                }
+            }
+            fragment B on User {
+                name
+                friends {
+                    ...A
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_ok());
+    }
+
+    #[test]
+    fn test_sql_in_mutually_recursive_fragments_reject_sql() {
+        let query = r#"
+            query {
+                users {
+                    ...A
+                }
+            }
+            fragment A on User {
+                friends {
+                    ...B
+                }
+            }
+            fragment B on User {
+                friends {
+                    ...A
+                }
+                sql(input: { query: "SELECT * FROM users" }) {
+                    id
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        assert!(validate_query(&ctx, reject_sql()).is_err());
+    }
+
+    #[test]
+    fn test_custom_banned_field() {
+        let query = r#"
+            {
+                users {
+                    id
+                    passthrough(raw: "...") {
+                        id
+                    }
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+        let banned_fields = HashSet::from(["passthrough".to_string()]);
+
+        let err = validate_banned_fields(&ctx, &banned_fields, None).unwrap_err();
+        assert!(err.to_string().contains("`passthrough`"));
+
+        let err = validate_banned_fields(&ctx, &banned_fields, Some("Passthrough not supported"))
+            .unwrap_err();
+        assert!(err.to_string().contains("Passthrough not supported"));
+
+        // Only the banned fields are rejected
+        assert!(validate_banned_fields(&ctx, &HashSet::from(["sql".to_string()]), None).is_ok());
+    }
+
+    #[test]
+    fn test_reject_sql_wrapper_still_rejects_sql() {
+        let query = r#"
+            {
+                sql(input: { query: "SELECT * FROM users" }) {
+                    passthrough
+                }
+            }
+        "#;
+        let ctx = create_context(query);
+
+        let err = validate_query(&ctx, reject_sql()).unwrap_err();
+        assert!(err.to_string().contains("Query contains SQL"));
+    }
 }
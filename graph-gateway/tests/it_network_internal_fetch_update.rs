@@ -7,6 +7,7 @@ use std::{
 use alloy_primitives::Address;
 use anyhow::anyhow;
 use assert_matches::assert_matches;
+use gateway_framework::topology::network::DEFAULT_MAX_ALLOCATED_TOKENS;
 use graph_gateway::network::{
     indexer_addr_blocklist::AddrBlocklist,
     indexer_host_blocklist::HostBlocklist,
@@ -18,7 +19,9 @@ use graph_gateway::network::{
     internal::{
         fetch_and_pre_process_indexers_info as internal_fetch_and_pre_process_indexers_info,
         fetch_update as internal_fetch_update, process_indexers_info, types as internal_types,
-        InternalState,
+        InternalState, NetworkFetchError, DEFAULT_FILTER_STAGES,
+        DEFAULT_INDEXERS_PROCESSING_CONCURRENCY, DEFAULT_INDEXERS_PROCESSING_DEADLINE,
+        DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT, DEFAULT_NETWORK_TOPOLOGY_UPDATE_TIMEOUT,
     },
     subgraph::Client,
     NetworkTopologySnapshot,
@@ -93,13 +96,29 @@ fn test_service_state(
         indexer_http_client: indexers_http_client.clone(),
         indexer_min_agent_version: Version::new(0, 0, 0),
         indexer_min_graph_node_version: Version::new(0, 0, 0),
+        indexer_min_graph_node_version_per_network: HashMap::new(),
         indexer_addr_blocklist: None,
         indexer_host_resolver: indexers_host_resolver,
         indexer_host_blocklist: None,
         indexer_version_resolver: indexers_version_resolver,
         indexer_indexing_pois_blocklist: None,
+        indexer_indexing_poi_collision_policy: Default::default(),
         indexer_indexing_status_resolver: indexers_indexing_status_resolver,
+        indexer_indexing_staleness_filter: None,
         indexer_indexing_cost_model_resolver: indexers_cost_model_resolver,
+        indexer_indexing_cost_model_overrides: HashMap::new(),
+        indexer_filter_stages: DEFAULT_FILTER_STAGES.to_vec(),
+        indexer_max_allocated_tokens: DEFAULT_MAX_ALLOCATED_TOKENS,
+        indexer_min_staked_tokens: 0,
+        max_indexers: None,
+        known_networks: None,
+        indexers_fetch_timeout: DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+        subgraphs_fetch_timeout: DEFAULT_NETWORK_TOPOLOGY_FETCH_TIMEOUT,
+        fetch_retry: Default::default(),
+        update_timeout: DEFAULT_NETWORK_TOPOLOGY_UPDATE_TIMEOUT,
+        indexers_processing_deadline: DEFAULT_INDEXERS_PROCESSING_DEADLINE,
+        indexers_processing_concurrency: DEFAULT_INDEXERS_PROCESSING_CONCURRENCY,
+        last_fetch_report: Default::default(),
     };
 
     if !addr_blocklist.is_empty() {
@@ -145,11 +164,12 @@ async fn fetch_and_pre_process_indexers_info() -> HashMap<Address, internal_type
                 Client::new(subgraph_client, true)
             };
 
-            let indexers = internal_fetch_and_pre_process_indexers_info(&mut client)
-                .await
-                .map_err(|err| {
-                    anyhow!("Failed to fetch and pre-process the indexers info: {err}")
-                })?;
+            let indexers = internal_fetch_and_pre_process_indexers_info(
+                &mut client,
+                DEFAULT_MAX_ALLOCATED_TOKENS,
+            )
+            .await
+            .map_err(|err| anyhow!("Failed to fetch and pre-process the indexers info: {err}"))?;
 
             Ok::<_, anyhow::Error>(indexers)
         })
@@ -159,7 +179,9 @@ async fn fetch_and_pre_process_indexers_info() -> HashMap<Address, internal_type
 }
 
 /// Test helper to fetch, process and construct the network topology snapshot.
-async fn fetch_update(service: &InternalState) -> anyhow::Result<NetworkTopologySnapshot> {
+async fn fetch_update(
+    service: &InternalState,
+) -> Result<NetworkTopologySnapshot, NetworkFetchError> {
     let subgraph_url = url_with_subgraph_id(GRAPH_NETWORK_ARBITRUM_SUBGRAPH_ID);
     let auth_token = test_auth_token();
 
@@ -171,7 +193,7 @@ async fn fetch_update(service: &InternalState) -> anyhow::Result<NetworkTopology
         Mutex::new(Client::new(subgraph_client, true))
     };
 
-    internal_fetch_update(&client, service).await
+    internal_fetch_update(&client, service, None, Default::default()).await
 }
 
 #[test_with::env(IT_TEST_ARBITRUM_GATEWAY_URL, IT_TEST_ARBITRUM_GATEWAY_AUTH)]
@@ -514,7 +536,5 @@ async fn fetch_indexers_info_and_block_all_indexers_by_agent_version() {
 
     //* Then
     // Assert the failure, as all indexers are blocked
-    assert_matches!(res, Err(err) => {
-        assert_eq!(err.to_string(), "no valid indexers found")
-    });
+    assert_matches!(res, Err(NetworkFetchError::NoValidIndexers));
 }